@@ -1,12 +1,16 @@
 // HTTP 客户端实现
 
 pub use super::models::{ApiError};
+use super::models::{RefreshRequest, RefreshResponse};
 use crate::config::AppConfig;
 use crate::utils::auth::AuthManager;
 use reqwest::{Client as ReqwestClient, RequestBuilder, multipart::Form};
 use std::collections::HashMap;
 use std::time::Duration;
 
+// 访问令牌剩余有效期小于该值（秒）时提前刷新
+const ACCESS_TOKEN_REFRESH_MARGIN_SECONDS: i64 = 60;
+
 pub struct ApiClient {
     base_url: String,
     client: ReqwestClient,
@@ -29,6 +33,32 @@ impl ApiClient {
         }
     }
     
+    // 获取认证头，访问令牌即将过期时先用刷新令牌换取新的令牌
+    async fn auth_header(&self) -> Option<String> {
+        let auth_manager = self.auth_manager.as_ref()?;
+        if auth_manager.is_token_expiring_within(ACCESS_TOKEN_REFRESH_MARGIN_SECONDS) {
+            if let Some(refresh_token) = auth_manager.get_refresh_token() {
+                if let Err(e) = self.refresh_tokens(auth_manager, refresh_token).await {
+                    log::warn!("Failed to refresh access token: {}", e);
+                }
+            }
+        }
+        auth_manager.get_auth_header()
+    }
+    
+    // 调用 /api/users/refresh 换取新的访问令牌和刷新令牌
+    async fn refresh_tokens(&self, auth_manager: &AuthManager, refresh_token: String) -> Result<(), ApiError> {
+        let url = format!("{}/api/users/refresh", self.base_url);
+        let request_builder = self.client.post(&url).json(&RefreshRequest { refresh_token });
+        let response: RefreshResponse = self.execute_request(request_builder).await?;
+        
+        auth_manager
+            .set_token(&response.token)
+            .and_then(|_| auth_manager.set_refresh_token(&response.refresh_token))
+            .map_err(|e| ApiError::ValidationError(format!("Failed to save tokens: {}", e)))?;
+        Ok(())
+    }
+    
     pub async fn post<T, U>(&self, path: &str, body: &T) -> Result<U, ApiError>
     where
         T: serde::Serialize,
@@ -38,10 +68,8 @@ impl ApiClient {
         let mut request_builder = self.client.post(&url).json(body);
         
         // 添加认证头
-        if let Some(auth_manager) = &self.auth_manager {
-            if let Some(auth_header) = auth_manager.get_auth_header() {
-                request_builder = request_builder.header("Authorization", auth_header);
-            }
+        if let Some(auth_header) = self.auth_header().await {
+            request_builder = request_builder.header("Authorization", auth_header);
         }
 
         self.execute_request(request_builder).await
@@ -62,12 +90,8 @@ impl ApiClient {
         }
         
         // 添加认证头
-        if let Some(auth_manager) = &self.auth_manager {
-            if let Some(auth_header) = auth_manager.get_auth_header() {
-                request_builder = request_builder.header("Authorization", auth_header);
-            }
-        } else {
-            
+        if let Some(auth_header) = self.auth_header().await {
+            request_builder = request_builder.header("Authorization", auth_header);
         }
         
         self.execute_request(request_builder).await
@@ -78,10 +102,8 @@ impl ApiClient {
         let mut request_builder = self.client.get(&url);
         
         // 添加认证头
-        if let Some(auth_manager) = &self.auth_manager {
-            if let Some(auth_header) = auth_manager.get_auth_header() {
-                request_builder = request_builder.header("Authorization", auth_header);
-            }
+        if let Some(auth_header) = self.auth_header().await {
+            request_builder = request_builder.header("Authorization", auth_header);
         }
         
         let mut retries = 0;
@@ -189,10 +211,8 @@ impl ApiClient {
                 .multipart(form);
             
             // 添加认证头
-            if let Some(auth_manager) = &self.auth_manager {
-                if let Some(auth_header) = auth_manager.get_auth_header() {
-                    request_builder = request_builder.header("Authorization", auth_header);
-                }
+            if let Some(auth_header) = self.auth_header().await {
+                request_builder = request_builder.header("Authorization", auth_header);
             }
             
             match request_builder.send().await {
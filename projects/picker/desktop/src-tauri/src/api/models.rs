@@ -73,9 +73,72 @@ pub struct LoginRequest {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LoginResponse {
     pub token: String,
+    #[serde(default)]
+    pub refresh_token: String,
     pub user: UserInfo,
 }
 
+// 刷新令牌请求
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+// 刷新令牌响应
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefreshResponse {
+    pub token: String,
+    pub refresh_token: String,
+    pub expires_in: i64,
+}
+
+// 登出请求
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogoutRequest {
+    pub refresh_token: Option<String>,
+}
+
+// 登出响应
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogoutResponse {
+    pub message: String,
+}
+
+// 忘记密码请求
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+// 重置密码请求
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResetPasswordRequest {
+    pub email: String,
+    pub code: String,
+    pub new_password: String,
+}
+
+// 忘记密码与重置密码响应
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PasswordResponse {
+    pub message: String,
+}
+
+// 修改密码请求
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+// 修改密码响应，旧令牌已失效
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChangePasswordResponse {
+    pub token: String,
+    pub refresh_token: String,
+    pub expires_in: i64,
+}
+
 // 用户信息
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UserInfo {
@@ -178,6 +241,8 @@ pub enum OrderStatus {
     Pending,
     Success,
     Expired,
+    Refunded,
+    Cancelled,
 }
 
 // 订单信息
@@ -211,8 +276,11 @@ pub struct CreateOrderRequest {
 }
 
 // 创建订单响应
+// 钱包订单返回时为 pending，可通过 get_order_detail 查询确认进度
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateOrderResponse {
+    pub order_id: String,
+    pub status: OrderStatus,
     pub token: String,
     pub message: String,
 }
@@ -227,5 +295,7 @@ pub struct VerifyRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VerifyResponse {
     pub token: String,
+    #[serde(default)]
+    pub refresh_token: String,
     pub user: UserInfo,
 }
\ No newline at end of file
@@ -1,7 +1,7 @@
 // 用户相关命令
 
 use crate::api::client::ApiClient;
-use crate::api::models::{ConnectionStatus, SystemInfo, LoginRequest, LoginResponse, ResponseUserInfo, RegisterRequest, RegisterResponse, UserInfo, VerifyRequest, VerifyResponse};
+use crate::api::models::{ConnectionStatus, SystemInfo, LoginRequest, LoginResponse, LogoutRequest, LogoutResponse, ResponseUserInfo, RegisterRequest, RegisterResponse, UserInfo, VerifyRequest, VerifyResponse, ForgotPasswordRequest, ResetPasswordRequest, PasswordResponse, ChangePasswordRequest, ChangePasswordResponse};
 use crate::config::AppConfig;
 use crate::utils::auth::AuthManager;
 use tauri::State;
@@ -93,8 +93,9 @@ pub async fn login(
     
     let response: LoginResponse = api_client.post("/api/users/login", &request).await.map_err(|e| e.to_string())?;
     
-    // 保存 token
+    // 保存 token 和刷新令牌
     auth_manager.set_token(&response.token).map_err(|e| e.to_string())?;
+    auth_manager.set_refresh_token(&response.refresh_token).map_err(|e| e.to_string())?;
 
     let system_info_response = system_info(auth_manager.clone()).await.map_err(|e| e.to_string())?;
     let rpc_url = system_info_response.chain_url;
@@ -219,15 +220,91 @@ pub async fn get_user_profile(
     Ok(response)
 }
 
-// 登出命令
+// 登出命令：先通知服务端吊销访问令牌和刷新令牌，再清除本地保存的令牌
 #[tauri::command]
 pub async fn logout(
     auth_manager: State<'_, AuthManager>,
 ) -> Result<bool, String> {
+    if auth_manager.is_logged_in() {
+        let config = AppConfig::load().unwrap_or_else(|_| AppConfig::default());
+        let api_client = ApiClient::new(&config, Some(auth_manager.inner().clone()));
+        let request = LogoutRequest {
+            refresh_token: auth_manager.get_refresh_token(),
+        };
+        // 服务端不可达或令牌已失效时仍然完成本地登出
+        if let Err(e) = api_client.post::<_, LogoutResponse>("/api/users/logout", &request).await {
+            log::warn!("Failed to revoke tokens on server: {}", e);
+        }
+    }
     auth_manager.clear_token().map_err(|e| e.to_string())?;
     Ok(true)
 }
 
+// 忘记密码命令：请求服务端向邮箱发送重置验证码
+#[tauri::command]
+pub async fn forgot_password(
+    email: String,
+) -> Result<PasswordResponse, String> {
+    let config = AppConfig::load().unwrap_or_else(|_| AppConfig::default());
+    let api_client = ApiClient::new(&config, None);
+
+    let request = ForgotPasswordRequest { email };
+    let response: PasswordResponse = api_client.post("/api/users/password/forgot", &request).await.map_err(|e| e.to_string())?;
+    Ok(response)
+}
+
+// 重置密码命令：服务端会作废该账号此前的令牌，本地已登录时一并清除
+#[tauri::command]
+pub async fn reset_password(
+    email: String,
+    code: String,
+    new_password: String,
+    auth_manager: State<'_, AuthManager>,
+) -> Result<PasswordResponse, String> {
+    let config = AppConfig::load().unwrap_or_else(|_| AppConfig::default());
+    let api_client = ApiClient::new(&config, None);
+
+    let request = ResetPasswordRequest {
+        email: email.clone(),
+        code,
+        new_password,
+    };
+    let response: PasswordResponse = api_client.post("/api/users/password/reset", &request).await.map_err(|e| e.to_string())?;
+
+    // 登录时保存的是 ResponseUserInfo，获取资料后保存的是 UserInfo
+    let logged_in_email = auth_manager.get_user_info().and_then(|info| {
+        info["user_info"]["email"]
+            .as_str()
+            .or_else(|| info["email"].as_str())
+            .map(str::to_string)
+    });
+    if logged_in_email.is_some_and(|logged_in| logged_in.eq_ignore_ascii_case(&email)) {
+        auth_manager.clear_token().map_err(|e| e.to_string())?;
+    }
+    Ok(response)
+}
+
+// 修改密码命令：成功后改用服务端返回的新令牌
+#[tauri::command]
+pub async fn change_password(
+    current_password: String,
+    new_password: String,
+    auth_manager: State<'_, AuthManager>,
+) -> Result<bool, String> {
+    let config = AppConfig::load().unwrap_or_else(|_| AppConfig::default());
+    let api_client = ApiClient::new(&config, Some(auth_manager.inner().clone()));
+
+    let request = ChangePasswordRequest {
+        current_password,
+        new_password,
+    };
+    let response: ChangePasswordResponse = api_client.post("/api/users/password", &request).await.map_err(|e| e.to_string())?;
+
+    auth_manager.set_token(&response.token).map_err(|e| e.to_string())?;
+    auth_manager.set_refresh_token(&response.refresh_token).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
 // 检查登录状态命令
 #[tauri::command]
 pub async fn check_login_status(
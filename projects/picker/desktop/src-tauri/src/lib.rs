@@ -66,6 +66,9 @@ pub fn run() {
       commands::users::verify_email,
       commands::users::get_user_profile,
       commands::users::logout,
+      commands::users::forgot_password,
+      commands::users::reset_password,
+      commands::users::change_password,
       commands::users::check_login_status,
       commands::users::get_current_user_info,
       
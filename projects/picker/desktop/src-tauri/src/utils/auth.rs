@@ -8,6 +8,7 @@ use std::sync::Arc;
 
 // Token 存储键名
 pub const TOKEN_STORAGE_KEY: &str = "auth_token";
+pub const REFRESH_TOKEN_STORAGE_KEY: &str = "refresh_token";
 pub const USER_INFO_KEY: &str = "user_info";
 pub const STORE_FILE_NAME: &str = "auth.json";
 
@@ -40,6 +41,20 @@ impl AuthManager {
         Ok(())
     }
     
+    // 设置刷新令牌
+    pub fn set_refresh_token(&self, refresh_token: &str) -> Result<(), anyhow::Error> {
+        self.token_storage.set(REFRESH_TOKEN_STORAGE_KEY, serde_json::Value::String(refresh_token.to_string()));
+        self.token_storage.save()?;
+        Ok(())
+    }
+    
+    // 获取刷新令牌
+    pub fn get_refresh_token(&self) -> Option<String> {
+        self.token_storage
+            .get(REFRESH_TOKEN_STORAGE_KEY)
+            .and_then(|value| value.as_str().map(String::from))
+    }
+    
     // 获取 token
     pub fn get_token(&self) -> Option<String> {
         self.token_storage
@@ -52,6 +67,9 @@ impl AuthManager {
         if self.token_storage.has(TOKEN_STORAGE_KEY) {
             self.token_storage.delete(TOKEN_STORAGE_KEY);
         }
+        if self.token_storage.has(REFRESH_TOKEN_STORAGE_KEY) {
+            self.token_storage.delete(REFRESH_TOKEN_STORAGE_KEY);
+        }
         if self.token_storage.has(USER_INFO_KEY) {
             self.token_storage.delete(USER_INFO_KEY);
         }
@@ -95,12 +113,17 @@ impl AuthManager {
     
     // 检查 token 是否已过期
     pub fn is_token_expired(&self) -> bool {
+        self.is_token_expiring_within(0)
+    }
+    
+    // 检查 token 是否会在 seconds 秒内过期，用于提前刷新访问令牌
+    pub fn is_token_expiring_within(&self, seconds: i64) -> bool {
         if let Some(expiry) = self.get_token_expiry() {
             let current_time = std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_secs() as i64;
-            current_time > expiry
+            current_time + seconds > expiry
         } else {
             false
         }
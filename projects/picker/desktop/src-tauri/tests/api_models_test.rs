@@ -64,6 +64,7 @@ fn test_login_response() {
     
     let login_response = LoginResponse {
         token: "jwt-token-123".to_string(),
+        refresh_token: "refresh-token-123".to_string(),
         user: user_info.clone(),
     };
     
@@ -75,6 +76,7 @@ fn test_login_response() {
     // 反序列化
     let deserialized: LoginResponse = serde_json::from_str(&json).unwrap();
     assert_eq!(deserialized.token, "jwt-token-123");
+    assert_eq!(deserialized.refresh_token, "refresh-token-123");
     assert_eq!(deserialized.user.user_id, user_info.user_id);
 }
 
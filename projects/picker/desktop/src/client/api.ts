@@ -515,7 +515,7 @@ class APIService {
       if (!createOrderResponse || !createOrderResponse.token) {
         throw new Error('Create Order failed.')
       }
-      // "Order created successfully, Never close the client and wait for execution to complete!"
+      // 钱包订单返回时为 pending，链上确认后才能下载，可通过 getOrderDetails 查询进度
       return createOrderResponse.token;
     } catch (error) {
       const errorMessage = error instanceof Error ? 
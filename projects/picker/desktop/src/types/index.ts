@@ -45,6 +45,8 @@ export interface PickerListResponse {
 }
 
 export interface CreateOrderResponse {
+  order_id: string
+  status: string
   token: string
   message: string
 }
@@ -58,6 +60,8 @@ export interface OrderStatus {
   Pending: 'pending'
   Success: 'success'
   Expired: 'expired'
+  Refunded: 'refunded'
+  Cancelled: 'cancelled'
 }
 
 export interface OrderInfo {
@@ -0,0 +1,4 @@
+// 迁移文件通过 sqlx::migrate! 在编译期内嵌，目录变化时需要重新编译
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
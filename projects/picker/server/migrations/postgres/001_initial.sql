@@ -0,0 +1,50 @@
+-- 创建用户表
+-- user_password 加密存储使用的 salt 是 user_id<UUID>字符串与"openpick"字符串的组合，这样使得每个用户的密码都有独立的加密salt
+CREATE TABLE IF NOT EXISTS users (
+    user_id UUID PRIMARY KEY,
+    email TEXT UNIQUE NOT NULL,
+    user_name TEXT NOT NULL,
+    user_password TEXT NOT NULL,
+    user_type TEXT NOT NULL CHECK (user_type IN ('gen', 'dev')),
+    private_key TEXT NOT NULL,
+    wallet_address TEXT NOT NULL,
+    premium_balance BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+-- 创建Picker表
+CREATE TABLE IF NOT EXISTS pickers (
+    picker_id UUID PRIMARY KEY,
+    dev_user_id UUID NOT NULL REFERENCES users (user_id),
+    alias TEXT NOT NULL,
+    description TEXT NOT NULL,
+    price BIGINT NOT NULL,
+    image_path TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    version TEXT NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('active', 'inactive')),
+    download_count BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+-- 创建订单表
+CREATE TABLE IF NOT EXISTS orders (
+    order_id UUID PRIMARY KEY,
+    status TEXT NOT NULL CHECK (status IN ('pending', 'success', 'expired')),
+    user_id UUID NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+    picker_id UUID NOT NULL REFERENCES pickers (picker_id) ON DELETE CASCADE,
+    pay_type TEXT NOT NULL CHECK (pay_type IN ('wallet', 'premium')),
+    amount BIGINT NOT NULL,
+    tx_hash TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ
+);
+
+-- 创建索引
+CREATE INDEX IF NOT EXISTS idx_users_email ON users (email);
+CREATE INDEX IF NOT EXISTS idx_pickers_status ON pickers (status);
+CREATE INDEX IF NOT EXISTS idx_orders_user_id ON orders (user_id);
+CREATE INDEX IF NOT EXISTS idx_orders_status ON orders (status);
+CREATE INDEX IF NOT EXISTS idx_orders_picker_id ON orders (picker_id);
+CREATE INDEX IF NOT EXISTS idx_orders_pay_type ON orders (pay_type);
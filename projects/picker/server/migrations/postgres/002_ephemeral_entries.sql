@@ -0,0 +1,11 @@
+-- 短期数据（验证码、待注册信息、下载令牌），按 namespace 区分
+-- expires_at 为过期时间的 Unix 毫秒时间戳，由定时任务清理
+CREATE TABLE IF NOT EXISTS ephemeral_entries (
+    namespace TEXT NOT NULL,
+    entry_key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    expires_at BIGINT NOT NULL,
+    PRIMARY KEY (namespace, entry_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_ephemeral_entries_expires_at ON ephemeral_entries (namespace, expires_at);
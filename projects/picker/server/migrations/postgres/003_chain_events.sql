@@ -0,0 +1,28 @@
+-- PickerPayment 合约事件，(tx_hash, log_index) 唯一标识一条日志
+-- picker_id 为事件中的 bytes16，即 Picker 的 UUID；amount 为 wei 的十进制字符串
+-- order_id 为 PaymentProcessed 按交易哈希匹配到的订单
+CREATE TABLE IF NOT EXISTS chain_events (
+    tx_hash TEXT NOT NULL,
+    log_index BIGINT NOT NULL,
+    block_number BIGINT NOT NULL,
+    block_hash TEXT NOT NULL,
+    event_name TEXT NOT NULL,
+    picker_id UUID NOT NULL,
+    wallet_address TEXT,
+    amount TEXT,
+    order_id UUID,
+    created_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (tx_hash, log_index)
+);
+
+-- 索引进度：已处理到的区块号及其哈希，重启后从下一个区块继续，哈希不一致时说明发生了重组
+CREATE TABLE IF NOT EXISTS chain_cursors (
+    name TEXT PRIMARY KEY,
+    block_number BIGINT NOT NULL,
+    block_hash TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_chain_events_block_number ON chain_events (block_number);
+CREATE INDEX IF NOT EXISTS idx_chain_events_order_id ON chain_events (order_id);
+CREATE INDEX IF NOT EXISTS idx_orders_tx_hash ON orders (tx_hash);
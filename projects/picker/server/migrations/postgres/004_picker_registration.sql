@@ -0,0 +1,8 @@
+-- Picker 在 PickerPayment 合约上的注册状态
+-- chain_status: unregistered（未提交）/ pending（交易待确认）/ registered / failed / removed
+-- chain_tx_hash 为最近一次 registerPicker 或 removePicker 交易的哈希
+ALTER TABLE pickers ADD COLUMN IF NOT EXISTS chain_status TEXT NOT NULL DEFAULT 'unregistered'
+    CHECK (chain_status IN ('unregistered', 'pending', 'registered', 'failed', 'removed'));
+ALTER TABLE pickers ADD COLUMN IF NOT EXISTS chain_tx_hash TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_pickers_chain_status ON pickers (chain_status);
@@ -0,0 +1,21 @@
+-- 订单新增 refunded（已退款）与 cancelled（已取消）状态
+ALTER TABLE orders DROP CONSTRAINT IF EXISTS orders_status_check;
+ALTER TABLE orders ADD CONSTRAINT orders_status_check
+    CHECK (status IN ('pending', 'success', 'expired', 'refunded', 'cancelled'));
+
+-- 退款记录：Premium 订单退款时直接为 completed；
+-- 钱包订单退款关联合约 withdrawFunds 交易（tx_hash），交易确认后为 completed，回滚为 failed
+CREATE TABLE IF NOT EXISTS refunds (
+    refund_id UUID PRIMARY KEY,
+    order_id UUID NOT NULL REFERENCES orders (order_id) ON DELETE CASCADE,
+    requested_by UUID NOT NULL,
+    amount BIGINT NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('pending', 'completed', 'failed')),
+    tx_hash TEXT,
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_refunds_order_id ON refunds (order_id);
+CREATE INDEX IF NOT EXISTS idx_refunds_status ON refunds (status);
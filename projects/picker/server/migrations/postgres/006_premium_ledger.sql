@@ -0,0 +1,26 @@
+-- Premium 积分流水（复式记账）：每笔业务写入一组金额之和为 0 的分录
+-- amount 为带符号整数，正数为入账、负数为出账；user_id 为空表示平台账户
+CREATE TABLE IF NOT EXISTS premium_ledger (
+    entry_id UUID PRIMARY KEY,
+    transaction_id UUID NOT NULL,
+    user_id UUID,
+    order_id UUID,
+    kind TEXT NOT NULL CHECK (kind IN ('opening', 'purchase', 'dev_payout', 'platform_fee', 'free_grant', 'refund')),
+    amount BIGINT NOT NULL,
+    -- 用户分录写入后的余额，平台分录为空
+    balance_after BIGINT,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_premium_ledger_user_id ON premium_ledger (user_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_premium_ledger_order_id ON premium_ledger (order_id);
+CREATE INDEX IF NOT EXISTS idx_premium_ledger_transaction_id ON premium_ledger (transaction_id);
+
+-- 现有余额记为期初分录，与平台账户的对应分录共用以 user_id 作为的 transaction_id
+INSERT INTO premium_ledger (entry_id, transaction_id, user_id, order_id, kind, amount, balance_after, created_at)
+SELECT gen_random_uuid(), user_id, user_id, NULL, 'opening', premium_balance, premium_balance, NOW()
+FROM users WHERE premium_balance <> 0;
+
+INSERT INTO premium_ledger (entry_id, transaction_id, user_id, order_id, kind, amount, balance_after, created_at)
+SELECT gen_random_uuid(), user_id, NULL, NULL, 'opening', -premium_balance, NULL, NOW()
+FROM users WHERE premium_balance <> 0;
@@ -0,0 +1,10 @@
+-- 免费积分发放记录：每个用户每个周期最多一条，服务重启或多实例同时运行也不会重复发放
+-- period_index 为从注册时间起算的第几个周期（从 0 开始）
+CREATE TABLE IF NOT EXISTS premium_grants (
+    user_id UUID NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+    period_index BIGINT NOT NULL,
+    amount BIGINT NOT NULL,
+    transaction_id UUID NOT NULL,
+    granted_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (user_id, period_index)
+);
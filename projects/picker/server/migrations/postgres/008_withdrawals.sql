@@ -0,0 +1,24 @@
+-- 积分流水新增 withdrawal（开发者提现）分录类型
+ALTER TABLE premium_ledger DROP CONSTRAINT IF EXISTS premium_ledger_kind_check;
+ALTER TABLE premium_ledger ADD CONSTRAINT premium_ledger_kind_check
+    CHECK (kind IN ('opening', 'purchase', 'dev_payout', 'platform_fee', 'free_grant', 'refund', 'withdrawal'));
+
+-- 开发者提现：申请时扣除积分（requested），管理员链上转账后提交交易哈希（processing），
+-- 交易确认后为 completed，回滚为 failed；requested 状态下开发者可以取消（cancelled），failed/cancelled 时退回积分
+CREATE TABLE IF NOT EXISTS withdrawals (
+    withdrawal_id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+    amount BIGINT NOT NULL,
+    amount_usd BIGINT NOT NULL,
+    token_price TEXT NOT NULL,
+    amount_wei TEXT NOT NULL,
+    wallet_address TEXT NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('requested', 'processing', 'completed', 'failed', 'cancelled')),
+    tx_hash TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_withdrawals_user_id ON withdrawals (user_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_withdrawals_status ON withdrawals (status);
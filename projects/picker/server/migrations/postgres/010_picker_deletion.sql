@@ -0,0 +1,5 @@
+-- Picker 软删除：删除时置为 inactive 并记录 deleted_at，保留记录以便已有订单继续下载与退款
+-- 已删除的 Picker 不再出现在开发者列表中，也不能再修改或重新上架
+ALTER TABLE pickers ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_pickers_dev_user_id ON pickers (dev_user_id, created_at);
@@ -1,4 +1,5 @@
 -- 创建用户表
+-- user_password 加密存储使用的 salt 是 user_id<UUID>字符串与"openpick"字符串的组合，这样使得每个用户的密码都有独立的加密salt
 CREATE TABLE IF NOT EXISTS users (
     user_id BLOB PRIMARY KEY,
     email TEXT UNIQUE NOT NULL,
@@ -48,5 +49,5 @@ CREATE INDEX IF NOT EXISTS idx_users_email ON users (email);
 CREATE INDEX IF NOT EXISTS idx_pickers_status ON pickers (status);
 CREATE INDEX IF NOT EXISTS idx_orders_user_id ON orders (user_id);
 CREATE INDEX IF NOT EXISTS idx_orders_status ON orders (status);
-CREATE INDEX IF NOT EXISTS idx_orders_picker_id ON orders (picker_id)
-CREATE INDEX IF NOT EXISTS idx_orders_pay_type ON orders (pay_type)
+CREATE INDEX IF NOT EXISTS idx_orders_picker_id ON orders (picker_id);
+CREATE INDEX IF NOT EXISTS idx_orders_pay_type ON orders (pay_type);
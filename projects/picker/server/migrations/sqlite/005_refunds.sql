@@ -0,0 +1,45 @@
+-- 订单新增 refunded（已退款）与 cancelled（已取消）状态
+-- SQLite 不支持修改 CHECK 约束，重建 orders 表
+CREATE TABLE orders_new (
+    order_id BLOB PRIMARY KEY,
+    status TEXT NOT NULL CHECK (status IN ('pending', 'success', 'expired', 'refunded', 'cancelled')),
+    user_id BLOB NOT NULL,
+    picker_id BLOB NOT NULL,
+    pay_type TEXT NOT NULL CHECK (pay_type IN ('wallet', 'premium')),
+    amount INTEGER NOT NULL,
+    tx_hash TEXT,
+    created_at TEXT NOT NULL,
+    expires_at TEXT,
+    FOREIGN KEY (user_id) REFERENCES users (user_id) ON DELETE CASCADE,
+    FOREIGN KEY (picker_id) REFERENCES pickers (picker_id) ON DELETE CASCADE
+);
+
+INSERT INTO orders_new (order_id, status, user_id, picker_id, pay_type, amount, tx_hash, created_at, expires_at)
+SELECT order_id, status, user_id, picker_id, pay_type, amount, tx_hash, created_at, expires_at FROM orders;
+
+DROP TABLE orders;
+ALTER TABLE orders_new RENAME TO orders;
+
+CREATE INDEX IF NOT EXISTS idx_orders_user_id ON orders (user_id);
+CREATE INDEX IF NOT EXISTS idx_orders_status ON orders (status);
+CREATE INDEX IF NOT EXISTS idx_orders_picker_id ON orders (picker_id);
+CREATE INDEX IF NOT EXISTS idx_orders_pay_type ON orders (pay_type);
+CREATE INDEX IF NOT EXISTS idx_orders_tx_hash ON orders (tx_hash);
+
+-- 退款记录：Premium 订单退款时直接为 completed；
+-- 钱包订单退款关联合约 withdrawFunds 交易（tx_hash），交易确认后为 completed，回滚为 failed
+CREATE TABLE IF NOT EXISTS refunds (
+    refund_id BLOB PRIMARY KEY,
+    order_id BLOB NOT NULL,
+    requested_by BLOB NOT NULL,
+    amount INTEGER NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('pending', 'completed', 'failed')),
+    tx_hash TEXT,
+    reason TEXT,
+    created_at TEXT NOT NULL,
+    completed_at TEXT,
+    FOREIGN KEY (order_id) REFERENCES orders (order_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_refunds_order_id ON refunds (order_id);
+CREATE INDEX IF NOT EXISTS idx_refunds_status ON refunds (status);
@@ -0,0 +1,43 @@
+-- 积分流水新增 withdrawal（开发者提现）分录类型
+-- SQLite 不支持修改 CHECK 约束，重建 premium_ledger 表
+CREATE TABLE premium_ledger_new (
+    entry_id BLOB PRIMARY KEY,
+    transaction_id BLOB NOT NULL,
+    user_id BLOB,
+    order_id BLOB,
+    kind TEXT NOT NULL CHECK (kind IN ('opening', 'purchase', 'dev_payout', 'platform_fee', 'free_grant', 'refund', 'withdrawal')),
+    amount INTEGER NOT NULL,
+    balance_after INTEGER,
+    created_at TEXT NOT NULL
+);
+
+INSERT INTO premium_ledger_new (entry_id, transaction_id, user_id, order_id, kind, amount, balance_after, created_at)
+SELECT entry_id, transaction_id, user_id, order_id, kind, amount, balance_after, created_at FROM premium_ledger;
+
+DROP TABLE premium_ledger;
+ALTER TABLE premium_ledger_new RENAME TO premium_ledger;
+
+CREATE INDEX IF NOT EXISTS idx_premium_ledger_user_id ON premium_ledger (user_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_premium_ledger_order_id ON premium_ledger (order_id);
+CREATE INDEX IF NOT EXISTS idx_premium_ledger_transaction_id ON premium_ledger (transaction_id);
+
+-- 开发者提现：申请时扣除积分（requested），管理员链上转账后提交交易哈希（processing），
+-- 交易确认后为 completed，回滚为 failed；requested 状态下开发者可以取消（cancelled），failed/cancelled 时退回积分
+CREATE TABLE IF NOT EXISTS withdrawals (
+    withdrawal_id BLOB PRIMARY KEY,
+    user_id BLOB NOT NULL,
+    amount INTEGER NOT NULL,
+    amount_usd INTEGER NOT NULL,
+    token_price TEXT NOT NULL,
+    amount_wei TEXT NOT NULL,
+    wallet_address TEXT NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('requested', 'processing', 'completed', 'failed', 'cancelled')),
+    tx_hash TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    completed_at TEXT,
+    FOREIGN KEY (user_id) REFERENCES users (user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_withdrawals_user_id ON withdrawals (user_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_withdrawals_status ON withdrawals (status);
@@ -0,0 +1,18 @@
+-- Picker 版本历史：发布新版本时写入一条记录，并将 pickers.version / file_path 更新为该版本，
+-- 订单按 picker_id 下载，已购买旧版本的用户因此始终下载到最新版本
+CREATE TABLE IF NOT EXISTS picker_versions (
+    version_id BLOB PRIMARY KEY,
+    picker_id BLOB NOT NULL,
+    version TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    changelog TEXT,
+    created_at TEXT NOT NULL,
+    UNIQUE (picker_id, version),
+    FOREIGN KEY (picker_id) REFERENCES pickers (picker_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_picker_versions_picker_id ON picker_versions (picker_id, created_at);
+
+-- 已有 Picker 的当前文件记为首个版本，version_id 复用 picker_id
+INSERT INTO picker_versions (version_id, picker_id, version, file_path, changelog, created_at)
+SELECT picker_id, picker_id, version, file_path, NULL, created_at FROM pickers;
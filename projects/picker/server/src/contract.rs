@@ -0,0 +1,26 @@
+use alloy::primitives::FixedBytes;
+use alloy::sol;
+use uuid::Uuid;
+
+// PickerPayment 合约（contract/contracts/PickerPayment.sol）的调用与事件绑定
+// 合约中的 pickerId / devUserId 为 bytes16，与 UUID 的 16 个字节一一对应
+sol! {
+    #[sol(rpc)]
+    contract PickerPayment {
+        event PickerRegistered(bytes16 indexed pickerId, address indexed wallet);
+        event PickerRemoved(bytes16 indexed pickerId);
+        event PaymentProcessed(bytes16 indexed pickerId, uint256 amount);
+
+        function registerPicker(bytes16 pickerId, bytes16 devUserId, address devWalletAddress) external;
+        function removePicker(bytes16 pickerId) external;
+        function pay(bytes16 pickerId, bytes16 devUserId, address devWalletAddress) external payable;
+    }
+}
+
+pub fn uuid_to_bytes16(id: Uuid) -> FixedBytes<16> {
+    FixedBytes::from(id.into_bytes())
+}
+
+pub fn bytes16_to_uuid(bytes: FixedBytes<16>) -> Uuid {
+    Uuid::from_bytes(bytes.0)
+}
@@ -1,13 +1,45 @@
-use sqlx::{sqlite::SqlitePoolOptions, Pool, Sqlite};
-use tracing::info;
-use uuid::Uuid;
-use chrono::Utc;
-use crate::utils::{hash_password_with_user_id, generate_wallet};
+use sqlx::{
+    migrate::{MigrateError, Migrator},
+    postgres::PgPoolOptions,
+    sqlite::SqlitePoolOptions,
+    Pool, Postgres, Sqlite,
+};
+use tracing::{error, info};
+use crate::config::{Config, DatabaseBackend, DatabaseConfig};
+use crate::keyring::WalletKeyring;
+use crate::utils::PasswordHashing;
+use crate::seed::{apply_fixtures, dev_fixtures};
 use std::fs;
 use url::Url;
 use std::time::Duration;
 
 pub type DbPool = Pool<Sqlite>;
+pub type PgPool = Pool<Postgres>;
+
+// 数据库后端，由 config.toml 的 [database] 配置决定
+#[derive(Debug, Clone)]
+pub enum Database {
+    Sqlite(DbPool),
+    Postgres(PgPool),
+}
+
+impl From<DbPool> for Database {
+    fn from(pool: DbPool) -> Self {
+        Database::Sqlite(pool)
+    }
+}
+
+impl From<&DbPool> for Database {
+    fn from(pool: &DbPool) -> Self {
+        Database::Sqlite(pool.clone())
+    }
+}
+
+impl From<PgPool> for Database {
+    fn from(pool: PgPool) -> Self {
+        Database::Postgres(pool)
+    }
+}
 
 // 使用内存数据库
 // pub async fn create_pool() -> Result<DbPool, sqlx::Error> {
@@ -53,215 +85,114 @@ pub async fn create_pool() -> Result<DbPool, sqlx::Error> {
     Ok(pool)
 }
 
-pub async fn init_database(pool: &DbPool) -> Result<(), sqlx::Error> {
+/// 创建独立的内存数据库，供测试和夹具场景使用
+/// 内存数据库按连接隔离，因此连接池只保留一个连接
+pub async fn create_memory_pool() -> Result<DbPool, sqlx::Error> {
+    SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+}
+
+// 内嵌 migrations/<backend>/ 目录下按编号命名的 SQL 迁移文件（如 001_initial.sql）
+// 已执行的版本及其校验和记录在 _sqlx_migrations 表中
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite");
+pub static PG_MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// 按配置连接数据库
+pub async fn connect_database(config: &DatabaseConfig) -> Result<Database, sqlx::Error> {
+    match config.backend {
+        DatabaseBackend::Sqlite => match &config.url {
+            Some(url) => {
+                let pool = SqlitePoolOptions::new()
+                    .max_connections(config.max_connections)
+                    .acquire_timeout(Duration::from_secs(15))
+                    .connect(url)
+                    .await?;
+                info!("Connected to SQLite database: {}", url);
+                Ok(Database::Sqlite(pool))
+            }
+            None => Ok(Database::Sqlite(create_pool().await?)),
+        },
+        DatabaseBackend::Postgres => {
+            let url = config.url.as_deref().ok_or_else(|| {
+                sqlx::Error::Configuration("database.url is required for the postgres backend".into())
+            })?;
+            let pool = PgPoolOptions::new()
+                .max_connections(config.max_connections)
+                .acquire_timeout(Duration::from_secs(15))
+                .connect(url)
+                .await?;
+            info!("Connected to PostgreSQL database");
+            Ok(Database::Postgres(pool))
+        }
+    }
+}
+
+/// 按编号顺序执行尚未应用的迁移
+/// 已应用的迁移文件被修改（校验和不一致）或缺失时返回错误，拒绝启动
+pub async fn run_migrations(db: impl Into<Database>) -> Result<(), sqlx::Error> {
+    let db = db.into();
+    let (migrator, result) = match &db {
+        Database::Sqlite(pool) => (&MIGRATOR, MIGRATOR.run(pool).await),
+        Database::Postgres(pool) => (&PG_MIGRATOR, PG_MIGRATOR.run(pool).await),
+    };
+
+    result.map_err(|e| {
+        match &e {
+            MigrateError::VersionMismatch(version) => error!(
+                "Migration {} was modified after being applied (checksum drift), refusing to start",
+                version
+            ),
+            MigrateError::VersionMissing(version) => error!(
+                "Migration {} was applied but is missing from migrations/, refusing to start",
+                version
+            ),
+            _ => error!("Failed to run migrations: {}", e),
+        }
+        sqlx::Error::from(e)
+    })?;
+
+    for migration in migrator.iter() {
+        info!("Migration applied: {:03}_{}", migration.version, migration.description);
+    }
+
+    Ok(())
+}
+
+pub async fn init_database(db: impl Into<Database>) -> Result<(), sqlx::Error> {
+    let db = db.into();
+
     // 启用外键约束
-    sqlx::query("PRAGMA foreign_keys = ON")
-        .execute(pool)
-        .await?;
+    if let Database::Sqlite(pool) = &db {
+        sqlx::query("PRAGMA foreign_keys = ON")
+            .execute(pool)
+            .await?;
+    }
 
-    // 创建用户表
-    // user_password 加密存储使用的 salt 是 user_id<UUID>字符串与"openpick"字符串的组合，这样使得每个用户的密码都有独立的加密salt，密码存储更加安全
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS users (
-            user_id BLOB PRIMARY KEY,
-            email TEXT UNIQUE NOT NULL,
-            user_name TEXT NOT NULL,
-            user_password TEXT NOT NULL,
-            user_type TEXT NOT NULL CHECK (user_type IN ('gen', 'dev')),
-            private_key TEXT NOT NULL,
-            wallet_address TEXT NOT NULL,
-            premium_balance INTEGER DEFAULT 0,
-            created_at TEXT NOT NULL
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // 创建Picker表
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS pickers (
-            picker_id BLOB PRIMARY KEY,
-            dev_user_id BLOB NOT NULL,
-            alias TEXT NOT NULL,
-            description TEXT NOT NULL,
-            price INTEGER NOT NULL,
-            image_path TEXT NOT NULL,
-            file_path TEXT NOT NULL,
-            version TEXT NOT NULL,
-            status TEXT NOT NULL CHECK (status IN ('active', 'inactive')),
-            download_count INTEGER DEFAULT 0,
-            created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL,
-            FOREIGN KEY (dev_user_id) REFERENCES users (user_id)
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // 创建订单表
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS orders (
-            order_id BLOB PRIMARY KEY,
-            status TEXT NOT NULL CHECK (status IN ('pending', 'success', 'expired')),
-            user_id BLOB NOT NULL,
-            picker_id BLOB NOT NULL,
-            pay_type TEXT NOT NULL CHECK (pay_type IN ('wallet', 'premium')),
-            amount INTEGER NOT NULL,
-            tx_hash TEXT,
-            created_at TEXT NOT NULL,
-            expires_at TEXT,
-            FOREIGN KEY (user_id) REFERENCES users (user_id) ON DELETE CASCADE,
-            FOREIGN KEY (picker_id) REFERENCES pickers (picker_id) ON DELETE CASCADE
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // 创建索引
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_users_email ON users (email)")
-        .execute(pool)
-        .await?;
-    
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_pickers_status ON pickers (status)")
-        .execute(pool)
-        .await?;
-    
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_orders_user_id ON orders (user_id)")
-        .execute(pool)
-        .await?;
-    
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_orders_picker_id ON orders (picker_id)")
-        .execute(pool)
-        .await?;
-    
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_orders_status ON orders (status)")
-        .execute(pool)
-        .await?;
-    
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_orders_pay_type ON orders (pay_type)")
-        .execute(pool)
-        .await?;
+    // 执行数据库迁移
+    run_migrations(db).await?;
 
-    insert_test_data(pool).await?;
+    // 种子数据不在此写入，由 seed 模块按 seed.profile 决定
     Ok(())
 }
 
-/// 插入测试数据到数据库，默认执行，自动被调用
-pub async fn insert_test_data(pool: &DbPool) -> Result<(), sqlx::Error> {
+/// 插入内置开发数据（testdata@openpick.org 账号、测试 Picker 与订单）
+/// 仅在 seed.profile = dev 时由 seed::seed_database 调用，生产环境不会写入
+pub async fn insert_test_data(db: impl Into<Database>) -> Result<(), sqlx::Error> {
     info!("Inserting test data...");
 
-    // 使用固定的UUID以确保幂等性
-    let test_user_id = Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000").unwrap();
-    let test_picker_id = Uuid::parse_str("550e8400-e29b-41d4-a716-446655440001").unwrap();
-    let test_order_id = Uuid::parse_str("550e8400-e29b-41d4-a716-446655440002").unwrap();
-    
     // 配置信息（使用默认值）
-    let salt = "openpick";
-    let master_key = "openpickopenpickopenpickopenpick";
-    let nonce = "openpickopen";
-    
-    // 1. 创建测试用户
-    let test_email = "testdata@openpick.org";
-    let test_password = "testpassword";
-    let test_user_name: &'static str = "Test Data User";
-    let test_user_premium_balance = 1000;
-    
-    // 哈希密码
-    let hashed_password = hash_password_with_user_id(test_password, test_user_id, salt);
-    
-    // 生成钱包
-    let (private_key, wallet_address) = generate_wallet(master_key, nonce);
-    
-    let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
-    
-    // 插入测试用户
-    sqlx::query(
-        r#"
-        INSERT OR IGNORE INTO users (
-            user_id, email, user_name, user_password, user_type, 
-            private_key, wallet_address, premium_balance, created_at
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
-        "#,
-    )
-    .bind(test_user_id)
-    .bind(test_email)
-    .bind(test_user_name)
-    .bind(hashed_password)
-    .bind("dev") // 设置为开发者类型，可以创建picker
-    .bind(private_key)
-    .bind(&wallet_address)
-    .bind(test_user_premium_balance) // premium_balance
-    .bind(&now)
-    .execute(pool)
-    .await?;
-
-    info!("Inserted test user: {} ({})", test_user_name, test_email);
-
-    // 2. 创建测试Picker
-    let test_picker_alias = "testpicker";
-    let test_picker_description = "testpicker description";
-    let test_picker_price = 1; // 示例价格
-    
-    // 插入测试Picker
-    sqlx::query(
-        r#"
-        INSERT OR IGNORE INTO pickers (
-            picker_id, dev_user_id, alias, description, price, 
-            image_path, file_path, version, status, download_count, 
-            created_at, updated_at
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-        "#,
-    )
-    .bind(test_picker_id)
-    .bind(test_user_id)
-    .bind(test_picker_alias)
-    .bind(test_picker_description)
-    .bind(test_picker_price)
-    .bind("test_image.jpg") // 示例图片路径
-    .bind("test_picker.zip") // 示例文件路径
-    .bind("1.0.0") // 版本号
-    .bind("active") // 状态
-    .bind(0) // download_count
-    .bind(&now)
-    .bind(&now)
-    .execute(pool)
-    .await?;
-
-    info!("Inserted test picker: {} - {}", test_picker_alias, test_picker_description);
-
-    // 3. 创建测试订单
-    // 插入测试订单
-    sqlx::query(
-        r#"
-        INSERT OR IGNORE INTO orders (
-            order_id, status, user_id, picker_id, pay_type, 
-            amount, tx_hash, created_at, expires_at
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
-        "#,
-    )
-    .bind(test_order_id)
-    .bind("pending") // 订单状态
-    .bind(test_user_id)
-    .bind(test_picker_id)
-    .bind("wallet") // 支付方式
-    .bind(test_picker_price) // 金额
-    .bind(Option::<String>::None) // tx_hash (暂时为空)
-    .bind(&now)
-    .bind(Option::<String>::None) // expires_at (暂时为空)
-    .execute(pool)
-    .await?;
-
-    info!("Inserted test order: {} (wallet payment, pending)", test_order_id);
+    let config = Config::load();
+    let hashing = PasswordHashing::from(&config.password);
+    let keyring = WalletKeyring::from_config(&config.password).map_err(|e| sqlx::Error::Configuration(Box::new(e)))?;
+
+    let fixtures = dev_fixtures();
+    apply_fixtures(&db.into(), &fixtures, &hashing, &keyring).await?;
 
+    let user = &fixtures.users[0];
     info!("Test data insertion completed successfully!");
-    info!("Test user credentials - Email: {}, Password: {}", test_email, test_password);
-    info!("Test user wallet address: {}", wallet_address);
+    info!("Test user credentials - Email: {}, Password: {}", user.email, user.password);
 
     Ok(())
 }
@@ -273,6 +204,7 @@ pub async fn insert_test_data(pool: &DbPool) -> Result<(), sqlx::Error> {
         use super::*;
         use serial_test::serial;
         use sqlx::Row;
+        use uuid::Uuid;
 
         #[tokio::test]
         #[serial]
@@ -1057,4 +989,65 @@ pub async fn insert_test_data(pool: &DbPool) -> Result<(), sqlx::Error> {
         let status = result.unwrap();
         assert_eq!(status, OrderStatus::Pending, "Expected status to be Pending, but got {:?}", status);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_run_migrations_records_versions() {
+        let pool = create_memory_pool().await.expect("Failed to create in-memory pool");
+        run_migrations(&pool).await.expect("Failed to run migrations");
+
+        let versions: Vec<i64> = sqlx::query_scalar("SELECT version FROM _sqlx_migrations ORDER BY version")
+            .fetch_all(&pool)
+            .await
+            .expect("Failed to query applied migrations");
+        let expected: Vec<i64> = MIGRATOR.iter().map(|m| m.version).collect();
+        assert_eq!(versions, expected, "Every embedded migration should be recorded");
+
+        let result = sqlx::query("SELECT name FROM sqlite_master WHERE type='table' AND name='orders'")
+            .fetch_one(&pool)
+            .await;
+        assert!(result.is_ok(), "Orders table should be created by migrations");
+    }
+
+    #[tokio::test]
+    async fn test_init_database_does_not_seed() {
+        let pool = create_memory_pool().await.expect("Failed to create in-memory pool");
+        init_database(&pool).await.expect("Failed to init database");
+
+        let users: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(users, 0, "init_database should not insert any seed data");
+    }
+
+    #[tokio::test]
+    async fn test_run_migrations_idempotent() {
+        let pool = create_memory_pool().await.expect("Failed to create in-memory pool");
+        run_migrations(&pool).await.expect("First run should succeed");
+        run_migrations(&pool).await.expect("Second run should be a no-op");
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM _sqlx_migrations")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count as usize, MIGRATOR.iter().count());
+    }
+
+    #[tokio::test]
+    async fn test_run_migrations_checksum_drift() {
+        let pool = create_memory_pool().await.expect("Failed to create in-memory pool");
+        run_migrations(&pool).await.expect("Failed to run migrations");
+
+        // 模拟已应用的迁移文件被修改
+        sqlx::query("UPDATE _sqlx_migrations SET checksum = X'00' WHERE version = 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let result = run_migrations(&pool).await;
+        match result {
+            Err(sqlx::Error::Migrate(e)) => assert!(matches!(*e, MigrateError::VersionMismatch(1))),
+            other => panic!("Expected checksum mismatch, got {:?}", other),
+        }
+    }
+}
@@ -6,13 +6,12 @@ use axum::{
 };
 use serde::Deserialize;
 use utoipa::ToSchema;
-use tokio::fs::File;
-use tokio_util::io::ReaderStream;
-use tracing::info;
+use tracing::{error, info};
 use chrono::Utc;
 
 use crate::config::AppState;
 use crate::models::{Order, OrderStatus, Picker};
+use crate::storage::StorageError;
 use crate::utils::AppError;
 
 // 下载请求的查询参数
@@ -45,22 +44,22 @@ pub async fn download(
     info!("Download request received with token: {}", query.token);
     // 1. 验证token
     let token = query.token;
-    let order_id = {
-        let mut tokens = state.download_tokens.lock().map_err(|_| AppError::InternalServerError)?;
-        let download_token = tokens.remove(&token).ok_or(AppError::Unauthorized("Invalid download token".to_string()))?;
-        
-        // 2. 检查token是否过期
-        if download_token.is_expired() {
-            return Err(AppError::Unauthorized("Download token is expired".to_string()));
-        }
-        
-        download_token.order_id
-    };
+    let download_token = state
+        .take_download_token(&token)
+        .await
+        .map_err(|_| AppError::DatabaseError)?
+        .ok_or(AppError::Unauthorized("Invalid download token".to_string()))?;
+
+    // 2. 检查token是否过期
+    if download_token.is_expired() {
+        return Err(AppError::Unauthorized("Download token is expired".to_string()));
+    }
+    let order_id = download_token.order_id;
     info!("Download request for order ID: {}", order_id);
     // 3. 获取订单信息
-    let order_result = sqlx::query_as("SELECT * FROM orders WHERE order_id = ?")
-        .bind(order_id)
-        .fetch_optional(&state.db)
+    let order_result = state
+        .orders
+        .find_by_id(order_id)
         .await
         .map_err(|_| AppError::DatabaseError);
     
@@ -80,9 +79,9 @@ pub async fn download(
     }
     
     // 5. 获取Picker信息
-    let picker_result = sqlx::query_as("SELECT * FROM pickers WHERE picker_id = ?")
-        .bind(order.picker_id)
-        .fetch_optional(&state.db)
+    let picker_result = state
+        .pickers
+        .find_by_id(order.picker_id)
         .await
         .map_err(|_| AppError::DatabaseError);
     
@@ -97,31 +96,39 @@ pub async fn download(
     };
     info!("Download request for order ID: {}, picker ID: {}, file path: {}", order_id, order.picker_id, picker.file_path);
     
-    // 6. 检查文件是否存在
-    let file_path = &picker.file_path;
-    if !tokio::fs::metadata(file_path).await.is_ok() {
-        return Err(AppError::NotFound("File not found".to_string()));
-    }
+    // 6. 从文件存储读取（订单只关联 picker_id，购买过旧版本的用户同样下载最新版本）
+    let blob = match state.blobs.get(&picker.file_path).await {
+        Ok(blob) => blob,
+        // 迁移时找不到原文件的旧记录保存的仍是相对路径，同样视为文件不存在
+        Err(StorageError::NotFound(_) | StorageError::Invalid(_)) => {
+            return Err(AppError::NotFound("File not found".to_string()))
+        }
+        Err(e) => {
+            error!("Failed to read picker file {}: {}", picker.file_path, e);
+            return Err(AppError::InternalServerError);
+        }
+    };
     
-    // 7. 打开文件
-    let file = File::open(file_path).await.map_err(|_| AppError::InternalServerError)?;
-    let stream = ReaderStream::new(file);
-    let body = AxumBody::from_stream(stream);
+    // 7. 以流式返回文件内容
+    let body = AxumBody::from_stream(blob.stream);
     info!("Download request update times");
     // 8. 更新下载次数
-    sqlx::query("UPDATE pickers SET download_count = download_count + 1 WHERE picker_id = ?")
-        .bind(picker.picker_id)
-        .execute(&state.db)
+    state
+        .pickers
+        .increment_download_count(picker.picker_id)
         .await
         .map_err(|_| AppError::DatabaseError)?;
     
     // 9. 设置响应头
     let mut headers = HeaderMap::new();
     headers.insert("Content-Type", "application/octet-stream".parse().unwrap());
+    if let Some(size) = blob.size {
+        headers.insert("Content-Length", size.into());
+    }
     // 重新格式化文件名
     let download_date = Utc::now().format("%Y-%m-%d");
     // 从文件路径中提取文件名
-    let original_filename = picker.file_path.split('/').last().unwrap_or("picker.exe");
+    let original_filename = picker.file_path.split('/').next_back().unwrap_or("picker.exe");
     // 分离文件名和扩展名
     let mut parts: Vec<&str> = original_filename.split('.').collect();
     let extension = if parts.len() > 1 {
@@ -134,6 +141,10 @@ pub async fn download(
     let filename = format!("{}_{}.{}", base_name, download_date, extension);
     info!("Downloading file: {}", filename);
     headers.insert("Content-Disposition", format!("attachment; filename=\"{}\"", filename).parse().unwrap());
+    // 客户端据此判断本地安装的版本是否需要更新
+    if let Ok(version) = picker.version.parse() {
+        headers.insert("X-Picker-Version", version);
+    }
     
     Ok((headers, body).into_response())
 }
@@ -141,18 +152,22 @@ pub async fn download(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils_tests::create_test_app_state;
+    use crate::ephemeral::DOWNLOAD_TOKENS;
+    use crate::utils_tests::{create_test_app_state, test_pool};
     use crate::models::{DownloadToken, PayType};
+    use crate::storage::file_content_key;
     use axum::extract::{Query, State};
     use chrono::{Duration, Utc};
     use serial_test::serial;
     use uuid::Uuid;
 
-    async fn create_test_file(path: &str, content: &[u8]) -> std::io::Result<()> {
-        if let Some(parent) = std::path::Path::new(path).parent() {
-            tokio::fs::create_dir_all(parent).await?;
-        }
-        tokio::fs::write(path, content).await
+    // 将测试文件写入文件存储，返回其 key
+    async fn create_test_file(state: &AppState, content: &[u8]) -> String {
+        let source = tempfile::NamedTempFile::new().unwrap();
+        tokio::fs::write(source.path(), content).await.unwrap();
+        let key = file_content_key(source.path(), "zip").await.unwrap();
+        state.blobs.put_file(&key, source.path()).await.unwrap();
+        key
     }
 
     #[tokio::test]
@@ -164,10 +179,9 @@ mod tests {
         let dev_user_id = Uuid::new_v4();
         let order_id = Uuid::new_v4();
         let token = "test_token_123".to_string();
-        let file_path = "test_files/test_picker.exe";
 
         // 创建测试文件
-        create_test_file(file_path, b"test file content").await.unwrap();
+        let file_path = create_test_file(&state, b"test file content").await;
 
         // 创建测试数据
         sqlx::query(
@@ -178,7 +192,7 @@ mod tests {
         )
         .bind(user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -190,21 +204,22 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
         sqlx::query(
             r#"
             INSERT INTO pickers (picker_id, dev_user_id, alias, description, price, image_path, file_path, version, status, download_count, created_at, updated_at)
-            VALUES (?, ?, 'Test Picker', 'Test Description', 500, 'test.jpg', 'test_files/test_picker.exe', '1.0', 'active', 0, ?, ?)
+            VALUES (?, ?, 'Test Picker', 'Test Description', 500, 'test.jpg', ?, '1.0', 'active', 0, ?, ?)
             "#,
         )
         .bind(picker_id)
         .bind(dev_user_id)
+        .bind(&file_path)
         .bind(Utc::now().to_rfc3339())
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -221,7 +236,7 @@ mod tests {
         .bind(&PayType::Premium)
         .bind(Utc::now().to_rfc3339())
         .bind((Utc::now() + Duration::minutes(30)).to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -231,7 +246,7 @@ mod tests {
             order_id,
             expires_at: Utc::now() + Duration::minutes(10),
         };
-        state.download_tokens.lock().unwrap().insert(token.clone(), download_token);
+        state.save_download_token(&download_token).await.unwrap();
 
         let query = DownloadQuery { token: token.clone() };
         let result = download(State(state.clone()), Query(query)).await;
@@ -247,22 +262,24 @@ mod tests {
             }
         }
         assert!(result.is_ok(), "Download should succeed but got error: {:?}", result.err());
+        let response = result.unwrap().into_response();
+        assert_eq!(response.headers()["Content-Length"], "17");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"test file content");
 
         // 验证token已被移除
-        let tokens = state.download_tokens.lock().unwrap();
-        assert!(!tokens.contains_key(&token));
+        assert!(state.ephemeral.get(DOWNLOAD_TOKENS, &token).await.unwrap().is_none());
 
         // 验证下载次数已更新
         let picker: Picker = sqlx::query_as("SELECT * FROM pickers WHERE picker_id = ?")
             .bind(picker_id)
-            .fetch_one(&state.db)
+            .fetch_one(test_pool(&state))
             .await
             .unwrap();
         assert_eq!(picker.download_count, 1);
 
         // 清理测试文件
-        let _ = tokio::fs::remove_file(file_path).await;
-        let _ = tokio::fs::remove_dir("test_files").await;
+        state.blobs.delete(&file_path).await.unwrap();
     }
 
     #[tokio::test]
@@ -298,7 +315,7 @@ mod tests {
             order_id,
             expires_at: Utc::now() - Duration::minutes(10), // 已过期
         };
-        state.download_tokens.lock().unwrap().insert(token.clone(), download_token);
+        state.save_download_token(&download_token).await.unwrap();
 
         let query = DownloadQuery { token: token.clone() };
         let result = download(State(state.clone()), Query(query)).await;
@@ -314,8 +331,7 @@ mod tests {
         }
 
         // 验证过期token已被移除
-        let tokens = state.download_tokens.lock().unwrap();
-        assert!(!tokens.contains_key(&token));
+        assert!(state.ephemeral.get(DOWNLOAD_TOKENS, &token).await.unwrap().is_none());
     }
 
     #[tokio::test]
@@ -331,7 +347,7 @@ mod tests {
             order_id,
             expires_at: Utc::now() + Duration::minutes(10),
         };
-        state.download_tokens.lock().unwrap().insert(token.clone(), download_token);
+        state.save_download_token(&download_token).await.unwrap();
 
         let query = DownloadQuery { token };
         let result = download(State(state.clone()), Query(query)).await;
@@ -366,7 +382,7 @@ mod tests {
         )
         .bind(user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -378,7 +394,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -392,7 +408,7 @@ mod tests {
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -410,7 +426,7 @@ mod tests {
         .bind(&PayType::Wallet)
         .bind(Utc::now().to_rfc3339())
         .bind((Utc::now() + Duration::minutes(30)).to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -421,7 +437,7 @@ mod tests {
             expires_at: Utc::now() + Duration::minutes(10),
         };
         info!("Inserting download token: {}, order_id: {}", token, order_id);
-        state.download_tokens.lock().unwrap().insert(token.clone(), download_token);
+        state.save_download_token(&download_token).await.unwrap();
 
         let query = DownloadQuery { token };
         let result = download(State(state.clone()), Query(query)).await;
@@ -446,7 +462,6 @@ mod tests {
         let dev_user_id = Uuid::new_v4();
         let order_id = Uuid::new_v4();
         let token = "valid_token_123".to_string();
-        let file_path = "test_files/test_picker.exe";
 
         // 创建测试用户
         sqlx::query(
@@ -457,7 +472,7 @@ mod tests {
         )
         .bind(user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -470,12 +485,12 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
         // 创建测试文件
-        create_test_file(file_path, b"test file content").await.unwrap();
+        let file_path = create_test_file(&state, b"test file content").await;
 
         // 创建Picker
         sqlx::query(
@@ -486,10 +501,10 @@ mod tests {
         )
         .bind(picker_id)
         .bind(dev_user_id)
-        .bind(file_path)  // 使用模拟的文件路径
+        .bind(&file_path)
         .bind(Utc::now().to_rfc3339())
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -507,14 +522,14 @@ mod tests {
         .bind(&PayType::Premium)
         .bind(Utc::now().to_rfc3339())
         .bind((Utc::now() + Duration::minutes(30)).to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
         // 移除Picker以模拟Picker不存在的情况
         sqlx::query("DELETE FROM pickers WHERE picker_id = ?")
             .bind(picker_id)
-            .execute(&state.db)
+            .execute(test_pool(&state))
             .await
             .unwrap();
 
@@ -524,7 +539,7 @@ mod tests {
             order_id,
             expires_at: Utc::now() + Duration::minutes(10),
         };
-        state.download_tokens.lock().unwrap().insert(token.clone(), download_token);
+        state.save_download_token(&download_token).await.unwrap();
 
         let query = DownloadQuery { token };
         let result = download(State(state.clone()), Query(query)).await;
@@ -546,8 +561,7 @@ mod tests {
         }
 
         // 清理测试文件
-        let _ = tokio::fs::remove_file(file_path).await;
-        let _ = tokio::fs::remove_dir_all("test_files").await;
+        state.blobs.delete(&file_path).await.unwrap();
     }
 
     #[tokio::test]
@@ -569,7 +583,7 @@ mod tests {
         )
         .bind(user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -581,7 +595,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -595,7 +609,7 @@ mod tests {
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -611,7 +625,7 @@ mod tests {
         .bind(picker_id)
         .bind(&PayType::Premium)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -621,7 +635,7 @@ mod tests {
             order_id,
             expires_at: Utc::now() + Duration::minutes(10),
         };
-        state.download_tokens.lock().unwrap().insert(token.clone(), download_token);
+        state.save_download_token(&download_token).await.unwrap();
 
         let query = DownloadQuery { token };
         let result = download(State(state.clone()), Query(query)).await;
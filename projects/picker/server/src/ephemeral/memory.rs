@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use super::EphemeralStore;
+
+type Entries = HashMap<(String, String), (String, DateTime<Utc>)>;
+
+// 进程内存储，服务重启后数据丢失，仅适用于单实例开发环境和测试
+#[derive(Clone, Default)]
+pub struct MemoryEphemeralStore {
+    entries: Arc<Mutex<Entries>>,
+}
+
+impl MemoryEphemeralStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().expect("ephemeral store lock poisoned")
+    }
+}
+
+fn entry_key(namespace: &str, key: &str) -> (String, String) {
+    (namespace.to_string(), key.to_string())
+}
+
+#[async_trait]
+impl EphemeralStore for MemoryEphemeralStore {
+    async fn put(&self, namespace: &str, key: &str, value: &str, expires_at: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        self.lock().insert(entry_key(namespace, key), (value.to_string(), expires_at));
+        Ok(())
+    }
+
+    async fn get(&self, namespace: &str, key: &str) -> Result<Option<String>, sqlx::Error> {
+        Ok(self.lock().get(&entry_key(namespace, key)).map(|(value, _)| value.clone()))
+    }
+
+    async fn take(&self, namespace: &str, key: &str) -> Result<Option<String>, sqlx::Error> {
+        Ok(self.lock().remove(&entry_key(namespace, key)).map(|(value, _)| value))
+    }
+
+    async fn remove(&self, namespace: &str, key: &str) -> Result<(), sqlx::Error> {
+        self.lock().remove(&entry_key(namespace, key));
+        Ok(())
+    }
+
+    async fn compare_and_swap(
+        &self,
+        namespace: &str,
+        key: &str,
+        expected: Option<&str>,
+        value: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error> {
+        let mut entries = self.lock();
+        let entry_key = entry_key(namespace, key);
+        if entries.get(&entry_key).map(|(current, _)| current.as_str()) != expected {
+            return Ok(false);
+        }
+        entries.insert(entry_key, (value.to_string(), expires_at));
+        Ok(true)
+    }
+
+    async fn purge_expired(&self, namespace: &str, now: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+        let mut entries = self.lock();
+        let before = entries.len();
+        entries.retain(|(ns, _), (_, expires_at)| ns != namespace || *expires_at > now);
+        Ok((before - entries.len()) as u64)
+    }
+
+    async fn count(&self, namespace: &str) -> Result<i64, sqlx::Error> {
+        Ok(self.lock().keys().filter(|(ns, _)| ns == namespace).count() as i64)
+    }
+}
@@ -0,0 +1,256 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::config::{EphemeralBackend, EphemeralConfig};
+use crate::database::Database;
+
+mod memory;
+mod sql;
+
+pub use memory::MemoryEphemeralStore;
+pub use sql::SqlEphemeralStore;
+
+// 各类短期数据使用的命名空间
+pub const VERIFICATION_CODES: &str = "verification_code";
+pub const PENDING_REGISTRATIONS: &str = "pending_registration";
+pub const DOWNLOAD_TOKENS: &str = "download_token";
+pub const VERIFICATION_FAILURES: &str = "verification_failure";
+pub const REFRESH_TOKENS: &str = "refresh_token";
+pub const REVOKED_ACCESS_TOKENS: &str = "revoked_access_token";
+pub const PASSWORD_RESET_CODES: &str = "password_reset_code";
+pub const SESSIONS_REVOKED: &str = "sessions_revoked";
+pub const PRICE_QUOTES: &str = "price_quote";
+
+// 带过期时间的键值存储，用于验证码、待注册信息、下载令牌等短期数据
+// 默认使用主数据库中的 ephemeral_entries 表，服务重启或多实例部署时数据仍然有效；
+// 实现该 trait 即可接入 Redis 等外部存储
+// 读取时不过滤已过期的条目，由调用方判断过期，并由定时任务调用 purge_expired 清理
+#[async_trait]
+pub trait EphemeralStore: Send + Sync {
+    /// 写入或覆盖一个条目
+    async fn put(&self, namespace: &str, key: &str, value: &str, expires_at: DateTime<Utc>) -> Result<(), sqlx::Error>;
+    async fn get(&self, namespace: &str, key: &str) -> Result<Option<String>, sqlx::Error>;
+    /// 读取并删除一个条目，并发调用时只有一个调用方能取到值
+    async fn take(&self, namespace: &str, key: &str) -> Result<Option<String>, sqlx::Error>;
+    async fn remove(&self, namespace: &str, key: &str) -> Result<(), sqlx::Error>;
+    /// 当前值等于 expected（None 表示条目不存在）时写入新值并返回 true，否则不做修改并返回 false
+    async fn compare_and_swap(
+        &self,
+        namespace: &str,
+        key: &str,
+        expected: Option<&str>,
+        value: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error>;
+    /// 删除在 now 之前过期的条目，返回删除数量
+    async fn purge_expired(&self, namespace: &str, now: DateTime<Utc>) -> Result<u64, sqlx::Error>;
+    async fn count(&self, namespace: &str) -> Result<i64, sqlx::Error>;
+}
+
+fn decode_error(e: serde_json::Error) -> sqlx::Error {
+    sqlx::Error::Decode(Box::new(e))
+}
+
+// 以 JSON 形式读写结构化数据
+impl dyn EphemeralStore {
+    pub async fn put_json<T: Serialize + Sync>(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: &T,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let value = serde_json::to_string(value).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+        self.put(namespace, key, &value, expires_at).await
+    }
+
+    pub async fn get_json<T: DeserializeOwned>(&self, namespace: &str, key: &str) -> Result<Option<T>, sqlx::Error> {
+        self.get(namespace, key)
+            .await?
+            .map(|value| serde_json::from_str(&value).map_err(decode_error))
+            .transpose()
+    }
+
+    pub async fn take_json<T: DeserializeOwned>(&self, namespace: &str, key: &str) -> Result<Option<T>, sqlx::Error> {
+        self.take(namespace, key)
+            .await?
+            .map(|value| serde_json::from_str(&value).map_err(decode_error))
+            .transpose()
+    }
+
+    /// 读取条目并用 update 计算新值后写回，期间条目被并发修改时重新读取再计算，返回最终写入的值
+    pub async fn update_json<T, F>(
+        &self,
+        namespace: &str,
+        key: &str,
+        expires_at: DateTime<Utc>,
+        mut update: F,
+    ) -> Result<T, sqlx::Error>
+    where
+        T: Serialize + DeserializeOwned + Sync,
+        F: FnMut(Option<T>) -> T + Send,
+    {
+        loop {
+            let current = self.get(namespace, key).await?;
+            let parsed = current
+                .as_deref()
+                .map(|value| serde_json::from_str(value).map_err(decode_error))
+                .transpose()?;
+            let updated = update(parsed);
+            let value = serde_json::to_string(&updated).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+            if self
+                .compare_and_swap(namespace, key, current.as_deref(), &value, expires_at)
+                .await?
+            {
+                return Ok(updated);
+            }
+        }
+    }
+}
+
+/// 按 [ephemeral] 配置创建短期数据存储
+pub fn build_ephemeral_store(config: &EphemeralConfig, db: &Database) -> Arc<dyn EphemeralStore> {
+    match config.backend {
+        EphemeralBackend::Database => match db {
+            Database::Sqlite(pool) => Arc::new(SqlEphemeralStore::new(pool.clone())),
+            Database::Postgres(pool) => Arc::new(SqlEphemeralStore::new(pool.clone())),
+        },
+        EphemeralBackend::Memory => Arc::new(MemoryEphemeralStore::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{create_memory_pool, init_database};
+    use chrono::Duration;
+    use sqlx::postgres::PgPoolOptions;
+
+    // 各存储实现共用的行为校验
+    async fn exercise_store(store: Arc<dyn EphemeralStore>) {
+        let now = Utc::now();
+
+        store.put("ns", "a", "1", now + Duration::minutes(10)).await.unwrap();
+        store.put("ns", "b", "2", now - Duration::minutes(10)).await.unwrap();
+        store.put("other", "a", "3", now - Duration::minutes(10)).await.unwrap();
+        assert_eq!(store.count("ns").await.unwrap(), 2);
+
+        // 已过期但尚未清理的条目仍可读取
+        assert_eq!(store.get("ns", "b").await.unwrap().as_deref(), Some("2"));
+        assert_eq!(store.get("ns", "missing").await.unwrap(), None);
+
+        // 覆盖写入
+        store.put("ns", "a", "10", now + Duration::minutes(10)).await.unwrap();
+        assert_eq!(store.get("ns", "a").await.unwrap().as_deref(), Some("10"));
+
+        // 清理只影响指定命名空间
+        assert_eq!(store.purge_expired("ns", now).await.unwrap(), 1);
+        assert_eq!(store.count("ns").await.unwrap(), 1);
+        assert_eq!(store.count("other").await.unwrap(), 1);
+
+        assert_eq!(store.take("ns", "a").await.unwrap().as_deref(), Some("10"));
+        assert_eq!(store.take("ns", "a").await.unwrap(), None);
+
+        store.remove("other", "a").await.unwrap();
+        assert_eq!(store.count("other").await.unwrap(), 0);
+
+        // 比较并交换：只有当前值与预期一致时才写入
+        assert!(store.compare_and_swap("cas", "k", None, "1", now + Duration::minutes(1)).await.unwrap());
+        assert!(!store.compare_and_swap("cas", "k", None, "2", now + Duration::minutes(1)).await.unwrap());
+        assert!(!store.compare_and_swap("cas", "k", Some("0"), "2", now + Duration::minutes(1)).await.unwrap());
+        assert!(store.compare_and_swap("cas", "k", Some("1"), "2", now + Duration::minutes(1)).await.unwrap());
+        assert_eq!(store.get("cas", "k").await.unwrap().as_deref(), Some("2"));
+
+        // 并发的读改写不会丢失更新
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let store = store.clone();
+                tokio::spawn(async move {
+                    store
+                        .update_json("counter", "k", now + Duration::minutes(1), |count: Option<u32>| count.unwrap_or(0) + 1)
+                        .await
+                        .unwrap()
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        let count: Option<u32> = store.get_json("counter", "k").await.unwrap();
+        assert_eq!(count, Some(8));
+
+        // JSON 读写
+        let value = vec!["x".to_string(), "y".to_string()];
+        store.put_json("json", "k", &value, now + Duration::minutes(1)).await.unwrap();
+        let loaded: Option<Vec<String>> = store.get_json("json", "k").await.unwrap();
+        assert_eq!(loaded, Some(value));
+        let taken: Option<Vec<String>> = store.take_json("json", "k").await.unwrap();
+        assert!(taken.is_some());
+        assert_eq!(store.count("json").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_memory_ephemeral_store() {
+        exercise_store(Arc::new(MemoryEphemeralStore::new())).await;
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_ephemeral_store() {
+        let pool = create_memory_pool().await.unwrap();
+        init_database(&pool).await.unwrap();
+        exercise_store(Arc::new(SqlEphemeralStore::new(pool))).await;
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_ephemeral_store_survives_reconnect() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite://{}?mode=rwc", dir.path().join("ephemeral.db").display());
+        let expires_at = Utc::now() + Duration::minutes(10);
+
+        let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+        init_database(&pool).await.unwrap();
+        SqlEphemeralStore::new(pool.clone()).put("ns", "k", "v", expires_at).await.unwrap();
+        pool.close().await;
+
+        // 模拟服务重启后重新连接
+        let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+        let store = SqlEphemeralStore::new(pool);
+        assert_eq!(store.get("ns", "k").await.unwrap().as_deref(), Some("v"));
+    }
+
+    // 需要可用的 PostgreSQL，通过 PICKER_TEST_POSTGRES_URL 指定，未设置时跳过
+    #[tokio::test]
+    async fn test_postgres_ephemeral_store() {
+        let Ok(url) = std::env::var("PICKER_TEST_POSTGRES_URL") else {
+            return;
+        };
+
+        // 每次运行使用独立的 schema，避免测试数据相互影响
+        let schema = format!("ephemeral_test_{}", uuid::Uuid::new_v4().simple());
+        let admin = PgPoolOptions::new().max_connections(1).connect(&url).await.unwrap();
+        sqlx::query(&format!("CREATE SCHEMA {}", schema)).execute(&admin).await.unwrap();
+
+        let search_path = schema.clone();
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .after_connect(move |conn, _| {
+                let sql = format!("SET search_path TO {}", search_path);
+                Box::pin(async move {
+                    sqlx::query(&sql).execute(conn).await?;
+                    Ok(())
+                })
+            })
+            .connect(&url)
+            .await
+            .unwrap();
+
+        init_database(Database::Postgres(pool.clone())).await.unwrap();
+        exercise_store(Arc::new(SqlEphemeralStore::new(pool.clone()))).await;
+
+        pool.close().await;
+        sqlx::query(&format!("DROP SCHEMA {} CASCADE", schema)).execute(&admin).await.unwrap();
+    }
+}
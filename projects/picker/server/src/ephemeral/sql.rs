@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Postgres, Sqlite};
+
+use super::EphemeralStore;
+
+// 基于 ephemeral_entries 表的存储，SQLite 与 PostgreSQL 共用同一套 SQL
+// expires_at 以 Unix 毫秒保存，避免两种后端时间格式比较不一致
+#[derive(Clone)]
+pub struct SqlEphemeralStore<DB: sqlx::Database> {
+    pool: Pool<DB>,
+}
+
+impl<DB: sqlx::Database> SqlEphemeralStore<DB> {
+    pub fn new(pool: Pool<DB>) -> Self {
+        Self { pool }
+    }
+}
+
+macro_rules! impl_sql_ephemeral_store {
+    ($db:ty) => {
+        #[async_trait]
+        impl EphemeralStore for SqlEphemeralStore<$db> {
+            async fn put(&self, namespace: &str, key: &str, value: &str, expires_at: DateTime<Utc>) -> Result<(), sqlx::Error> {
+                sqlx::query(
+                    r#"
+                    INSERT INTO ephemeral_entries (namespace, entry_key, value, expires_at)
+                    VALUES ($1, $2, $3, $4)
+                    ON CONFLICT (namespace, entry_key) DO UPDATE SET value = excluded.value, expires_at = excluded.expires_at
+                    "#,
+                )
+                .bind(namespace)
+                .bind(key)
+                .bind(value)
+                .bind(expires_at.timestamp_millis())
+                .execute(&self.pool)
+                .await?;
+                Ok(())
+            }
+
+            async fn get(&self, namespace: &str, key: &str) -> Result<Option<String>, sqlx::Error> {
+                sqlx::query_scalar("SELECT value FROM ephemeral_entries WHERE namespace = $1 AND entry_key = $2")
+                    .bind(namespace)
+                    .bind(key)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn take(&self, namespace: &str, key: &str) -> Result<Option<String>, sqlx::Error> {
+                sqlx::query_scalar("DELETE FROM ephemeral_entries WHERE namespace = $1 AND entry_key = $2 RETURNING value")
+                    .bind(namespace)
+                    .bind(key)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn remove(&self, namespace: &str, key: &str) -> Result<(), sqlx::Error> {
+                sqlx::query("DELETE FROM ephemeral_entries WHERE namespace = $1 AND entry_key = $2")
+                    .bind(namespace)
+                    .bind(key)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn compare_and_swap(
+                &self,
+                namespace: &str,
+                key: &str,
+                expected: Option<&str>,
+                value: &str,
+                expires_at: DateTime<Utc>,
+            ) -> Result<bool, sqlx::Error> {
+                // 条目不存在时依靠主键冲突保证只有一个调用方插入成功，存在时按旧值条件更新
+                let result = match expected {
+                    None => {
+                        sqlx::query(
+                            r#"
+                            INSERT INTO ephemeral_entries (namespace, entry_key, value, expires_at)
+                            VALUES ($1, $2, $3, $4)
+                            ON CONFLICT (namespace, entry_key) DO NOTHING
+                            "#,
+                        )
+                        .bind(namespace)
+                        .bind(key)
+                        .bind(value)
+                        .bind(expires_at.timestamp_millis())
+                        .execute(&self.pool)
+                        .await?
+                    }
+                    Some(expected) => {
+                        sqlx::query(
+                            "UPDATE ephemeral_entries SET value = $3, expires_at = $4 WHERE namespace = $1 AND entry_key = $2 AND value = $5",
+                        )
+                        .bind(namespace)
+                        .bind(key)
+                        .bind(value)
+                        .bind(expires_at.timestamp_millis())
+                        .bind(expected)
+                        .execute(&self.pool)
+                        .await?
+                    }
+                };
+                Ok(result.rows_affected() == 1)
+            }
+
+            async fn purge_expired(&self, namespace: &str, now: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+                let result = sqlx::query("DELETE FROM ephemeral_entries WHERE namespace = $1 AND expires_at <= $2")
+                    .bind(namespace)
+                    .bind(now.timestamp_millis())
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected())
+            }
+
+            async fn count(&self, namespace: &str) -> Result<i64, sqlx::Error> {
+                sqlx::query_scalar("SELECT COUNT(*) FROM ephemeral_entries WHERE namespace = $1")
+                    .bind(namespace)
+                    .fetch_one(&self.pool)
+                    .await
+            }
+        }
+    };
+}
+
+impl_sql_ephemeral_store!(Sqlite);
+impl_sql_ephemeral_store!(Postgres);
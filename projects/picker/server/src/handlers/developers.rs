@@ -0,0 +1,542 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+    Extension,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::config::AppState;
+use crate::handlers::pickers::PickerInfo;
+use crate::models::{Withdrawal, WithdrawalStatus};
+use crate::services::earnings::{developer_earnings, DeveloperEarnings};
+use crate::services::reconciler::RpcReceiptSource;
+use crate::services::refunds::Requester;
+use crate::services::withdrawals::{self, confirm_withdrawal, WithdrawalSettings};
+use crate::utils::AppError;
+
+// 销售汇总查询参数，默认为最近 30 天
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EarningsQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+// 开发者 Picker 列表查询参数
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DeveloperPickerQuery {
+    pub page: Option<u32>,
+    pub size: Option<u32>,
+}
+
+// 开发者 Picker 列表响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeveloperPickerListResponse {
+    pub pickers: Vec<PickerInfo>,
+    pub total: u64,
+    pub page: u32,
+    pub size: u32,
+    pub has_next: bool,
+}
+
+// 提现申请请求
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateWithdrawalRequest {
+    /// 提现的 Premium 积分
+    pub amount: i64,
+}
+
+// 提现列表查询参数
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct WithdrawalQuery {
+    pub page: Option<u32>,
+    pub size: Option<u32>,
+}
+
+// 提现列表响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WithdrawalListResponse {
+    pub withdrawals: Vec<Withdrawal>,
+    pub total: u64,
+    pub page: u32,
+    pub size: u32,
+    pub has_next: bool,
+}
+
+// 管理员查询提现的参数
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AdminWithdrawalQuery {
+    /// 默认为 requested（等待转账）
+    pub status: Option<WithdrawalStatus>,
+}
+
+// 提交转账请求
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PayoutWithdrawalRequest {
+    /// 管理员向开发者钱包转账的交易哈希
+    pub tx_hash: String,
+}
+
+// 获取开发者销售汇总
+#[utoipa::path(
+    get,
+    path = "/api/developers/me/earnings",
+    tag = "developers",
+    summary = "Get developer earnings",
+    description = "Aggregate the successful orders of the current developer's pickers per picker and per day (UTC), split into premium and wallet sales. Defaults to the last 30 days, the range cannot exceed 366 days",
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("from" = Option<String>, Query, description = "Start of the range (RFC 3339, inclusive), default is 30 days before `to`"),
+        ("to" = Option<String>, Query, description = "End of the range (RFC 3339, exclusive), default is now")
+    ),
+    responses(
+        (status = 200, description = "Get earnings successful", body = DeveloperEarnings),
+        (status = 400, description = "Invalid range or not a developer", body = crate::openapi::ErrorResponse),
+        (status = 401, description = "Unauthorized access", body = crate::openapi::ErrorResponse),
+        (status = 404, description = "User not found", body = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn get_earnings(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Query(query): Query<EarningsQuery>,
+) -> Result<Json<DeveloperEarnings>, AppError> {
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - chrono::Duration::days(30));
+
+    let earnings = developer_earnings(
+        state.users.as_ref(),
+        state.pickers.as_ref(),
+        state.orders.as_ref(),
+        user_id,
+        from,
+        to,
+        state.premium_payment_rate,
+    )
+    .await?;
+
+    Ok(Json(earnings))
+}
+
+// 申请提现
+#[utoipa::path(
+    post,
+    path = "/api/developers/me/withdrawals",
+    tag = "developers",
+    summary = "Request withdrawal",
+    description = "Convert premium earnings into an on-chain payout to the developer's wallet_address. Only sales income can be withdrawn, not free or purchased credits. The credits are deducted immediately and the wei amount is locked at the current token price; an admin pays it out and submits the transaction hash",
+    security(
+        ("bearer_auth" = [])
+    ),
+    request_body(content = CreateWithdrawalRequest, description = "Premium credits to withdraw", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Withdrawal requested", body = Withdrawal),
+        (status = 400, description = "Invalid amount, insufficient balance or earnings, or not a developer", body = crate::openapi::ErrorResponse),
+        (status = 401, description = "Unauthorized access", body = crate::openapi::ErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn create_withdrawal(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(payload): Json<CreateWithdrawalRequest>,
+) -> Result<Json<Withdrawal>, AppError> {
+    let settings = WithdrawalSettings {
+        premium_to_usd: state.premium_to_usd,
+        min_amount: state.premium_min_withdrawal,
+    };
+    let withdrawal = withdrawals::request_withdrawal(
+        state.users.as_ref(),
+        state.withdrawals.as_ref(),
+        state.price_oracle.as_ref(),
+        user_id,
+        payload.amount,
+        settings,
+    )
+    .await?;
+
+    Ok(Json(withdrawal))
+}
+
+// 获取当前开发者的 Picker
+#[utoipa::path(
+    get,
+    path = "/api/developers/me/pickers",
+    tag = "developers",
+    summary = "Get developer pickers",
+    description = "Get the pickers uploaded by the current user, including inactive ones, newest first. Deleted pickers are not listed",
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("page" = Option<u32>, Query, description = "Page number, default is 1"),
+        ("size" = Option<u32>, Query, description = "Number of items per page, default is 20")
+    ),
+    responses(
+        (status = 200, description = "Get pickers successful", body = DeveloperPickerListResponse),
+        (status = 401, description = "Unauthorized access", body = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn get_my_pickers(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Query(query): Query<DeveloperPickerQuery>,
+) -> Result<Json<DeveloperPickerListResponse>, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let size = query.size.unwrap_or(20).clamp(1, 100);
+    let offset = (page - 1) * size;
+
+    let (pickers, total) = state
+        .pickers
+        .list_for_dev(user_id, size as i64, offset as i64)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+
+    Ok(Json(DeveloperPickerListResponse {
+        pickers: pickers.into_iter().map(PickerInfo::from).collect(),
+        total: total as u64,
+        page,
+        size,
+        has_next: ((page * size) as i64) < total,
+    }))
+}
+
+// 获取当前开发者的提现记录
+#[utoipa::path(
+    get,
+    path = "/api/developers/me/withdrawals",
+    tag = "developers",
+    summary = "Get withdrawals",
+    description = "Get the withdrawals of the current user, newest first",
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("page" = Option<u32>, Query, description = "Page number, default is 1"),
+        ("size" = Option<u32>, Query, description = "Number of items per page, default is 20")
+    ),
+    responses(
+        (status = 200, description = "Get withdrawals successful", body = WithdrawalListResponse),
+        (status = 401, description = "Unauthorized access", body = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn get_withdrawals(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Query(query): Query<WithdrawalQuery>,
+) -> Result<Json<WithdrawalListResponse>, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let size = query.size.unwrap_or(20).clamp(1, 100);
+    let offset = (page - 1) * size;
+
+    let (withdrawals, total) = state
+        .withdrawals
+        .list_for_user(user_id, size as i64, offset as i64)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+
+    Ok(Json(WithdrawalListResponse {
+        withdrawals,
+        total: total as u64,
+        page,
+        size,
+        has_next: ((page * size) as i64) < total,
+    }))
+}
+
+// 取消提现
+#[utoipa::path(
+    post,
+    path = "/api/developers/me/withdrawals/{withdrawal_id}/cancel",
+    tag = "developers",
+    summary = "Cancel withdrawal",
+    description = "Cancel a withdrawal that has not been paid out yet and return the credits",
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("withdrawal_id" = uuid::Uuid, Path, description = "Withdrawal ID")
+    ),
+    responses(
+        (status = 200, description = "Withdrawal cancelled", body = Withdrawal),
+        (status = 400, description = "Withdrawal has already been paid out", body = crate::openapi::ErrorResponse),
+        (status = 401, description = "Unauthorized access", body = crate::openapi::ErrorResponse),
+        (status = 404, description = "Withdrawal not found", body = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn cancel_withdrawal(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(withdrawal_id): Path<Uuid>,
+) -> Result<Json<Withdrawal>, AppError> {
+    let withdrawal = withdrawals::cancel_withdrawal(state.withdrawals.as_ref(), user_id, withdrawal_id).await?;
+    Ok(Json(withdrawal))
+}
+
+// 管理员查询提现
+#[utoipa::path(
+    get,
+    path = "/api/withdrawals",
+    tag = "developers",
+    summary = "List withdrawals (admin)",
+    description = "List up to 100 withdrawals with the given status, oldest first. Only available to admins",
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("status" = Option<WithdrawalStatus>, Query, description = "Withdrawal status, default is requested")
+    ),
+    responses(
+        (status = 200, description = "Get withdrawals successful", body = Vec<Withdrawal>),
+        (status = 400, description = "Not an admin", body = crate::openapi::ErrorResponse),
+        (status = 401, description = "Unauthorized access", body = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn list_withdrawals(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Query(query): Query<AdminWithdrawalQuery>,
+) -> Result<Json<Vec<Withdrawal>>, AppError> {
+    if !state.admin_user_ids.contains(&user_id) {
+        return Err(AppError::BadRequest("Only admins can list withdrawals".to_string()));
+    }
+
+    let withdrawals = state
+        .withdrawals
+        .list_by_status(query.status.unwrap_or(WithdrawalStatus::Requested), 100)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+
+    Ok(Json(withdrawals))
+}
+
+// 管理员提交转账交易
+#[utoipa::path(
+    post,
+    path = "/api/withdrawals/{withdrawal_id}/payout",
+    tag = "developers",
+    summary = "Submit withdrawal payout (admin)",
+    description = "Record the transaction that transfers amount_wei to the developer's wallet. The withdrawal becomes processing, then completed once the transaction is confirmed; if it reverts the withdrawal fails and the credits are returned",
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("withdrawal_id" = uuid::Uuid, Path, description = "Withdrawal ID")
+    ),
+    request_body(content = PayoutWithdrawalRequest, description = "Payout transaction", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Payout recorded", body = Withdrawal),
+        (status = 400, description = "Invalid transaction hash or withdrawal is not requested", body = crate::openapi::ErrorResponse),
+        (status = 401, description = "Unauthorized access", body = crate::openapi::ErrorResponse),
+        (status = 404, description = "Withdrawal not found", body = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn payout_withdrawal(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(withdrawal_id): Path<Uuid>,
+    Json(payload): Json<PayoutWithdrawalRequest>,
+) -> Result<Json<Withdrawal>, AppError> {
+    let requester = Requester {
+        user_id,
+        is_admin: state.admin_user_ids.contains(&user_id),
+    };
+    let withdrawal =
+        withdrawals::submit_payout(state.withdrawals.as_ref(), requester, withdrawal_id, &payload.tx_hash).await?;
+
+    // 在后台确认转账交易，未确认的交给后台对账处理
+    if cfg!(not(test)) {
+        let confirm_state = state.clone();
+        let processing = withdrawal.clone();
+        tokio::spawn(async move {
+            let receipts = match RpcReceiptSource::new(&confirm_state.blockchain_rpc_url) {
+                Ok(receipts) => receipts,
+                Err(_) => return,
+            };
+            let interval = Duration::from_secs(confirm_state.blockchain_retry_interval_seconds.max(0) as u64);
+            if let Err(e) = confirm_withdrawal(
+                confirm_state.withdrawals.as_ref(),
+                &receipts,
+                &processing,
+                confirm_state.blockchain_retry_times.max(1) as u32,
+                interval,
+            )
+            .await
+            {
+                error!("Failed to confirm withdrawal {}: {:?}", processing.withdrawal_id, e);
+            }
+        });
+    }
+
+    Ok(Json(withdrawal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{LedgerEntryKind, Order, OrderStatus, PayType, Picker, UserType};
+    use crate::price_oracle::FixedPriceOracle;
+    use crate::repository::fixtures::{order, picker, user};
+    use crate::repository::{MemoryRepository, UserRepository};
+    use crate::utils_tests::{create_mock_app_state, create_test_app_state};
+    use std::sync::Arc;
+
+    const PAYOUT_TX: &str = "0xabababababababababababababababababababababababababababababababab";
+
+    #[tokio::test]
+    async fn test_earnings_and_withdrawal_flow() {
+        let mut state = create_test_app_state().await;
+        state.price_oracle = Arc::new(FixedPriceOracle::new("0.5".parse().unwrap()));
+        let admin_id = Uuid::new_v4();
+        state.admin_user_ids = vec![admin_id];
+
+        let dev = user("earnings-dev@example.com", UserType::Dev, 0);
+        let buyer = user("earnings-buyer@example.com", UserType::Gen, 1000);
+        state.users.create(&dev).await.unwrap();
+        state.users.create(&buyer).await.unwrap();
+        let sold = picker(dev.user_id, 100);
+        state.pickers.create(&sold).await.unwrap();
+        let order = Order {
+            amount: 100,
+            created_at: Utc::now() - chrono::Duration::hours(1),
+            ..order(buyer.user_id, sold.picker_id, PayType::Premium, OrderStatus::Success)
+        };
+        state.orders.settle_premium(&order, dev.user_id, 95).await.unwrap();
+
+        let earnings = get_earnings(
+            State(state.clone()),
+            Extension(dev.user_id),
+            Query(EarningsQuery { from: None, to: None }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(earnings.premium_balance, 95);
+        assert_eq!(earnings.totals.premium_income, 95);
+        assert_eq!(earnings.pickers[0].picker_id, sold.picker_id);
+        assert!(earnings.days.len() >= 30);
+
+        let withdrawal = create_withdrawal(
+            State(state.clone()),
+            Extension(dev.user_id),
+            Json(CreateWithdrawalRequest { amount: 50 }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(withdrawal.status, WithdrawalStatus::Requested);
+        assert_eq!(withdrawal.amount_wei, "100000000000000000000");
+
+        // 非管理员不能查看待转账的提现，也不能提交转账
+        let result = list_withdrawals(
+            State(state.clone()),
+            Extension(dev.user_id),
+            Query(AdminWithdrawalQuery { status: None }),
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+        let requested = list_withdrawals(State(state.clone()), Extension(admin_id), Query(AdminWithdrawalQuery { status: None }))
+            .await
+            .unwrap();
+        assert_eq!(requested.len(), 1);
+
+        let paid = payout_withdrawal(
+            State(state.clone()),
+            Extension(admin_id),
+            Path(withdrawal.withdrawal_id),
+            Json(PayoutWithdrawalRequest { tx_hash: PAYOUT_TX.to_string() }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(paid.status, WithdrawalStatus::Processing);
+
+        let list = get_withdrawals(
+            State(state.clone()),
+            Extension(dev.user_id),
+            Query(WithdrawalQuery { page: None, size: None }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(list.total, 1);
+        assert_eq!(list.withdrawals[0].tx_hash.as_deref(), Some(PAYOUT_TX));
+
+        let stored = state.users.find_by_id(dev.user_id).await.unwrap().unwrap();
+        assert_eq!(stored.premium_balance, 45);
+        let (entries, _) = state.premium_ledger.list_for_user(dev.user_id, 10, 0).await.unwrap();
+        assert_eq!(entries[0].kind, LedgerEntryKind::Withdrawal);
+        assert!(state.premium_ledger.balance_mismatches(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_withdrawal() {
+        let repo = MemoryRepository::new();
+        let mut state = create_mock_app_state(&repo).await;
+        state.price_oracle = Arc::new(FixedPriceOracle::new("1".parse().unwrap()));
+        let dev = user("cancel-dev@example.com", UserType::Dev, 0);
+        let buyer = user("cancel-buyer@example.com", UserType::Gen, 100);
+        UserRepository::create(&repo, &dev).await.unwrap();
+        UserRepository::create(&repo, &buyer).await.unwrap();
+        let sold = picker(dev.user_id, 100);
+        state.pickers.create(&sold).await.unwrap();
+        let order = Order { amount: 100, ..order(buyer.user_id, sold.picker_id, PayType::Premium, OrderStatus::Success) };
+        state.orders.settle_premium(&order, dev.user_id, 100).await.unwrap();
+
+        let withdrawal = create_withdrawal(
+            State(state.clone()),
+            Extension(dev.user_id),
+            Json(CreateWithdrawalRequest { amount: 100 }),
+        )
+        .await
+        .unwrap();
+        let result = create_withdrawal(
+            State(state.clone()),
+            Extension(dev.user_id),
+            Json(CreateWithdrawalRequest { amount: 10 }),
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+
+        let cancelled = cancel_withdrawal(State(state.clone()), Extension(dev.user_id), Path(withdrawal.withdrawal_id))
+            .await
+            .unwrap();
+        assert_eq!(cancelled.status, WithdrawalStatus::Cancelled);
+        assert_eq!(UserRepository::find_by_id(&repo, dev.user_id).await.unwrap().unwrap().premium_balance, 100);
+    }
+
+    #[tokio::test]
+    async fn test_get_my_pickers_lists_inactive_but_not_deleted() {
+        let repo = MemoryRepository::new();
+        let state = create_mock_app_state(&repo).await;
+        let dev = user("pickers-dev@example.com", UserType::Dev, 0);
+        let other = user("pickers-other@example.com", UserType::Dev, 0);
+        UserRepository::create(&repo, &dev).await.unwrap();
+        UserRepository::create(&repo, &other).await.unwrap();
+
+        let listed = picker(dev.user_id, 100);
+        let unlisted = Picker { status: "inactive".to_string(), ..picker(dev.user_id, 100) };
+        let deleted = picker(dev.user_id, 100);
+        for p in [&listed, &unlisted, &deleted, &picker(other.user_id, 100)] {
+            state.pickers.create(p).await.unwrap();
+        }
+        state.pickers.soft_delete(deleted.picker_id, Utc::now()).await.unwrap();
+
+        let response = get_my_pickers(
+            State(state.clone()),
+            Extension(dev.user_id),
+            Query(DeveloperPickerQuery { page: None, size: None }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.total, 2);
+        assert!(!response.has_next);
+        let mut ids: Vec<Uuid> = response.pickers.iter().map(|p| p.picker_id).collect();
+        ids.sort();
+        let mut expected = vec![listed.picker_id, unlisted.picker_id];
+        expected.sort();
+        assert_eq!(ids, expected);
+    }
+}
@@ -1,14 +1,17 @@
 pub mod users;
 pub mod pickers;
 pub mod orders;
+pub mod developers;
 
 pub use users::*;
 pub use pickers::*;
 pub use orders::*;
+pub use developers::*;
 
 use axum::{
+    extract::DefaultBodyLimit,
     middleware,
-    routing::{get, post},
+    routing::{get, patch, post},
     Router,
 };
 use tower_http::cors::CorsLayer;
@@ -16,7 +19,7 @@ use tower_http::cors::CorsLayer;
 use crate::config::AppState;
 use crate::download::download;
 use crate::openapi::create_swagger_routes;
-use crate::middleware::auth_middleware;
+use crate::middleware::{auth_middleware, RateLimitLayer};
 
 
 /// 健康检查处理函数
@@ -35,18 +38,27 @@ pub async fn health_check() -> &'static str {
 }
 
 /// 创建公开路由
-pub fn create_routes() -> Router<AppState> {
+pub fn create_routes(state: AppState) -> Router<AppState> {
+    // 登录、注册、验证、刷新令牌及找回密码按 IP 与邮箱限流
+    let rate_limited = Router::new()
+        .route("/api/users/register", post(register))
+        .route("/api/users/verify", post(verify))
+        .route("/api/users/login", post(login))
+        .route("/api/users/refresh", post(refresh))
+        .route("/api/users/password/forgot", post(forgot_password))
+        .route("/api/users/password/reset", post(reset_password))
+        .route_layer(RateLimitLayer::new(state.rate_limiter.clone()));
+
     Router::new()
         // 健康检查
         .route("/", get(health_check))
         // 用户相关路由（公开）
         .route("/api/users/system_info", get(get_system_info))
-        .route("/api/users/register", post(register))
-        .route("/api/users/verify", post(verify))
-        .route("/api/users/login", post(login))
+        .merge(rate_limited)
         // Picker相关路由（公开）
         .route("/api/pickers", get(get_market))
         .route("/api/pickers/{picker_id}", get(get_picker_detail))
+        .route("/api/pickers/{picker_id}/versions", get(get_picker_versions))
         // 下载路由
         .route("/download", get(download))
         // Swagger UI 路由
@@ -57,12 +69,31 @@ pub fn create_routes() -> Router<AppState> {
 
 /// 创建需要认证的路由
 pub fn create_protected_routes(state: AppState) -> Router<AppState> {
+    // 上传接口以流式写入文件，请求体上限按 [upload] 配置放宽
+    let uploads = Router::new()
+        .route("/api/pickers", post(upload_picker))
+        .route("/api/pickers/{picker_id}", patch(update_picker).delete(delete_picker))
+        .route("/api/pickers/{picker_id}/versions", post(publish_picker_version))
+        .layer(DefaultBodyLimit::max(state.upload_limits().body_limit()));
+
     Router::new()
         .route("/api/users/profile", get(get_profile))
-        .route("/api/pickers", post(upload_picker))
+        .route("/api/users/logout", post(logout))
+        .route("/api/users/password", post(change_password))
+        .route("/api/users/premium/ledger", get(get_premium_ledger))
+        .merge(uploads)
         .route("/api/orders", post(create_order))
+        .route("/api/orders/quote", post(create_quote))
         .route("/api/orders/{order_id}", get(get_order_detail))
+        .route("/api/orders/{order_id}/events", get(order_events))
+        .route("/api/orders/{order_id}/refund", post(refund_order))
         .route("/api/orders", get(get_user_orders))
+        .route("/api/developers/me/earnings", get(get_earnings))
+        .route("/api/developers/me/pickers", get(get_my_pickers))
+        .route("/api/developers/me/withdrawals", post(create_withdrawal).get(get_withdrawals))
+        .route("/api/developers/me/withdrawals/{withdrawal_id}/cancel", post(cancel_withdrawal))
+        .route("/api/withdrawals", get(list_withdrawals))
+        .route("/api/withdrawals/{withdrawal_id}/payout", post(payout_withdrawal))
         // 应用认证中间件到所有受保护的路由
         .layer(middleware::from_fn_with_state(state, auth_middleware))
 }
@@ -1,13 +1,13 @@
-use reqwest;
-use serde_json;
-use std::ops::{Div, Mul};
-
 use axum::{
     extract::{Path, Query, State},
-    response::Json,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Json,
+    },
     Extension,
 };
 use chrono::Utc;
+use futures_util::stream::{self, Stream};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use tracing::{info, error};
@@ -15,27 +15,56 @@ use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::config::AppState;
-use crate::models::{DownloadToken, Order, OrderStatus, PayType, Picker, User};
-use crate::utils::{decrypt_private_key, AppError};
-use alloy::primitives::Address;
-use alloy::primitives::{FixedBytes, U256};
-use alloy::rpc::types::TransactionReceipt;
+use crate::contract::{uuid_to_bytes16, PickerPayment};
+use crate::models::{DownloadToken, Order, OrderStatus, PayType, Refund};
+use crate::models::PriceQuote;
+use crate::price_oracle::{usd_to_wei, TokenPrice};
+use crate::services::orders::purchase_with_premium;
+use crate::services::reconciler::{confirm_wallet_order, RpcReceiptSource};
+use crate::services::refunds::{self, confirm_refund, Requester};
+use crate::utils::AppError;
+use alloy::primitives::{Address, U256};
 use alloy::signers::local::PrivateKeySigner;
-use alloy::sol;
-use alloy::{
-    providers::{Provider, ProviderBuilder},
-};
+use alloy::providers::{Provider, ProviderBuilder};
 
 // 创建订单请求
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateOrderRequest {
     pub picker_id: Uuid,
     pub pay_type: PayType,
+    /// 钱包支付时使用 /api/orders/quote 返回的报价，按报价锁定的 wei 金额支付；不传时按当前价格换算
+    #[serde(default)]
+    pub quote_id: Option<Uuid>,
+}
+
+// 报价请求
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct QuoteRequest {
+    pub picker_id: Uuid,
+}
+
+// 报价响应，下单时传入 quote_id 即按 amount_wei 支付
+#[derive(Debug, Serialize, ToSchema)]
+pub struct QuoteResponse {
+    pub quote_id: Uuid,
+    pub picker_id: Uuid,
+    /// Picker 价格（USD）
+    pub price: i64,
+    /// 代币的 USD 价格
+    pub token_price: String,
+    /// 应支付的 wei 数量
+    pub amount_wei: String,
+    pub expires_at: chrono::DateTime<Utc>,
+    /// 服务端对报价字段的 HMAC-SHA256 签名
+    pub signature: String,
 }
 
 // 创建订单响应
+// Premium 订单直接为 success；钱包订单为 pending，链上确认后变为 success 或 expired
 #[derive(Debug, Serialize, ToSchema)]
 pub struct CreateOrderResponse {
+    pub order_id: Uuid,
+    pub status: OrderStatus,
     pub token: String,
     pub message: String,
 }
@@ -48,6 +77,26 @@ pub struct OrderQuery {
     pub status: Option<OrderStatus>,
 }
 
+// 退款/取消订单请求
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct RefundOrderRequest {
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// 钱包订单退款时必填：管理员调用合约 withdrawFunds 向买家退款的交易哈希
+    #[serde(default)]
+    pub tx_hash: Option<String>,
+}
+
+// 退款/取消订单响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RefundOrderResponse {
+    pub order_id: Uuid,
+    /// 订单的新状态；钱包订单在提现交易确认前仍为 success
+    pub status: OrderStatus,
+    /// 取消待支付订单时为空
+    pub refund: Option<Refund>,
+}
+
 // 订单信息
 #[derive(Debug, Serialize, ToSchema)]
 pub struct OrderInfo {
@@ -58,9 +107,23 @@ pub struct OrderInfo {
     pub amount: i64,
     pub pay_type: PayType,
     pub status: OrderStatus,
+    pub tx_hash: Option<String>,
     pub created_at: chrono::DateTime<Utc>,
+    pub expires_at: Option<chrono::DateTime<Utc>>,
+}
+
+// 订单状态事件，/api/orders/{id}/events 中每个 status 事件的数据
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OrderStatusEvent {
+    pub order_id: Uuid,
+    pub status: OrderStatus,
+    pub tx_hash: Option<String>,
+    pub expires_at: Option<chrono::DateTime<Utc>>,
 }
 
+// 订单事件流查询订单状态的间隔
+const ORDER_EVENTS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 // 订单列表响应
 #[derive(Debug, Serialize, ToSchema)]
 pub struct OrderListResponse {
@@ -99,12 +162,23 @@ pub async fn create_order(
         user_id, payload.picker_id, payload.pay_type
     );
 
+    // Premium 支付的校验与结算由业务层完成
+    if matches!(payload.pay_type, PayType::Premium) {
+        let order = purchase_with_premium(
+            state.users.as_ref(),
+            state.pickers.as_ref(),
+            state.orders.as_ref(),
+            user_id,
+            payload.picker_id,
+            state.premium_payment_rate,
+        )
+        .await?;
+        return issue_download_token(&state, &order).await;
+    }
+
     // 获取用户信息
     info!("Fetching user information...");
-    let user_result = sqlx::query_as::<_, User>("SELECT * FROM users WHERE user_id = ?")
-        .bind(user_id)
-        .fetch_optional(&state.db)
-        .await;
+    let user_result = state.users.find_by_id(user_id).await;
 
     match &user_result {
         Ok(Some(user)) => info!("User found: {:?}", user),
@@ -118,12 +192,7 @@ pub async fn create_order(
 
     // 获取Picker信息
     info!("Fetching picker information...");
-    let picker_result = sqlx::query_as::<_, Picker>(
-        "SELECT * FROM pickers WHERE picker_id = ? AND status = 'active'",
-    )
-    .bind(payload.picker_id)
-    .fetch_optional(&state.db)
-    .await;
+    let picker_result = state.pickers.find_active(payload.picker_id).await;
 
     match &picker_result {
         Ok(Some(picker)) => info!("Picker found: {:?}", picker),
@@ -135,105 +204,97 @@ pub async fn create_order(
         .map_err(|_| AppError::DatabaseError)?
         .ok_or_else(|| AppError::NotFound("Picker not found".to_string()))?;
 
-    // 检查支付方式和余额
-    match payload.pay_type {
-        PayType::Premium => {
-            info!(
-                "Processing premium payment, user balance: {}, picker price: {}",
-                user.premium_balance, picker.price
-            );
-            if user.premium_balance < picker.price {
-                return Err(AppError::BadRequest(
-                    "Insufficient premium balance.".to_string(),
-                ));
-            }
-        }
-        PayType::Wallet => {
-            info!(
-                "Processing wallet payment for address: {}, picker price: {}",
-                user.wallet_address, picker.price
-            );
+    // 钱包支付金额（wei）：带 quote_id 时使用报价锁定的金额，否则按当前代币价格换算
+    // 测试环境不请求价格源
+    let order_amount = match payload.quote_id {
+        Some(quote_id) => quoted_amount(&state, quote_id, user_id, &picker).await?,
+        None if cfg!(not(test)) => current_order_amount(&state, picker.price).await?.1,
+        None => U256::ZERO,
+    };
 
-            // 测试环境下跳过真实区块链操作
-            if cfg!(not(test)) {
-                // 解密用户私钥
-                let private_key_plaintext = decrypt_private_key(
-                    &user.private_key,
-                    &state.password_master_key,
-                    &state.password_nonce,
-                )
-                .map_err(|e| {
-                    tracing::error!("Decryption to plaintext failed: {:?}", e);
-                    AppError::InternalServerError
-                })?;
+    // 检查钱包余额
+    info!(
+        "Processing wallet payment for address: {}, picker price: {}, amount (wei): {}",
+        user.wallet_address, picker.price, order_amount
+    );
 
-                // 初始化签名器（使用用户的私钥明文）
-                let user_signer: PrivateKeySigner = private_key_plaintext.parse().map_err(|e| {
-                    tracing::error!("Invalid private key: {}", e);
-                    AppError::InternalServerError
-                })?;
+    // 测试环境下跳过真实区块链操作
+    if cfg!(not(test)) {
+        // 解密用户私钥
+        let private_key_plaintext = state
+            .wallet_keyring
+            .decrypt(&user.private_key)
+            .map_err(|e| {
+                tracing::error!("Decryption to plaintext failed: {:?}", e);
+                AppError::InternalServerError
+            })?;
 
-                // 初始化provider
-                let provider = ProviderBuilder::new().wallet(user_signer).connect_http(
-                    state.blockchain_rpc_url.parse().map_err(|e| {
-                        tracing::error!("Invalid RPC URL: {}", e);
-                        AppError::InternalServerError
-                    })?,
-                );
-
-                // 检查钱包余额
-                info!("Blockchain RPC URL: {}", state.blockchain_rpc_url);
-                info!("Parsing wallet address: {}", user.wallet_address);
-                let address: Address = user.wallet_address.parse().map_err(|e| {
-                    tracing::error!("Invalid wallet address: {} - {}", user.wallet_address, e);
-                    AppError::BadRequest("Invalid wallet address".to_string())
-                })?;
-                info!("Parsed wallet address successfully: {}", address);
+        // 初始化签名器（使用用户的私钥明文）
+        let user_signer: PrivateKeySigner = private_key_plaintext.parse().map_err(|e| {
+            tracing::error!("Invalid private key: {}", e);
+            AppError::InternalServerError
+        })?;
 
-                // 获取钱包余额
-                info!("Getting wallet balance for address: {}", address);
-                let balance = provider.get_balance(address).await.map_err(|e| {
-                    tracing::error!("Failed to get wallet balance: {}", e);
-                    AppError::InternalServerError
-                })?;
+        // 初始化provider
+        let provider = ProviderBuilder::new().wallet(user_signer).connect_http(
+            state.blockchain_rpc_url.parse().map_err(|e| {
+                tracing::error!("Invalid RPC URL: {}", e);
+                AppError::InternalServerError
+            })?,
+        );
+
+        // 检查钱包余额
+        info!("Blockchain RPC URL: {}", state.blockchain_rpc_url);
+        info!("Parsing wallet address: {}", user.wallet_address);
+        let address: Address = user.wallet_address.parse().map_err(|e| {
+            tracing::error!("Invalid wallet address: {} - {}", user.wallet_address, e);
+            AppError::BadRequest("Invalid wallet address".to_string())
+        })?;
+        info!("Parsed wallet address successfully: {}", address);
+
+        // 获取钱包余额
+        info!("Getting wallet balance for address: {}", address);
+        let balance = provider.get_balance(address).await.map_err(|e| {
+            tracing::error!("Failed to get wallet balance: {}", e);
+            AppError::InternalServerError
+        })?;
+
+        // 检查钱包余额是否足够支付订单金额
+        info!(
+            "Wallet balance: {}, order amount: {}",
+            balance, order_amount
+        );
+        if balance < order_amount {
+            return Err(AppError::BadRequest(
+                "Insufficient wallet balance.".to_string(),
+            ));
+        }
 
-                // 检查钱包余额是否足够支付订单金额
-                let order_amount_in_wei = alloy::primitives::U256::from(picker.price);
-                info!(
-                    "Wallet balance: {}, order amount: {}",
-                    balance, order_amount_in_wei
-                );
-                if balance < order_amount_in_wei {
-                    return Err(AppError::BadRequest(
-                        "Insufficient wallet balance.".to_string(),
-                    ));
-                }
+        // 记录钱包支付信息
+        tracing::info!(
+            "Wallet balance check passed for address: {}, balance: {} wei",
+            user.wallet_address,
+            balance
+        );
+    } else {
+        // 测试环境下模拟余额足够
+        info!("Test environment: skipping real blockchain operations and balance check");
+    }
 
-                // 记录钱包支付信息
-                tracing::info!(
-                    "Wallet balance check passed for address: {}, balance: {} wei",
-                    user.wallet_address,
-                    balance
-                );
-            } else {
-                // 测试环境下模拟余额足够
-                info!("Test environment: skipping real blockchain operations and balance check");
-            }
+    // 报价只能使用一次，发送交易前作废；并发请求中只有一个能取到报价
+    if let Some(quote_id) = payload.quote_id {
+        let taken = state.take_price_quote(quote_id).await.map_err(|_| AppError::DatabaseError)?;
+        if taken.is_none() {
+            return Err(AppError::BadRequest("Quote has already been used".to_string()));
         }
     }
 
-    let pay_rate = (state.premium_payment_rate as f32).div(100.00);
-    info!("PAYMENT RATE: {}", pay_rate);
-
     // 查找用户钱包地址
     // let user_wallet_address = user.wallet_address;
 
     // 查找picker开发者钱包地址
     let dev_uid = picker.dev_user_id;
-    let dev_user_result = sqlx::query_as::<_, User>("SELECT * FROM users WHERE user_id = ?")
-        .bind(dev_uid)
-        .fetch_optional(&state.db)
-        .await;
+    let dev_user_result = state.users.find_by_id(dev_uid).await;
 
     match &dev_user_result {
         Ok(Some(dev_user)) => info!("Dev User found: {:?}", dev_user),
@@ -249,23 +310,11 @@ pub async fn create_order(
     let order_id = Uuid::new_v4();
     let now = Utc::now();
     let expires_at = now + chrono::Duration::hours(1); // 订单1小时后过期
-    let tx_hash = if matches!(payload.pay_type, PayType::Wallet) {
+    let tx_hash = {
         // 执行链上转账操作，获取交易hash
         // 调用授权支付合约的pay方法，转移用户钱包的代币
         // user.wallet_address ---> devWalletAddress
-        // 合约的pay 签名
-        // function pay(
-        //     bytes32 pickerId,
-        //     uint256 devUserId,
-        //     address devWalletAddress
-        // ) external payable;
-        // 生成合约绑定
-        sol! {
-            #[sol(rpc)]
-            contract PickerPayment {
-                function pay(bytes16 pickerId, bytes16 devUserId, address devWalletAddress) external payable;
-            }
-        }
+        // 合约绑定见 crate::contract::PickerPayment
 
         // 判断是否为测试环境
         if cfg!(test) {
@@ -274,15 +323,13 @@ pub async fn create_order(
         } else {
             // 生产环境下执行实际的区块链操作
             // 解密用户私钥
-            let private_key_plaintext = decrypt_private_key(
-                &user.private_key,
-                &state.password_master_key,
-                &state.password_nonce,
-            )
-            .map_err(|e| {
-                tracing::error!("Decryption to plaintext failed: {:?}", e);
-                AppError::InternalServerError
-            })?;
+            let private_key_plaintext = state
+                .wallet_keyring
+                .decrypt(&user.private_key)
+                .map_err(|e| {
+                    tracing::error!("Decryption to plaintext failed: {:?}", e);
+                    AppError::InternalServerError
+                })?;
 
             // 初始化签名器（使用用户的私钥明文）
             let user_signer: PrivateKeySigner = private_key_plaintext.parse().map_err(|e| {
@@ -299,11 +346,8 @@ pub async fn create_order(
             );
 
             // 准备参数
-            let picker_id_bytes = payload.picker_id.as_bytes();
-            let dev_user_id_bytes = dev_user.user_id.as_bytes();
-
-            let picker_id_fixed = FixedBytes::from_slice(&picker_id_bytes[0..16]);
-            let dev_user_id_fixed = FixedBytes::from_slice(&dev_user_id_bytes[0..16]);
+            let picker_id_fixed = uuid_to_bytes16(payload.picker_id);
+            let dev_user_id_fixed = uuid_to_bytes16(dev_user.user_id);
 
             let dev_wallet = dev_user.wallet_address.parse::<Address>().map_err(|e| {
                 tracing::error!("Invalid developer wallet address: {}", e);
@@ -322,59 +366,6 @@ pub async fn create_order(
             // 创建合约实例
             let contract = PickerPayment::new(contract_address, provider);
 
-            // 构建并发送交易
-            let token_usdt_url: String = state.blockchain_token_usdt_url.parse().map_err(|e| {
-                tracing::error!("Invalid Token Usdt URL: {}", e);
-                AppError::InternalServerError
-            })?;
-
-            // 使用reqwest请求token_usdt_url获取CFX Token价格
-            let client = reqwest::Client::new();
-            let response = client.get(&token_usdt_url).send().await.map_err(|e| {
-                tracing::error!("Failed to fetch CFX price: {}", e);
-                AppError::InternalServerError
-            })?;
-
-            // 解析JSON响应
-            let json_response: serde_json::Value = response.json().await.map_err(|e| {
-                tracing::error!("Failed to parse CFX price response: {}", e);
-                AppError::InternalServerError
-            })?;
-
-            // 检查响应是否成功
-            if json_response.get("code").and_then(|c| c.as_str()) != Some("0") {
-                let error_msg = json_response
-                    .get("msg")
-                    .and_then(|m| m.as_str())
-                    .unwrap_or("Unknown error");
-                tracing::error!("CFX price API returned error: {}", error_msg);
-                return Err(AppError::InternalServerError);
-            }
-
-            // 获取data数组中的第一个元素的last字段（CFX价格）
-            let cfx_price = json_response
-                .get("data")
-                .and_then(|data| data.as_array())
-                .and_then(|data_array| data_array.get(0))
-                .and_then(|item| item.get("last"))
-                .and_then(|last| last.as_str())
-                .and_then(|last_str| last_str.parse::<f64>().ok())
-                .ok_or_else(|| {
-                    tracing::error!("Failed to extract CFX price from response");
-                    AppError::InternalServerError
-                })?;
-
-            // 计算订单金额（picker.price / cfx_price）并转换为U256
-            let order_amount_float = (picker.price as f64).div(cfx_price);
-            // 由于U256不支持直接从浮点数转换，我们需要先转换为整数（乘以10^18来保留精度）
-            let order_amount = U256::from((order_amount_float * 10_f64.powf(18.0)) as u128);
-
-            // 记录日志
-            info!(
-                "BlockChain Token price: {:.6}, order_amount_float: {:.6}, order_amount (wei): {}",
-                cfx_price, order_amount_float, order_amount
-            );
-
             let pending_tx = contract
                 .pay(picker_id_fixed, dev_user_id_fixed, dev_wallet)
                 .value(order_amount)
@@ -388,375 +379,184 @@ pub async fn create_order(
             // 获取交易哈希字符串
             format!("0x{}", hex::encode(pending_tx.tx_hash()))
         }
-    } else {
-        "".to_string()
     };
 
-    // 开始事务
-    info!("Starting transaction...");
-    let mut tx = state.db.begin().await.map_err(|e| {
-        info!("Failed to start transaction: {:?}", e);
-        AppError::DatabaseError
-    })?;
-
-    // 插入订单记录
-    if matches!(payload.pay_type, PayType::Wallet) {
-        info!("Inserting wallet order...");
-        let result = sqlx::query(
-            r#"
-            INSERT INTO orders (order_id, user_id, picker_id, amount, pay_type, status, tx_hash, created_at, expires_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
-            "#,
-        )
-        .bind(order_id)
-        .bind(user_id)
-        .bind(payload.picker_id)
-        .bind(picker.price)
-        .bind(&payload.pay_type)
-        .bind(&OrderStatus::Pending)
-        .bind(&tx_hash)
-        .bind(now.to_rfc3339())
-        .bind(expires_at.to_rfc3339())
-        .execute(&mut *tx)
-        .await;
-
-        match &result {
-            Ok(_) => info!("Wallet order inserted successfully"),
-            Err(e) => error!("Failed to insert wallet order: {:?}", e),
-        }
-
-        result.map_err(|_| AppError::DatabaseError)?;
-    } else {
-        info!("Inserting premium order...");
-        let result = sqlx::query(
-            r#"
-            INSERT INTO orders (order_id, user_id, picker_id, amount, pay_type, status, created_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
-            "#,
-        )
-        .bind(order_id)
-        .bind(user_id)
-        .bind(payload.picker_id)
-        .bind(picker.price)
-        .bind(&payload.pay_type)
-        .bind(&OrderStatus::Pending)
-        .bind(now.to_rfc3339())
-        .execute(&mut *tx)
-        .await;
-
-        match &result {
-            Ok(_) => info!("Premium order inserted successfully"),
-            Err(e) => info!("Failed to insert premium order: {:?}", e),
-        }
+    // 插入待支付的钱包订单
+    info!("Inserting wallet order...");
+    let order = Order {
+        order_id,
+        user_id,
+        picker_id: payload.picker_id,
+        amount: picker.price,
+        pay_type: PayType::Wallet,
+        status: OrderStatus::Pending,
+        tx_hash: Some(tx_hash),
+        created_at: now,
+        expires_at: Some(expires_at),
+    };
+    let result = state.orders.create(&order).await;
 
-        result.map_err(|_| AppError::DatabaseError)?;
+    match &result {
+        Ok(_) => info!("Wallet order inserted successfully"),
+        Err(e) => error!("Failed to insert wallet order: {:?}", e),
     }
 
-    // 扣除用户余额，并增加开发者账户余额（如果是Premium支付）
-    if matches!(payload.pay_type, PayType::Premium) {
-        info!("Processing premium payment...");
-        let result =
-            sqlx::query("UPDATE users SET premium_balance = premium_balance - ? WHERE user_id = ?")
-                .bind(picker.price)
-                .bind(user_id)
-                .execute(&mut *tx)
-                .await;
-
-        match &result {
-            Ok(_) => info!("User balance updated successfully"),
-            Err(e) => info!("Failed to update user balance: {:?}", e),
-        }
-
-        result.map_err(|_| AppError::DatabaseError)?;
-
-        let increase_balance_to_dev = picker
-            .price
-            .checked_sub((picker.price as f32).mul(pay_rate) as i64)
-            .unwrap_or_default();
-
-        info!("Increase balance to dev: {}", increase_balance_to_dev);
-
-        let result =
-            sqlx::query("UPDATE users SET premium_balance = premium_balance + ? WHERE user_id = ?")
-                .bind(increase_balance_to_dev)
-                .bind(dev_uid)
-                .execute(&mut *tx)
-                .await;
-
-        match &result {
-            Ok(_) => info!("Developer balance updated successfully"),
-            Err(e) => info!("Failed to update Developer balance: {:?}", e),
-        }
-
-        result.map_err(|_| AppError::DatabaseError)?;
-
-        // 更新订单状态为成功
-        info!("Updating order status to success...");
-        let result = sqlx::query("UPDATE orders SET status = ? WHERE order_id = ?")
-            .bind(&OrderStatus::Success)
-            .bind(order_id)
-            .execute(&mut *tx)
-            .await;
-
-        match &result {
-            Ok(_) => info!("Order status updated successfully"),
-            Err(e) => info!("Failed to update order status: {:?}", e),
-        }
-
-        result.map_err(|_| AppError::DatabaseError)?;
-
-        // 增加Picker下载次数
-        info!("Increasing picker download count...");
-        let result = sqlx::query(
-            "UPDATE pickers SET download_count = download_count + 1 WHERE picker_id = ?",
-        )
-        .bind(payload.picker_id)
-        .execute(&mut *tx)
-        .await;
-
-        match &result {
-            Ok(_) => info!("Picker download count updated successfully"),
-            Err(e) => info!("Failed to update picker download count: {:?}", e),
-        }
-
-        result.map_err(|_| AppError::DatabaseError)?;
-    } else {
-        // 实现重试机制，查询链上钱包交易状态是否成功
-        if !tx_hash.is_empty() {
-            info!("开始查询交易状态，交易哈希: {}", tx_hash);
-
-            // 将交易哈希字符串转换为TxHash类型
-            let tx_hash_parsed = tx_hash.parse().map_err(|e| {
-                tracing::error!("无效的交易哈希: {} - {}", tx_hash, e);
-                AppError::InternalServerError
-            })?;
-
-            // 创建provider用于查询交易状态
-            let provider = ProviderBuilder::new().connect_http(
-                state.blockchain_rpc_url.parse().map_err(|e| {
-                    tracing::error!("Invalid RPC URL: {}", e);
-                    AppError::InternalServerError
-                })?,
-            );
+    result.map_err(|_| AppError::DatabaseError)?;
 
-            // 调用带重试机制的函数查询交易回执
-            if let Ok(Some(receipt)) = get_receipt_with_retry(
-                &provider,
-                tx_hash_parsed,
-                state.blockchain_retry_times as u32,
-                state.blockchain_retry_interval_seconds,
+    // 在后台确认链上交易，接口立即返回 pending 订单；进度通过订单详情或 /api/orders/{id}/events 获取
+    // 测试环境使用模拟交易哈希，不查询链上回执
+    if cfg!(not(test)) {
+        let confirm_state = state.clone();
+        let order = order.clone();
+        tokio::spawn(async move {
+            let receipts = match RpcReceiptSource::new(&confirm_state.blockchain_rpc_url) {
+                Ok(receipts) => receipts,
+                Err(_) => return,
+            };
+            let interval = Duration::from_secs(confirm_state.blockchain_retry_interval_seconds.max(0) as u64);
+            if let Err(e) = confirm_wallet_order(
+                confirm_state.orders.as_ref(),
+                &receipts,
+                &order,
+                confirm_state.blockchain_retry_times.max(1) as u32,
+                interval,
             )
             .await
             {
-                info!(
-                    "交易状态: {}, 区块号: {:?}",
-                    if receipt.status() { "成功" } else { "失败" },
-                    receipt.block_number
-                );
-
-                // 如果交易成功，更新订单状态和增加下载次数
-                if receipt.status() {
-                    info!("交易成功，更新订单状态和Picker下载次数");
-
-                    // 更新订单状态为成功
-                    let result = sqlx::query("UPDATE orders SET status = ? WHERE order_id = ?")
-                        .bind(&OrderStatus::Success)
-                        .bind(order_id)
-                        .execute(&mut *tx)
-                        .await;
-
-                    match &result {
-                        Ok(_) => info!("订单状态更新成功"),
-                        Err(e) => info!("Failed to update order status: {:?}", e),
-                    }
-
-                    result.map_err(|_| AppError::DatabaseError)?;
-
-                    // 增加Picker下载次数
-                    let result = sqlx::query(
-                        "UPDATE pickers SET download_count = download_count + 1 WHERE picker_id = ?",
-                    )
-                    .bind(payload.picker_id)
-                    .execute(&mut *tx)
-                    .await;
-
-                    match &result {
-                        Ok(_) => info!("Picker下载次数更新成功"),
-                        Err(e) => info!("Failed to update picker download count: {:?}", e),
-                    }
-
-                    result.map_err(|_| AppError::DatabaseError)?;
-                } else {
-                    info!("交易失败，订单保持待处理状态");
-                }
-            } else {
-                info!("未能获取交易回执，尝试查询原始交易...");
-                // 调用带重试机制的函数查询原始交易
-                if let Ok(()) = get_raw_transaction_with_retry(
-                    &provider,
-                    tx_hash_parsed,
-                    state.blockchain_retry_times as u32,
-                    state.blockchain_retry_interval_seconds,
-                )
-                .await
-                {
-                    // 继续实现钱包支付订单剩余逻辑
-                    info!("交易成功，更新订单状态和Picker下载次数");
-
-                    // 更新订单状态为成功
-                    let result = sqlx::query("UPDATE orders SET status = ? WHERE order_id = ?")
-                        .bind(&OrderStatus::Success)
-                        .bind(order_id)
-                        .execute(&mut *tx)
-                        .await;
-
-                    match &result {
-                        Ok(_) => info!("订单状态更新成功"),
-                        Err(e) => info!("Failed to update order status: {:?}", e),
-                    }
-
-                    result.map_err(|_| AppError::DatabaseError)?;
-
-                    // 增加Picker下载次数
-                    let result = sqlx::query(
-                        "UPDATE pickers SET download_count = download_count + 1 WHERE picker_id = ?",
-                    )
-                    .bind(payload.picker_id)
-                    .execute(&mut *tx)
-                    .await;
-
-                    match &result {
-                        Ok(_) => info!("Picker下载次数更新成功"),
-                        Err(e) => info!("Failed to update picker download count: {:?}", e),
-                    }
-
-                    result.map_err(|_| AppError::DatabaseError)?;
-                } else {
-                    info!(
-                        "既未能获取交易回执，也未能查询到原始交易，订单保持待处理状态，交易未成功"
-                    );
-                }
-            }
-        }
-    }
-    // 带重试机制的交易回执查询函数
-    async fn get_receipt_with_retry<P: Provider>(
-        provider: &P,
-        tx_hash: FixedBytes<32>,
-        max_retries: u32,
-        retry_interval_seconds: i8,
-    ) -> Result<Option<TransactionReceipt>, AppError> {
-        for attempt in 1..=max_retries {
-            match provider.get_transaction_receipt(tx_hash).await {
-                // 需要进一步判断如果回执是None，则说明交易未被确认，需要继续重试
-                Ok(receipt) => {
-                    if receipt.is_none() {
-                        info!("交易未被确认，等待 {} 秒后重试", retry_interval_seconds);
-                        if attempt < max_retries {
-                            tokio::time::sleep(Duration::from_secs(retry_interval_seconds as u64))
-                                .await;
-                        }
-                        continue;
-                    }
-                    return Ok(receipt);
-                }
-                Err(e) => {
-                    info!(
-                        "尝试 receipt {} 获取交易回执失败: {}, 等待 {} 秒后重试",
-                        attempt, e, retry_interval_seconds
-                    );
-                    if attempt < max_retries {
-                        tokio::time::sleep(Duration::from_secs(retry_interval_seconds as u64))
-                            .await;
-                    }
-                }
-            }
-        }
-        Err(AppError::InternalServerError)
-    }
-
-    // 带重试机制的Raw交易查询函数
-    async fn get_raw_transaction_with_retry<P: Provider>(
-        provider: &P,
-        tx_hash: FixedBytes<32>,
-        max_retries: u32,
-        retry_interval_seconds: i8,
-    ) -> Result<(), AppError> {
-        for attempt in 1..=max_retries {
-            match provider
-                .get_raw_transaction_by_hash(tx_hash)
-                .await
-                // .map_err(|_e| AppError::InternalServerError)
-            {
-                Ok(raw_tx) => {
-                    match raw_tx {
-                        Some(raw_tx) => {
-                            // raw_tx 是 Bytes 类型，包含 RLP 编码的原始交易数据
-                            // 在 Alloy 1.0.30 中，没有直接的解码方法
-                            // 但我们可以利用这个信息判断交易状态
-
-                            // 如果获取到原始交易数据，说明交易已经被网络接收
-                            // 但还没有被确认（没有回执）
-                            info!(
-                                "获取到原始交易数据，交易已发送但未确认，长度: {} 字节",
-                                raw_tx.len()
-                            );
-                        }
-                        None => {
-                            info!("交易不存在");
-                        }
-                    }
-                    return Ok(());
-                }
-                Err(e) => {
-                    info!(
-                        "尝试 raw transaction {} 获取交易失败: {:?}, 等待 {} 秒后重试",
-                        attempt, e, retry_interval_seconds
-                    );
-                    if attempt < max_retries {
-                        tokio::time::sleep(Duration::from_secs(retry_interval_seconds as u64))
-                            .await;
-                    }
-                }
+                error!("Failed to confirm order {}: {:?}", order.order_id, e);
             }
-        }
-        Err(AppError::InternalServerError)
-    }
-
-    // 提交事务
-    info!("Committing transaction...");
-    let result = tx.commit().await;
-
-    match &result {
-        Ok(_) => info!("Transaction committed successfully"),
-        Err(e) => info!("Failed to commit transaction: {:?}", e),
+        });
     }
 
-    result.map_err(|_| AppError::DatabaseError)?;
-
     info!("Order created successfully with ID: {}", order_id);
 
-    // 生成下载token
-    let download_token = DownloadToken::new(order_id);
+    issue_download_token(&state, &order).await
+}
+
+// 为已创建的订单生成下载token，钱包订单需等待确认成功后才能下载
+async fn issue_download_token(state: &AppState, order: &Order) -> Result<Json<CreateOrderResponse>, AppError> {
+    let download_token = DownloadToken::new(order.order_id);
     let token_value = download_token.token.clone();
 
-    // 将生成的下载token存储到state.download_tokens中
+    // 保存下载token
     state
-        .download_tokens
-        .lock()
-        .map_err(|_| AppError::InternalServerError)?
-        .insert(token_value.clone(), download_token);
+        .save_download_token(&download_token)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
 
     info!(
         "Generated download token for order {}: {}",
-        order_id, token_value
+        order.order_id, token_value
     );
 
     Ok(Json(CreateOrderResponse {
+        order_id: order.order_id,
+        status: order.status.clone(),
         token: token_value,
-        message:
-            "Order created successfully, Never close the client and wait for execution to complete!"
-                .to_string(),
+        message: "Order created successfully".to_string(),
+    }))
+}
+
+// 按当前代币价格将 Picker 价格（USD）换算为 wei，全程整数运算
+async fn current_order_amount(state: &AppState, price: i64) -> Result<(TokenPrice, U256), AppError> {
+    let token_price = state.price_oracle.token_price().await.map_err(|e| {
+        tracing::error!("Failed to get token price: {}", e);
+        AppError::InternalServerError
+    })?;
+    let amount = usd_to_wei(price, &token_price.price).map_err(|e| {
+        tracing::error!("Failed to convert order amount: {}", e);
+        AppError::InternalServerError
+    })?;
+    info!(
+        "BlockChain Token price: {} (as of {}), order amount (wei): {}",
+        token_price.price, token_price.as_of, amount
+    );
+    Ok((token_price, amount))
+}
+
+// 校验报价属于当前用户与 Picker、未过期且签名有效，返回锁定的 wei 金额
+// 报价在发送交易前才作废，校验失败时仍可用于下一次下单
+async fn quoted_amount(
+    state: &AppState,
+    quote_id: Uuid,
+    user_id: Uuid,
+    picker: &crate::models::Picker,
+) -> Result<U256, AppError> {
+    let quote = state
+        .get_price_quote(quote_id)
+        .await
+        .map_err(|_| AppError::DatabaseError)?
+        .filter(|quote| quote.user_id == user_id && quote.expires_at > Utc::now())
+        .ok_or_else(|| AppError::BadRequest("Quote not found or expired".to_string()))?;
+
+    if !state.quote_signer.verify(&quote) {
+        error!("Price quote {} has an invalid signature", quote_id);
+        return Err(AppError::BadRequest("Invalid quote".to_string()));
+    }
+    if quote.picker_id != picker.picker_id {
+        return Err(AppError::BadRequest("Quote does not match the picker".to_string()));
+    }
+    if quote.price != picker.price {
+        return Err(AppError::BadRequest("Picker price has changed, please request a new quote".to_string()));
+    }
+
+    U256::from_str_radix(&quote.amount_wei, 10).map_err(|e| {
+        error!("Invalid amount in price quote {}: {}", quote_id, e);
+        AppError::InternalServerError
+    })
+}
+
+// 钱包支付报价
+#[utoipa::path(
+    post,
+    path = "/api/orders/quote",
+    tag = "orders",
+    summary = "Quote Wallet Payment",
+    description = "Lock the token price for a wallet payment. The quote is signed, expires after a short time and can be used for one order",
+    request_body(content = QuoteRequest, description = "Picker to quote", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Quote created", body = QuoteResponse),
+        (status = 404, description = "Picker not found", body = crate::openapi::ErrorResponse),
+        (status = 500, description = "Price source unavailable", body = crate::openapi::ErrorResponse)
+    ),
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+pub async fn create_quote(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(payload): Json<QuoteRequest>,
+) -> Result<Json<QuoteResponse>, AppError> {
+    let picker = state
+        .pickers
+        .find_active(payload.picker_id)
+        .await
+        .map_err(|_| AppError::DatabaseError)?
+        .ok_or_else(|| AppError::NotFound("Picker not found".to_string()))?;
+
+    let (token_price, amount) = current_order_amount(&state, picker.price).await?;
+    let mut quote = PriceQuote {
+        quote_id: Uuid::new_v4(),
+        user_id,
+        picker_id: picker.picker_id,
+        price: picker.price,
+        token_price: token_price.price.to_string(),
+        amount_wei: amount.to_string(),
+        expires_at: Utc::now() + chrono::Duration::seconds(state.quote_ttl_seconds),
+        signature: String::new(),
+    };
+    quote.signature = state.quote_signer.sign(&quote);
+    state.save_price_quote(&quote).await.map_err(|_| AppError::DatabaseError)?;
+
+    Ok(Json(QuoteResponse {
+        quote_id: quote.quote_id,
+        picker_id: quote.picker_id,
+        price: quote.price,
+        token_price: quote.token_price,
+        amount_wei: quote.amount_wei,
+        expires_at: quote.expires_at,
+        signature: quote.signature,
     }))
 }
 
@@ -790,73 +590,22 @@ pub async fn get_user_orders(
     let size = query.size.unwrap_or(10);
     let offset = (page - 1) * size;
 
-    // 构建查询条件
-    let (where_clause, count_where_clause) = if let Some(_status) = &query.status {
-        (
-            format!(
-                "WHERE o.user_id = ? AND o.status = ? ORDER BY o.created_at DESC LIMIT ? OFFSET ?"
-            ),
-            "WHERE o.user_id = ? AND o.status = ?".to_string(),
-        )
-    } else {
-        (
-            "WHERE o.user_id = ? ORDER BY o.created_at DESC LIMIT ? OFFSET ?".to_string(),
-            "WHERE o.user_id = ?".to_string(),
-        )
-    };
-
-    // 获取总数
-    let total = if let Some(status) = &query.status {
-        let result: (i64,) = sqlx::query_as(&format!(
-            "SELECT COUNT(*) as count FROM orders o {}",
-            count_where_clause
-        ))
-        .bind(user_id)
-        .bind(status)
-        .fetch_one(&state.db)
-        .await
-        .map_err(|_| AppError::DatabaseError)?;
-        result.0
-    } else {
-        let result: (i64,) = sqlx::query_as(&format!(
-            "SELECT COUNT(*) as count FROM orders o {}",
-            count_where_clause
-        ))
-        .bind(user_id)
-        .fetch_one(&state.db)
+    // 获取订单列表和总数
+    let (orders, total) = state
+        .orders
+        .list_for_user(user_id, query.status.as_ref(), size as i64, offset as i64)
         .await
         .map_err(|_| AppError::DatabaseError)?;
-        result.0
-    };
-
-    // 获取订单列表
-    let orders = if let Some(status) = &query.status {
-        sqlx::query_as::<_, Order>(&format!("SELECT o.* FROM orders o {}", where_clause))
-            .bind(user_id)
-            .bind(status)
-            .bind(size as i64)
-            .bind(offset as i64)
-            .fetch_all(&state.db)
-            .await
-            .map_err(|_| AppError::DatabaseError)?
-    } else {
-        sqlx::query_as::<_, Order>(&format!("SELECT o.* FROM orders o {}", where_clause))
-            .bind(user_id)
-            .bind(size as i64)
-            .bind(offset as i64)
-            .fetch_all(&state.db)
-            .await
-            .map_err(|_| AppError::DatabaseError)?
-    };
 
     // 获取Picker信息
     let mut order_infos = Vec::new();
     for order in orders {
-        let picker = sqlx::query_as::<_, Picker>("SELECT * FROM pickers WHERE picker_id = ?")
-            .bind(order.picker_id)
-            .fetch_one(&state.db)
+        let picker = state
+            .pickers
+            .find_by_id(order.picker_id)
             .await
-            .map_err(|_| AppError::DatabaseError)?;
+            .map_err(|_| AppError::DatabaseError)?
+            .ok_or(AppError::DatabaseError)?;
 
         order_infos.push(OrderInfo {
             order_id: order.order_id,
@@ -866,7 +615,9 @@ pub async fn get_user_orders(
             amount: order.amount,
             pay_type: order.pay_type,
             status: order.status,
+            tx_hash: order.tx_hash,
             created_at: order.created_at,
+            expires_at: order.expires_at,
         });
     }
 
@@ -909,13 +660,7 @@ pub async fn get_order_detail(
     // info!("get_order_detail called with order_id: {}, user_id: {}", order_id, user_id);
 
     // 获取订单信息
-    let order_query = "SELECT * FROM orders WHERE order_id = ? AND user_id = ?";
-    // info!("Executing query: {}", order_query);
-    let order_result = sqlx::query_as::<_, Order>(order_query)
-        .bind(order_id)
-        .bind(user_id)
-        .fetch_optional(&state.db)
-        .await;
+    let order_result = state.orders.find_for_user(order_id, user_id).await;
 
     match &order_result {
         Ok(Some(order)) => info!("Order found: {:?}", order),
@@ -928,12 +673,7 @@ pub async fn get_order_detail(
         .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
 
     // 获取Picker信息
-    let picker_query = "SELECT * FROM pickers WHERE picker_id = ?";
-    // info!("Executing picker query: {}", picker_query);
-    let picker_result = sqlx::query_as::<_, Picker>(picker_query)
-        .bind(order.picker_id)
-        .fetch_optional(&state.db)
-        .await;
+    let picker_result = state.pickers.find_by_id(order.picker_id).await;
 
     let picker = match picker_result {
         Ok(Some(picker)) => {
@@ -958,7 +698,9 @@ pub async fn get_order_detail(
         amount: order.amount,
         pay_type: order.pay_type,
         status: order.status,
+        tx_hash: order.tx_hash,
         created_at: order.created_at,
+        expires_at: order.expires_at,
     };
 
     // info!("Returning order info: {:?}", order_info);
@@ -966,11 +708,164 @@ pub async fn get_order_detail(
     Ok(Json(order_info))
 }
 
+// 退款或取消订单
+#[utoipa::path(
+    post,
+    path = "/api/orders/{order_id}/refund",
+    tag = "orders",
+    summary = "Refund Or Cancel Order",
+    description = "Cancel a pending order (buyer, picker developer or admin) or refund a paid order. Pending wallet orders whose payment was already broadcast cannot be cancelled. Premium orders are refunded immediately by the picker developer or an admin; wallet orders are refunded by an admin with the tx_hash of the contract withdrawal and become refunded once it is confirmed",
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("order_id" = uuid::Uuid, Path, description = "Order ID")
+    ),
+    request_body(content = RefundOrderRequest, description = "Refund reason and withdrawal transaction", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Order cancelled or refund recorded", body = RefundOrderResponse),
+        (status = 400, description = "Order cannot be refunded", body = crate::openapi::ErrorResponse),
+        (status = 401, description = "Unauthorized access", body = crate::openapi::ErrorResponse),
+        (status = 404, description = "Order not found", body = crate::openapi::ErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn refund_order(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(order_id): Path<Uuid>,
+    Json(payload): Json<RefundOrderRequest>,
+) -> Result<Json<RefundOrderResponse>, AppError> {
+    let requester = Requester {
+        user_id,
+        is_admin: state.admin_user_ids.contains(&user_id),
+    };
+    let outcome = refunds::refund_order(
+        state.users.as_ref(),
+        state.pickers.as_ref(),
+        state.orders.as_ref(),
+        state.refunds.as_ref(),
+        state.premium_ledger.as_ref(),
+        requester,
+        order_id,
+        payload.reason,
+        payload.tx_hash,
+        state.premium_payment_rate,
+    )
+    .await?;
+
+    // 钱包订单退款在后台确认提现交易，未确认的交给后台对账处理
+    if cfg!(not(test)) {
+        if let Some(refund) = outcome.refund.clone().filter(|refund| refund.tx_hash.is_some()) {
+            let confirm_state = state.clone();
+            tokio::spawn(async move {
+                let receipts = match RpcReceiptSource::new(&confirm_state.blockchain_rpc_url) {
+                    Ok(receipts) => receipts,
+                    Err(_) => return,
+                };
+                let interval = Duration::from_secs(confirm_state.blockchain_retry_interval_seconds.max(0) as u64);
+                if let Err(e) = confirm_refund(
+                    confirm_state.refunds.as_ref(),
+                    &receipts,
+                    &refund,
+                    confirm_state.blockchain_retry_times.max(1) as u32,
+                    interval,
+                )
+                .await
+                {
+                    error!("Failed to confirm refund {}: {:?}", refund.refund_id, e);
+                }
+            });
+        }
+    }
+
+    Ok(Json(RefundOrderResponse {
+        order_id,
+        status: outcome.order_status,
+        refund: outcome.refund,
+    }))
+}
+
+// 订单状态事件流
+#[utoipa::path(
+    get,
+    path = "/api/orders/{order_id}/events",
+    tag = "orders",
+    summary = "Order Status Events",
+    description = "Server-sent events stream of the order status. A `status` event is sent immediately and whenever the status changes; the stream ends once the order is no longer pending",
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("order_id" = uuid::Uuid, Path, description = "Order ID")
+    ),
+    responses(
+        (status = 200, description = "Event stream, each `status` event carries an OrderStatusEvent", content_type = "text/event-stream", body = OrderStatusEvent),
+        (status = 401, description = "Unauthorized access", body = crate::openapi::ErrorResponse),
+        (status = 404, description = "Order not found", body = crate::openapi::ErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn order_events(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(order_id): Path<Uuid>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, AppError> {
+    let order = state
+        .orders
+        .find_for_user(order_id, user_id)
+        .await
+        .map_err(|_| AppError::DatabaseError)?
+        .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
+
+    // (当前订单, 上次发送的状态)：首个事件使用已查询的订单，之后按间隔重新查询，状态变化时才发送
+    let events = stream::unfold((Some(order), None::<OrderStatus>), move |(mut current, mut last)| {
+        let state = state.clone();
+        async move {
+            loop {
+                if last.as_ref().is_some_and(|status| *status != OrderStatus::Pending) {
+                    return None;
+                }
+                let order = match current.take() {
+                    Some(order) => order,
+                    None => {
+                        tokio::time::sleep(ORDER_EVENTS_POLL_INTERVAL).await;
+                        match state.orders.find_for_user(order_id, user_id).await {
+                            Ok(Some(order)) => order,
+                            Ok(None) => return None,
+                            Err(e) => {
+                                error!("Failed to poll order {}: {}", order_id, e);
+                                return None;
+                            }
+                        }
+                    }
+                };
+                if last.as_ref() == Some(&order.status) {
+                    continue;
+                }
+
+                let event = Event::default().event("status").json_data(OrderStatusEvent {
+                    order_id: order.order_id,
+                    status: order.status.clone(),
+                    tx_hash: order.tx_hash,
+                    expires_at: order.expires_at,
+                });
+                last = Some(order.status);
+                return Some((event, (None, last)));
+            }
+        }
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::{OrderStatus, PayType};
-    use crate::utils_tests::create_test_app_state;
+    use crate::ephemeral::DOWNLOAD_TOKENS;
+    use crate::models::{OrderStatus, PayType, Picker, PickerChainStatus, User};
+    use crate::repository::{MemoryRepository, OrderRepository, PickerRepository, UserRepository};
+    use crate::utils_tests::{create_mock_app_state, create_test_app_state, test_pool};
     use axum::extract::{Path, State};
     use axum::Extension;
     use chrono::Utc;
@@ -995,7 +890,7 @@ mod tests {
         )
         .bind(user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await;
 
         info!("Insert user result: {:?}", result);
@@ -1011,7 +906,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await;
 
         match &result {
@@ -1032,7 +927,7 @@ mod tests {
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await;
 
         match &result {
@@ -1045,6 +940,7 @@ mod tests {
         let request = CreateOrderRequest {
             picker_id,
             pay_type: PayType::Premium,
+            quote_id: None,
         };
 
         let result = create_order(State(state.clone()), Extension(user_id), Json(request)).await;
@@ -1058,7 +954,7 @@ mod tests {
         // 验证用户余额被扣除
         let user: User = sqlx::query_as("SELECT * FROM users WHERE user_id = ?")
             .bind(user_id)
-            .fetch_one(&state.db)
+            .fetch_one(test_pool(&state))
             .await
             .unwrap();
         assert_eq!(user.premium_balance, 500); // 1000 - 500
@@ -1066,7 +962,7 @@ mod tests {
         // 验证Picker下载次数增加
         let picker: Picker = sqlx::query_as("SELECT * FROM pickers WHERE picker_id = ?")
             .bind(picker_id)
-            .fetch_one(&state.db)
+            .fetch_one(test_pool(&state))
             .await
             .unwrap();
         assert_eq!(picker.download_count, 1);
@@ -1089,7 +985,7 @@ mod tests {
         )
         .bind(user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -1102,7 +998,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -1117,13 +1013,14 @@ mod tests {
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
         let request = CreateOrderRequest {
             picker_id,
             pay_type: PayType::Premium,
+            quote_id: None,
         };
 
         let result = create_order(State(state), Extension(user_id), Json(request)).await;
@@ -1151,13 +1048,14 @@ mod tests {
         )
         .bind(user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
         let request = CreateOrderRequest {
             picker_id,
             pay_type: PayType::Premium,
+            quote_id: None,
         };
 
         let result = create_order(State(state), Extension(user_id), Json(request)).await;
@@ -1192,7 +1090,7 @@ mod tests {
         )
         .bind(user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await;
 
         match &result {
@@ -1212,7 +1110,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await;
 
         match &result {
@@ -1235,7 +1133,7 @@ mod tests {
         .bind(dev_user_id)
         .bind(now.to_rfc3339())
         .bind(now.to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await;
 
         match &result {
@@ -1248,6 +1146,7 @@ mod tests {
         let request = CreateOrderRequest {
             picker_id,
             pay_type: PayType::Wallet,
+            quote_id: None,
         };
 
         // info!("Calling create_order...");
@@ -1275,7 +1174,7 @@ mod tests {
         // 验证Picker下载次数没有增加
         let picker: Picker = sqlx::query_as("SELECT * FROM pickers WHERE picker_id = ?")
             .bind(picker_id)
-            .fetch_one(&state.db)
+            .fetch_one(test_pool(&state))
             .await
             .unwrap();
         // info!("Picker download count: {}", picker.download_count);
@@ -1304,7 +1203,7 @@ mod tests {
         )
         .bind(user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await;
 
         info!("Insert user result: {:?}", result);
@@ -1319,7 +1218,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await;
 
         info!("Insert dev user result: {:?}", result);
@@ -1336,7 +1235,7 @@ mod tests {
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await;
 
         info!("Insert picker result: {:?}", result);
@@ -1357,7 +1256,7 @@ mod tests {
         .bind(&PayType::Premium)
         .bind(&OrderStatus::Success)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await;
 
         info!("Insert order result: {:?}", result);
@@ -1416,7 +1315,7 @@ mod tests {
         )
         .bind(user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -1429,7 +1328,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -1444,7 +1343,7 @@ mod tests {
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -1461,7 +1360,7 @@ mod tests {
         .bind(&PayType::Premium)
         .bind(&OrderStatus::Success)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -1497,7 +1396,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -1511,13 +1410,14 @@ mod tests {
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
         let request = CreateOrderRequest {
             picker_id,
             pay_type: PayType::Premium,
+            quote_id: None,
         };
 
         let result = create_order(State(state), Extension(user_id), Json(request)).await;
@@ -1547,7 +1447,7 @@ mod tests {
         )
         .bind(user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -1560,7 +1460,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -1575,13 +1475,14 @@ mod tests {
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
         let request = CreateOrderRequest {
             picker_id,
             pay_type: PayType::Premium,
+            quote_id: None,
         };
 
         let result = create_order(State(state), Extension(user_id), Json(request)).await;
@@ -1615,7 +1516,7 @@ mod tests {
         assert_eq!(response.total, 0);
         assert_eq!(response.page, 1);
         assert_eq!(response.size, 10);
-        assert_eq!(response.has_next, false);
+        assert!(!response.has_next);
     }
 
     // 新增测试用例：测试获取用户订单列表按状态筛选
@@ -1637,7 +1538,7 @@ mod tests {
         )
         .bind(user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -1650,7 +1551,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -1665,7 +1566,7 @@ mod tests {
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -1682,7 +1583,7 @@ mod tests {
         .bind(&PayType::Premium)
         .bind(&OrderStatus::Success)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -1738,7 +1639,7 @@ mod tests {
         )
         .bind(user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -1751,7 +1652,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -1766,7 +1667,7 @@ mod tests {
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -1783,14 +1684,14 @@ mod tests {
         .bind(&PayType::Premium)
         .bind(&OrderStatus::Success)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
         // 删除Picker以模拟Picker不存在的情况
         sqlx::query("DELETE FROM pickers WHERE picker_id = ?")
             .bind(picker_id)
-            .execute(&state.db)
+            .execute(test_pool(&state))
             .await
             .unwrap();
 
@@ -1818,7 +1719,7 @@ mod tests {
         )
         .bind(user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -1831,7 +1732,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -1846,7 +1747,7 @@ mod tests {
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -1886,4 +1787,481 @@ mod tests {
         let response = app.oneshot(request).await.unwrap();
         assert_eq!(response.status(), StatusCode::OK);
     }
+
+    #[tokio::test]
+    async fn test_create_order_premium_with_memory_repository() {
+        let repo = MemoryRepository::new();
+        let state = create_mock_app_state(&repo).await;
+        let now = Utc::now();
+
+        let user = User {
+            user_id: Uuid::new_v4(),
+            email: "user@test.com".to_string(),
+            user_name: "Test User".to_string(),
+            user_password: "hashed_password".to_string(),
+            user_type: crate::models::UserType::Gen,
+            wallet_address: "0x1234567890123456789012345678901234567890".to_string(),
+            private_key: "private_key_123".to_string(),
+            premium_balance: 1000,
+            created_at: now,
+        };
+        let dev_user = User {
+            user_id: Uuid::new_v4(),
+            email: "dev@test.com".to_string(),
+            user_type: crate::models::UserType::Dev,
+            premium_balance: 0,
+            ..user.clone()
+        };
+        let picker = Picker {
+            picker_id: Uuid::new_v4(),
+            dev_user_id: dev_user.user_id,
+            alias: "Test Picker".to_string(),
+            description: "Test Description".to_string(),
+            price: 500,
+            file_path: "test.exe".to_string(),
+            download_count: 0,
+            created_at: now,
+            updated_at: now,
+            image_path: "test.jpg".to_string(),
+            version: "1.0".to_string(),
+            status: "active".to_string(),
+            chain_status: PickerChainStatus::Unregistered,
+            chain_tx_hash: None,
+            deleted_at: None,
+        };
+        UserRepository::create(&repo, &user).await.unwrap();
+        UserRepository::create(&repo, &dev_user).await.unwrap();
+        PickerRepository::create(&repo, &picker).await.unwrap();
+
+        let request = CreateOrderRequest {
+            picker_id: picker.picker_id,
+            pay_type: PayType::Premium,
+            quote_id: None,
+        };
+        let response = create_order(State(state.clone()), Extension(user.user_id), Json(request))
+            .await
+            .unwrap();
+        assert!(state.ephemeral.get(DOWNLOAD_TOKENS, &response.token).await.unwrap().is_some());
+
+        // 结算结果只写入内存仓储，数据库中没有任何订单
+        let user = UserRepository::find_by_id(&repo, user.user_id).await.unwrap().unwrap();
+        let dev_user = UserRepository::find_by_id(&repo, dev_user.user_id).await.unwrap().unwrap();
+        assert_eq!(user.premium_balance, 500);
+        assert_eq!(dev_user.premium_balance, 500 - 500 * state.premium_payment_rate / 100);
+
+        let (orders, total) = OrderRepository::list_for_user(&repo, user.user_id, None, 10, 0).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(orders[0].status, OrderStatus::Success);
+
+        let db_orders: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM orders")
+            .fetch_one(test_pool(&state))
+            .await
+            .unwrap();
+        assert_eq!(db_orders.0, 0);
+    }
+
+    // 在内存仓储中写入买家、开发者和一个上架的 Picker
+    async fn memory_fixtures(repo: &MemoryRepository) -> (User, Picker) {
+        let now = Utc::now();
+        let user = User {
+            user_id: Uuid::new_v4(),
+            email: "user@test.com".to_string(),
+            user_name: "Test User".to_string(),
+            user_password: "hashed_password".to_string(),
+            user_type: crate::models::UserType::Gen,
+            wallet_address: "0x1234567890123456789012345678901234567890".to_string(),
+            private_key: "private_key_123".to_string(),
+            premium_balance: 1000,
+            created_at: now,
+        };
+        let dev_user = User {
+            user_id: Uuid::new_v4(),
+            email: "dev@test.com".to_string(),
+            user_type: crate::models::UserType::Dev,
+            premium_balance: 0,
+            ..user.clone()
+        };
+        let picker = Picker {
+            picker_id: Uuid::new_v4(),
+            dev_user_id: dev_user.user_id,
+            alias: "Test Picker".to_string(),
+            description: "Test Description".to_string(),
+            price: 500,
+            file_path: "test.exe".to_string(),
+            download_count: 0,
+            created_at: now,
+            updated_at: now,
+            image_path: "test.jpg".to_string(),
+            version: "1.0".to_string(),
+            status: "active".to_string(),
+            chain_status: PickerChainStatus::Unregistered,
+            chain_tx_hash: None,
+            deleted_at: None,
+        };
+        UserRepository::create(repo, &user).await.unwrap();
+        UserRepository::create(repo, &dev_user).await.unwrap();
+        PickerRepository::create(repo, &picker).await.unwrap();
+        (user, picker)
+    }
+
+    // 读取事件流直到结束，返回每个事件的 (事件名, 数据)
+    async fn collect_events(response: axum::response::Response) -> Vec<(String, serde_json::Value)> {
+        let body = tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            axum::body::to_bytes(response.into_body(), usize::MAX),
+        )
+        .await
+        .expect("event stream did not finish")
+        .unwrap();
+        String::from_utf8(body.to_vec())
+            .unwrap()
+            .split("\n\n")
+            .filter(|block| block.contains("data:"))
+            .map(|block| {
+                let field = |name: &str| {
+                    block
+                        .lines()
+                        .find_map(|line| line.strip_prefix(name))
+                        .unwrap_or_default()
+                        .trim()
+                        .to_string()
+                };
+                (field("event:"), serde_json::from_str(&field("data:")).unwrap())
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_create_order_wallet_returns_pending_order() {
+        let repo = MemoryRepository::new();
+        let state = create_mock_app_state(&repo).await;
+        let (user, picker) = memory_fixtures(&repo).await;
+
+        let request = CreateOrderRequest {
+            picker_id: picker.picker_id,
+            pay_type: PayType::Wallet,
+            quote_id: None,
+        };
+        let response = create_order(State(state.clone()), Extension(user.user_id), Json(request))
+            .await
+            .unwrap();
+        assert_eq!(response.status, OrderStatus::Pending);
+
+        // 接口不等待链上确认，订单保持 pending，详情中可以看到交易哈希和过期时间
+        let detail = get_order_detail(State(state.clone()), Extension(user.user_id), Path(response.order_id))
+            .await
+            .unwrap();
+        assert_eq!(detail.status, OrderStatus::Pending);
+        assert!(detail.tx_hash.is_some());
+        assert!(detail.expires_at.is_some());
+        let picker = PickerRepository::find_by_id(&repo, picker.picker_id).await.unwrap().unwrap();
+        assert_eq!(picker.download_count, 0);
+    }
+
+    // 使用固定代币价格，不请求外部价格源
+    fn with_fixed_price(mut state: AppState, price: &str) -> AppState {
+        state.price_oracle = std::sync::Arc::new(crate::price_oracle::FixedPriceOracle::new(price.parse().unwrap()));
+        state
+    }
+
+    #[tokio::test]
+    async fn test_create_quote_and_pay_with_locked_amount() {
+        let repo = MemoryRepository::new();
+        let state = with_fixed_price(create_mock_app_state(&repo).await, "0.3");
+        let (user, picker) = memory_fixtures(&repo).await;
+
+        let quote = create_quote(
+            State(state.clone()),
+            Extension(user.user_id),
+            Json(QuoteRequest { picker_id: picker.picker_id }),
+        )
+        .await
+        .unwrap();
+        // 500 / 0.3 个代币，精确到 wei 并向下取整
+        assert_eq!(quote.amount_wei, "1666666666666666666666");
+        assert_eq!(quote.token_price, "0.3");
+        assert_eq!(quote.price, 500);
+        assert!(quote.expires_at > Utc::now());
+        let stored = state.get_price_quote(quote.quote_id).await.unwrap().unwrap();
+        assert_eq!(stored.signature, quote.signature);
+        assert!(state.quote_signer.verify(&stored));
+
+        let request = CreateOrderRequest {
+            picker_id: picker.picker_id,
+            pay_type: PayType::Wallet,
+            quote_id: Some(quote.quote_id),
+        };
+        let response = create_order(State(state.clone()), Extension(user.user_id), Json(request))
+            .await
+            .unwrap();
+        assert_eq!(response.status, OrderStatus::Pending);
+
+        // 报价只能使用一次
+        let request = CreateOrderRequest {
+            picker_id: picker.picker_id,
+            pay_type: PayType::Wallet,
+            quote_id: Some(quote.quote_id),
+        };
+        let result = create_order(State(state), Extension(user.user_id), Json(request)).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_create_order_rejects_invalid_quote() {
+        let repo = MemoryRepository::new();
+        let state = with_fixed_price(create_mock_app_state(&repo).await, "0.3");
+        let (user, picker) = memory_fixtures(&repo).await;
+        let order_with = |quote_id| CreateOrderRequest {
+            picker_id: picker.picker_id,
+            pay_type: PayType::Wallet,
+            quote_id: Some(quote_id),
+        };
+
+        let quote = create_quote(
+            State(state.clone()),
+            Extension(user.user_id),
+            Json(QuoteRequest { picker_id: picker.picker_id }),
+        )
+        .await
+        .unwrap();
+
+        // 不存在的报价、其他用户的报价
+        let result = create_order(State(state.clone()), Extension(user.user_id), Json(order_with(Uuid::new_v4()))).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+        let result = create_order(State(state.clone()), Extension(Uuid::new_v4()), Json(order_with(quote.quote_id))).await;
+        assert!(matches!(result, Err(AppError::NotFound(_)) | Err(AppError::BadRequest(_))));
+
+        // 存储中的金额被篡改后签名校验失败
+        let mut tampered = state.get_price_quote(quote.quote_id).await.unwrap().unwrap();
+        tampered.amount_wei = "1".to_string();
+        state.save_price_quote(&tampered).await.unwrap();
+        let result = create_order(State(state.clone()), Extension(user.user_id), Json(order_with(quote.quote_id))).await;
+        assert!(matches!(result, Err(AppError::BadRequest(msg)) if msg == "Invalid quote"));
+
+        // 已过期的报价
+        let mut expired = tampered;
+        expired.amount_wei = quote.amount_wei.clone();
+        expired.expires_at = Utc::now() - chrono::Duration::seconds(1);
+        expired.signature = state.quote_signer.sign(&expired);
+        state.save_price_quote(&expired).await.unwrap();
+        let result = create_order(State(state.clone()), Extension(user.user_id), Json(order_with(quote.quote_id))).await;
+        assert!(matches!(result, Err(AppError::BadRequest(msg)) if msg == "Quote not found or expired"));
+
+        let (orders, total) = OrderRepository::list_for_user(&repo, user.user_id, None, 10, 0).await.unwrap();
+        assert!(orders.is_empty());
+        assert_eq!(total, 0);
+    }
+
+    #[tokio::test]
+    async fn test_create_quote_picker_not_found() {
+        let repo = MemoryRepository::new();
+        let state = with_fixed_price(create_mock_app_state(&repo).await, "0.3");
+        let (user, _) = memory_fixtures(&repo).await;
+
+        let result = create_quote(
+            State(state),
+            Extension(user.user_id),
+            Json(QuoteRequest { picker_id: Uuid::new_v4() }),
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_order_events_streams_status_changes() {
+        use axum::response::IntoResponse;
+
+        let repo = MemoryRepository::new();
+        let state = create_mock_app_state(&repo).await;
+        let (user, picker) = memory_fixtures(&repo).await;
+        let order = Order {
+            order_id: Uuid::new_v4(),
+            user_id: user.user_id,
+            picker_id: picker.picker_id,
+            amount: picker.price,
+            pay_type: PayType::Wallet,
+            status: OrderStatus::Pending,
+            tx_hash: Some("0xabc".to_string()),
+            created_at: Utc::now(),
+            expires_at: Some(Utc::now() + chrono::Duration::hours(1)),
+        };
+        OrderRepository::create(&repo, &order).await.unwrap();
+
+        let response = order_events(State(state.clone()), Extension(user.user_id), Path(order.order_id))
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "text/event-stream"
+        );
+
+        let confirm_repo = repo.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            confirm_repo.mark_success(order.order_id, order.picker_id).await.unwrap();
+        });
+
+        // 先发送当前的 pending 状态，确认后发送 success 并结束
+        let events = collect_events(response).await;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].0, "status");
+        assert_eq!(events[0].1["status"], "pending");
+        assert_eq!(events[0].1["tx_hash"], "0xabc");
+        assert_eq!(events[1].1["status"], "success");
+    }
+
+    #[tokio::test]
+    async fn test_order_events_not_found_for_other_user() {
+        let repo = MemoryRepository::new();
+        let state = create_mock_app_state(&repo).await;
+        let (user, picker) = memory_fixtures(&repo).await;
+        let order = Order {
+            order_id: Uuid::new_v4(),
+            user_id: user.user_id,
+            picker_id: picker.picker_id,
+            amount: picker.price,
+            pay_type: PayType::Wallet,
+            status: OrderStatus::Expired,
+            tx_hash: None,
+            created_at: Utc::now(),
+            expires_at: None,
+        };
+        OrderRepository::create(&repo, &order).await.unwrap();
+
+        let result = order_events(State(state), Extension(Uuid::new_v4()), Path(order.order_id)).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_refund_premium_order_by_developer() {
+        let repo = MemoryRepository::new();
+        let state = create_mock_app_state(&repo).await;
+        let (user, picker) = memory_fixtures(&repo).await;
+        let request = CreateOrderRequest {
+            picker_id: picker.picker_id,
+            pay_type: PayType::Premium,
+            quote_id: None,
+        };
+        let created = create_order(State(state.clone()), Extension(user.user_id), Json(request)).await.unwrap();
+
+        // 买家不能为已支付的订单退款
+        let result = refund_order(
+            State(state.clone()),
+            Extension(user.user_id),
+            Path(created.order_id),
+            Json(RefundOrderRequest::default()),
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+
+        let response = refund_order(
+            State(state.clone()),
+            Extension(picker.dev_user_id),
+            Path(created.order_id),
+            Json(RefundOrderRequest {
+                reason: Some("broken build".to_string()),
+                tx_hash: None,
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status, OrderStatus::Refunded);
+        assert_eq!(response.refund.as_ref().unwrap().reason.as_deref(), Some("broken build"));
+
+        assert_eq!(UserRepository::find_by_id(&repo, user.user_id).await.unwrap().unwrap().premium_balance, 1000);
+        assert_eq!(UserRepository::find_by_id(&repo, picker.dev_user_id).await.unwrap().unwrap().premium_balance, 0);
+        let stored = OrderRepository::find_by_id(&repo, created.order_id).await.unwrap().unwrap();
+        assert_eq!(stored.status, OrderStatus::Refunded);
+    }
+
+    #[tokio::test]
+    async fn test_refund_wallet_order_requires_admin() {
+        let repo = MemoryRepository::new();
+        let mut state = create_mock_app_state(&repo).await;
+        let admin_id = Uuid::new_v4();
+        state.admin_user_ids = vec![admin_id];
+        let (user, picker) = memory_fixtures(&repo).await;
+        let order = Order {
+            order_id: Uuid::new_v4(),
+            user_id: user.user_id,
+            picker_id: picker.picker_id,
+            amount: picker.price,
+            pay_type: PayType::Wallet,
+            status: OrderStatus::Success,
+            tx_hash: Some("0xabc".to_string()),
+            created_at: Utc::now(),
+            expires_at: None,
+        };
+        OrderRepository::create(&repo, &order).await.unwrap();
+        let withdrawal = format!("0x{}", "7".repeat(64));
+
+        let result = refund_order(
+            State(state.clone()),
+            Extension(picker.dev_user_id),
+            Path(order.order_id),
+            Json(RefundOrderRequest {
+                reason: None,
+                tx_hash: Some(withdrawal.clone()),
+            }),
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+
+        let response = refund_order(
+            State(state.clone()),
+            Extension(admin_id),
+            Path(order.order_id),
+            Json(RefundOrderRequest {
+                reason: None,
+                tx_hash: Some(withdrawal.clone()),
+            }),
+        )
+        .await
+        .unwrap();
+        // 提现交易确认前订单仍为 success
+        assert_eq!(response.status, OrderStatus::Success);
+        let refund = response.0.refund.unwrap();
+        assert_eq!(refund.status, crate::models::RefundStatus::Pending);
+        assert_eq!(refund.tx_hash.as_deref(), Some(withdrawal.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_pending_order_and_hide_from_others() {
+        let repo = MemoryRepository::new();
+        let state = create_mock_app_state(&repo).await;
+        let (user, picker) = memory_fixtures(&repo).await;
+        let order = Order {
+            order_id: Uuid::new_v4(),
+            user_id: user.user_id,
+            picker_id: picker.picker_id,
+            amount: picker.price,
+            pay_type: PayType::Wallet,
+            status: OrderStatus::Pending,
+            tx_hash: None,
+            created_at: Utc::now(),
+            expires_at: Some(Utc::now() + chrono::Duration::minutes(30)),
+        };
+        OrderRepository::create(&repo, &order).await.unwrap();
+
+        let result = refund_order(
+            State(state.clone()),
+            Extension(Uuid::new_v4()),
+            Path(order.order_id),
+            Json(RefundOrderRequest::default()),
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+
+        let response = refund_order(
+            State(state.clone()),
+            Extension(user.user_id),
+            Path(order.order_id),
+            Json(RefundOrderRequest::default()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status, OrderStatus::Cancelled);
+        assert!(response.refund.is_none());
+    }
 }
@@ -11,7 +11,9 @@ use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
 use crate::config::AppState;
-use crate::models::{Picker, UserType, User};
+use crate::models::{Picker, PickerChainStatus, PickerVersion, UserType};
+use crate::services::picker_registry::{deactivate_picker, publish_picker};
+use crate::upload::{save_field, StoredUpload, UploadKind};
 use crate::utils::AppError;
 
 // 上传Picker请求
@@ -37,6 +39,8 @@ pub struct UploadPickerRequest {
 #[derive(Debug, Serialize, ToSchema)]
 pub struct UploadPickerResponse {
     pub picker_id: Uuid,
+    /// 合约登记状态，配置了运营方私钥时为 pending，登记结果在后台更新
+    pub chain_status: PickerChainStatus,
     pub message: String,
 }
 
@@ -62,6 +66,49 @@ pub struct PickerInfo {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub status: String,
+    pub chain_status: PickerChainStatus,
+}
+
+impl From<Picker> for PickerInfo {
+    fn from(picker: Picker) -> Self {
+        Self {
+            picker_id: picker.picker_id,
+            dev_user_id: picker.dev_user_id,
+            alias: picker.alias,
+            description: picker.description,
+            price: picker.price,
+            image_path: picker.image_path,
+            version: picker.version,
+            download_count: picker.download_count,
+            created_at: picker.created_at,
+            updated_at: picker.updated_at,
+            status: picker.status,
+            chain_status: picker.chain_status,
+        }
+    }
+}
+
+// 修改Picker请求，只更新提交的字段
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdatePickerRequest {
+    /// Picker别名
+    pub alias: Option<String>,
+    /// 描述信息
+    pub description: Option<String>,
+    /// 价格（分为单位）
+    pub price: Option<i64>,
+    /// 上架状态：active 重新上架，inactive 下架
+    pub status: Option<String>,
+    /// 新的图片文件
+    #[schema(value_type = Option<String>, format = Binary)]
+    pub image: Option<()>,
+}
+
+// 删除Picker响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeletePickerResponse {
+    pub picker_id: Uuid,
+    pub message: String,
 }
 
 // 市场响应
@@ -71,6 +118,48 @@ pub struct MarketResponse {
     pub total: u64,
 }
 
+// 发布新版本请求
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PublishVersionRequest {
+    /// 版本号，同一 Picker 内不能重复
+    pub version: String,
+    /// 更新说明
+    pub changelog: Option<String>,
+    /// Picker文件
+    #[schema(value_type = String, format = Binary)]
+    pub file: (),
+}
+
+// Picker版本信息
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PickerVersionInfo {
+    pub version_id: Uuid,
+    pub version: String,
+    pub changelog: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<PickerVersion> for PickerVersionInfo {
+    fn from(version: PickerVersion) -> Self {
+        Self {
+            version_id: version.version_id,
+            version: version.version,
+            changelog: version.changelog,
+            created_at: version.created_at,
+        }
+    }
+}
+
+// 版本列表响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PickerVersionsResponse {
+    pub picker_id: Uuid,
+    /// 当前下载到的版本
+    pub latest_version: String,
+    /// 按发布时间倒序排列
+    pub versions: Vec<PickerVersionInfo>,
+}
+
 // 上传Picker
 #[utoipa::path(
     post,
@@ -99,13 +188,12 @@ pub async fn upload_picker(
     mut multipart: Multipart,
 ) -> Result<Json<UploadPickerResponse>, AppError> {
     // 验证用户是否为开发者
-    let user = sqlx::query_as::<_, User>(
-        "SELECT * FROM users WHERE user_id = ?",
-    )
-    .bind(user_id)
-    .fetch_one(&state.db)
-    .await
-    .map_err(|_| AppError::NotFound("User not found".to_string()))?;
+    let user = state
+        .users
+        .find_by_id(user_id)
+        .await
+        .map_err(|_| AppError::DatabaseError)?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
 
     if user.user_type != UserType::Dev {
         return Err(AppError::BadRequest("Only developers can upload pickers".to_string()));
@@ -115,8 +203,8 @@ pub async fn upload_picker(
     let mut description = String::new();
     let mut price = 0i64;
     let mut version = String::new();
-    let mut image_path = String::new();
-    let mut file_path = String::new();
+    let mut image = None;
+    let mut file = None;
 
     // 处理multipart数据
     while let Some(field) = multipart.next_field().await.map_err(|_| AppError::BadRequest("Invalid multipart data".to_string()))? {
@@ -137,68 +225,72 @@ pub async fn upload_picker(
                 version = field.text().await.map_err(|_| AppError::BadRequest("Invalid version".to_string()))?;
             }
             "image" => {
-                let filename = field.file_name().unwrap_or("image.jpg").to_string();
-                let data = field.bytes().await.map_err(|_| AppError::BadRequest("Invalid image data".to_string()))?;
-                
-                // 创建上传目录
-                tokio::fs::create_dir_all("uploads/images").await.map_err(|_| AppError::InternalServerError)?;
-                
-                // 生成唯一文件名
-                let unique_filename = format!("{}_{}", Uuid::new_v4(), filename);
-                image_path = format!("uploads/images/{}", unique_filename);
-                
-                // 保存图片文件
-                tokio::fs::write(&image_path, data).await.map_err(|_| AppError::InternalServerError)?;
+                image = Some(save_field(field, UploadKind::Image, state.upload_limits(), &state.blobs).await?);
             }
             "file" => {
-                let filename = field.file_name().unwrap_or("picker_unknown.exe").to_string();
-                let data = field.bytes().await.map_err(|_| AppError::BadRequest("Invalid file data".to_string()))?;
-                
-                // 创建上传目录
-                tokio::fs::create_dir_all("uploads/files").await.map_err(|_| AppError::InternalServerError)?;
-                
-                // 生成唯一文件名
-                let unique_filename = format!("{}_{}", Uuid::new_v4(), filename);
-                file_path = format!("uploads/files/{}", unique_filename);
-                
-                // 保存文件
-                tokio::fs::write(&file_path, data).await.map_err(|_| AppError::InternalServerError)?;
+                file = Some(save_field(field, UploadKind::Picker, state.upload_limits(), &state.blobs).await?);
             }
             _ => {}
         }
     }
 
-    // 验证必填字段
-    if alias.is_empty() || description.is_empty() || version.is_empty() || image_path.is_empty() || file_path.is_empty() {
+    // 验证必填字段，提前返回时已保存的文件随 StoredUpload 一起删除
+    let (Some(image), Some(file)) = (image, file) else {
+        return Err(AppError::BadRequest("Missing required fields".to_string()));
+    };
+    if alias.is_empty() || description.is_empty() || version.is_empty() {
         return Err(AppError::BadRequest("Missing required fields".to_string()));
     }
 
     // 创建Picker记录
     let picker_id = Uuid::new_v4();
     let now = Utc::now();
+    let chain_status = if state.picker_registrar.is_some() {
+        PickerChainStatus::Pending
+    } else {
+        PickerChainStatus::Unregistered
+    };
 
-    sqlx::query(
-        r#"
-        INSERT INTO pickers (picker_id, dev_user_id, alias, description, price, image_path, file_path, version, status, download_count, created_at, updated_at)
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, 'active', 0, ?, ?)
-        "#,
-    )
-    .bind(picker_id)
-    .bind(user_id)
-    .bind(&alias)
-    .bind(&description)
-    .bind(price)
-    .bind(&image_path)
-    .bind(&file_path)
-    .bind(&version)
-    .bind(now.to_rfc3339())
-    .bind(now.to_rfc3339())
-    .execute(&state.db)
-    .await
-    .map_err(|_| AppError::DatabaseError)?;
+    let picker = Picker {
+        picker_id,
+        dev_user_id: user_id,
+        alias,
+        description,
+        price,
+        file_path: file.key().to_string(),
+        download_count: 0,
+        created_at: now,
+        updated_at: now,
+        image_path: image.key().to_string(),
+        version,
+        status: "active".to_string(),
+        chain_status,
+        chain_tx_hash: None,
+        deleted_at: None,
+    };
+    state
+        .pickers
+        .create(&picker)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+    image.keep();
+    file.keep();
+
+    // 在后台登记到 PickerPayment 合约，登记完成前用户无法为该 Picker 发起钱包支付
+    if let Some(registrar) = state.picker_registrar.clone() {
+        let pickers = state.pickers.clone();
+        let settings = state.picker_registration_settings();
+        let dev_wallet = user.wallet_address.clone();
+        tokio::spawn(async move {
+            if let Err(e) = publish_picker(pickers.as_ref(), registrar.as_ref(), &picker, &dev_wallet, settings).await {
+                tracing::error!("Failed to register picker {} on chain: {:?}", picker.picker_id, e);
+            }
+        });
+    }
 
     Ok(Json(UploadPickerResponse {
         picker_id,
+        chain_status,
         message: "Picker uploaded successfully".to_string(),
     }))
 }
@@ -230,68 +322,14 @@ pub async fn get_market(
     let page = if page < 1 { 1 } else { page };
     let offset = (page - 1) * size;
 
-    // 构建查询条件和获取数据
-    let (pickers, total) = if let Some(keyword) = &query.keyword {
-        let search_pattern = format!("%{}%", keyword);
-        
-        // 获取总数
-        let total: (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) as count FROM pickers WHERE status = 'active' AND (alias LIKE ? OR description LIKE ?)"
-        )
-        .bind(&search_pattern)
-        .bind(&search_pattern)
-        .fetch_one(&state.db)
-        .await
-        .map_err(|_| AppError::DatabaseError)?;
-
-        // 获取Picker列表
-        let pickers: Vec<Picker> = sqlx::query_as(
-            "SELECT * FROM pickers WHERE status = 'active' AND (alias LIKE ? OR description LIKE ?) ORDER BY created_at DESC LIMIT ? OFFSET ?"
-        )
-        .bind(&search_pattern)
-        .bind(&search_pattern)
-        .bind(size as i64)
-        .bind(offset as i64)
-        .fetch_all(&state.db)
-        .await
-        .map_err(|_| AppError::DatabaseError)?;
-
-        (pickers, total.0)
-    } else {
-        // 获取总数
-        let total: (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) as count FROM pickers WHERE status = 'active'"
-        )
-        .fetch_one(&state.db)
-        .await
-        .map_err(|_| AppError::DatabaseError)?;
-
-        // 获取Picker列表
-        let pickers: Vec<Picker> = sqlx::query_as(
-            "SELECT * FROM pickers WHERE status = 'active' ORDER BY created_at DESC LIMIT ? OFFSET ?"
-        )
-        .bind(size as i64)
-        .bind(offset as i64)
-        .fetch_all(&state.db)
+    // 获取上架的Picker列表和总数
+    let (pickers, total) = state
+        .pickers
+        .list_active(query.keyword.as_deref(), size as i64, offset as i64)
         .await
         .map_err(|_| AppError::DatabaseError)?;
 
-        (pickers, total.0)
-    };
-
-    let picker_infos: Vec<PickerInfo> = pickers.into_iter().map(|p| PickerInfo {
-        picker_id: p.picker_id,
-        dev_user_id: p.dev_user_id,
-        alias: p.alias,
-        description: p.description,
-        price: p.price,
-        image_path: p.image_path,
-        version: p.version,
-        download_count: p.download_count,
-        created_at: p.created_at,
-        updated_at: p.updated_at,
-        status: p.status,
-    }).collect();
+    let picker_infos: Vec<PickerInfo> = pickers.into_iter().map(PickerInfo::from).collect();
 
     Ok(Json(MarketResponse {
         pickers: picker_infos,
@@ -319,34 +357,339 @@ pub async fn get_picker_detail(
     State(state): State<AppState>,
     Path(picker_id): Path<Uuid>,
 ) -> Result<Json<PickerInfo>, AppError> {
-    let picker = sqlx::query_as::<_, Picker>(
-        "SELECT * FROM pickers WHERE picker_id = ? AND status = 'active'",
-    )
-    .bind(picker_id)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| AppError::DatabaseError)?
+    let picker = state
+        .pickers
+        .find_active(picker_id)
+        .await
+        .map_err(|_| AppError::DatabaseError)?
     .ok_or_else(|| AppError::NotFound("Picker not found".to_string()))?;
 
-    Ok(Json(PickerInfo {
+    Ok(Json(picker.into()))
+}
+
+// 查找当前开发者名下未删除的 Picker，其他用户的 Picker 视为不存在
+async fn find_owned_picker(state: &AppState, user_id: Uuid, picker_id: Uuid) -> Result<Picker, AppError> {
+    state
+        .pickers
+        .find_by_id(picker_id)
+        .await
+        .map_err(|_| AppError::DatabaseError)?
+        .filter(|picker| picker.dev_user_id == user_id && picker.deleted_at.is_none())
+        .ok_or_else(|| AppError::NotFound("Picker not found".to_string()))
+}
+
+// 在后台从 PickerPayment 合约移除已登记的 Picker，未配置运营方私钥时上架状态已由调用方更新
+fn spawn_deactivation(state: &AppState, picker: Picker) {
+    let Some(registrar) = state.picker_registrar.clone() else {
+        return;
+    };
+    let pickers = state.pickers.clone();
+    let settings = state.picker_registration_settings();
+    tokio::spawn(async move {
+        if let Err(e) = deactivate_picker(pickers.as_ref(), Some(registrar.as_ref()), &picker, settings).await {
+            tracing::error!("Failed to remove picker {} from chain: {:?}", picker.picker_id, e);
+        }
+    });
+}
+
+// 修改Picker
+#[utoipa::path(
+    patch,
+    path = "/api/pickers/{picker_id}",
+    tag = "pickers",
+    summary = "Update a Picker",
+    description = "The owning developer edits the alias, description, price or image, or lists / unlists the picker. Only submitted fields are changed",
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("picker_id" = uuid::Uuid, Path, description = "Picker's unique identifier")
+    ),
+    request_body(
+        content = UpdatePickerRequest,
+        content_type = "multipart/form-data",
+        description = "Fields to update"
+    ),
+    responses(
+        (status = 200, description = "Picker updated", body = PickerInfo),
+        (status = 400, description = "Bad request, invalid field values", body = crate::openapi::ErrorResponse),
+        (status = 401, description = "Unauthorized access", body = crate::openapi::ErrorResponse),
+        (status = 404, description = "Picker not found, deleted or not owned by the user", body = crate::openapi::ErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn update_picker(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(picker_id): Path<Uuid>,
+    mut multipart: Multipart,
+) -> Result<Json<PickerInfo>, AppError> {
+    let previous = find_owned_picker(&state, user_id, picker_id).await?;
+    let mut picker = previous.clone();
+    let mut image: Option<StoredUpload> = None;
+
+    // 图片在更新成功前由 StoredUpload 管理，任一字段校验失败时自动删除
+    while let Some(field) = multipart.next_field().await.map_err(|_| AppError::BadRequest("Invalid multipart data".to_string()))? {
+        let name = field.name().unwrap_or("").to_string();
+        match name.as_str() {
+            "alias" => {
+                let alias = field.text().await.map_err(|_| AppError::BadRequest("Invalid alias".to_string()))?;
+                if alias.trim().is_empty() {
+                    return Err(AppError::BadRequest("Alias cannot be empty".to_string()));
+                }
+                picker.alias = alias;
+            }
+            "description" => {
+                let description = field.text().await.map_err(|_| AppError::BadRequest("Invalid description".to_string()))?;
+                if description.trim().is_empty() {
+                    return Err(AppError::BadRequest("Description cannot be empty".to_string()));
+                }
+                picker.description = description;
+            }
+            "price" => {
+                let price_str = field.text().await.map_err(|_| AppError::BadRequest("Invalid price".to_string()))?;
+                picker.price = price_str.parse().map_err(|_| AppError::BadRequest("Invalid price format".to_string()))?;
+                if picker.price < 0 {
+                    return Err(AppError::BadRequest("Price cannot be negative".to_string()));
+                }
+            }
+            "status" => {
+                let status = field.text().await.map_err(|_| AppError::BadRequest("Invalid status".to_string()))?;
+                if status != "active" && status != "inactive" {
+                    return Err(AppError::BadRequest("Status must be active or inactive".to_string()));
+                }
+                picker.status = status;
+            }
+            "image" => {
+                let upload = save_field(field, UploadKind::Image, state.upload_limits(), &state.blobs).await?;
+                picker.image_path = upload.key().to_string();
+                image = Some(upload);
+            }
+            _ => {}
+        }
+    }
+
+    picker.updated_at = Utc::now();
+
+    let updated = state
+        .pickers
+        .update(&picker)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+    if !updated {
+        return Err(AppError::NotFound("Picker not found".to_string()));
+    }
+    if let Some(image) = image {
+        image.keep();
+        // 相同内容的文件只保存一份，旧图片不再被任何 Picker 引用时才删除
+        if let Ok(false) = state.pickers.is_file_referenced(&previous.image_path).await {
+            if let Err(e) = state.blobs.delete(&previous.image_path).await {
+                tracing::warn!("Failed to remove replaced image {}: {}", previous.image_path, e);
+            }
+        }
+    }
+
+    // 上架状态变化时同步合约登记：下架时移除，重新上架时重新登记
+    match (previous.status.as_str(), picker.status.as_str()) {
+        ("active", "inactive") => spawn_deactivation(&state, picker.clone()),
+        ("inactive", "active") => {
+            if let Some(registrar) = state.picker_registrar.clone() {
+                let dev = state
+                    .users
+                    .find_by_id(user_id)
+                    .await
+                    .map_err(|_| AppError::DatabaseError)?
+                    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+                let pickers = state.pickers.clone();
+                let settings = state.picker_registration_settings();
+                let listed = picker.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = publish_picker(pickers.as_ref(), registrar.as_ref(), &listed, &dev.wallet_address, settings).await {
+                        tracing::error!("Failed to register picker {} on chain: {:?}", listed.picker_id, e);
+                    }
+                });
+            }
+        }
+        _ => {}
+    }
+
+    Ok(Json(picker.into()))
+}
+
+// 删除Picker
+#[utoipa::path(
+    delete,
+    path = "/api/pickers/{picker_id}",
+    tag = "pickers",
+    summary = "Delete a Picker",
+    description = "The owning developer removes the picker from the market. The picker is soft-deleted: existing orders can still download and be refunded, but it can no longer be edited or listed",
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("picker_id" = uuid::Uuid, Path, description = "Picker's unique identifier")
+    ),
+    responses(
+        (status = 200, description = "Picker deleted", body = DeletePickerResponse),
+        (status = 401, description = "Unauthorized access", body = crate::openapi::ErrorResponse),
+        (status = 404, description = "Picker not found, already deleted or not owned by the user", body = crate::openapi::ErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn delete_picker(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(picker_id): Path<Uuid>,
+) -> Result<Json<DeletePickerResponse>, AppError> {
+    let picker = find_owned_picker(&state, user_id, picker_id).await?;
+
+    let deleted = state
+        .pickers
+        .soft_delete(picker_id, Utc::now())
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+    if !deleted {
+        return Err(AppError::NotFound("Picker not found".to_string()));
+    }
+    spawn_deactivation(&state, picker);
+
+    Ok(Json(DeletePickerResponse {
+        picker_id,
+        message: "Picker deleted successfully".to_string(),
+    }))
+}
+
+// 发布新版本
+#[utoipa::path(
+    post,
+    path = "/api/pickers/{picker_id}/versions",
+    tag = "pickers",
+    summary = "Publish a new Picker version",
+    description = "The owning developer uploads a new build; existing orders download the latest version",
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("picker_id" = uuid::Uuid, Path, description = "Picker's unique identifier")
+    ),
+    request_body(
+        content = PublishVersionRequest,
+        content_type = "multipart/form-data",
+        description = "Picker file and version information"
+    ),
+    responses(
+        (status = 200, description = "Version published", body = PickerVersionInfo),
+        (status = 400, description = "Bad request, missing version or file", body = crate::openapi::ErrorResponse),
+        (status = 401, description = "Unauthorized access", body = crate::openapi::ErrorResponse),
+        (status = 404, description = "Picker not found or not owned by the user", body = crate::openapi::ErrorResponse),
+        (status = 422, description = "Version already exists", body = crate::openapi::ErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn publish_picker_version(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(picker_id): Path<Uuid>,
+    mut multipart: Multipart,
+) -> Result<Json<PickerVersionInfo>, AppError> {
+    let picker = find_owned_picker(&state, user_id, picker_id).await?;
+
+    let mut version = String::new();
+    let mut changelog: Option<String> = None;
+    let mut file = None;
+
+    while let Some(field) = multipart.next_field().await.map_err(|_| AppError::BadRequest("Invalid multipart data".to_string()))? {
+        let name = field.name().unwrap_or("").to_string();
+        match name.as_str() {
+            "version" => {
+                version = field.text().await.map_err(|_| AppError::BadRequest("Invalid version".to_string()))?;
+            }
+            "changelog" => {
+                let text = field.text().await.map_err(|_| AppError::BadRequest("Invalid changelog".to_string()))?;
+                changelog = Some(text).filter(|text| !text.trim().is_empty());
+            }
+            "file" => {
+                file = Some(save_field(field, UploadKind::Picker, state.upload_limits(), &state.blobs).await?);
+            }
+            _ => {}
+        }
+    }
+
+    let version = version.trim().to_string();
+    let Some(file) = file.filter(|_| !version.is_empty()) else {
+        return Err(AppError::BadRequest("Missing required fields".to_string()));
+    };
+
+    let picker_version = PickerVersion {
+        version_id: Uuid::new_v4(),
         picker_id: picker.picker_id,
-        dev_user_id: picker.dev_user_id,
-        alias: picker.alias,
-        description: picker.description,
-        price: picker.price,
-        image_path: picker.image_path,
-        version: picker.version,
-        download_count: picker.download_count as i64,
-        created_at: picker.created_at,
-        updated_at: picker.updated_at,
-        status: picker.status,
+        version,
+        file_path: file.key().to_string(),
+        changelog,
+        created_at: Utc::now(),
+    };
+    let published = state
+        .pickers
+        .publish_version(&picker_version)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+    if !published {
+        return Err(AppError::UnprocessableEntity("Version already exists".to_string()));
+    }
+    file.keep();
+
+    Ok(Json(picker_version.into()))
+}
+
+// 获取版本历史
+#[utoipa::path(
+    get,
+    path = "/api/pickers/{picker_id}/versions",
+    tag = "pickers",
+    summary = "Get Picker Versions",
+    description = "Get the version history of a picker, newest first",
+    params(
+        ("picker_id" = uuid::Uuid, Path, description = "Picker's unique identifier")
+    ),
+    responses(
+        (status = 200, description = "Get versions successfully", body = PickerVersionsResponse),
+        (status = 404, description = "Picker not found", body = crate::openapi::ErrorResponse),
+        (status = 500, description = "Internal server error", body = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn get_picker_versions(
+    State(state): State<AppState>,
+    Path(picker_id): Path<Uuid>,
+) -> Result<Json<PickerVersionsResponse>, AppError> {
+    let picker = state
+        .pickers
+        .find_active(picker_id)
+        .await
+        .map_err(|_| AppError::DatabaseError)?
+        .ok_or_else(|| AppError::NotFound("Picker not found".to_string()))?;
+
+    let versions = state
+        .pickers
+        .list_versions(picker_id)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+
+    Ok(Json(PickerVersionsResponse {
+        picker_id,
+        latest_version: picker.version,
+        versions: versions.into_iter().map(PickerVersionInfo::from).collect(),
     }))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils_tests::create_test_app_state;
+    use crate::storage::content_key;
+    use crate::utils_tests::{
+        create_test_app_state, eventually, multipart_body, picker_archive, test_blob_root, test_pool, TEST_PNG,
+    };
+    use futures_util::TryStreamExt;
+    use sha2::{Digest, Sha256};
     // use crate::models::{OrderStatus};
     use axum::extract::{Query, State, Path};
     use chrono::Utc;
@@ -369,7 +712,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -385,7 +728,7 @@ mod tests {
         .bind(dev_user_id)
         .bind(now.to_rfc3339())
         .bind(now.to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -423,7 +766,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -441,7 +784,7 @@ mod tests {
         .bind(dev_user_id)
         .bind(now.to_rfc3339())
         .bind(now.to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
         
@@ -457,7 +800,7 @@ mod tests {
         .bind(dev_user_id)
         .bind(now.to_rfc3339())
         .bind(now.to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -491,7 +834,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -511,7 +854,7 @@ mod tests {
             .bind(format!("{}{}", test_picker_prefix, i))
             .bind(now.to_rfc3339())
             .bind(now.to_rfc3339())
-            .execute(&state.db)
+            .execute(test_pool(&state))
             .await
             .unwrap();
         }
@@ -551,7 +894,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -570,7 +913,7 @@ mod tests {
         .bind(test_picker_name)
         .bind(now.to_rfc3339())
         .bind(now.to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -606,7 +949,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -621,7 +964,7 @@ mod tests {
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -668,7 +1011,7 @@ mod tests {
         )
         .bind(non_dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -716,7 +1059,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -784,7 +1127,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -803,7 +1146,7 @@ mod tests {
         .bind(test_picker_name)
         .bind(now.to_rfc3339())
         .bind(now.to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -842,7 +1185,7 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -857,7 +1200,7 @@ mod tests {
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
@@ -886,13 +1229,13 @@ mod tests {
         )
         .bind(dev_user_id)
         .bind(Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
 
         // 创建测试路由
         use axum::Router;
-        use axum::http::{Request, StatusCode};
+        use axum::http::StatusCode;
         use tower::ServiceExt;
         
         let app = Router::new()
@@ -903,18 +1246,317 @@ mod tests {
             }))
             .with_state(state.clone());
 
-        // 创建multipart请求体
-        let boundary = "boundary123";
-        let body = format!("--{boundary}\r\nContent-Disposition: form-data; name=\"alias\"\r\n\r\nTest Picker\r\n--{boundary}\r\nContent-Disposition: form-data; name=\"description\"\r\n\r\nTest Description\r\n--{boundary}\r\nContent-Disposition: form-data; name=\"price\"\r\n\r\n500\r\n--{boundary}\r\nContent-Disposition: form-data; name=\"version\"\r\n\r\n1.0\r\n--{boundary}\r\nContent-Disposition: form-data; name=\"image\"; filename=\"test.jpg\"\r\nContent-Type: image/jpeg\r\n\r\ntest_image_data\r\n--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"test.exe\"\r\nContent-Type: application/octet-stream\r\n\r\ntest_file_data\r\n--{boundary}--\r\n");
+        let archive = picker_archive(&["my-picker/entry.py", "my-picker/config.toml"]);
+        let response = app.clone().oneshot(upload_request(&archive, TEST_PNG)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
 
-        let request = Request::builder()
+        // 文件以内容的 sha256 与识别出的格式命名，不使用客户端提供的文件名
+        let (pickers, _) = state.pickers.list_for_dev(dev_user_id, 10, 0).await.unwrap();
+        assert_eq!(pickers.len(), 1);
+        let picker = &pickers[0];
+        assert_eq!(picker.file_path, content_key(&Sha256::digest(&archive), "zip"));
+        assert_eq!(picker.image_path, content_key(&Sha256::digest(TEST_PNG), "png"));
+        let blob = state.blobs.get(&picker.file_path).await.unwrap();
+        let chunks: Vec<axum::body::Bytes> = blob.stream.try_collect().await.unwrap();
+        assert_eq!(chunks.concat(), archive);
+
+        // 相同内容只保存一份
+        let response = app.oneshot(upload_request(&archive, TEST_PNG)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let (pickers, _) = state.pickers.list_for_dev(dev_user_id, 10, 0).await.unwrap();
+        assert_eq!(pickers.len(), 2);
+        assert_eq!(pickers[0].file_path, pickers[1].file_path);
+        state.blobs.delete(&pickers[0].file_path).await.unwrap();
+        state.blobs.delete(&pickers[0].image_path).await.unwrap();
+    }
+
+    // 上传请求：基本信息、图片与 Picker 压缩包
+    fn upload_request(file: &[u8], image: &[u8]) -> axum::http::Request<axum::body::Body> {
+        let boundary = "boundary123";
+        let body = multipart_body(
+            boundary,
+            &[
+                ("alias", None, b"Test Picker"),
+                ("description", None, b"Test Description"),
+                ("price", None, b"500"),
+                ("version", None, b"1.0"),
+                ("image", Some("test.jpg"), image),
+                ("file", Some("test.exe"), file),
+            ],
+        );
+        axum::http::Request::builder()
             .method("POST")
             .uri("/api/pickers")
             .header("content-type", format!("multipart/form-data; boundary={boundary}"))
             .body(axum::body::Body::from(body))
+            .unwrap()
+    }
+
+    // 文件存储与临时目录中的文件数，用于确认失败的上传没有留下文件
+    fn stored_upload_count() -> usize {
+        let shards = std::fs::read_dir(test_blob_root()).into_iter().flatten().flatten().map(|entry| entry.path());
+        std::iter::once(std::env::temp_dir().join("pickers-server-uploads"))
+            .chain(shards)
+            .filter_map(|dir| std::fs::read_dir(dir).ok())
+            .map(|entries| entries.count())
+            .sum()
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_upload_picker_rejects_invalid_files() {
+        let mut state = create_test_app_state().await;
+        state.upload_max_picker_bytes = 1024;
+        let dev_user_id = Uuid::new_v4();
+        sqlx::query(
+            r#"
+            INSERT INTO users (user_id, email, user_name, user_password, user_type, private_key, wallet_address, premium_balance, created_at)
+            VALUES (?, 'dev@test.com', 'Dev User', 'hashed_password', 'dev', 'private_key_123', 'devwallet123', 0, ?)
+            "#,
+        )
+        .bind(dev_user_id)
+        .bind(Utc::now().to_rfc3339())
+        .execute(test_pool(&state))
+        .await
+        .unwrap();
+
+        use axum::Router;
+        use axum::http::StatusCode;
+        use tower::ServiceExt;
+
+        let app = Router::new()
+            .route("/api/pickers", axum::routing::post(upload_picker))
+            .layer(axum::middleware::from_fn_with_state(state.clone(), move |State(_state): State<AppState>, mut request: axum::http::Request<axum::body::Body>, next: axum::middleware::Next| async move {
+                request.extensions_mut().insert(dev_user_id);
+                next.run(request).await
+            }))
+            .with_state(state.clone());
+
+        let before = stored_upload_count();
+        let archive = picker_archive(&["entry.sh"]);
+        let cases: [(Vec<u8>, &[u8]); 5] = [
+            // 非 zip 文件
+            (b"MZ\x90\0 not an archive".to_vec(), TEST_PNG),
+            // 缺少入口文件的压缩包
+            (picker_archive(&["main.py", "entry.py.bak"]), TEST_PNG),
+            // 超过大小上限
+            ([archive.as_slice(), &[0u8; 1024]].concat(), TEST_PNG),
+            // 图片格式不被接受
+            (archive.clone(), b"GIF89a\x01\0\x01\0"),
+            (archive.clone(), b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"),
+        ];
+        for (file, image) in cases {
+            let response = app.clone().oneshot(upload_request(&file, image)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+        assert!(state.pickers.list_for_dev(dev_user_id, 10, 0).await.unwrap().0.is_empty());
+        assert_eq!(stored_upload_count(), before);
+
+        // 写入数据库失败时删除已保存的文件
+        sqlx::query("DROP TABLE picker_versions").execute(test_pool(&state)).await.unwrap();
+        let response = app.oneshot(upload_request(&archive, TEST_PNG)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(eventually(|| async { stored_upload_count() == before }).await);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_publish_picker_version() {
+        let state = create_test_app_state().await;
+        let dev_user_id = Uuid::new_v4();
+        let other_user_id = Uuid::new_v4();
+
+        for (user_id, email) in [(dev_user_id, "dev@test.com"), (other_user_id, "other@test.com")] {
+            sqlx::query(
+                r#"
+                INSERT INTO users (user_id, email, user_name, user_password, user_type, private_key, wallet_address, premium_balance, created_at)
+                VALUES (?, ?, 'Dev User', 'hashed_password', 'dev', 'private_key_123', 'devwallet123', 0, ?)
+                "#,
+            )
+            .bind(user_id)
+            .bind(email)
+            .bind(Utc::now().to_rfc3339())
+            .execute(test_pool(&state))
+            .await
             .unwrap();
+        }
 
-        let response = app.oneshot(request).await.unwrap();
+        let now = Utc::now();
+        let picker = Picker {
+            picker_id: Uuid::new_v4(),
+            dev_user_id,
+            alias: "Test Picker".to_string(),
+            description: "Test Description".to_string(),
+            price: 500,
+            file_path: "uploads/files/test.exe".to_string(),
+            download_count: 0,
+            created_at: now,
+            updated_at: now,
+            image_path: "test.jpg".to_string(),
+            version: "1.0".to_string(),
+            status: "active".to_string(),
+            chain_status: PickerChainStatus::Unregistered,
+            chain_tx_hash: None,
+            deleted_at: None,
+        };
+        state.pickers.create(&picker).await.unwrap();
+
+        use axum::Router;
+        use axum::http::{Request, StatusCode};
+        use tower::ServiceExt;
+
+        let publish = |user_id: Uuid, version: &str| {
+            let app = Router::new()
+                .route("/api/pickers/{picker_id}/versions", axum::routing::post(publish_picker_version))
+                .layer(axum::middleware::from_fn_with_state(state.clone(), move |State(_state): State<AppState>, mut request: axum::http::Request<axum::body::Body>, next: axum::middleware::Next| async move {
+                    request.extensions_mut().insert(user_id);
+                    next.run(request).await
+                }))
+                .with_state(state.clone());
+            let boundary = "boundary123";
+            let body = multipart_body(
+                boundary,
+                &[
+                    ("version", None, version.as_bytes()),
+                    ("changelog", None, b"Bug fixes"),
+                    ("file", Some("picker.zip"), &picker_archive(&["entry.js"])),
+                ],
+            );
+            let request = Request::builder()
+                .method("POST")
+                .uri(format!("/api/pickers/{}/versions", picker.picker_id))
+                .header("content-type", format!("multipart/form-data; boundary={boundary}"))
+                .body(axum::body::Body::from(body))
+                .unwrap();
+            app.oneshot(request)
+        };
+
+        // 其他开发者不能为该 Picker 发布版本
+        let response = publish(other_user_id, "1.1").await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = publish(dev_user_id, "1.1").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = publish(dev_user_id, "1.1").await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // Picker 指向最新版本的文件，已有订单下载到的即为该文件
+        let stored = state.pickers.find_by_id(picker.picker_id).await.unwrap().unwrap();
+        assert_eq!(stored.version, "1.1");
+        assert_ne!(stored.file_path, picker.file_path);
+        state.blobs.delete(&stored.file_path).await.unwrap();
+
+        let response = get_picker_versions(State(state.clone()), Path(picker.picker_id)).await.unwrap();
+        assert_eq!(response.latest_version, "1.1");
+        let versions: Vec<&str> = response.versions.iter().map(|v| v.version.as_str()).collect();
+        assert_eq!(versions, vec!["1.1", "1.0"]);
+        assert_eq!(response.versions[0].changelog.as_deref(), Some("Bug fixes"));
+
+        let result = get_picker_versions(State(state), Path(Uuid::new_v4())).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_update_and_delete_picker() {
+        let state = create_test_app_state().await;
+        let dev_user_id = Uuid::new_v4();
+        let other_user_id = Uuid::new_v4();
+
+        for (user_id, email) in [(dev_user_id, "dev@test.com"), (other_user_id, "other@test.com")] {
+            sqlx::query(
+                r#"
+                INSERT INTO users (user_id, email, user_name, user_password, user_type, private_key, wallet_address, premium_balance, created_at)
+                VALUES (?, ?, 'Dev User', 'hashed_password', 'dev', 'private_key_123', 'devwallet123', 0, ?)
+                "#,
+            )
+            .bind(user_id)
+            .bind(email)
+            .bind(Utc::now().to_rfc3339())
+            .execute(test_pool(&state))
+            .await
+            .unwrap();
+        }
+
+        let now = Utc::now();
+        let picker = Picker {
+            picker_id: Uuid::new_v4(),
+            dev_user_id,
+            alias: "Test Picker".to_string(),
+            description: "Test Description".to_string(),
+            price: 500,
+            file_path: "uploads/files/test.exe".to_string(),
+            download_count: 3,
+            created_at: now,
+            updated_at: now,
+            image_path: "test.jpg".to_string(),
+            version: "1.0".to_string(),
+            status: "active".to_string(),
+            chain_status: PickerChainStatus::Unregistered,
+            chain_tx_hash: None,
+            deleted_at: None,
+        };
+        state.pickers.create(&picker).await.unwrap();
+
+        use axum::Router;
+        use axum::http::{Request, StatusCode};
+        use tower::ServiceExt;
+
+        let send = |user_id: Uuid, method: &str, fields: &[(&str, &str)]| {
+            let app = Router::new()
+                .route("/api/pickers/{picker_id}", axum::routing::patch(update_picker).delete(delete_picker))
+                .layer(axum::middleware::from_fn_with_state(state.clone(), move |State(_state): State<AppState>, mut request: axum::http::Request<axum::body::Body>, next: axum::middleware::Next| async move {
+                    request.extensions_mut().insert(user_id);
+                    next.run(request).await
+                }))
+                .with_state(state.clone());
+            let boundary = "boundary123";
+            let mut body = String::new();
+            for (name, value) in fields {
+                body.push_str(&format!("--{boundary}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n"));
+            }
+            body.push_str(&format!("--{boundary}--\r\n"));
+            let request = Request::builder()
+                .method(method)
+                .uri(format!("/api/pickers/{}", picker.picker_id))
+                .header("content-type", format!("multipart/form-data; boundary={boundary}"))
+                .body(axum::body::Body::from(body))
+                .unwrap();
+            app.oneshot(request)
+        };
+
+        // 其他开发者看不到该 Picker
+        let response = send(other_user_id, "PATCH", &[("price", "1")]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = send(other_user_id, "DELETE", &[]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = send(dev_user_id, "PATCH", &[("price", "-1")]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = send(dev_user_id, "PATCH", &[("status", "deleted")]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // 只修改提交的字段，下架后不再出现在市场中
+        let response = send(dev_user_id, "PATCH", &[("alias", "Renamed Picker"), ("price", "800"), ("status", "inactive")])
+            .await
+            .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+        let stored = state.pickers.find_by_id(picker.picker_id).await.unwrap().unwrap();
+        assert_eq!(stored.alias, "Renamed Picker");
+        assert_eq!(stored.description, "Test Description");
+        assert_eq!(stored.price, 800);
+        assert_eq!(stored.status, "inactive");
+        assert!(state.pickers.find_active(picker.picker_id).await.unwrap().is_none());
+
+        // 软删除后记录仍在，已有订单可以继续下载，但不能再修改
+        let response = send(dev_user_id, "DELETE", &[]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let stored = state.pickers.find_by_id(picker.picker_id).await.unwrap().unwrap();
+        assert!(stored.deleted_at.is_some());
+        assert_eq!(stored.download_count, 3);
+        let response = send(dev_user_id, "PATCH", &[("status", "active")]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = send(dev_user_id, "DELETE", &[]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
-}
\ No newline at end of file
+}
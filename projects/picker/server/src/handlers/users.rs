@@ -1,19 +1,23 @@
 use axum::{
-    extract::State,
+    extract::{Query, State},
     response::Json,
     Extension,
 };
 use chrono::{DateTime, Utc, Duration};
-use jsonwebtoken::{encode, Header, EncodingKey};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
-use tracing::info;
+use std::path::Path;
+use tracing::{error, info};
 use uuid::Uuid;
 
 use crate::config::{AppState, Claims, PendingRegistration};
-use crate::models::{User, UserType, VerificationCode};
-use crate::utils::{generate_wallet, hash_password_with_user_id, verify_password_with_user_id, AppError};
+use crate::mailer::{password_reset_email, verification_email};
+use crate::models::{PremiumLedgerEntry, RefreshToken, User, UserType, VerificationCode};
+use crate::utils::{generate_token, generate_wallet, verify_password_with_user_id, AppError};
+
+// 验证码有效期（分钟）
+const VERIFICATION_CODE_EXPIRES_MINUTES: i64 = 10;
 
 // 注册请求
 #[derive(Debug, Deserialize, ToSchema)]
@@ -42,6 +46,9 @@ pub struct VerifyRequest {
 #[derive(Debug, Serialize, ToSchema)]
 pub struct VerifyResponse {
     pub token: String,
+    pub refresh_token: String,
+    // 访问令牌有效期（秒）
+    pub expires_in: i64,
     pub user: UserInfo,
 }
 
@@ -56,9 +63,76 @@ pub struct LoginRequest {
 #[derive(Debug, Serialize, ToSchema)]
 pub struct LoginResponse {
     pub token: String,
+    pub refresh_token: String,
+    // 访问令牌有效期（秒）
+    pub expires_in: i64,
     pub user: UserInfo,
 }
 
+// 刷新令牌请求
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+// 刷新令牌响应，旧的刷新令牌随即失效
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RefreshResponse {
+    pub token: String,
+    pub refresh_token: String,
+    // 访问令牌有效期（秒）
+    pub expires_in: i64,
+}
+
+// 登出请求，携带刷新令牌时一并作废
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LogoutRequest {
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+}
+
+// 登出响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LogoutResponse {
+    pub message: String,
+}
+
+// 忘记密码请求
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+// 重置密码请求
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ResetPasswordRequest {
+    pub email: String,
+    pub code: String,
+    pub new_password: String,
+}
+
+// 忘记密码与重置密码响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PasswordResponse {
+    pub message: String,
+}
+
+// 修改密码请求
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+// 修改密码响应，此前签发的令牌全部失效，客户端需改用新令牌
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChangePasswordResponse {
+    pub token: String,
+    pub refresh_token: String,
+    // 访问令牌有效期（秒）
+    pub expires_in: i64,
+}
+
 // 用户信息
 #[derive(Debug, Serialize, ToSchema)]
 pub struct UserInfo {
@@ -85,6 +159,24 @@ impl From<User> for UserInfo {
     }
 }
 
+// 积分流水查询参数
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LedgerQuery {
+    pub page: Option<u32>,
+    pub size: Option<u32>,
+}
+
+// 积分流水响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PremiumLedgerResponse {
+    pub premium_balance: i64,
+    pub entries: Vec<PremiumLedgerEntry>,
+    pub total: u64,
+    pub page: u32,
+    pub size: u32,
+    pub has_next: bool,
+}
+
 // 系统信息响应
 #[derive(Debug, Serialize, ToSchema)]
 pub struct SystemInfoResponse {
@@ -135,7 +227,9 @@ pub async fn get_system_info(
     responses(
         (status = 200, description = "Registration successful", body = RegisterResponse),
         (status = 400, description = "Bad request, invalid parameters", body = crate::openapi::ErrorResponse),
-        (status = 422, description = "Email already registered", body = crate::openapi::ErrorResponse)
+        (status = 422, description = "Email already registered", body = crate::openapi::ErrorResponse),
+        (status = 429, description = "Too many requests, see Retry-After", body = crate::openapi::ErrorResponse),
+        (status = 500, description = "Failed to send verification email", body = crate::openapi::ErrorResponse)
     )
 )]
 pub async fn register(
@@ -148,25 +242,23 @@ pub async fn register(
     }
 
     // 检查邮箱是否已存在
-    let existing_user = sqlx::query_as::<_, User>(
-        "SELECT * FROM users WHERE email = ?",
-    )
-    .bind(&payload.email)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| AppError::DatabaseError)?;
+    let existing_user = state
+        .users
+        .find_by_email(&payload.email)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
 
     if existing_user.is_some() {
         return Err(AppError::UnprocessableEntity("Email already registered".to_string()));
     }
 
     // 检查是否有相同邮箱的待注册信息
-    let pending_exists = {
-        let pending = state.pending_registrations.lock().unwrap();
-        pending.contains_key(&payload.email)
-    };
+    let pending_registration = state
+        .pending_registration(&payload.email)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
 
-    if pending_exists {
+    if pending_registration.is_some() {
         return Err(AppError::UnprocessableEntity("Email is already in registration process, please verify or wait for expiration".to_string()));
     }
 
@@ -176,37 +268,56 @@ pub async fn register(
         // _ => return Err(AppError::UnprocessableEntity("Invalid user type".to_string())),
     }
 
+    // 先哈希密码，失败时不留下验证码
+    let user_password = state.password_hashing.hash(&payload.user_password)?;
+
     // 生成验证码
     let code = generate_verification_code();
     let now = Utc::now();
     let verification_code = VerificationCode {
         code: code.clone(),
-        expires_at: now + Duration::minutes(10),
+        expires_at: now + Duration::minutes(VERIFICATION_CODE_EXPIRES_MINUTES),
         email: payload.email.clone(),
     };
 
     // 存储验证码
-    state.verification_codes.lock().unwrap().insert(
-        payload.email.clone(),
-        verification_code,
-    );
+    state
+        .save_verification_code(&verification_code)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
 
-    // 存储待验证的注册信息
+    // 存储待验证的注册信息，提前分配用户ID，只保存密码哈希
+    let user_id = Uuid::new_v4();
+    let user_name = payload.user_name;
     let pending_registration = PendingRegistration {
+        user_id,
         email: payload.email.clone(),
-        user_name: payload.user_name,
-        user_password: payload.user_password,
+        user_name: user_name.clone(),
+        user_password,
         user_type: payload.user_type,
         created_at: now,
     };
 
-    state.pending_registrations.lock().unwrap().insert(
-        payload.email.clone(),
-        pending_registration,
+    state
+        .save_pending_registration(&pending_registration)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+
+    // 发送验证码邮件，发送失败时撤销本次注册请求，允许用户重新提交
+    let email = verification_email(
+        &payload.email,
+        &user_name,
+        &code,
+        VERIFICATION_CODE_EXPIRES_MINUTES,
+        state.mail_templates_dir.as_deref().map(Path::new),
     );
-
-    // 模拟发送邮件（实际应用中应该调用邮件服务）
-    info!("Verification code for {}: {}", payload.email, code);
+    if let Err(e) = state.mailer.send(&email).await {
+        error!("Failed to send verification email to {}: {}", payload.email, e);
+        let _ = state.remove_verification_code(&payload.email).await;
+        let _ = state.remove_pending_registration(&payload.email).await;
+        return Err(AppError::InternalServerError);
+    }
+    info!("Verification email sent to {}", payload.email);
 
     Ok(Json(RegisterResponse {
         user_id: Uuid::nil(), // 暂时返回空ID，因为用户还未创建
@@ -224,35 +335,51 @@ pub async fn register(
     request_body = VerifyRequest,
     responses(
         (status = 200, description = "Verification successful", body = VerifyResponse),
-        (status = 400, description = "Verification failed, invalid code or expired", body = crate::openapi::ErrorResponse)
+        (status = 400, description = "Verification failed, invalid code or expired", body = crate::openapi::ErrorResponse),
+        (status = 429, description = "Too many requests or too many failed attempts, see Retry-After", body = crate::openapi::ErrorResponse)
     )
 )]
 pub async fn verify(
     State(state): State<AppState>,
     Json(payload): Json<VerifyRequest>,
 ) -> Result<Json<VerifyResponse>, AppError> {
-    // 检查验证码
-    let verification_code = {
-        let codes = state.verification_codes.lock().unwrap();
-        codes.get(&payload.email).cloned()
-    };
-
-    let verification_code = verification_code.ok_or_else(|| {
-        AppError::BadRequest("Verification failed, invalid code or expired".to_string())
-    })?;
+    // 验证失败次数过多的邮箱在锁定期内直接拒绝
+    let locked_until = state
+        .verification_locked_until(&payload.email)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+    if let Some(locked_until) = locked_until {
+        let retry_after = (locked_until - Utc::now()).num_seconds().max(1) as u64;
+        return Err(AppError::TooManyRequests(retry_after));
+    }
 
-    if verification_code.code != payload.code || verification_code.expires_at < Utc::now() {
-        // 验证失败，清理临时数据
-        state.verification_codes.lock().unwrap().remove(&payload.email);
-        state.pending_registrations.lock().unwrap().remove(&payload.email);
+    // 检查验证码
+    let verification_code = state
+        .verification_code(&payload.email)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+
+    let code_valid = matches!(
+        &verification_code,
+        Some(code) if code.code == payload.code && code.expires_at >= Utc::now()
+    );
+    if !code_valid {
+        // 验证失败，记录失败次数并清理临时数据
+        if state.record_verification_failure(&payload.email).await.is_err() {
+            error!("Failed to record verification failure for {}", payload.email);
+        }
+        if verification_code.is_some() {
+            let _ = state.remove_verification_code(&payload.email).await;
+            let _ = state.remove_pending_registration(&payload.email).await;
+        }
         return Err(AppError::BadRequest("Verification failed, invalid code or expired".to_string()));
     }
 
     // 获取待验证的注册信息
-    let pending_registration = {
-        let pending = state.pending_registrations.lock().unwrap();
-        pending.get(&payload.email).cloned()
-    };
+    let pending_registration = state
+        .pending_registration(&payload.email)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
 
     let pending_registration = pending_registration.ok_or_else(|| {
         AppError::BadRequest("Verification failed, registration information does not exist, please re-register".to_string())
@@ -260,60 +387,41 @@ pub async fn verify(
 
     // 验证通过，开始创建用户
     // 生成钱包地址和私钥
-    let (private_key, wallet_address) = generate_wallet(&state.password_master_key, &state.password_nonce);
+    let (private_key, wallet_address) = generate_wallet(&state.wallet_keyring)?;
     
-    // 创建用户
-    let user_id = Uuid::new_v4();
+    // 创建用户（用户ID与密码哈希在注册时已生成）
     let now = Utc::now();
-    
-    // 哈希密码
-    let hashed_password = hash_password_with_user_id(&pending_registration.user_password, user_id, &state.password_salt);
-
-    sqlx::query(
-        r#"
-        INSERT INTO users (user_id, email, user_name, user_password, user_type, private_key, wallet_address, premium_balance, created_at)
-        VALUES (?, ?, ?, ?, ?, ?, ?, 0, ?)
-        "#,
-    )
-    .bind(user_id)
-    .bind(&pending_registration.email)
-    .bind(&pending_registration.user_name)
-    .bind(&hashed_password)
-    .bind(match pending_registration.user_type {
-        UserType::Gen => "gen",
-        UserType::Dev => "dev",
-    })
-    .bind(&private_key)
-    .bind(&wallet_address)
-    .bind(now.to_rfc3339())
-    .execute(&state.db)
-    .await
-    .map_err(|_| AppError::DatabaseError)?;
-
-    // 获取创建的用户信息
-    let user = sqlx::query_as::<_, User>(
-        "SELECT * FROM users WHERE user_id = ?",
-    )
-    .bind(user_id)
-    .fetch_one(&state.db)
-    .await
-    .map_err(|_| AppError::DatabaseError)?;
-
-    // 生成JWT token
-    let claims = Claims::new(user.user_id);
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(state.jwt_secret.as_ref()),
-    )
-    .map_err(|_| AppError::InternalServerError)?;
+
+    let user = User {
+        user_id: pending_registration.user_id,
+        email: pending_registration.email.clone(),
+        user_name: pending_registration.user_name.clone(),
+        user_password: pending_registration.user_password.clone(),
+        user_type: pending_registration.user_type.clone(),
+        wallet_address,
+        private_key,
+        premium_balance: 0,
+        created_at: now,
+    };
+
+    state
+        .users
+        .create(&user)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+
+    // 签发访问令牌与刷新令牌
+    let tokens = issue_tokens(&state, user.user_id).await?;
 
     // 清理临时数据
-    state.verification_codes.lock().unwrap().remove(&payload.email);
-    state.pending_registrations.lock().unwrap().remove(&payload.email);
+    let _ = state.remove_verification_code(&payload.email).await;
+    let _ = state.remove_pending_registration(&payload.email).await;
+    let _ = state.clear_verification_failures(&payload.email).await;
 
     Ok(Json(VerifyResponse {
-        token,
+        token: tokens.token,
+        refresh_token: tokens.refresh_token,
+        expires_in: tokens.expires_in,
         user: user.into(),
     }))
 }
@@ -329,7 +437,8 @@ pub async fn verify(
     responses(
         (status = 200, description = "Login successful", body = LoginResponse),
         (status = 401, description = "Email or password incorrect", body = crate::openapi::ErrorResponse),
-        (status = 404, description = "User not found", body = crate::openapi::ErrorResponse)
+        (status = 404, description = "User not found", body = crate::openapi::ErrorResponse),
+        (status = 429, description = "Too many requests, see Retry-After", body = crate::openapi::ErrorResponse)
     )
 )]
 pub async fn login(
@@ -338,13 +447,11 @@ pub async fn login(
 ) -> Result<Json<LoginResponse>, AppError> {
     info!("User Login Begin: {:?}", payload);
     // 检查用户是否存在
-    let user = sqlx::query_as::<_, User>(
-        "SELECT * FROM users WHERE email = ?",
-    )
-    .bind(&payload.email)
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|_| AppError::DatabaseError)?;
+    let user = state
+        .users
+        .find_by_email(&payload.email)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
 
     let user = user.ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
 
@@ -353,21 +460,310 @@ pub async fn login(
         return Err(AppError::Unauthorized("Email or password incorrect".to_string()));
     }
 
-    // 生成JWT token
-    let claims = Claims::new(user.user_id);
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(state.jwt_secret.as_ref()),
-    )
-    .map_err(|_| AppError::InternalServerError)?;
+    // 旧版或按旧参数生成的密码哈希，借登录时拿到的明文密码升级；失败不影响本次登录
+    if state.password_hashing.needs_rehash(&user.user_password) {
+        match state.password_hashing.hash(&payload.user_password) {
+            Ok(password_hash) => match state.users.update_password(user.user_id, &password_hash).await {
+                Ok(_) => info!("Password hash upgraded for user {}", user.user_id),
+                Err(e) => error!("Failed to upgrade password hash for user {}: {}", user.user_id, e),
+            },
+            Err(_) => error!("Failed to rehash password for user {}", user.user_id),
+        }
+    }
+
+    // 签发访问令牌与刷新令牌
+    let tokens = issue_tokens(&state, user.user_id).await?;
     info!("User Login Over: {:?}", user);
     Ok(Json(LoginResponse {
-        token,
+        token: tokens.token,
+        refresh_token: tokens.refresh_token,
+        expires_in: tokens.expires_in,
         user: user.into(),
     }))
 }
 
+// 刷新访问令牌
+#[utoipa::path(
+    post,
+    path = "/api/users/refresh",
+    tag = "users",
+    summary = "Refresh access token",
+    description = "Exchange a refresh token for a new access token and a new refresh token, the old refresh token becomes invalid",
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, description = "Refresh successful", body = RefreshResponse),
+        (status = 401, description = "Invalid or expired refresh token", body = crate::openapi::ErrorResponse),
+        (status = 429, description = "Too many requests, see Retry-After", body = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn refresh(
+    State(state): State<AppState>,
+    Json(payload): Json<RefreshRequest>,
+) -> Result<Json<RefreshResponse>, AppError> {
+    // 取出即作废，同一个刷新令牌只能使用一次
+    let record = state
+        .take_refresh_token(&payload.refresh_token)
+        .await
+        .map_err(|_| AppError::DatabaseError)?
+        .filter(|record| record.expires_at > Utc::now())
+        .ok_or_else(|| AppError::Unauthorized("Invalid or expired refresh token".to_string()))?;
+
+    // 修改或重置密码前签发的刷新令牌已失效
+    let revoked_at = state
+        .token_revocations
+        .sessions_revoked_at(record.user_id)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+    if revoked_at.is_some_and(|revoked_at| record.created_at < revoked_at) {
+        return Err(AppError::Unauthorized("Invalid or expired refresh token".to_string()));
+    }
+
+    let user_exists = state
+        .users
+        .exists(record.user_id)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+    if !user_exists {
+        return Err(AppError::Unauthorized("User not found".to_string()));
+    }
+
+    let tokens = issue_tokens(&state, record.user_id).await?;
+    Ok(Json(tokens))
+}
+
+// 用户登出
+#[utoipa::path(
+    post,
+    path = "/api/users/logout",
+    tag = "users",
+    summary = "Logout user",
+    description = "Revoke the current access token and the given refresh token",
+    request_body = LogoutRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Logout successful", body = LogoutResponse),
+        (status = 401, description = "Unauthorized access", body = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn logout(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<LogoutRequest>,
+) -> Result<Json<LogoutResponse>, AppError> {
+    state
+        .token_revocations
+        .revoke_access_token(&claims)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+
+    // 只作废属于当前用户的刷新令牌
+    if let Some(refresh_token) = payload.refresh_token {
+        let record = state
+            .refresh_token(&refresh_token)
+            .await
+            .map_err(|_| AppError::DatabaseError)?;
+        if record.is_some_and(|record| record.user_id == user_id) {
+            state
+                .remove_refresh_token(&refresh_token)
+                .await
+                .map_err(|_| AppError::DatabaseError)?;
+        }
+    }
+
+    Ok(Json(LogoutResponse {
+        message: "Logged out".to_string(),
+    }))
+}
+
+// 忘记密码，向邮箱发送重置密码验证码
+#[utoipa::path(
+    post,
+    path = "/api/users/password/forgot",
+    tag = "users",
+    summary = "Request password reset",
+    description = "Send a password reset code to the email. The response is the same whether or not the email is registered",
+    request_body = ForgotPasswordRequest,
+    responses(
+        (status = 200, description = "Request accepted", body = PasswordResponse),
+        (status = 400, description = "Invalid email format", body = crate::openapi::ErrorResponse),
+        (status = 429, description = "Too many requests, see Retry-After", body = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> Result<Json<PasswordResponse>, AppError> {
+    if !crate::utils::is_valid_email(&payload.email) {
+        return Err(AppError::BadRequest("Invalid email format".to_string()));
+    }
+
+    // 无论邮箱是否注册都返回相同的响应，避免被用来探测已注册邮箱
+    let response = PasswordResponse {
+        message: "If the email is registered, a password reset code has been sent".to_string(),
+    };
+
+    let user = state
+        .users
+        .find_by_email(&payload.email)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+    let Some(user) = user else {
+        info!("Password reset requested for unregistered email {}", payload.email);
+        return Ok(Json(response));
+    };
+
+    let code = generate_verification_code();
+    let reset_code = VerificationCode {
+        code: code.clone(),
+        expires_at: Utc::now() + Duration::minutes(VERIFICATION_CODE_EXPIRES_MINUTES),
+        email: payload.email.clone(),
+    };
+    state
+        .save_password_reset_code(&reset_code)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+
+    // 发送失败时只记录日志并撤销验证码，响应保持不变
+    let email = password_reset_email(
+        &payload.email,
+        &user.user_name,
+        &code,
+        VERIFICATION_CODE_EXPIRES_MINUTES,
+        state.mail_templates_dir.as_deref().map(Path::new),
+    );
+    if let Err(e) = state.mailer.send(&email).await {
+        error!("Failed to send password reset email to {}: {}", payload.email, e);
+        let _ = state.remove_password_reset_code(&payload.email).await;
+    } else {
+        info!("Password reset email sent to {}", payload.email);
+    }
+
+    Ok(Json(response))
+}
+
+// 使用验证码重置密码
+#[utoipa::path(
+    post,
+    path = "/api/users/password/reset",
+    tag = "users",
+    summary = "Reset password",
+    description = "Reset the password using the code sent by /api/users/password/forgot. All previously issued tokens become invalid",
+    request_body = ResetPasswordRequest,
+    responses(
+        (status = 200, description = "Password reset successful", body = PasswordResponse),
+        (status = 400, description = "Reset failed, invalid code or expired, or empty password", body = crate::openapi::ErrorResponse),
+        (status = 429, description = "Too many requests or too many failed attempts, see Retry-After", body = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<Json<PasswordResponse>, AppError> {
+    validate_new_password(&payload.new_password)?;
+
+    // 与邮箱验证共用失败计数和锁定
+    let locked_until = state
+        .verification_locked_until(&payload.email)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+    if let Some(locked_until) = locked_until {
+        let retry_after = (locked_until - Utc::now()).num_seconds().max(1) as u64;
+        return Err(AppError::TooManyRequests(retry_after));
+    }
+
+    let reset_code = state
+        .password_reset_code(&payload.email)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+
+    let code_valid = matches!(
+        &reset_code,
+        Some(code) if code.code == payload.code && code.expires_at >= Utc::now()
+    );
+    if !code_valid {
+        if state.record_verification_failure(&payload.email).await.is_err() {
+            error!("Failed to record verification failure for {}", payload.email);
+        }
+        if reset_code.is_some() {
+            let _ = state.remove_password_reset_code(&payload.email).await;
+        }
+        return Err(AppError::BadRequest("Reset failed, invalid code or expired".to_string()));
+    }
+
+    let user = state
+        .users
+        .find_by_email(&payload.email)
+        .await
+        .map_err(|_| AppError::DatabaseError)?
+        .ok_or_else(|| AppError::BadRequest("Reset failed, invalid code or expired".to_string()))?;
+
+    update_password(&state, user.user_id, &payload.new_password).await?;
+
+    let _ = state.remove_password_reset_code(&payload.email).await;
+    let _ = state.clear_verification_failures(&payload.email).await;
+
+    Ok(Json(PasswordResponse {
+        message: "Password has been reset, please login with the new password".to_string(),
+    }))
+}
+
+// 修改密码
+#[utoipa::path(
+    post,
+    path = "/api/users/password",
+    tag = "users",
+    summary = "Change password",
+    description = "Change the password of the current user. All previously issued tokens become invalid and a new token pair is returned",
+    request_body = ChangePasswordRequest,
+    security(
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Password changed", body = ChangePasswordResponse),
+        (status = 400, description = "Empty new password", body = crate::openapi::ErrorResponse),
+        (status = 401, description = "Unauthorized access or current password incorrect", body = crate::openapi::ErrorResponse),
+        (status = 404, description = "User not found", body = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn change_password(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> Result<Json<ChangePasswordResponse>, AppError> {
+    validate_new_password(&payload.new_password)?;
+
+    let user = state
+        .users
+        .find_by_id(user_id)
+        .await
+        .map_err(|_| AppError::DatabaseError)?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    if !verify_password_with_user_id(&payload.current_password, user.user_id, &user.user_password, &state.password_salt) {
+        return Err(AppError::Unauthorized("Current password incorrect".to_string()));
+    }
+
+    update_password(&state, user.user_id, &payload.new_password).await?;
+    // 当前访问令牌可能与作废操作在同一秒内签发，单独吊销
+    state
+        .token_revocations
+        .revoke_access_token(&claims)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+
+    // 旧令牌已全部作废，为当前客户端签发新令牌
+    let tokens = issue_tokens(&state, user.user_id).await?;
+    Ok(Json(ChangePasswordResponse {
+        token: tokens.token,
+        refresh_token: tokens.refresh_token,
+        expires_in: tokens.expires_in,
+    }))
+}
+
 // 获取用户信息
 #[utoipa::path(
     get,
@@ -388,17 +784,120 @@ pub async fn get_profile(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
 ) -> Result<Json<UserInfo>, AppError> {
-    let user = sqlx::query_as::<_, User>(
-        "SELECT * FROM users WHERE user_id = ?",
-    )
-    .bind(user_id)
-    .fetch_one(&state.db)
-    .await
-    .map_err(|_| AppError::NotFound("User not found".to_string()))?;
+    let user = state
+        .users
+        .find_by_id(user_id)
+        .await
+        .map_err(|_| AppError::DatabaseError)?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
 
     Ok(Json(user.into()))
 }
 
+// 获取 Premium 积分流水
+#[utoipa::path(
+    get,
+    path = "/api/users/premium/ledger",
+    tag = "users",
+    summary = "Get premium ledger",
+    description = "Get the premium credit history of the current user (purchases, developer payouts, free grants, refunds), newest first",
+    security(
+        ("bearer_auth" = [])
+    ),
+    params(
+        ("page" = Option<u32>, Query, description = "Page number, default is 1"),
+        ("size" = Option<u32>, Query, description = "Number of items per page, default is 20")
+    ),
+    responses(
+        (status = 200, description = "Get premium ledger successful", body = PremiumLedgerResponse),
+        (status = 401, description = "Unauthorized access", body = crate::openapi::ErrorResponse),
+        (status = 404, description = "User not found", body = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn get_premium_ledger(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Query(query): Query<LedgerQuery>,
+) -> Result<Json<PremiumLedgerResponse>, AppError> {
+    let page = query.page.unwrap_or(1).max(1);
+    let size = query.size.unwrap_or(20).clamp(1, 100);
+    let offset = (page - 1) * size;
+
+    let user = state
+        .users
+        .find_by_id(user_id)
+        .await
+        .map_err(|_| AppError::DatabaseError)?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let (entries, total) = state
+        .premium_ledger
+        .list_for_user(user_id, size as i64, offset as i64)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+
+    Ok(Json(PremiumLedgerResponse {
+        premium_balance: user.premium_balance,
+        entries,
+        total: total as u64,
+        page,
+        size,
+        has_next: ((page * size) as i64) < total,
+    }))
+}
+
+// 签发访问令牌，并生成一个新的刷新令牌保存到服务端
+async fn issue_tokens(state: &AppState, user_id: Uuid) -> Result<RefreshResponse, AppError> {
+    let (token, _) = state
+        .issue_access_token(user_id)
+        .map_err(|_| AppError::InternalServerError)?;
+
+    let refresh_token = generate_token();
+    let now = Utc::now();
+    let record = RefreshToken {
+        user_id,
+        created_at: now,
+        expires_at: now + Duration::days(state.jwt_refresh_token_days),
+    };
+    state
+        .save_refresh_token(&refresh_token, &record)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+
+    Ok(RefreshResponse {
+        token,
+        refresh_token,
+        expires_in: state.jwt_access_token_minutes * 60,
+    })
+}
+
+fn validate_new_password(password: &str) -> Result<(), AppError> {
+    if password.is_empty() {
+        return Err(AppError::BadRequest("Password cannot be empty".to_string()));
+    }
+    Ok(())
+}
+
+// 保存新密码哈希，并作废该用户此前签发的全部令牌
+async fn update_password(state: &AppState, user_id: Uuid, new_password: &str) -> Result<(), AppError> {
+    let password_hash = state.password_hashing.hash(new_password)?;
+    let updated = state
+        .users
+        .update_password(user_id, &password_hash)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+    if !updated {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+
+    state
+        .token_revocations
+        .revoke_user_sessions(user_id)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+    Ok(())
+}
+
 // 生成6位数字验证码
 fn generate_verification_code() -> String {
     let mut rng = rand::rng();
@@ -409,6 +908,7 @@ fn generate_verification_code() -> String {
 mod tests {
     use super::*;
     use crate::database::{create_pool, init_database};
+    use crate::utils_tests::test_pool;
 
     async fn create_test_state() -> AppState {
         let pool = create_pool().await.expect("Failed to create test pool");
@@ -424,7 +924,7 @@ mod tests {
         assert!(code.chars().all(|c| c.is_ascii_digit()));
         
         let code_num: u32 = code.parse().unwrap();
-        assert!(code_num >= 100000 && code_num <= 999999);
+        assert!((100000..=999999).contains(&code_num));
     }
 
     #[test]
@@ -536,15 +1036,12 @@ mod tests {
         // 检查用户是否真的被插入到数据库中
         let user_check = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = ?")
             .bind(email)
-            .fetch_optional(&state.db)
+            .fetch_optional(test_pool(&state))
             .await;
         info!("User check result: {:?}", user_check);
 
         // 获取生成的验证码
-        let verification_codes = state.verification_codes.lock().unwrap();
-        let verification_code = verification_codes.get(email).unwrap();
-        let code = verification_code.code.clone();
-        drop(verification_codes);
+        let code = state.verification_code(email).await.unwrap().unwrap().code;
 
         // 验证邮箱
         let verify_request = VerifyRequest {
@@ -611,15 +1108,12 @@ mod tests {
         assert!(register_result.is_ok());
 
         // 手动设置过期的验证码
-        {
-            let mut verification_codes = state.verification_codes.lock().unwrap();
-            let expired_code = VerificationCode {
-                email: email.to_string(),
-                code: "123456".to_string(),
-                expires_at: Utc::now() - Duration::minutes(10), // 10分钟前过期
-            };
-            verification_codes.insert(email.to_string(), expired_code);
-        }
+        let expired_code = VerificationCode {
+            email: email.to_string(),
+            code: "123456".to_string(),
+            expires_at: Utc::now() - Duration::minutes(10), // 10分钟前过期
+        };
+        state.save_verification_code(&expired_code).await.unwrap();
 
         // 使用过期的验证码
         let verify_request = VerifyRequest {
@@ -652,9 +1146,7 @@ mod tests {
         let _ = register(State(state.clone()), Json(register_request)).await.unwrap();
         
         // 获取验证码并验证
-        let verification_codes = state.verification_codes.lock().unwrap();
-        let code = verification_codes.get(email).unwrap().code.clone();
-        drop(verification_codes);
+        let code = state.verification_code(email).await.unwrap().unwrap().code;
         
         let verify_request = VerifyRequest {
             email: email.to_string(),
@@ -711,9 +1203,7 @@ mod tests {
         let _ = register(State(state.clone()), Json(register_request)).await.unwrap();
         
         // 获取验证码并验证
-        let verification_codes = state.verification_codes.lock().unwrap();
-        let code = verification_codes.get(email).unwrap().code.clone();
-        drop(verification_codes);
+        let code = state.verification_code(email).await.unwrap().unwrap().code;
         
         let verify_request = VerifyRequest {
             email: email.to_string(),
@@ -736,4 +1226,527 @@ mod tests {
             _ => panic!("Expected Unauthorized error"),
         }
     }
-}
\ No newline at end of file
+
+    struct FailingMailer;
+
+    #[async_trait::async_trait]
+    impl crate::mailer::Mailer for FailingMailer {
+        async fn send(&self, _email: &crate::mailer::Email) -> Result<(), crate::mailer::MailError> {
+            Err(crate::mailer::MailError::Transport("connection refused".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_register_sends_verification_email() {
+        let outbox = tempfile::tempdir().unwrap();
+        let mut state = crate::utils_tests::create_test_app_state().await;
+        state.mailer = std::sync::Arc::new(crate::mailer::OutboxMailer::to_dir(
+            "OpenPick <no-reply@openpick.org>",
+            outbox.path(),
+        ));
+
+        let request = RegisterRequest {
+            email: "mail@example.com".to_string(),
+            user_name: "Mail User".to_string(),
+            user_password: "test_password".to_string(),
+            user_type: UserType::Gen,
+        };
+        let _ = register(State(state.clone()), Json(request)).await.unwrap();
+
+        let code = state.verification_code("mail@example.com").await.unwrap().unwrap().code;
+        let files: Vec<_> = std::fs::read_dir(outbox.path()).unwrap().map(|e| e.unwrap().path()).collect();
+        assert_eq!(files.len(), 1);
+
+        let content = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(content.contains("To: mail@example.com"));
+        assert!(content.contains("Hi Mail User,"));
+        assert!(content.contains(&code));
+    }
+
+    #[tokio::test]
+    async fn test_register_mail_failure_rolls_back() {
+        let mut state = crate::utils_tests::create_test_app_state().await;
+        state.mailer = std::sync::Arc::new(FailingMailer);
+
+        let request = RegisterRequest {
+            email: "unreachable@example.com".to_string(),
+            user_name: "Test User".to_string(),
+            user_password: "test_password".to_string(),
+            user_type: UserType::Gen,
+        };
+        let result = register(State(state.clone()), Json(request)).await;
+        assert!(matches!(result, Err(AppError::InternalServerError)));
+
+        // 发送失败后不残留验证码和待注册信息，用户可以重新注册
+        assert!(state.verification_code("unreachable@example.com").await.unwrap().is_none());
+        assert!(state.pending_registration("unreachable@example.com").await.unwrap().is_none());
+    }
+
+    async fn register_for_verify(state: &AppState, email: &str) -> String {
+        let request = RegisterRequest {
+            email: email.to_string(),
+            user_name: "Lockout User".to_string(),
+            user_password: "test_password".to_string(),
+            user_type: UserType::Gen,
+        };
+        let _ = register(State(state.clone()), Json(request)).await.unwrap();
+        state.verification_code(email).await.unwrap().unwrap().code
+    }
+
+    fn wrong_code(code: &str) -> String {
+        if code == "000000" { "111111".to_string() } else { "000000".to_string() }
+    }
+
+    #[tokio::test]
+    async fn test_verify_locks_email_after_repeated_failures() {
+        let mut state = crate::utils_tests::create_test_app_state().await;
+        state.verification_max_failures = 3;
+        let email = "lockout@example.com";
+
+        for _ in 0..3 {
+            let code = register_for_verify(&state, email).await;
+            let request = VerifyRequest { email: email.to_string(), code: wrong_code(&code) };
+            let result = verify(State(state.clone()), Json(request)).await;
+            assert!(matches!(result, Err(AppError::BadRequest(_))));
+        }
+
+        // 锁定期内即使验证码正确也被拒绝
+        let code = register_for_verify(&state, email).await;
+        let request = VerifyRequest { email: email.to_string(), code };
+        let result = verify(State(state.clone()), Json(request)).await;
+        match result {
+            Err(AppError::TooManyRequests(retry_after)) => {
+                assert!(retry_after > 0 && retry_after <= 15 * 60);
+            }
+            other => panic!("expected TooManyRequests, got {:?}", other.map(|_| ())),
+        }
+        assert!(state.users.find_by_email(email).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_verify_success_clears_failures() {
+        let mut state = crate::utils_tests::create_test_app_state().await;
+        state.verification_max_failures = 2;
+        let email = "recovered@example.com";
+
+        let code = register_for_verify(&state, email).await;
+        let request = VerifyRequest { email: email.to_string(), code: wrong_code(&code) };
+        assert!(verify(State(state.clone()), Json(request)).await.is_err());
+
+        let code = register_for_verify(&state, email).await;
+        let request = VerifyRequest { email: email.to_string(), code };
+        assert!(verify(State(state.clone()), Json(request)).await.is_ok());
+
+        assert_eq!(
+            state.ephemeral.count(crate::ephemeral::VERIFICATION_FAILURES).await.unwrap(),
+            0
+        );
+        assert!(state.verification_locked_until(email).await.unwrap().is_none());
+    }
+
+    // 注册并验证一个用户，返回验证接口签发的令牌
+    async fn verified_user(state: &AppState, email: &str) -> VerifyResponse {
+        let code = register_for_verify(state, email).await;
+        let request = VerifyRequest { email: email.to_string(), code };
+        verify(State(state.clone()), Json(request)).await.unwrap().0
+    }
+
+    fn decode_claims(state: &AppState, token: &str) -> Claims {
+        jsonwebtoken::decode::<Claims>(
+            token,
+            &jsonwebtoken::DecodingKey::from_secret(state.jwt_secret.as_ref()),
+            &jsonwebtoken::Validation::default(),
+        )
+        .unwrap()
+        .claims
+    }
+
+    #[tokio::test]
+    async fn test_login_issues_refresh_token() {
+        let state = crate::utils_tests::create_test_app_state().await;
+        let verified = verified_user(&state, "tokens@example.com").await;
+        assert!(!verified.refresh_token.is_empty());
+
+        let request = LoginRequest {
+            email: "tokens@example.com".to_string(),
+            user_password: "test_password".to_string(),
+        };
+        let response = login(State(state.clone()), Json(request)).await.unwrap();
+
+        assert_eq!(response.expires_in, state.jwt_access_token_minutes * 60);
+        assert_ne!(response.refresh_token, verified.refresh_token);
+        let claims = decode_claims(&state, &response.token);
+        assert_eq!(claims.sub, response.user.user_id.to_string());
+        assert_eq!(claims.exp - claims.iat, (state.jwt_access_token_minutes * 60) as usize);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_rotates_refresh_token() {
+        let state = crate::utils_tests::create_test_app_state().await;
+        let verified = verified_user(&state, "rotate@example.com").await;
+
+        let request = RefreshRequest { refresh_token: verified.refresh_token.clone() };
+        let refreshed = refresh(State(state.clone()), Json(request)).await.unwrap();
+        assert_ne!(refreshed.refresh_token, verified.refresh_token);
+        assert_eq!(decode_claims(&state, &refreshed.token).sub, verified.user.user_id.to_string());
+
+        // 旧的刷新令牌已作废
+        let request = RefreshRequest { refresh_token: verified.refresh_token };
+        let result = refresh(State(state.clone()), Json(request)).await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))));
+
+        // 新的刷新令牌可以继续使用
+        let request = RefreshRequest { refresh_token: refreshed.refresh_token.clone() };
+        assert!(refresh(State(state.clone()), Json(request)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_rejects_expired_token() {
+        let state = crate::utils_tests::create_test_app_state().await;
+        let verified = verified_user(&state, "expired-refresh@example.com").await;
+
+        let record = RefreshToken {
+            user_id: verified.user.user_id,
+            created_at: Utc::now() - Duration::days(31),
+            expires_at: Utc::now() - Duration::days(1),
+        };
+        state.save_refresh_token("stale-token", &record).await.unwrap();
+
+        let request = RefreshRequest { refresh_token: "stale-token".to_string() };
+        let result = refresh(State(state.clone()), Json(request)).await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))));
+    }
+
+    #[tokio::test]
+    async fn test_logout_revokes_access_and_refresh_tokens() {
+        let state = crate::utils_tests::create_test_app_state().await;
+        let verified = verified_user(&state, "logout@example.com").await;
+        let claims = decode_claims(&state, &verified.token);
+
+        let request = LogoutRequest { refresh_token: Some(verified.refresh_token.clone()) };
+        let _ = logout(
+            State(state.clone()),
+            Extension(verified.user.user_id),
+            Extension(claims.clone()),
+            Json(request),
+        )
+        .await
+        .unwrap();
+
+        assert!(state.token_revocations.is_access_token_revoked(&claims.jti).await.unwrap());
+        let request = RefreshRequest { refresh_token: verified.refresh_token };
+        let result = refresh(State(state.clone()), Json(request)).await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))));
+    }
+
+    #[tokio::test]
+    async fn test_logout_keeps_other_users_refresh_token() {
+        let state = crate::utils_tests::create_test_app_state().await;
+        let alice = verified_user(&state, "alice@example.com").await;
+        let bob = verified_user(&state, "bob@example.com").await;
+
+        // 不能用自己的登录态作废别人的刷新令牌
+        let request = LogoutRequest { refresh_token: Some(bob.refresh_token.clone()) };
+        let _ = logout(
+            State(state.clone()),
+            Extension(alice.user.user_id),
+            Extension(decode_claims(&state, &alice.token)),
+            Json(request),
+        )
+        .await
+        .unwrap();
+
+        let request = RefreshRequest { refresh_token: bob.refresh_token };
+        assert!(refresh(State(state.clone()), Json(request)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_forgot_password_sends_reset_code() {
+        let outbox = tempfile::tempdir().unwrap();
+        let mut state = crate::utils_tests::create_test_app_state().await;
+        let _ = verified_user(&state, "forgot@example.com").await;
+        state.mailer = std::sync::Arc::new(crate::mailer::OutboxMailer::to_dir(
+            "OpenPick <no-reply@openpick.org>",
+            outbox.path(),
+        ));
+
+        let request = ForgotPasswordRequest { email: "forgot@example.com".to_string() };
+        let _ = forgot_password(State(state.clone()), Json(request)).await.unwrap();
+
+        let code = state.password_reset_code("forgot@example.com").await.unwrap().unwrap().code;
+        // 与注册验证码分开保存
+        assert!(state.verification_code("forgot@example.com").await.unwrap().is_none());
+        let files: Vec<_> = std::fs::read_dir(outbox.path()).unwrap().map(|e| e.unwrap().path()).collect();
+        assert_eq!(files.len(), 1);
+        let content = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(content.contains("To: forgot@example.com"));
+        assert!(content.contains("password reset code"));
+        assert!(content.contains(&code));
+    }
+
+    #[tokio::test]
+    async fn test_forgot_password_unknown_email_returns_same_response() {
+        let state = crate::utils_tests::create_test_app_state().await;
+        let _ = verified_user(&state, "known@example.com").await;
+
+        let request = ForgotPasswordRequest { email: "known@example.com".to_string() };
+        let known = forgot_password(State(state.clone()), Json(request)).await.unwrap();
+        let request = ForgotPasswordRequest { email: "unknown@example.com".to_string() };
+        let unknown = forgot_password(State(state.clone()), Json(request)).await.unwrap();
+
+        assert_eq!(known.message, unknown.message);
+        assert!(state.password_reset_code("unknown@example.com").await.unwrap().is_none());
+
+        let request = ForgotPasswordRequest { email: "not-an-email".to_string() };
+        let result = forgot_password(State(state.clone()), Json(request)).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+    }
+
+    async fn request_reset_code(state: &AppState, email: &str) -> String {
+        let request = ForgotPasswordRequest { email: email.to_string() };
+        let _ = forgot_password(State(state.clone()), Json(request)).await.unwrap();
+        state.password_reset_code(email).await.unwrap().unwrap().code
+    }
+
+    #[tokio::test]
+    async fn test_reset_password_with_code() {
+        let state = crate::utils_tests::create_test_app_state().await;
+        let email = "reset@example.com";
+        let verified = verified_user(&state, email).await;
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+        let code = request_reset_code(&state, email).await;
+        let request = ResetPasswordRequest {
+            email: email.to_string(),
+            code,
+            new_password: "new_password".to_string(),
+        };
+        let _ = reset_password(State(state.clone()), Json(request)).await.unwrap();
+        assert!(state.password_reset_code(email).await.unwrap().is_none());
+
+        let request = LoginRequest { email: email.to_string(), user_password: "test_password".to_string() };
+        assert!(matches!(login(State(state.clone()), Json(request)).await, Err(AppError::Unauthorized(_))));
+        let request = LoginRequest { email: email.to_string(), user_password: "new_password".to_string() };
+        assert!(login(State(state.clone()), Json(request)).await.is_ok());
+
+        // 重置前签发的刷新令牌失效
+        let request = RefreshRequest { refresh_token: verified.refresh_token };
+        let result = refresh(State(state.clone()), Json(request)).await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))));
+    }
+
+    #[tokio::test]
+    async fn test_reset_password_wrong_code_invalidates_code() {
+        let state = crate::utils_tests::create_test_app_state().await;
+        let email = "reset-wrong@example.com";
+        let _ = verified_user(&state, email).await;
+
+        let code = request_reset_code(&state, email).await;
+        let request = ResetPasswordRequest {
+            email: email.to_string(),
+            code: wrong_code(&code),
+            new_password: "new_password".to_string(),
+        };
+        let result = reset_password(State(state.clone()), Json(request)).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+
+        // 猜错一次后原验证码作废，需要重新申请
+        let request = ResetPasswordRequest {
+            email: email.to_string(),
+            code,
+            new_password: "new_password".to_string(),
+        };
+        let result = reset_password(State(state.clone()), Json(request)).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+        assert_eq!(
+            state.ephemeral.count(crate::ephemeral::VERIFICATION_FAILURES).await.unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reset_password_rejects_empty_password() {
+        let state = crate::utils_tests::create_test_app_state().await;
+        let email = "reset-empty@example.com";
+        let _ = verified_user(&state, email).await;
+
+        let code = request_reset_code(&state, email).await;
+        let request = ResetPasswordRequest { email: email.to_string(), code, new_password: String::new() };
+        let result = reset_password(State(state.clone()), Json(request)).await;
+        assert!(matches!(result, Err(AppError::BadRequest(msg)) if msg == "Password cannot be empty"));
+        assert!(state.password_reset_code(email).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_change_password() {
+        let state = crate::utils_tests::create_test_app_state().await;
+        let email = "change@example.com";
+        let verified = verified_user(&state, email).await;
+        let user_id = verified.user.user_id;
+        let claims = decode_claims(&state, &verified.token);
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+        let request = ChangePasswordRequest {
+            current_password: "wrong_password".to_string(),
+            new_password: "new_password".to_string(),
+        };
+        let result = change_password(State(state.clone()), Extension(user_id), Extension(claims.clone()), Json(request)).await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))));
+
+        let request = ChangePasswordRequest {
+            current_password: "test_password".to_string(),
+            new_password: "new_password".to_string(),
+        };
+        let changed = change_password(State(state.clone()), Extension(user_id), Extension(claims.clone()), Json(request))
+            .await
+            .unwrap();
+
+        // 旧令牌失效，新令牌可用
+        assert!(state.token_revocations.is_claims_revoked(user_id, &claims).await.unwrap());
+        let new_claims = decode_claims(&state, &changed.token);
+        assert!(!state.token_revocations.is_claims_revoked(user_id, &new_claims).await.unwrap());
+        let request = RefreshRequest { refresh_token: verified.refresh_token };
+        assert!(matches!(refresh(State(state.clone()), Json(request)).await, Err(AppError::Unauthorized(_))));
+        let request = RefreshRequest { refresh_token: changed.refresh_token.clone() };
+        assert!(refresh(State(state.clone()), Json(request)).await.is_ok());
+
+        let request = LoginRequest { email: email.to_string(), user_password: "new_password".to_string() };
+        assert!(login(State(state.clone()), Json(request)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_login_upgrades_legacy_password_hash() {
+        let state = crate::utils_tests::create_test_app_state().await;
+        let user_id = Uuid::new_v4();
+        let legacy_hash = crate::utils::hash_password_with_user_id("legacy_password", user_id, &state.password_salt);
+        let (private_key, wallet_address) = generate_wallet(&state.wallet_keyring).unwrap();
+        let user = User {
+            user_id,
+            email: "legacy@example.com".to_string(),
+            user_name: "Legacy User".to_string(),
+            user_password: legacy_hash.clone(),
+            user_type: UserType::Gen,
+            wallet_address,
+            private_key,
+            premium_balance: 0,
+            created_at: Utc::now(),
+        };
+        state.users.create(&user).await.unwrap();
+
+        // 密码错误时不升级
+        let request = LoginRequest { email: user.email.clone(), user_password: "wrong_password".to_string() };
+        assert!(login(State(state.clone()), Json(request)).await.is_err());
+        let stored = state.users.find_by_id(user_id).await.unwrap().unwrap();
+        assert_eq!(stored.user_password, legacy_hash);
+
+        let request = LoginRequest { email: user.email.clone(), user_password: "legacy_password".to_string() };
+        assert!(login(State(state.clone()), Json(request)).await.is_ok());
+        let stored = state.users.find_by_id(user_id).await.unwrap().unwrap();
+        assert!(stored.user_password.starts_with("$argon2id$"));
+        assert!(!state.password_hashing.needs_rehash(&stored.user_password));
+
+        // 升级后的哈希可以继续登录
+        let request = LoginRequest { email: user.email.clone(), user_password: "legacy_password".to_string() };
+        assert!(login(State(state.clone()), Json(request)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_register_stores_versioned_password_hash() {
+        let state = crate::utils_tests::create_test_app_state().await;
+        let _ = register_for_verify(&state, "versioned@example.com").await;
+
+        let pending = state.pending_registration("versioned@example.com").await.unwrap().unwrap();
+        assert!(pending.user_password.starts_with("$argon2id$"));
+        assert!(!pending.user_password.contains("test_password"));
+    }
+
+    #[tokio::test]
+    async fn test_premium_ledger_records_purchase() {
+        use crate::models::{LedgerEntryKind, Order, OrderStatus, PayType, Picker, PickerChainStatus};
+
+        let state = crate::utils_tests::create_test_app_state().await;
+        let buyer = User {
+            user_id: Uuid::new_v4(),
+            email: "ledger@example.com".to_string(),
+            user_name: "Ledger User".to_string(),
+            user_password: "hash".to_string(),
+            user_type: UserType::Gen,
+            wallet_address: "0x0000000000000000000000000000000000000000".to_string(),
+            private_key: "encrypted".to_string(),
+            premium_balance: 1000,
+            created_at: Utc::now(),
+        };
+        let dev = User {
+            user_id: Uuid::new_v4(),
+            email: "ledger-dev@example.com".to_string(),
+            user_type: UserType::Dev,
+            premium_balance: 0,
+            ..buyer.clone()
+        };
+        state.users.create(&buyer).await.unwrap();
+        state.users.create(&dev).await.unwrap();
+        let picker = Picker {
+            picker_id: Uuid::new_v4(),
+            dev_user_id: dev.user_id,
+            alias: "Ledger Picker".to_string(),
+            description: "Ledger".to_string(),
+            price: 300,
+            file_path: "test.exe".to_string(),
+            download_count: 0,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            image_path: "test.jpg".to_string(),
+            version: "1.0".to_string(),
+            status: "active".to_string(),
+            chain_status: PickerChainStatus::Unregistered,
+            chain_tx_hash: None,
+            deleted_at: None,
+        };
+        state.pickers.create(&picker).await.unwrap();
+        let order = Order {
+            order_id: Uuid::new_v4(),
+            user_id: buyer.user_id,
+            picker_id: picker.picker_id,
+            amount: 300,
+            pay_type: PayType::Premium,
+            status: OrderStatus::Success,
+            tx_hash: None,
+            created_at: Utc::now(),
+            expires_at: None,
+        };
+        state.orders.settle_premium(&order, dev.user_id, 285).await.unwrap();
+
+        let response = get_premium_ledger(
+            State(state.clone()),
+            Extension(buyer.user_id),
+            Query(LedgerQuery { page: None, size: None }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.premium_balance, 700);
+        assert_eq!(response.total, 2);
+        let kinds: Vec<LedgerEntryKind> = response.entries.iter().map(|entry| entry.kind).collect();
+        assert!(kinds.contains(&LedgerEntryKind::Opening));
+        assert!(kinds.contains(&LedgerEntryKind::Purchase));
+        let purchase = response.entries.iter().find(|entry| entry.kind == LedgerEntryKind::Purchase).unwrap();
+        assert_eq!(purchase.amount, -300);
+        assert_eq!(purchase.balance_after, Some(700));
+
+        // 买家、开发者与平台分录之和为 0，余额与流水一致
+        let entries = state.premium_ledger.entries_for_order(order.order_id).await.unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries.iter().map(|entry| entry.amount).sum::<i64>(), 0);
+        assert!(entries.iter().any(|entry| entry.user_id.is_none() && entry.amount == 15));
+        assert!(state.premium_ledger.balance_mismatches(10).await.unwrap().is_empty());
+
+        let response = get_premium_ledger(
+            State(state.clone()),
+            Extension(buyer.user_id),
+            Query(LedgerQuery { page: Some(2), size: Some(1) }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.entries.len(), 1);
+        assert!(!response.has_next);
+    }
+}
@@ -0,0 +1,288 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, OsRng},
+    Aes256Gcm, Key, KeyInit, Nonce,
+};
+use base64::{engine::general_purpose, Engine as _};
+
+use crate::config::PasswordConfig;
+use crate::utils::AppError;
+
+// 托管钱包私钥的加密格式：v1:<key_id>:<base64 nonce>:<base64 密文>
+// 每条记录使用随机的 96 位 nonce，key_id 标明加密所用的主密钥，轮换主密钥后旧记录仍可解密
+const ENVELOPE_VERSION: &str = "v1";
+
+#[derive(Debug)]
+pub struct KeyringError(String);
+
+impl std::fmt::Display for KeyringError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for KeyringError {}
+
+struct MasterKey {
+    id: String,
+    key: [u8; 32],
+    // 配置中的原始字符串，解密旧格式数据时按旧规则补齐/截断
+    raw: String,
+}
+
+/// 钱包私钥加解密使用的主密钥集合
+/// 当前密钥用于加密，previous_keys 只用于解密轮换前写入的记录
+pub struct WalletKeyring {
+    keys: Vec<MasterKey>,
+    legacy_nonce: String,
+}
+
+// 主密钥必须是 32 字节字符串或 64 位十六进制，不再补齐或截断
+fn parse_master_key(id: &str, value: &str) -> Result<MasterKey, KeyringError> {
+    if id.is_empty() || id.contains(':') {
+        return Err(KeyringError(format!("invalid key id {:?}", id)));
+    }
+    let mut key = [0u8; 32];
+    if value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit()) {
+        hex::decode_to_slice(value, &mut key).map_err(|e| KeyringError(e.to_string()))?;
+    } else if value.len() == 32 {
+        key.copy_from_slice(value.as_bytes());
+    } else {
+        return Err(KeyringError(format!(
+            "master key {:?} must be 32 bytes or 64 hex characters",
+            id
+        )));
+    }
+    Ok(MasterKey {
+        id: id.to_string(),
+        key,
+        raw: value.to_string(),
+    })
+}
+
+impl WalletKeyring {
+    /// 按 [password] 配置创建，主密钥格式不正确或 key_id 重复时返回错误
+    pub fn from_config(config: &PasswordConfig) -> Result<Self, KeyringError> {
+        let mut keys = vec![parse_master_key(&config.key_id, &config.master_key)?];
+        for previous in &config.previous_keys {
+            let key = parse_master_key(&previous.id, &previous.master_key)?;
+            if keys.iter().any(|existing| existing.id == key.id) {
+                return Err(KeyringError(format!("duplicate key id {:?}", key.id)));
+            }
+            keys.push(key);
+        }
+        Ok(Self {
+            keys,
+            legacy_nonce: config.nonce.clone(),
+        })
+    }
+
+    /// 不含任何密钥，所有加解密都会失败；仅在配置无效时作为占位
+    pub fn unavailable() -> Self {
+        Self {
+            keys: Vec::new(),
+            legacy_nonce: String::new(),
+        }
+    }
+
+    pub fn current_key_id(&self) -> Option<&str> {
+        self.keys.first().map(|key| key.id.as_str())
+    }
+
+    /// 使用当前主密钥和随机 nonce 加密
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, AppError> {
+        let current = self.keys.first().ok_or_else(|| {
+            tracing::error!("Wallet keyring has no master key configured");
+            AppError::InternalServerError
+        })?;
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&current.key));
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher.encrypt(&nonce, plaintext.as_bytes()).map_err(|e| {
+            tracing::error!("AES-256-GCM encrypt failed: {:?}", e);
+            AppError::InternalServerError
+        })?;
+
+        Ok(format!(
+            "{}:{}:{}:{}",
+            ENVELOPE_VERSION,
+            current.id,
+            general_purpose::STANDARD.encode(nonce),
+            general_purpose::STANDARD.encode(ciphertext)
+        ))
+    }
+
+    /// 解密 encrypt 生成的记录，也兼容使用全局 nonce 的旧格式（纯 base64 密文）
+    pub fn decrypt(&self, stored: &str) -> Result<String, AppError> {
+        let plaintext = match parse_envelope(stored) {
+            Some((key_id, nonce, ciphertext)) => {
+                let key = self.keys.iter().find(|key| key.id == key_id).ok_or_else(|| {
+                    tracing::error!("Unknown wallet key id: {}", key_id);
+                    AppError::InternalServerError
+                })?;
+                let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key.key));
+                cipher
+                    .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+                    .map_err(|e| {
+                        tracing::error!("AES-256-GCM decrypt failed for key {}: {:?}", key_id, e);
+                        AppError::InternalServerError
+                    })?
+            }
+            None => self.decrypt_legacy(stored)?,
+        };
+
+        String::from_utf8(plaintext).map_err(|e| {
+            tracing::error!("Decrypted plaintext is not valid UTF-8: {:?}", e);
+            AppError::InternalServerError
+        })
+    }
+
+    // 旧格式：主密钥与 nonce 取配置字符串的字节，不足补零、超出截断；依次尝试所有已配置的密钥
+    fn decrypt_legacy(&self, stored: &str) -> Result<Vec<u8>, AppError> {
+        let ciphertext = general_purpose::STANDARD.decode(stored).map_err(|e| {
+            tracing::error!("Base64 decode failed: {:?}", e);
+            AppError::InternalServerError
+        })?;
+        let nonce = pad_bytes::<12>(&self.legacy_nonce);
+
+        self.keys
+            .iter()
+            .find_map(|key| {
+                let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&pad_bytes::<32>(&key.raw)));
+                cipher.decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref()).ok()
+            })
+            .ok_or_else(|| {
+                tracing::error!("AES-256-GCM decrypt failed for legacy private key");
+                AppError::InternalServerError
+            })
+    }
+
+    /// 记录是旧格式或不是用当前主密钥加密时返回 true
+    pub fn needs_reencrypt(&self, stored: &str) -> bool {
+        match parse_envelope(stored) {
+            Some((key_id, _, _)) => Some(key_id) != self.current_key_id(),
+            None => true,
+        }
+    }
+}
+
+fn parse_envelope(stored: &str) -> Option<(&str, Vec<u8>, Vec<u8>)> {
+    let mut parts = stored.split(':');
+    if parts.next()? != ENVELOPE_VERSION {
+        return None;
+    }
+    let key_id = parts.next()?;
+    let nonce = general_purpose::STANDARD.decode(parts.next()?).ok()?;
+    let ciphertext = general_purpose::STANDARD.decode(parts.next()?).ok()?;
+    if nonce.len() != 12 || parts.next().is_some() {
+        return None;
+    }
+    Some((key_id, nonce, ciphertext))
+}
+
+fn pad_bytes<const N: usize>(value: &str) -> [u8; N] {
+    let mut bytes = [0u8; N];
+    let len = value.len().min(N);
+    bytes[..len].copy_from_slice(&value.as_bytes()[..len]);
+    bytes
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::config::{Config, PreviousKey};
+
+    pub(crate) fn password_config(key_id: &str, master_key: &str, previous: &[(&str, &str)]) -> PasswordConfig {
+        let mut config = Config::load().password;
+        config.key_id = key_id.to_string();
+        config.master_key = master_key.to_string();
+        config.nonce = "openpickopen".to_string();
+        config.previous_keys = previous
+            .iter()
+            .map(|(id, master_key)| PreviousKey {
+                id: id.to_string(),
+                master_key: master_key.to_string(),
+            })
+            .collect();
+        config
+    }
+
+    // 按旧实现（全局 nonce）生成的密文
+    pub(crate) fn legacy_encrypt(plaintext: &str, master_key: &str, nonce: &str) -> String {
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&pad_bytes::<32>(master_key)));
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&pad_bytes::<12>(nonce)), plaintext.as_bytes())
+            .unwrap();
+        general_purpose::STANDARD.encode(ciphertext)
+    }
+
+    const KEY_A: &str = "openpickopenpickopenpickopenpick";
+    const KEY_B: &str = "00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff";
+
+    #[test]
+    fn test_encrypt_uses_random_nonce_per_record() {
+        let keyring = WalletKeyring::from_config(&password_config("k1", KEY_A, &[])).unwrap();
+
+        let first = keyring.encrypt("secret").unwrap();
+        let second = keyring.encrypt("secret").unwrap();
+        assert_ne!(first, second);
+        assert!(first.starts_with("v1:k1:"));
+        assert_eq!(keyring.decrypt(&first).unwrap(), "secret");
+        assert_eq!(keyring.decrypt(&second).unwrap(), "secret");
+        assert!(!keyring.needs_reencrypt(&first));
+    }
+
+    #[test]
+    fn test_decrypt_with_previous_key_after_rotation() {
+        let old = WalletKeyring::from_config(&password_config("k1", KEY_A, &[])).unwrap();
+        let stored = old.encrypt("secret").unwrap();
+
+        let rotated = WalletKeyring::from_config(&password_config("k2", KEY_B, &[("k1", KEY_A)])).unwrap();
+        assert_eq!(rotated.decrypt(&stored).unwrap(), "secret");
+        assert!(rotated.needs_reencrypt(&stored));
+        assert!(rotated.encrypt("secret").unwrap().starts_with("v1:k2:"));
+
+        // 移除旧密钥后无法解密
+        let without_old = WalletKeyring::from_config(&password_config("k2", KEY_B, &[])).unwrap();
+        assert!(without_old.decrypt(&stored).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_legacy_format() {
+        let stored = legacy_encrypt("secret", KEY_A, "openpickopen");
+
+        let keyring = WalletKeyring::from_config(&password_config("k1", KEY_A, &[])).unwrap();
+        assert_eq!(keyring.decrypt(&stored).unwrap(), "secret");
+        assert!(keyring.needs_reencrypt(&stored));
+
+        // 旧密钥移入 previous_keys 后仍可解密旧格式
+        let rotated = WalletKeyring::from_config(&password_config("k2", KEY_B, &[("k1", KEY_A)])).unwrap();
+        assert_eq!(rotated.decrypt(&stored).unwrap(), "secret");
+    }
+
+    #[test]
+    fn test_decrypt_rejects_tampered_ciphertext() {
+        let keyring = WalletKeyring::from_config(&password_config("k1", KEY_A, &[])).unwrap();
+        let stored = keyring.encrypt("secret").unwrap();
+        let (prefix, ciphertext) = stored.rsplit_once(':').unwrap();
+        let mut bytes = general_purpose::STANDARD.decode(ciphertext).unwrap();
+        bytes[0] ^= 1;
+        let tampered = format!("{}:{}", prefix, general_purpose::STANDARD.encode(bytes));
+
+        assert!(keyring.decrypt(&tampered).is_err());
+        assert!(keyring.decrypt("v1:unknown:AAAAAAAAAAAAAAAA:AAAA").is_err());
+    }
+
+    #[test]
+    fn test_from_config_validates_keys() {
+        assert!(WalletKeyring::from_config(&password_config("k1", "too-short", &[])).is_err());
+        assert!(WalletKeyring::from_config(&password_config("k:1", KEY_A, &[])).is_err());
+        assert!(WalletKeyring::from_config(&password_config("k1", KEY_A, &[("k1", KEY_B)])).is_err());
+        assert!(WalletKeyring::from_config(&password_config("k1", KEY_B, &[])).is_ok());
+    }
+
+    #[test]
+    fn test_unavailable_keyring_fails_closed() {
+        let keyring = WalletKeyring::unavailable();
+        assert!(keyring.encrypt("secret").is_err());
+        assert!(keyring.current_key_id().is_none());
+    }
+}
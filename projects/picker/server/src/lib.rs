@@ -1,11 +1,23 @@
 pub mod config;
+pub mod contract;
 pub mod database;
 pub mod models;
 pub mod utils;
 pub mod handlers;
+pub mod keyring;
 pub mod middleware;
 pub mod download;
+pub mod storage;
+pub mod upload;
+pub mod ephemeral;
+pub mod mailer;
 pub mod openapi;
+pub mod price_oracle;
+pub mod quotes;
+pub mod repository;
+pub mod seed;
+pub mod services;
+pub mod token_revocation;
 
 #[cfg(test)]
 pub mod utils_tests;
@@ -0,0 +1,436 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use tracing::info;
+
+use crate::config::{MailConfig, MailTransport, SmtpConfig};
+
+// 内置邮件模板，可通过 mail.templates_dir 下的同名文件覆盖
+const VERIFICATION_TEXT: &str = include_str!("../templates/email/verification.txt");
+const VERIFICATION_HTML: &str = include_str!("../templates/email/verification.html");
+const PASSWORD_RESET_TEXT: &str = include_str!("../templates/email/password_reset.txt");
+const PASSWORD_RESET_HTML: &str = include_str!("../templates/email/password_reset.html");
+
+// 邮件发送错误
+#[derive(Debug)]
+pub enum MailError {
+    // 地址或邮件内容不合法
+    InvalidMessage(String),
+    // SMTP 连接或投递失败
+    Transport(String),
+    // 写入 outbox 目录失败
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for MailError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MailError::InvalidMessage(msg) => write!(f, "invalid message: {}", msg),
+            MailError::Transport(msg) => write!(f, "transport error: {}", msg),
+            MailError::Io(e) => write!(f, "io error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MailError {}
+
+impl From<std::io::Error> for MailError {
+    fn from(e: std::io::Error) -> Self {
+        MailError::Io(e)
+    }
+}
+
+// 待发送的邮件
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: Option<String>,
+}
+
+// 邮件发送接口
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: &Email) -> Result<(), MailError>;
+}
+
+// 通过 SMTP 服务器发送邮件
+pub struct SmtpMailer {
+    from: Mailbox,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    pub fn new(from: &str, config: &SmtpConfig) -> Result<Self, MailError> {
+        let from = from
+            .parse::<Mailbox>()
+            .map_err(|e| MailError::InvalidMessage(format!("mail.from: {}", e)))?;
+
+        // tls: "starttls"（默认，587）、"tls"（隐式 TLS，465）、"none"（仅限本地调试）
+        let builder = match config.tls.as_str() {
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host),
+            "none" => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)),
+            _ => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host),
+        }
+        .map_err(|e| MailError::Transport(e.to_string()))?;
+
+        let mut builder = builder.port(config.port);
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self {
+            from,
+            transport: builder.build(),
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: &Email) -> Result<(), MailError> {
+        let to = email
+            .to
+            .parse::<Mailbox>()
+            .map_err(|e| MailError::InvalidMessage(format!("to: {}", e)))?;
+
+        let builder = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(email.subject.clone());
+        let message = match &email.html_body {
+            Some(html) => builder.multipart(MultiPart::alternative_plain_html(email.text_body.clone(), html.clone())),
+            None => builder.body(email.text_body.clone()),
+        }
+        .map_err(|e| MailError::InvalidMessage(e.to_string()))?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| MailError::Transport(e.to_string()))?;
+        info!("Email sent to {} via SMTP: {}", email.to, email.subject);
+        Ok(())
+    }
+}
+
+// 本地开发与测试用的 outbox：不真正投递，写入目录或打印到标准输出
+pub struct OutboxMailer {
+    from: String,
+    dir: Option<PathBuf>,
+}
+
+impl OutboxMailer {
+    /// 每封邮件写入 dir 下的一个 .eml 文件
+    pub fn to_dir(from: &str, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            from: from.to_string(),
+            dir: Some(dir.into()),
+        }
+    }
+
+    /// 邮件内容打印到标准输出
+    pub fn stdout(from: &str) -> Self {
+        Self {
+            from: from.to_string(),
+            dir: None,
+        }
+    }
+
+    fn render(&self, email: &Email) -> String {
+        let mut content = format!(
+            "From: {}\nTo: {}\nSubject: {}\nDate: {}\n\n{}",
+            self.from,
+            email.to,
+            email.subject,
+            chrono::Utc::now().to_rfc2822(),
+            email.text_body
+        );
+        if let Some(html) = &email.html_body {
+            content.push_str("\n--- html ---\n");
+            content.push_str(html);
+        }
+        content
+    }
+}
+
+#[async_trait]
+impl Mailer for OutboxMailer {
+    async fn send(&self, email: &Email) -> Result<(), MailError> {
+        let content = self.render(email);
+        match &self.dir {
+            Some(dir) => {
+                tokio::fs::create_dir_all(dir).await?;
+                // 文件名以时间戳开头，按名称排序即为发送顺序
+                let file_name = format!(
+                    "{}-{}.eml",
+                    chrono::Utc::now().format("%Y%m%d%H%M%S%6f"),
+                    uuid::Uuid::new_v4().simple()
+                );
+                let path = dir.join(file_name);
+                tokio::fs::write(&path, content).await?;
+                info!("Email to {} written to outbox: {}", email.to, path.display());
+            }
+            None => println!("{}\n", content),
+        }
+        Ok(())
+    }
+}
+
+/// 按 [mail] 配置创建邮件发送器
+pub fn build_mailer(config: &MailConfig) -> Result<Arc<dyn Mailer>, MailError> {
+    Ok(match config.transport {
+        MailTransport::Smtp => {
+            let smtp = config.smtp.as_ref().ok_or_else(|| {
+                MailError::InvalidMessage("mail.smtp is required for the smtp transport".to_string())
+            })?;
+            Arc::new(SmtpMailer::new(&config.from, smtp)?)
+        }
+        MailTransport::File => Arc::new(OutboxMailer::to_dir(&config.from, &config.outbox_dir)),
+        MailTransport::Stdout => Arc::new(OutboxMailer::stdout(&config.from)),
+    })
+}
+
+// 将模板中的 {{key}} 替换为对应的值
+fn render_template(template: &str, values: &[(&str, String)]) -> String {
+    values.iter().fold(template.to_string(), |rendered, (key, value)| {
+        rendered.replace(&format!("{{{{{}}}}}", key), value)
+    })
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+// 读取模板：templates_dir 下存在同名文件时优先使用，否则使用内置模板
+fn load_template(templates_dir: Option<&Path>, name: &str, builtin: &str) -> String {
+    templates_dir
+        .map(|dir| dir.join(name))
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .unwrap_or_else(|| builtin.to_string())
+}
+
+/// 生成注册验证码邮件
+pub fn verification_email(
+    to: &str,
+    user_name: &str,
+    code: &str,
+    expires_minutes: i64,
+    templates_dir: Option<&Path>,
+) -> Email {
+    let text = load_template(templates_dir, "verification.txt", VERIFICATION_TEXT);
+    let html = load_template(templates_dir, "verification.html", VERIFICATION_HTML);
+    let subject = format!("Your OpenPick verification code: {}", code);
+    code_email(to, subject, &text, &html, user_name, code, expires_minutes)
+}
+
+/// 生成重置密码验证码邮件
+pub fn password_reset_email(
+    to: &str,
+    user_name: &str,
+    code: &str,
+    expires_minutes: i64,
+    templates_dir: Option<&Path>,
+) -> Email {
+    let text = load_template(templates_dir, "password_reset.txt", PASSWORD_RESET_TEXT);
+    let html = load_template(templates_dir, "password_reset.html", PASSWORD_RESET_HTML);
+    let subject = format!("Your OpenPick password reset code: {}", code);
+    code_email(to, subject, &text, &html, user_name, code, expires_minutes)
+}
+
+// 渲染包含验证码的邮件，模板可使用 user_name、code、expires_minutes
+fn code_email(
+    to: &str,
+    subject: String,
+    text: &str,
+    html: &str,
+    user_name: &str,
+    code: &str,
+    expires_minutes: i64,
+) -> Email {
+    let values = |escape: bool| {
+        let user_name = if escape { escape_html(user_name) } else { user_name.to_string() };
+        vec![
+            ("user_name", user_name),
+            ("code", code.to_string()),
+            ("expires_minutes", expires_minutes.to_string()),
+        ]
+    };
+
+    Email {
+        to: to.to_string(),
+        subject,
+        text_body: render_template(text, &values(false)),
+        html_body: Some(render_template(html, &values(true))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verification_email_renders_templates() {
+        let email = verification_email("user@example.com", "<Alice>", "123456", 10, None);
+
+        assert_eq!(email.to, "user@example.com");
+        assert!(email.subject.contains("123456"));
+        assert!(email.text_body.contains("Hi <Alice>,"));
+        assert!(email.text_body.contains("123456"));
+        assert!(email.text_body.contains("10 minutes"));
+        assert!(!email.text_body.contains("{{"));
+
+        let html = email.html_body.unwrap();
+        assert!(html.contains("Hi &lt;Alice&gt;,"));
+        assert!(html.contains("123456"));
+        assert!(!html.contains("{{"));
+    }
+
+    #[test]
+    fn test_verification_email_template_override() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("verification.txt"), "code={{code}}").unwrap();
+
+        let email = verification_email("user@example.com", "Alice", "654321", 10, Some(dir.path()));
+
+        assert_eq!(email.text_body, "code=654321");
+        // 未覆盖的 html 模板仍使用内置模板
+        assert!(email.html_body.unwrap().contains("654321"));
+    }
+
+    #[test]
+    fn test_password_reset_email_renders_templates() {
+        let email = password_reset_email("user@example.com", "<Alice>", "246810", 10, None);
+
+        assert!(email.subject.contains("password reset"));
+        assert!(email.subject.contains("246810"));
+        assert!(email.text_body.contains("Hi <Alice>,"));
+        assert!(email.text_body.contains("10 minutes"));
+        assert!(!email.text_body.contains("{{"));
+
+        let html = email.html_body.unwrap();
+        assert!(html.contains("Hi &lt;Alice&gt;,"));
+        assert!(html.contains("246810"));
+        assert!(!html.contains("{{"));
+    }
+
+    #[tokio::test]
+    async fn test_outbox_mailer_writes_eml_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mailer = OutboxMailer::to_dir("OpenPick <no-reply@openpick.org>", dir.path().join("outbox"));
+
+        let email = verification_email("user@example.com", "Alice", "111222", 10, None);
+        mailer.send(&email).await.unwrap();
+
+        let files: Vec<_> = std::fs::read_dir(dir.path().join("outbox"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].extension().unwrap(), "eml");
+
+        let content = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(content.contains("From: OpenPick <no-reply@openpick.org>"));
+        assert!(content.contains("To: user@example.com"));
+        assert!(content.contains("111222"));
+    }
+
+    #[test]
+    fn test_build_mailer_requires_smtp_section() {
+        let config = MailConfig {
+            transport: MailTransport::Smtp,
+            smtp: None,
+            ..MailConfig::default()
+        };
+        assert!(matches!(build_mailer(&config), Err(MailError::InvalidMessage(_))));
+    }
+
+    // 连接池需要在 tokio 运行时中创建
+    #[tokio::test]
+    async fn test_smtp_mailer_rejects_invalid_from() {
+        let smtp = SmtpConfig {
+            host: "localhost".to_string(),
+            port: 2525,
+            username: None,
+            password: None,
+            tls: "none".to_string(),
+        };
+        assert!(SmtpMailer::new("not an address", &smtp).is_err());
+        assert!(SmtpMailer::new("OpenPick <no-reply@openpick.org>", &smtp).is_ok());
+    }
+
+    // 极简 SMTP 服务端，只接收一封邮件并返回 DATA 内容
+    async fn fake_smtp_server(listener: tokio::net::TcpListener) -> String {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        let mut data = String::new();
+        let mut in_data = false;
+
+        writer.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            if in_data {
+                if line == "." {
+                    in_data = false;
+                    writer.write_all(b"250 OK queued\r\n").await.unwrap();
+                } else {
+                    data.push_str(&line);
+                    data.push('\n');
+                }
+                continue;
+            }
+            let command = line.to_uppercase();
+            let reply: &[u8] = if command.starts_with("EHLO") || command.starts_with("HELO") {
+                b"250 localhost\r\n"
+            } else if command.starts_with("DATA") {
+                in_data = true;
+                b"354 End data with <CR><LF>.<CR><LF>\r\n"
+            } else if command.starts_with("QUIT") {
+                writer.write_all(b"221 Bye\r\n").await.unwrap();
+                break;
+            } else {
+                b"250 OK\r\n"
+            };
+            writer.write_all(reply).await.unwrap();
+        }
+        data
+    }
+
+    #[tokio::test]
+    async fn test_smtp_mailer_delivers_message() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(fake_smtp_server(listener));
+
+        let smtp = SmtpConfig {
+            host: "127.0.0.1".to_string(),
+            port,
+            username: None,
+            password: None,
+            tls: "none".to_string(),
+        };
+        let mailer = SmtpMailer::new("OpenPick <no-reply@openpick.org>", &smtp).unwrap();
+        let email = verification_email("user@example.com", "Alice", "987654", 10, None);
+        mailer.send(&email).await.unwrap();
+        drop(mailer);
+
+        let data = tokio::time::timeout(std::time::Duration::from_secs(10), server)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(data.contains("To: user@example.com"));
+        assert!(data.contains("987654"));
+        assert!(data.contains("text/html"));
+    }
+}
@@ -1,15 +1,27 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
 use axum::{
-    extract::{Request, State},
+    body::Body,
+    extract::{ConnectInfo, Request, State},
     http::{header::AUTHORIZATION, StatusCode},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
     Json,
 };
 use jsonwebtoken::{decode, DecodingKey, Validation};
 use serde_json::json;
+use tower::{Layer, Service};
 use uuid::Uuid;
 
-use crate::config::{AppState, Claims};
+use crate::config::{AppState, BucketConfig, Claims, RateLimitConfig};
+use crate::utils::AppError;
 
 pub async fn auth_middleware(
     State(state): State<AppState>,
@@ -21,13 +33,7 @@ pub async fn auth_middleware(
         .headers()
         .get(AUTHORIZATION)
         .and_then(|header| header.to_str().ok())
-        .and_then(|header| {
-            if header.starts_with("Bearer ") {
-                Some(&header[7..])
-            } else {
-                None
-            }
-        });
+        .and_then(|header| header.strip_prefix("Bearer "));
 
     let token = auth_header.ok_or_else(|| {
         (
@@ -74,10 +80,30 @@ pub async fn auth_middleware(
         )
     })?;
 
+    // 已登出（吊销）的令牌，以及修改密码前签发的令牌，在过期前不能继续使用
+    let revoked = state.token_revocations.is_claims_revoked(user_id, &claims).await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({
+                "error": "Internal Server Error",
+                "message": "Database error during authentication"
+            }))
+        )
+    })?;
+    if revoked {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({
+                "error": "Unauthorized",
+                "message": "Token has been revoked"
+            }))
+        ));
+    }
+
     // 验证用户是否存在
-    let user_exists = sqlx::query("SELECT 1 FROM users WHERE user_id = ? LIMIT 1")
-        .bind(user_id)
-        .fetch_optional(&state.db)
+    let user_exists = state
+        .users
+        .exists(user_id)
         .await
         .map_err(|_| {
             (
@@ -89,7 +115,7 @@ pub async fn auth_middleware(
             )
         })?;
 
-    if user_exists.is_none() {
+    if !user_exists {
         return Err((
             StatusCode::UNAUTHORIZED,
             Json(json!({
@@ -99,12 +125,204 @@ pub async fn auth_middleware(
         ));
     }
 
-    // 将用户ID添加到请求扩展中
+    // 将用户ID与 Claims 添加到请求扩展中，Claims 供登出时吊销当前令牌
     request.extensions_mut().insert(user_id);
+    request.extensions_mut().insert(claims);
 
     Ok(next.run(request).await)
 }
 
+
+// 限流时读取请求体的上限，登录、注册、验证的请求体都很小
+const RATE_LIMIT_BODY_LIMIT: usize = 64 * 1024;
+
+struct TokenBucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// 令牌桶限流器，桶保存在进程内存中
+/// 每个接口分别按客户端 IP 和请求体中的 email 字段计数
+pub struct RateLimiter {
+    enabled: bool,
+    per_ip: BucketConfig,
+    per_email: BucketConfig,
+    trust_forwarded_for: bool,
+    trusted_proxies: usize,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            per_ip: config.per_ip,
+            per_email: config.per_email,
+            trust_forwarded_for: config.trust_forwarded_for,
+            trusted_proxies: config.trusted_proxies.max(1),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 从 key 对应的桶中取出一个令牌，桶已空时返回需要等待的时间
+    pub fn try_acquire(&self, key: &str, bucket: BucketConfig) -> Result<(), Duration> {
+        self.try_acquire_at(key, bucket, Instant::now())
+    }
+
+    fn try_acquire_at(&self, key: &str, bucket: BucketConfig, now: Instant) -> Result<(), Duration> {
+        let capacity = bucket.burst.max(1) as f64;
+        let refill_per_second = bucket.per_minute as f64 / 60.0;
+
+        let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+        let entry = buckets.entry(key.to_string()).or_insert(TokenBucket {
+            tokens: capacity,
+            updated_at: now,
+        });
+
+        let elapsed = now.saturating_duration_since(entry.updated_at).as_secs_f64();
+        entry.tokens = (entry.tokens + elapsed * refill_per_second).min(capacity);
+        entry.updated_at = now;
+
+        if entry.tokens >= 1.0 {
+            entry.tokens -= 1.0;
+            Ok(())
+        } else if refill_per_second > 0.0 {
+            Err(Duration::from_secs_f64((1.0 - entry.tokens) / refill_per_second))
+        } else {
+            // per_minute 为 0 时不会补充令牌
+            Err(Duration::from_secs(60))
+        }
+    }
+
+    /// 移除已经回满的令牌桶，由定时任务调用，避免长时间运行后占用过多内存
+    pub fn purge_idle(&self) -> usize {
+        let refill_time = |bucket: BucketConfig| match bucket.per_minute {
+            0 => Duration::MAX,
+            per_minute => Duration::from_secs_f64(bucket.burst.max(1) as f64 * 60.0 / per_minute as f64),
+        };
+        let idle = refill_time(self.per_ip).max(refill_time(self.per_email));
+
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+        let before = buckets.len();
+        buckets.retain(|_, bucket| now.saturating_duration_since(bucket.updated_at) < idle);
+        before - buckets.len()
+    }
+
+    // 客户端 IP：默认取连接地址，trust_forwarded_for 开启时优先取 X-Forwarded-For 中从右数第 trusted_proxies 个地址
+    // 更靠左的地址由客户端自行填写，伪造后可以绕过按 IP 限流；地址数量不足时退回连接地址
+    fn client_ip(&self, request: &Request) -> String {
+        if self.trust_forwarded_for {
+            let forwarded = request
+                .headers()
+                .get("x-forwarded-for")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.rsplit(',').nth(self.trusted_proxies - 1))
+                .map(str::trim)
+                .filter(|value| !value.is_empty());
+            if let Some(ip) = forwarded {
+                return ip.to_string();
+            }
+        }
+
+        request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string())
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+
+    /// 依次检查 IP 与邮箱的令牌桶，通过时返回重新组装的请求
+    pub async fn check(&self, request: Request) -> Result<Request, AppError> {
+        if !self.enabled {
+            return Ok(request);
+        }
+
+        let path = request.uri().path().to_string();
+        let ip = self.client_ip(&request);
+        self.try_acquire(&format!("ip:{}:{}", path, ip), self.per_ip)
+            .map_err(too_many_requests)?;
+
+        // 读取请求体中的 email 字段，之后把请求体原样放回
+        let (parts, body) = request.into_parts();
+        let bytes = axum::body::to_bytes(body, RATE_LIMIT_BODY_LIMIT)
+            .await
+            .map_err(|_| AppError::BadRequest("Invalid request body".to_string()))?;
+
+        let email = serde_json::from_slice::<serde_json::Value>(&bytes)
+            .ok()
+            .and_then(|value| value.get("email")?.as_str().map(|email| email.trim().to_lowercase()))
+            .filter(|email| !email.is_empty());
+        if let Some(email) = email {
+            self.try_acquire(&format!("email:{}:{}", path, email), self.per_email)
+                .map_err(too_many_requests)?;
+        }
+
+        Ok(Request::from_parts(parts, Body::from(bytes)))
+    }
+}
+
+fn too_many_requests(retry_after: Duration) -> AppError {
+    AppError::TooManyRequests(retry_after.as_secs_f64().ceil() as u64)
+}
+
+/// 限流中间件，挂载在登录、注册、验证路由上
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitLayer {
+    pub fn new(limiter: Arc<RateLimiter>) -> Self {
+        Self { limiter }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<S> Service<Request> for RateLimitService<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        // 使用已就绪的服务处理本次请求，留下克隆体等待下一次 poll_ready
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limiter = self.limiter.clone();
+
+        Box::pin(async move {
+            match limiter.check(request).await {
+                Ok(request) => inner.call(request).await,
+                Err(e) => Ok(e.into_response()),
+            }
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -118,6 +336,7 @@ mod tests {
     use jsonwebtoken::{encode, EncodingKey, Header};
     use tower::ServiceExt;
     use uuid::Uuid;
+    use crate::utils_tests::test_pool;
 
     async fn test_handler() -> &'static str {
         "success"
@@ -135,6 +354,7 @@ mod tests {
             sub: user_id.to_string(),
             exp: (now + chrono::Duration::hours(24)).timestamp() as usize,
             iat: now.timestamp() as usize,
+            jti: Uuid::new_v4().to_string(),
         };
 
         encode(
@@ -150,7 +370,7 @@ mod tests {
         let state = create_test_app_state().await;
         
         // 初始化数据库
-        crate::database::init_database(&state.db).await.unwrap();
+        crate::database::init_database(test_pool(&state)).await.unwrap();
         
         let user_id = Uuid::new_v4();
         let user_id_str = user_id.to_string();
@@ -171,7 +391,7 @@ mod tests {
         .bind("test_wallet_address")
         .bind(0)
         .bind(chrono::Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
         
@@ -259,6 +479,7 @@ mod tests {
             sub: user_id,
             exp: (now - chrono::Duration::hours(1)).timestamp() as usize,
             iat: (now - chrono::Duration::hours(2)).timestamp() as usize,
+            jti: Uuid::new_v4().to_string(),
         };
 
         let expired_token = encode(
@@ -314,6 +535,7 @@ mod tests {
             sub: "invalid-uuid".to_string(), // 无效的UUID格式
             exp: (now + chrono::Duration::hours(24)).timestamp() as usize,
             iat: now.timestamp() as usize,
+            jti: Uuid::new_v4().to_string(),
         };
 
         let invalid_token = encode(
@@ -343,7 +565,7 @@ mod tests {
         let state = create_test_app_state().await;
         
         // 初始化数据库
-        crate::database::init_database(&state.db).await.unwrap();
+        crate::database::init_database(test_pool(&state)).await.unwrap();
         
         // 创建一个不存在于数据库中的用户ID
         let nonexistent_user_id = Uuid::new_v4();
@@ -449,7 +671,7 @@ mod tests {
         let state = create_test_app_state().await;
         
         // 关闭数据库连接池来模拟数据库错误
-        state.db.close().await;
+        test_pool(&state).close().await;
         
         let user_id = Uuid::new_v4();
         let token = create_test_token(&user_id.to_string(), &state.jwt_secret);
@@ -474,7 +696,7 @@ mod tests {
         let state = create_test_app_state().await;
         
         // 初始化数据库
-        crate::database::init_database(&state.db).await.unwrap();
+        crate::database::init_database(test_pool(&state)).await.unwrap();
         
         let user_id = Uuid::new_v4();
         let user_id_str = user_id.to_string();
@@ -495,7 +717,7 @@ mod tests {
         .bind("test_wallet_address")
         .bind(0)
         .bind(chrono::Utc::now().to_rfc3339())
-        .execute(&state.db)
+        .execute(test_pool(&state))
         .await
         .unwrap();
         
@@ -550,4 +772,305 @@ mod tests {
         assert_eq!(claims.sub, user_id);
         assert!(claims.exp > claims.iat);
     }
+
+    fn rate_limit_config(per_ip: BucketConfig, per_email: BucketConfig) -> RateLimitConfig {
+        RateLimitConfig {
+            per_ip,
+            per_email,
+            ..RateLimitConfig::default()
+        }
+    }
+
+    // 回显请求体，用于确认限流读取后请求体仍完整传给处理器
+    async fn echo_handler(body: String) -> String {
+        body
+    }
+
+    fn rate_limited_app(limiter: Arc<RateLimiter>) -> Router {
+        Router::new()
+            .route("/login", axum::routing::post(echo_handler))
+            .route_layer(RateLimitLayer::new(limiter))
+    }
+
+    fn login_request(ip: [u8; 4], email: &str) -> Request<Body> {
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/login")
+            .header("content-type", "application/json")
+            .body(Body::from(json!({ "email": email, "user_password": "x" }).to_string()))
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from((ip, 40000))));
+        request
+    }
+
+    #[test]
+    fn test_token_bucket_refill() {
+        let bucket = BucketConfig { burst: 2, per_minute: 60 };
+        let limiter = RateLimiter::new(&rate_limit_config(bucket, bucket));
+        let start = Instant::now();
+
+        assert!(limiter.try_acquire_at("k", bucket, start).is_ok());
+        assert!(limiter.try_acquire_at("k", bucket, start).is_ok());
+        let wait = limiter.try_acquire_at("k", bucket, start).unwrap_err();
+        assert!(wait > Duration::ZERO && wait <= Duration::from_secs(1));
+
+        // 其他 key 使用独立的桶
+        assert!(limiter.try_acquire_at("other", bucket, start).is_ok());
+
+        // 每秒补充一个令牌
+        assert!(limiter.try_acquire_at("k", bucket, start + Duration::from_secs(1)).is_ok());
+        assert!(limiter.try_acquire_at("k", bucket, start + Duration::from_secs(1)).is_err());
+    }
+
+    #[test]
+    fn test_purge_idle_buckets() {
+        let bucket = BucketConfig { burst: 1, per_minute: 60 };
+        let limiter = RateLimiter::new(&rate_limit_config(bucket, bucket));
+        let long_ago = Instant::now() - Duration::from_secs(10);
+
+        limiter.try_acquire_at("idle", bucket, long_ago).unwrap();
+        limiter.try_acquire("active", bucket).unwrap();
+
+        assert_eq!(limiter.purge_idle(), 1);
+        assert!(limiter.try_acquire("active", bucket).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_per_ip() {
+        let limiter = Arc::new(RateLimiter::new(&rate_limit_config(
+            BucketConfig { burst: 2, per_minute: 1 },
+            BucketConfig { burst: 100, per_minute: 100 },
+        )));
+        let app = rate_limited_app(limiter);
+
+        for i in 0..2 {
+            let request = login_request([10, 0, 0, 1], &format!("user{}@example.com", i));
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let response = app
+            .clone()
+            .oneshot(login_request([10, 0, 0, 1], "user9@example.com"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = response.headers()[axum::http::header::RETRY_AFTER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(retry_after > 0 && retry_after <= 60);
+
+        // 其他 IP 不受影响
+        let response = app
+            .oneshot(login_request([10, 0, 0, 2], "user9@example.com"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_per_email() {
+        let limiter = Arc::new(RateLimiter::new(&rate_limit_config(
+            BucketConfig { burst: 100, per_minute: 100 },
+            BucketConfig { burst: 1, per_minute: 1 },
+        )));
+        let app = rate_limited_app(limiter);
+
+        let response = app
+            .clone()
+            .oneshot(login_request([10, 0, 0, 1], "victim@example.com"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["email"], "victim@example.com");
+
+        // 换 IP、改大小写也共用同一个邮箱桶
+        let response = app
+            .clone()
+            .oneshot(login_request([10, 0, 0, 2], "Victim@Example.com"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        let response = app
+            .oneshot(login_request([10, 0, 0, 2], "other@example.com"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_trusts_forwarded_for_when_enabled() {
+        let bucket = BucketConfig { burst: 1, per_minute: 1 };
+        let limiter = Arc::new(RateLimiter::new(&RateLimitConfig {
+            trust_forwarded_for: true,
+            ..rate_limit_config(bucket, BucketConfig { burst: 100, per_minute: 100 })
+        }));
+        let app = rate_limited_app(limiter);
+
+        let forwarded_request = |forwarded: &str| {
+            let mut request = login_request([10, 0, 0, 1], "user@example.com");
+            request.headers_mut().insert("x-forwarded-for", forwarded.parse().unwrap());
+            request
+        };
+
+        // 同一个代理地址转发的不同客户端分别计数
+        for client in ["203.0.113.1", "203.0.113.2"] {
+            let response = app.clone().oneshot(forwarded_request(client)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        // 客户端在左侧伪造的地址不影响计数，仍按代理追加的最右侧地址限流
+        let response = app
+            .clone()
+            .oneshot(forwarded_request("198.51.100.9, 203.0.113.1"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_forwarded_for_with_multiple_proxies() {
+        let bucket = BucketConfig { burst: 1, per_minute: 1 };
+        let limiter = Arc::new(RateLimiter::new(&RateLimitConfig {
+            trust_forwarded_for: true,
+            trusted_proxies: 2,
+            ..rate_limit_config(bucket, BucketConfig { burst: 100, per_minute: 100 })
+        }));
+        let app = rate_limited_app(limiter);
+
+        // 两层代理时最右侧是内层代理的地址，客户端 IP 在它左边一位
+        let mut statuses = Vec::new();
+        for forwarded in [
+            "198.51.100.1, 203.0.113.1, 10.0.0.2",
+            "198.51.100.2, 203.0.113.2, 10.0.0.2",
+            "203.0.113.1, 10.0.0.2",
+        ] {
+            let mut request = login_request([10, 0, 0, 1], "user@example.com");
+            request.headers_mut().insert("x-forwarded-for", forwarded.parse().unwrap());
+            statuses.push(app.clone().oneshot(request).await.unwrap().status());
+        }
+        assert_eq!(statuses, [StatusCode::OK, StatusCode::OK, StatusCode::TOO_MANY_REQUESTS]);
+
+        // 地址数量不足时按连接地址计数
+        let mut request = login_request([10, 0, 0, 3], "user@example.com");
+        request.headers_mut().insert("x-forwarded-for", "203.0.113.3".parse().unwrap());
+        assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_disabled() {
+        let bucket = BucketConfig { burst: 1, per_minute: 1 };
+        let limiter = Arc::new(RateLimiter::new(&RateLimitConfig {
+            enabled: false,
+            ..rate_limit_config(bucket, bucket)
+        }));
+        let app = rate_limited_app(limiter);
+
+        for _ in 0..3 {
+            let response = app
+                .clone()
+                .oneshot(login_request([10, 0, 0, 1], "user@example.com"))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_auth_middleware_rejects_revoked_token() {
+        let repo = crate::repository::MemoryRepository::new();
+        let state = crate::utils_tests::create_mock_app_state(&repo).await;
+        let user = crate::models::User {
+            user_id: Uuid::new_v4(),
+            email: "revoked@example.com".to_string(),
+            user_name: "Revoked".to_string(),
+            user_password: "hash".to_string(),
+            user_type: crate::models::UserType::Gen,
+            wallet_address: "0x0000000000000000000000000000000000000000".to_string(),
+            private_key: "encrypted".to_string(),
+            premium_balance: 0,
+            created_at: chrono::Utc::now(),
+        };
+        state.users.create(&user).await.unwrap();
+
+        let (token, claims) = state.issue_access_token(user.user_id).unwrap();
+        let app = Router::new()
+            .route("/test", get(test_handler))
+            .layer(middleware::from_fn_with_state(state.clone(), auth_middleware))
+            .with_state(state.clone());
+        let request = || {
+            Request::builder()
+                .uri("/test")
+                .header(AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        state.token_revocations.revoke_access_token(&claims).await.unwrap();
+        let response = app.oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_auth_middleware_rejects_tokens_issued_before_session_revocation() {
+        let repo = crate::repository::MemoryRepository::new();
+        let state = crate::utils_tests::create_mock_app_state(&repo).await;
+        let user = crate::models::User {
+            user_id: Uuid::new_v4(),
+            email: "sessions@example.com".to_string(),
+            user_name: "Sessions".to_string(),
+            user_password: "hash".to_string(),
+            user_type: crate::models::UserType::Gen,
+            wallet_address: "0x0000000000000000000000000000000000000000".to_string(),
+            private_key: "encrypted".to_string(),
+            premium_balance: 0,
+            created_at: chrono::Utc::now(),
+        };
+        state.users.create(&user).await.unwrap();
+
+        // 一分钟前签发的令牌
+        let now = chrono::Utc::now();
+        let old_claims = Claims {
+            sub: user.user_id.to_string(),
+            exp: (now + chrono::Duration::minutes(10)).timestamp() as usize,
+            iat: (now - chrono::Duration::minutes(1)).timestamp() as usize,
+            jti: Uuid::new_v4().to_string(),
+        };
+        let old_token = encode(
+            &Header::default(),
+            &old_claims,
+            &EncodingKey::from_secret(state.jwt_secret.as_ref()),
+        )
+        .unwrap();
+
+        state.token_revocations.revoke_user_sessions(user.user_id).await.unwrap();
+        let (new_token, _) = state.issue_access_token(user.user_id).unwrap();
+
+        let app = Router::new()
+            .route("/test", get(test_handler))
+            .layer(middleware::from_fn_with_state(state.clone(), auth_middleware))
+            .with_state(state.clone());
+        let request = |token: &str| {
+            Request::builder()
+                .uri("/test")
+                .header(AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request(&old_token)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.oneshot(request(&new_token)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
 }
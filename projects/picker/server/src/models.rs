@@ -30,6 +30,48 @@ pub enum OrderStatus {
     Pending,
     Success,
     Expired,
+    // 已支付后退款
+    Refunded,
+    // 未支付前取消
+    Cancelled,
+}
+
+// 退款记录状态
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum RefundStatus {
+    Pending,
+    Completed,
+    Failed,
+}
+
+// 开发者提现状态
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum WithdrawalStatus {
+    // 已申请，积分已扣除，等待管理员转账
+    Requested,
+    // 已提交转账交易，等待确认
+    Processing,
+    Completed,
+    // 转账交易回滚，积分已退回
+    Failed,
+    // 开发者取消，积分已退回
+    Cancelled,
+}
+
+// Picker 在 PickerPayment 合约上的注册状态
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum PickerChainStatus {
+    Unregistered,
+    Pending,
+    Registered,
+    Failed,
+    Removed,
 }
 
 // 用户模型
@@ -61,6 +103,37 @@ pub struct Picker {
     pub image_path: String,
     pub version: String,
     pub status: String,
+    pub chain_status: PickerChainStatus,
+    // 最近一次 registerPicker / removePicker 交易的哈希
+    pub chain_tx_hash: Option<String>,
+    // 软删除时间，已删除的 Picker 保持 inactive，仅供已有订单下载与退款
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+// Picker 版本记录，pickers.version / file_path 始终为最新发布的版本
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PickerVersion {
+    pub version_id: Uuid,
+    pub picker_id: Uuid,
+    pub version: String,
+    pub file_path: String,
+    // 开发者填写的更新说明
+    pub changelog: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PickerVersion {
+    // 创建 Picker 时上传的文件即首个版本，version_id 复用 picker_id（与迁移时的回填一致）
+    pub fn initial(picker: &Picker) -> Self {
+        Self {
+            version_id: picker.picker_id,
+            picker_id: picker.picker_id,
+            version: picker.version.clone(),
+            file_path: picker.file_path.clone(),
+            changelog: None,
+            created_at: picker.created_at,
+        }
+    }
 }
 
 // 订单模型
@@ -77,6 +150,143 @@ pub struct Order {
     pub expires_at: Option<DateTime<Utc>>,
 }
 
+// 退款记录
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Refund {
+    pub refund_id: Uuid,
+    pub order_id: Uuid,
+    // 发起退款的管理员或开发者
+    pub requested_by: Uuid,
+    pub amount: i64,
+    pub status: RefundStatus,
+    // 钱包订单退款对应的合约 withdrawFunds 交易哈希，Premium 退款为空
+    pub tx_hash: Option<String>,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+// 开发者的一笔销售（成功的订单），dev_income 为流水中记录的开发者收入，钱包订单与启用流水前的订单为空
+#[derive(Debug, Clone, PartialEq, FromRow)]
+pub struct Sale {
+    pub order_id: Uuid,
+    pub picker_id: Uuid,
+    pub pay_type: PayType,
+    pub amount: i64,
+    pub dev_income: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+// 开发者提现申请：申请时扣除积分，并按当时的代币价格锁定应转账的 wei 金额
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Withdrawal {
+    pub withdrawal_id: Uuid,
+    pub user_id: Uuid,
+    // 提现的 Premium 积分
+    pub amount: i64,
+    // 按 premium.to_usd 折算的 USD 金额
+    pub amount_usd: i64,
+    // 代币的 USD 价格，十进制字符串
+    pub token_price: String,
+    // 应转账的 wei 数量，十进制字符串
+    pub amount_wei: String,
+    // 收款地址，申请时开发者的钱包地址
+    pub wallet_address: String,
+    pub status: WithdrawalStatus,
+    // 管理员提交的转账交易哈希
+    pub tx_hash: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+// Premium 积分流水分录类型
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerEntryKind {
+    // 启用流水前已有的余额
+    Opening,
+    // 用户购买 Picker 支付的积分
+    Purchase,
+    // 开发者的销售收入
+    DevPayout,
+    // 平台手续费
+    PlatformFee,
+    // 免费发放的积分
+    FreeGrant,
+    // 退款冲回
+    Refund,
+    // 开发者提现扣除，提现失败或取消时冲回
+    Withdrawal,
+}
+
+// Premium 积分流水分录，同一 transaction_id 下的分录金额之和为 0
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct PremiumLedgerEntry {
+    pub entry_id: Uuid,
+    pub transaction_id: Uuid,
+    // 为空表示平台账户
+    pub user_id: Option<Uuid>,
+    pub order_id: Option<Uuid>,
+    pub kind: LedgerEntryKind,
+    // 正数为入账，负数为出账
+    pub amount: i64,
+    // 用户分录写入后的余额，平台分录为空
+    pub balance_after: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PremiumLedgerEntry {
+    pub fn new(transaction_id: Uuid, user_id: Option<Uuid>, order_id: Option<Uuid>, kind: LedgerEntryKind, amount: i64) -> Self {
+        Self {
+            entry_id: Uuid::new_v4(),
+            transaction_id,
+            user_id,
+            order_id,
+            kind,
+            amount,
+            balance_after: None,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+// 用户余额与流水合计不一致的账户
+#[derive(Debug, Clone, PartialEq, FromRow)]
+pub struct BalanceMismatch {
+    pub user_id: Uuid,
+    pub premium_balance: i64,
+    pub ledger_balance: i64,
+}
+
+// 已索引的 PickerPayment 合约事件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct ChainEvent {
+    pub tx_hash: String,
+    pub log_index: i64,
+    pub block_number: i64,
+    pub block_hash: String,
+    // PaymentProcessed / PickerRegistered / PickerRemoved
+    pub event_name: String,
+    pub picker_id: Uuid,
+    // PickerRegistered 中的开发者钱包地址
+    pub wallet_address: Option<String>,
+    // PaymentProcessed 中的支付金额（wei，十进制字符串）
+    pub amount: Option<String>,
+    pub order_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+// 事件索引进度
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct ChainCursor {
+    pub name: String,
+    pub block_number: i64,
+    pub block_hash: String,
+    pub updated_at: DateTime<Utc>,
+}
+
 // JWT Claims
 #[derive(Debug, Clone)]
 pub struct Claims {
@@ -95,15 +305,48 @@ impl Claims {
 }
 
 // 验证码结构
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VerificationCode {
     pub code: String,
     pub expires_at: DateTime<Utc>,
     pub email: String,
 }
 
+// 邮箱验证失败计数，达到上限后在 locked_until 之前拒绝该邮箱的验证请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationFailures {
+    pub failures: u32,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+// 刷新令牌，按令牌摘要保存在短期数据存储中
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshToken {
+    pub user_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+// 钱包支付报价：锁定下单时使用的代币价格与 wei 金额，以 quote_id 为键，只能使用一次
+// signature 为服务端对报价字段的 HMAC-SHA256 签名
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceQuote {
+    pub quote_id: Uuid,
+    pub user_id: Uuid,
+    pub picker_id: Uuid,
+    // Picker 价格（USD）
+    pub price: i64,
+    // 代币的 USD 价格，十进制字符串
+    pub token_price: String,
+    // 应支付的 wei 数量，十进制字符串
+    pub amount_wei: String,
+    pub expires_at: DateTime<Utc>,
+    pub signature: String,
+}
+
 // 下载Token
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DownloadToken {
     pub token: String,
     pub order_id: Uuid,
@@ -117,6 +117,9 @@ mod tests {
             image_path: "/path/to/image".to_string(),
             version: "1.0.0".to_string(),
             status: "active".to_string(),
+            chain_status: PickerChainStatus::Unregistered,
+            chain_tx_hash: None,
+            deleted_at: None,
         };
         
         // 测试序列化和反序列化
@@ -14,15 +14,35 @@ use crate::download::DownloadQuery;
         crate::handlers::users::register,
         crate::handlers::users::verify,
         crate::handlers::users::login,
+        crate::handlers::users::refresh,
+        crate::handlers::users::forgot_password,
+        crate::handlers::users::reset_password,
         crate::handlers::pickers::get_market,
         crate::handlers::pickers::get_picker_detail,
+        crate::handlers::pickers::get_picker_versions,
         crate::download::download,
         // 受保护路由
         crate::handlers::users::get_profile,
+        crate::handlers::users::logout,
+        crate::handlers::users::change_password,
+        crate::handlers::users::get_premium_ledger,
         crate::handlers::pickers::upload_picker,
+        crate::handlers::pickers::update_picker,
+        crate::handlers::pickers::delete_picker,
+        crate::handlers::pickers::publish_picker_version,
         crate::handlers::orders::create_order,
+        crate::handlers::orders::create_quote,
         crate::handlers::orders::get_user_orders,
         crate::handlers::orders::get_order_detail,
+        crate::handlers::orders::order_events,
+        crate::handlers::orders::refund_order,
+        crate::handlers::developers::get_earnings,
+        crate::handlers::developers::get_my_pickers,
+        crate::handlers::developers::create_withdrawal,
+        crate::handlers::developers::get_withdrawals,
+        crate::handlers::developers::cancel_withdrawal,
+        crate::handlers::developers::list_withdrawals,
+        crate::handlers::developers::payout_withdrawal,
     ),
     components(
         schemas(
@@ -30,25 +50,62 @@ use crate::download::DownloadQuery;
             UserType,
             PayType,
             OrderStatus,
+            PickerChainStatus,
+            RefundStatus,
+            LedgerEntryKind,
+            WithdrawalStatus,
             // 请求结构体
             RegisterRequest,
             VerifyRequest,
             LoginRequest,
+            RefreshRequest,
+            LogoutRequest,
+            ForgotPasswordRequest,
+            ResetPasswordRequest,
+            ChangePasswordRequest,
             MarketQuery,
+            LedgerQuery,
             CreateOrderRequest,
+            QuoteRequest,
+            RefundOrderRequest,
             OrderQuery,
+            EarningsQuery,
+            CreateWithdrawalRequest,
+            WithdrawalQuery,
+            AdminWithdrawalQuery,
+            PayoutWithdrawalRequest,
             DownloadQuery,
             // 响应结构体
             RegisterResponse,
             VerifyResponse,
             LoginResponse,
+            RefreshResponse,
+            LogoutResponse,
+            PasswordResponse,
+            ChangePasswordResponse,
             UserInfo,
+            PremiumLedgerResponse,
+            PremiumLedgerEntry,
             PickerInfo,
             MarketResponse,
             UploadPickerResponse,
+            DeletePickerResponse,
+            PickerVersionInfo,
+            PickerVersionsResponse,
             CreateOrderResponse,
+            QuoteResponse,
             OrderInfo,
             OrderListResponse,
+            OrderStatusEvent,
+            RefundOrderResponse,
+            Refund,
+            crate::services::earnings::DeveloperEarnings,
+            crate::services::earnings::EarningsTotals,
+            crate::services::earnings::PickerEarnings,
+            crate::services::earnings::DailyEarnings,
+            DeveloperPickerListResponse,
+            WithdrawalListResponse,
+            Withdrawal,
             // 错误响应
             ErrorResponse,
         )
@@ -58,6 +115,7 @@ use crate::download::DownloadQuery;
         (name = "users", description = "User management endpoints"),
         (name = "pickers", description = "Picker management endpoints"),
         (name = "orders", description = "Order management endpoints"),
+        (name = "developers", description = "Developer earnings and withdrawal endpoints"),
         (name = "download", description = "File download endpoints"),
     ),
     info(
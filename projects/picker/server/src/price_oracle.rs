@@ -0,0 +1,469 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use alloy::primitives::U256;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use tracing::warn;
+
+use crate::config::{PriceOracleConfig, PriceSource};
+
+// 原生代币的精度，1 个代币 = 10^18 wei
+pub const TOKEN_DECIMALS: u32 = 18;
+
+// 小数位数上限，保证 10^(TOKEN_DECIMALS + scale) 不会溢出 U256
+const MAX_SCALE: u32 = 36;
+
+// 价格查询错误
+#[derive(Debug)]
+pub enum PriceError {
+    // 价格或金额格式不合法
+    Invalid(String),
+    // 价格源请求失败或返回了错误
+    Source(String),
+    // 价格超过了允许的最大陈旧时间
+    Stale { age_seconds: i64 },
+    // 读取价格文件失败
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for PriceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PriceError::Invalid(msg) => write!(f, "invalid price: {}", msg),
+            PriceError::Source(msg) => write!(f, "price source error: {}", msg),
+            PriceError::Stale { age_seconds } => write!(f, "price is stale ({}s old)", age_seconds),
+            PriceError::Io(e) => write!(f, "io error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PriceError {}
+
+impl From<std::io::Error> for PriceError {
+    fn from(e: std::io::Error) -> Self {
+        PriceError::Io(e)
+    }
+}
+
+/// 非负十进制数，值为 mantissa / 10^scale，全程使用整数运算，不经过浮点数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decimal {
+    mantissa: U256,
+    scale: u32,
+}
+
+impl Decimal {
+    pub fn is_zero(&self) -> bool {
+        self.mantissa.is_zero()
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = PriceError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let (int_part, frac_part) = value.split_once('.').unwrap_or((value, ""));
+        let digits = format!("{}{}", int_part, frac_part);
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(PriceError::Invalid(format!("{:?} is not a decimal number", value)));
+        }
+        if frac_part.len() > MAX_SCALE as usize {
+            return Err(PriceError::Invalid(format!("{:?} has more than {} decimal places", value, MAX_SCALE)));
+        }
+        let mantissa = U256::from_str_radix(&digits, 10)
+            .map_err(|e| PriceError::Invalid(format!("{:?}: {}", value, e)))?;
+        Ok(Self {
+            mantissa,
+            scale: frac_part.len() as u32,
+        })
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let digits = format!("{:0>width$}", self.mantissa.to_string(), width = self.scale as usize + 1);
+        let (int_part, frac_part) = digits.split_at(digits.len() - self.scale as usize);
+        if frac_part.is_empty() {
+            write!(f, "{}", int_part)
+        } else {
+            write!(f, "{}.{}", int_part, frac_part)
+        }
+    }
+}
+
+/// 将 USD 计价的金额按代币价格（每个代币的 USD 价格）换算为 wei，向下取整
+pub fn usd_to_wei(amount_usd: i64, token_price: &Decimal) -> Result<U256, PriceError> {
+    if amount_usd < 0 {
+        return Err(PriceError::Invalid(format!("negative amount {}", amount_usd)));
+    }
+    if token_price.is_zero() {
+        return Err(PriceError::Invalid("token price is zero".to_string()));
+    }
+    // amount / (mantissa / 10^scale) * 10^18 = amount * 10^(18 + scale) / mantissa
+    let factor = U256::from(10u64).pow(U256::from(TOKEN_DECIMALS + token_price.scale));
+    let numerator = U256::from(amount_usd as u64)
+        .checked_mul(factor)
+        .ok_or_else(|| PriceError::Invalid(format!("amount {} overflows", amount_usd)))?;
+    Ok(numerator / token_price.mantissa)
+}
+
+/// 代币的 USD 价格及其报价时间
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenPrice {
+    pub price: Decimal,
+    pub as_of: DateTime<Utc>,
+}
+
+// 价格来源接口
+#[async_trait]
+pub trait PriceOracle: Send + Sync {
+    async fn token_price(&self) -> Result<TokenPrice, PriceError>;
+}
+
+/// OKX 行情接口，如 https://www.okx.com/api/v5/market/ticker?instId=CFX-USDT
+pub struct OkxPriceOracle {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl OkxPriceOracle {
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl PriceOracle for OkxPriceOracle {
+    async fn token_price(&self) -> Result<TokenPrice, PriceError> {
+        let response = self
+            .client
+            .get(&self.url)
+            .send()
+            .await
+            .map_err(|e| PriceError::Source(e.to_string()))?;
+        let body: serde_json::Value = response.json().await.map_err(|e| PriceError::Source(e.to_string()))?;
+        parse_okx_ticker(&body)
+    }
+}
+
+// 响应格式：{"code":"0","msg":"","data":[{"last":"0.1234","ts":"1700000000000",...}]}
+fn parse_okx_ticker(body: &serde_json::Value) -> Result<TokenPrice, PriceError> {
+    if body.get("code").and_then(|c| c.as_str()) != Some("0") {
+        let msg = body.get("msg").and_then(|m| m.as_str()).unwrap_or("unknown error");
+        return Err(PriceError::Source(format!("OKX returned error: {}", msg)));
+    }
+    let ticker = body
+        .get("data")
+        .and_then(|data| data.as_array())
+        .and_then(|data| data.first())
+        .ok_or_else(|| PriceError::Source("OKX response has no ticker data".to_string()))?;
+    let last = ticker
+        .get("last")
+        .and_then(|last| last.as_str())
+        .ok_or_else(|| PriceError::Source("OKX ticker has no last price".to_string()))?;
+    let as_of = ticker
+        .get("ts")
+        .and_then(|ts| ts.as_str())
+        .and_then(|ts| ts.parse::<i64>().ok())
+        .and_then(|ts| Utc.timestamp_millis_opt(ts).single())
+        .unwrap_or_else(Utc::now);
+
+    Ok(TokenPrice {
+        price: last.parse()?,
+        as_of,
+    })
+}
+
+/// 固定价格，用于测试网或本地开发
+pub struct FixedPriceOracle {
+    price: Decimal,
+}
+
+impl FixedPriceOracle {
+    pub fn new(price: Decimal) -> Self {
+        Self { price }
+    }
+}
+
+#[async_trait]
+impl PriceOracle for FixedPriceOracle {
+    async fn token_price(&self) -> Result<TokenPrice, PriceError> {
+        Ok(TokenPrice {
+            price: self.price,
+            as_of: Utc::now(),
+        })
+    }
+}
+
+/// 从文件读取价格（文件内容为十进制数），报价时间取文件的修改时间，由外部任务定期更新
+pub struct FilePriceOracle {
+    path: PathBuf,
+}
+
+impl FilePriceOracle {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl PriceOracle for FilePriceOracle {
+    async fn token_price(&self) -> Result<TokenPrice, PriceError> {
+        let content = tokio::fs::read_to_string(&self.path).await?;
+        let modified = tokio::fs::metadata(&self.path).await?.modified()?;
+        Ok(TokenPrice {
+            price: content.parse()?,
+            as_of: DateTime::<Utc>::from(modified),
+        })
+    }
+}
+
+/// 缓存价格 cache_ttl；报价时间超过 max_staleness 的价格视为不可用
+/// 价格源失败时继续使用仍在 max_staleness 内的缓存价格
+pub struct CachedPriceOracle {
+    inner: Arc<dyn PriceOracle>,
+    cache_ttl: Duration,
+    max_staleness: Duration,
+    // (价格, 获取时间)
+    cached: Mutex<Option<(TokenPrice, DateTime<Utc>)>>,
+}
+
+impl CachedPriceOracle {
+    pub fn new(inner: Arc<dyn PriceOracle>, cache_ttl: Duration, max_staleness: Duration) -> Self {
+        Self {
+            inner,
+            cache_ttl,
+            max_staleness,
+            cached: Mutex::new(None),
+        }
+    }
+
+    fn check_staleness(&self, price: &TokenPrice, now: DateTime<Utc>) -> Result<(), PriceError> {
+        let age = now - price.as_of;
+        if age > self.max_staleness {
+            return Err(PriceError::Stale {
+                age_seconds: age.num_seconds(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PriceOracle for CachedPriceOracle {
+    async fn token_price(&self) -> Result<TokenPrice, PriceError> {
+        let now = Utc::now();
+        let cached = self.cached.lock().unwrap().clone();
+        if let Some((price, fetched_at)) = &cached {
+            if now - *fetched_at < self.cache_ttl && self.check_staleness(price, now).is_ok() {
+                return Ok(price.clone());
+            }
+        }
+
+        let fetched = self
+            .inner
+            .token_price()
+            .await
+            .and_then(|price| self.check_staleness(&price, now).map(|_| price));
+        match fetched {
+            Ok(price) => {
+                *self.cached.lock().unwrap() = Some((price.clone(), now));
+                Ok(price)
+            }
+            Err(e) => match cached {
+                Some((price, _)) if self.check_staleness(&price, now).is_ok() => {
+                    warn!("Price refresh failed, using cached price {}: {}", price.price, e);
+                    Ok(price)
+                }
+                _ => Err(e),
+            },
+        }
+    }
+}
+
+/// 按 [price_oracle] 配置创建价格来源，okx 未配置 url 时使用 default_url（blockchain.token_usdt_url）
+pub fn build_price_oracle(config: &PriceOracleConfig, default_url: &str) -> Result<Arc<dyn PriceOracle>, PriceError> {
+    let inner: Arc<dyn PriceOracle> = match config.source {
+        PriceSource::Okx => Arc::new(OkxPriceOracle::new(config.url.as_deref().unwrap_or(default_url))),
+        PriceSource::Fixed => {
+            let price = config.fixed_price.as_deref().ok_or_else(|| {
+                PriceError::Invalid("price_oracle.fixed_price is required for the fixed source".to_string())
+            })?;
+            Arc::new(FixedPriceOracle::new(price.parse()?))
+        }
+        PriceSource::File => {
+            let path = config.file_path.as_deref().ok_or_else(|| {
+                PriceError::Invalid("price_oracle.file_path is required for the file source".to_string())
+            })?;
+            Arc::new(FilePriceOracle::new(path))
+        }
+    };
+    Ok(Arc::new(CachedPriceOracle::new(
+        inner,
+        Duration::seconds(config.cache_seconds as i64),
+        Duration::seconds(config.max_staleness_seconds as i64),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    fn decimal(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_decimal_parse_and_display() {
+        assert_eq!(decimal("0.1234").to_string(), "0.1234");
+        assert_eq!(decimal("12").to_string(), "12");
+        assert_eq!(decimal("0.000001").to_string(), "0.000001");
+        assert_eq!(decimal(" 1.50 ").to_string(), "1.50");
+        assert!(decimal("0").is_zero());
+
+        for invalid in ["", ".", "-1", "1e5", "1.2.3", "abc", "1,5"] {
+            assert!(invalid.parse::<Decimal>().is_err(), "{:?} should be rejected", invalid);
+        }
+        assert!("0.0000000000000000000000000000000000001".parse::<Decimal>().is_err());
+    }
+
+    #[test]
+    fn test_usd_to_wei_is_exact() {
+        // 1 USD / 0.1 USD = 10 个代币
+        assert_eq!(usd_to_wei(1, &decimal("0.1")).unwrap(), U256::from(10u64) * U256::from(10u64).pow(U256::from(18)));
+        // 浮点数计算 5 / 0.3 * 1e18 会丢失精度，这里精确到 wei 并向下取整
+        assert_eq!(
+            usd_to_wei(5, &decimal("0.3")).unwrap(),
+            U256::from_str_radix("16666666666666666666", 10).unwrap()
+        );
+        assert_eq!(usd_to_wei(0, &decimal("0.3")).unwrap(), U256::ZERO);
+        assert!(usd_to_wei(1, &decimal("0")).is_err());
+        assert!(usd_to_wei(-1, &decimal("0.3")).is_err());
+    }
+
+    #[test]
+    fn test_parse_okx_ticker() {
+        let body = serde_json::json!({
+            "code": "0",
+            "msg": "",
+            "data": [{"instId": "CFX-USDT", "last": "0.0812", "ts": "1700000000000"}]
+        });
+        let price = parse_okx_ticker(&body).unwrap();
+        assert_eq!(price.price, decimal("0.0812"));
+        assert_eq!(price.as_of, Utc.timestamp_millis_opt(1_700_000_000_000).unwrap());
+
+        let error = serde_json::json!({"code": "51001", "msg": "Instrument ID does not exist", "data": []});
+        assert!(matches!(parse_okx_ticker(&error), Err(PriceError::Source(_))));
+        assert!(parse_okx_ticker(&serde_json::json!({"code": "0", "data": []})).is_err());
+    }
+
+    // 依次返回预设结果，记录调用次数
+    struct ScriptedOracle {
+        results: Mutex<Vec<Result<TokenPrice, PriceError>>>,
+        calls: AtomicUsize,
+    }
+
+    impl ScriptedOracle {
+        fn new(results: Vec<Result<TokenPrice, PriceError>>) -> Arc<Self> {
+            Arc::new(Self {
+                results: Mutex::new(results),
+                calls: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl PriceOracle for ScriptedOracle {
+        async fn token_price(&self) -> Result<TokenPrice, PriceError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            self.results.lock().unwrap().remove(0)
+        }
+    }
+
+    fn price_at(value: &str, age_seconds: i64) -> TokenPrice {
+        TokenPrice {
+            price: decimal(value),
+            as_of: Utc::now() - Duration::seconds(age_seconds),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cached_oracle_reuses_fresh_price() {
+        let inner = ScriptedOracle::new(vec![Ok(price_at("0.1", 0))]);
+        let oracle = CachedPriceOracle::new(inner.clone(), Duration::seconds(60), Duration::seconds(300));
+
+        assert_eq!(oracle.token_price().await.unwrap().price, decimal("0.1"));
+        assert_eq!(oracle.token_price().await.unwrap().price, decimal("0.1"));
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cached_oracle_falls_back_within_staleness() {
+        // cache_ttl 为 0，每次都刷新；刷新失败时使用仍在 max_staleness 内的缓存
+        let inner = ScriptedOracle::new(vec![
+            Ok(price_at("0.1", 0)),
+            Err(PriceError::Source("timeout".to_string())),
+            Ok(price_at("0.2", 0)),
+        ]);
+        let oracle = CachedPriceOracle::new(inner.clone(), Duration::zero(), Duration::seconds(300));
+
+        assert_eq!(oracle.token_price().await.unwrap().price, decimal("0.1"));
+        assert_eq!(oracle.token_price().await.unwrap().price, decimal("0.1"));
+        assert_eq!(oracle.token_price().await.unwrap().price, decimal("0.2"));
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_cached_oracle_rejects_stale_price() {
+        let inner = ScriptedOracle::new(vec![
+            Ok(price_at("0.1", 600)),
+            Err(PriceError::Source("timeout".to_string())),
+        ]);
+        let oracle = CachedPriceOracle::new(inner, Duration::seconds(60), Duration::seconds(300));
+
+        assert!(matches!(oracle.token_price().await, Err(PriceError::Stale { .. })));
+        assert!(matches!(oracle.token_price().await, Err(PriceError::Source(_))));
+    }
+
+    #[tokio::test]
+    async fn test_file_oracle_reads_price() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("price.txt");
+        std::fs::write(&path, "0.0812\n").unwrap();
+
+        let price = FilePriceOracle::new(&path).token_price().await.unwrap();
+        assert_eq!(price.price, decimal("0.0812"));
+        assert!(Utc::now() - price.as_of < Duration::seconds(60));
+
+        std::fs::write(&path, "not a price").unwrap();
+        assert!(FilePriceOracle::new(&path).token_price().await.is_err());
+        assert!(FilePriceOracle::new(dir.path().join("missing.txt")).token_price().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_build_price_oracle_validates_config() {
+        let mut config = PriceOracleConfig {
+            source: PriceSource::Fixed,
+            ..Default::default()
+        };
+        assert!(build_price_oracle(&config, "").is_err());
+
+        config.fixed_price = Some("0.25".to_string());
+        let oracle = build_price_oracle(&config, "").unwrap();
+        assert_eq!(oracle.token_price().await.unwrap().price, decimal("0.25"));
+
+        config.fixed_price = Some("abc".to_string());
+        assert!(build_price_oracle(&config, "").is_err());
+
+        config.source = PriceSource::File;
+        assert!(build_price_oracle(&config, "").is_err());
+    }
+}
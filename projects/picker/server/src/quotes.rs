@@ -0,0 +1,104 @@
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::models::PriceQuote;
+
+// HKDF 的用途标签，报价签名密钥与 JWT 等其他用途的密钥相互独立
+const QUOTE_KEY_LABEL: &[u8] = b"price-quote";
+
+/// 钱包支付报价的签名与校验
+/// 签名密钥由 [quote] secret（未配置时为 JWT 密钥）经 HKDF-SHA256 派生，不直接使用配置中的密钥
+pub struct QuoteSigner {
+    key: [u8; 32],
+}
+
+impl QuoteSigner {
+    pub fn new(secret: &str) -> Self {
+        let mut key = [0u8; 32];
+        Hkdf::<Sha256>::new(None, secret.as_bytes())
+            .expand(QUOTE_KEY_LABEL, &mut key)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        Self { key }
+    }
+
+    fn mac(&self, quote: &PriceQuote) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(price_quote_payload(quote).as_bytes());
+        mac
+    }
+
+    /// 对报价字段签名，返回十六进制的 HMAC-SHA256
+    pub fn sign(&self, quote: &PriceQuote) -> String {
+        hex::encode(self.mac(quote).finalize().into_bytes())
+    }
+
+    /// 校验报价签名，报价字段被篡改时返回 false
+    pub fn verify(&self, quote: &PriceQuote) -> bool {
+        let Ok(signature) = hex::decode(&quote.signature) else {
+            return false;
+        };
+        self.mac(quote).verify_slice(&signature).is_ok()
+    }
+}
+
+// 参与签名的报价字段，以 | 分隔
+fn price_quote_payload(quote: &PriceQuote) -> String {
+    format!(
+        "{}|{}|{}|{}|{}|{}|{}",
+        quote.quote_id,
+        quote.user_id,
+        quote.picker_id,
+        quote.price,
+        quote.token_price,
+        quote.amount_wei,
+        quote.expires_at.timestamp_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    fn quote() -> PriceQuote {
+        PriceQuote {
+            quote_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            picker_id: Uuid::new_v4(),
+            price: 100,
+            token_price: "0.5".to_string(),
+            amount_wei: "200000000000000000000".to_string(),
+            expires_at: Utc::now() + Duration::minutes(2),
+            signature: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify_quote() {
+        let signer = QuoteSigner::new("quote-secret");
+        let mut quote = quote();
+        quote.signature = signer.sign(&quote);
+        assert!(signer.verify(&quote));
+
+        // 任一字段被修改或换用其他密钥都无法通过校验
+        let mut tampered = quote.clone();
+        tampered.amount_wei = "1".to_string();
+        assert!(!signer.verify(&tampered));
+        assert!(!QuoteSigner::new("other-secret").verify(&quote));
+        quote.signature = "not-hex".to_string();
+        assert!(!signer.verify(&quote));
+    }
+
+    #[test]
+    fn test_signing_key_is_derived_from_secret() {
+        let secret = "shared-secret";
+        let quote = quote();
+
+        // 签名不等于直接用配置中的密钥计算的 HMAC
+        let mut raw = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        raw.update(price_quote_payload(&quote).as_bytes());
+        assert_ne!(QuoteSigner::new(secret).sign(&quote), hex::encode(raw.finalize().into_bytes()));
+    }
+}
@@ -0,0 +1,56 @@
+// 仓储与业务层测试共用的数据构造，按需用结构体更新语法覆盖个别字段
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::models::{Order, OrderStatus, PayType, Picker, PickerChainStatus, User, UserType};
+
+/// 用户名取邮箱 @ 之前的部分，钱包地址为零地址
+pub fn user(email: &str, user_type: UserType, premium_balance: i64) -> User {
+    User {
+        user_id: Uuid::new_v4(),
+        email: email.to_string(),
+        user_name: email.split('@').next().unwrap().to_string(),
+        user_password: "hash".to_string(),
+        user_type,
+        wallet_address: "0x0000000000000000000000000000000000000000".to_string(),
+        private_key: "encrypted".to_string(),
+        premium_balance,
+        created_at: Utc::now(),
+    }
+}
+
+/// 已上架的 Picker
+pub fn picker(dev_user_id: Uuid, price: i64) -> Picker {
+    Picker {
+        picker_id: Uuid::new_v4(),
+        dev_user_id,
+        alias: "Test Picker".to_string(),
+        description: "A test picker".to_string(),
+        price,
+        file_path: "uploads/picker.zip".to_string(),
+        download_count: 0,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        image_path: "uploads/picker.png".to_string(),
+        version: "1.0.0".to_string(),
+        status: "active".to_string(),
+        chain_status: PickerChainStatus::Unregistered,
+        chain_tx_hash: None,
+        deleted_at: None,
+    }
+}
+
+/// 金额为 10 的订单
+pub fn order(user_id: Uuid, picker_id: Uuid, pay_type: PayType, status: OrderStatus) -> Order {
+    Order {
+        order_id: Uuid::new_v4(),
+        user_id,
+        picker_id,
+        amount: 10,
+        pay_type,
+        status,
+        tx_hash: None,
+        created_at: Utc::now(),
+        expires_at: None,
+    }
+}
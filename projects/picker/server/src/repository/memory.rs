@@ -0,0 +1,829 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use super::{
+    free_grant_entries, is_withdrawable_entry, opening_entries, purchase_entries, refund_entries, withdrawal_entries,
+    withdrawal_reversal_entries, ChainEventRepository, OrderRepository, PickerRepository, PremiumLedgerRepository, RefundRepository, UserRepository,
+    WithdrawalRepository,
+};
+use crate::models::{
+    BalanceMismatch, ChainCursor, ChainEvent, LedgerEntryKind, Order, OrderStatus, PayType, Picker, PickerChainStatus,
+    PickerVersion, PremiumLedgerEntry, Refund, RefundStatus, Sale, User, Withdrawal, WithdrawalStatus,
+};
+
+#[derive(Default)]
+struct MemoryStore {
+    users: HashMap<Uuid, User>,
+    pickers: HashMap<Uuid, Picker>,
+    picker_versions: Vec<PickerVersion>,
+    orders: HashMap<Uuid, Order>,
+    refunds: HashMap<Uuid, Refund>,
+    withdrawals: HashMap<Uuid, Withdrawal>,
+    premium_ledger: Vec<PremiumLedgerEntry>,
+    // 以 (user_id, period_index) 为键
+    premium_grants: HashMap<(Uuid, i64), i64>,
+    // 以 (tx_hash, log_index) 为键
+    chain_events: HashMap<(String, i64), ChainEvent>,
+    chain_cursors: HashMap<String, ChainCursor>,
+}
+
+impl MemoryStore {
+    // 依次更新分录对应用户的余额并记录分录，调用方需事先完成余额校验
+    fn post_ledger_entries(&mut self, entries: Vec<PremiumLedgerEntry>) {
+        for mut entry in entries {
+            if let Some(user) = entry.user_id.and_then(|user_id| self.users.get_mut(&user_id)) {
+                user.premium_balance += entry.amount;
+                entry.balance_after = Some(user.premium_balance);
+            }
+            self.premium_ledger.push(entry);
+        }
+    }
+
+    fn withdrawable_earnings(&self, user_id: Uuid) -> i64 {
+        self.premium_ledger
+            .iter()
+            .filter(|entry| entry.user_id == Some(user_id) && is_withdrawable_entry(entry))
+            .map(|entry| entry.amount)
+            .sum()
+    }
+
+    // 将提现从 from 状态置为 to 状态并退回积分，提现已不是 from 状态时返回 false
+    fn release_withdrawal(&mut self, withdrawal_id: Uuid, from: WithdrawalStatus, to: WithdrawalStatus) -> bool {
+        let (user_id, amount) = match self.withdrawals.get_mut(&withdrawal_id) {
+            Some(withdrawal) if withdrawal.status == from => {
+                withdrawal.status = to;
+                withdrawal.updated_at = chrono::Utc::now();
+                (withdrawal.user_id, withdrawal.amount)
+            }
+            _ => return false,
+        };
+        self.post_ledger_entries(withdrawal_reversal_entries(user_id, amount));
+        true
+    }
+}
+
+// 内存仓储，三个仓储共享同一份数据，供测试替换数据库使用
+// 克隆后仍指向同一份数据
+#[derive(Clone, Default)]
+pub struct MemoryRepository {
+    store: Arc<Mutex<MemoryStore>>,
+}
+
+impl MemoryRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemoryStore> {
+        self.store.lock().expect("memory repository lock poisoned")
+    }
+
+    // 模拟数据库的唯一/外键约束，违反时返回与 sqlx 相同类型的错误
+    fn constraint_violation(message: &str) -> sqlx::Error {
+        sqlx::Error::Protocol(message.to_string())
+    }
+}
+
+// 按创建时间倒序分页，返回 (当前页, 总数)
+fn paginate<T>(
+    mut items: Vec<T>,
+    key: impl Fn(&T) -> chrono::DateTime<chrono::Utc>,
+    limit: i64,
+    offset: i64,
+) -> (Vec<T>, i64) {
+    items.sort_by_key(|item| std::cmp::Reverse(key(item)));
+    let total = items.len() as i64;
+    let page = items
+        .into_iter()
+        .skip(offset.max(0) as usize)
+        .take(limit.max(0) as usize)
+        .collect();
+    (page, total)
+}
+
+#[async_trait]
+impl UserRepository for MemoryRepository {
+    async fn find_by_id(&self, user_id: Uuid) -> Result<Option<User>, sqlx::Error> {
+        Ok(self.lock().users.get(&user_id).cloned())
+    }
+
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
+        Ok(self.lock().users.values().find(|user| user.email == email).cloned())
+    }
+
+    async fn exists(&self, user_id: Uuid) -> Result<bool, sqlx::Error> {
+        Ok(self.lock().users.contains_key(&user_id))
+    }
+
+    async fn create(&self, user: &User) -> Result<(), sqlx::Error> {
+        let mut store = self.lock();
+        if store.users.contains_key(&user.user_id) || store.users.values().any(|u| u.email == user.email) {
+            return Err(Self::constraint_violation("UNIQUE constraint failed: users"));
+        }
+        // 与 SQL 实现一致：初始余额通过期初分录计入
+        store.users.insert(user.user_id, User { premium_balance: 0, ..user.clone() });
+        store.post_ledger_entries(opening_entries(user));
+        Ok(())
+    }
+
+    async fn update_password(&self, user_id: Uuid, password_hash: &str) -> Result<bool, sqlx::Error> {
+        match self.lock().users.get_mut(&user_id) {
+            Some(user) => {
+                user.user_password = password_hash.to_string();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn list_private_keys(&self) -> Result<Vec<(Uuid, String)>, sqlx::Error> {
+        let store = self.lock();
+        let mut users: Vec<&User> = store.users.values().collect();
+        users.sort_by_key(|user| user.created_at);
+        Ok(users.into_iter().map(|user| (user.user_id, user.private_key.clone())).collect())
+    }
+
+    async fn replace_private_key(&self, user_id: Uuid, expected: &str, private_key: &str) -> Result<bool, sqlx::Error> {
+        match self.lock().users.get_mut(&user_id) {
+            Some(user) if user.private_key == expected => {
+                user.private_key = private_key.to_string();
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn list_registrations(
+        &self,
+        after: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<(Uuid, chrono::DateTime<chrono::Utc>)>, sqlx::Error> {
+        let mut users: Vec<(Uuid, chrono::DateTime<chrono::Utc>)> = self
+            .lock()
+            .users
+            .values()
+            .filter(|user| after.is_none_or(|after| user.user_id > after))
+            .map(|user| (user.user_id, user.created_at))
+            .collect();
+        users.sort_by_key(|(user_id, _)| *user_id);
+        users.truncate(limit.max(0) as usize);
+        Ok(users)
+    }
+}
+
+#[async_trait]
+impl PickerRepository for MemoryRepository {
+    async fn find_by_id(&self, picker_id: Uuid) -> Result<Option<Picker>, sqlx::Error> {
+        Ok(self.lock().pickers.get(&picker_id).cloned())
+    }
+
+    async fn find_active(&self, picker_id: Uuid) -> Result<Option<Picker>, sqlx::Error> {
+        Ok(self
+            .lock()
+            .pickers
+            .get(&picker_id)
+            .filter(|picker| picker.status == "active")
+            .cloned())
+    }
+
+    async fn list_active(
+        &self,
+        keyword: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<Picker>, i64), sqlx::Error> {
+        let keyword = keyword.map(|k| k.to_lowercase());
+        let matched: Vec<Picker> = self
+            .lock()
+            .pickers
+            .values()
+            .filter(|picker| picker.status == "active")
+            .filter(|picker| match &keyword {
+                Some(k) => picker.alias.to_lowercase().contains(k) || picker.description.to_lowercase().contains(k),
+                None => true,
+            })
+            .cloned()
+            .collect();
+        Ok(paginate(matched, |picker| picker.created_at, limit, offset))
+    }
+
+    async fn list_for_dev(&self, dev_user_id: Uuid, limit: i64, offset: i64) -> Result<(Vec<Picker>, i64), sqlx::Error> {
+        let matched: Vec<Picker> = self
+            .lock()
+            .pickers
+            .values()
+            .filter(|picker| picker.dev_user_id == dev_user_id && picker.deleted_at.is_none())
+            .cloned()
+            .collect();
+        Ok(paginate(matched, |picker| picker.created_at, limit, offset))
+    }
+
+    async fn create(&self, picker: &Picker) -> Result<(), sqlx::Error> {
+        let mut store = self.lock();
+        if store.pickers.contains_key(&picker.picker_id) {
+            return Err(Self::constraint_violation("UNIQUE constraint failed: pickers.picker_id"));
+        }
+        if !store.users.contains_key(&picker.dev_user_id) {
+            return Err(Self::constraint_violation("FOREIGN KEY constraint failed: pickers.dev_user_id"));
+        }
+        store.pickers.insert(picker.picker_id, picker.clone());
+        store.picker_versions.push(PickerVersion::initial(picker));
+        Ok(())
+    }
+
+    async fn publish_version(&self, version: &PickerVersion) -> Result<bool, sqlx::Error> {
+        let mut store = self.lock();
+        if store
+            .picker_versions
+            .iter()
+            .any(|v| v.picker_id == version.picker_id && v.version == version.version)
+        {
+            return Ok(false);
+        }
+        let Some(picker) = store.pickers.get_mut(&version.picker_id) else {
+            return Err(Self::constraint_violation("FOREIGN KEY constraint failed: picker_versions.picker_id"));
+        };
+        picker.version = version.version.clone();
+        picker.file_path = version.file_path.clone();
+        picker.updated_at = version.created_at;
+        store.picker_versions.push(version.clone());
+        Ok(true)
+    }
+
+    async fn list_versions(&self, picker_id: Uuid) -> Result<Vec<PickerVersion>, sqlx::Error> {
+        let mut versions: Vec<PickerVersion> = self
+            .lock()
+            .picker_versions
+            .iter()
+            .filter(|v| v.picker_id == picker_id)
+            .cloned()
+            .collect();
+        versions.sort_by_key(|version| std::cmp::Reverse(version.created_at));
+        Ok(versions)
+    }
+
+    async fn increment_download_count(&self, picker_id: Uuid) -> Result<(), sqlx::Error> {
+        if let Some(picker) = self.lock().pickers.get_mut(&picker_id) {
+            picker.download_count += 1;
+        }
+        Ok(())
+    }
+
+    async fn update(&self, picker: &Picker) -> Result<bool, sqlx::Error> {
+        match self.lock().pickers.get_mut(&picker.picker_id) {
+            Some(stored) if stored.deleted_at.is_none() => {
+                stored.alias = picker.alias.clone();
+                stored.description = picker.description.clone();
+                stored.price = picker.price;
+                stored.image_path = picker.image_path.clone();
+                stored.status = picker.status.clone();
+                stored.updated_at = picker.updated_at;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn soft_delete(&self, picker_id: Uuid, deleted_at: chrono::DateTime<chrono::Utc>) -> Result<bool, sqlx::Error> {
+        match self.lock().pickers.get_mut(&picker_id) {
+            Some(picker) if picker.deleted_at.is_none() => {
+                picker.status = "inactive".to_string();
+                picker.updated_at = deleted_at;
+                picker.deleted_at = Some(deleted_at);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn set_status(&self, picker_id: Uuid, status: &str) -> Result<bool, sqlx::Error> {
+        match self.lock().pickers.get_mut(&picker_id) {
+            Some(picker) => {
+                picker.status = status.to_string();
+                picker.updated_at = chrono::Utc::now();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn update_chain_status(
+        &self,
+        picker_id: Uuid,
+        chain_status: PickerChainStatus,
+        tx_hash: Option<&str>,
+    ) -> Result<bool, sqlx::Error> {
+        match self.lock().pickers.get_mut(&picker_id) {
+            Some(picker) => {
+                picker.chain_status = chain_status;
+                if let Some(tx_hash) = tx_hash {
+                    picker.chain_tx_hash = Some(tx_hash.to_string());
+                }
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn file_references(&self) -> Result<Vec<String>, sqlx::Error> {
+        let store = self.lock();
+        let mut references: Vec<String> = store
+            .pickers
+            .values()
+            .flat_map(|picker| [picker.file_path.clone(), picker.image_path.clone()])
+            .chain(store.picker_versions.iter().map(|version| version.file_path.clone()))
+            .collect();
+        references.sort();
+        references.dedup();
+        Ok(references)
+    }
+
+    async fn is_file_referenced(&self, key: &str) -> Result<bool, sqlx::Error> {
+        let store = self.lock();
+        Ok(store.pickers.values().any(|picker| picker.file_path == key || picker.image_path == key)
+            || store.picker_versions.iter().any(|version| version.file_path == key))
+    }
+
+    async fn replace_file_reference(&self, old: &str, new: &str) -> Result<u64, sqlx::Error> {
+        let mut store = self.lock();
+        let mut replaced = 0;
+        for picker in store.pickers.values_mut() {
+            if picker.file_path == old {
+                picker.file_path = new.to_string();
+                replaced += 1;
+            }
+            if picker.image_path == old {
+                picker.image_path = new.to_string();
+                replaced += 1;
+            }
+        }
+        for version in store.picker_versions.iter_mut().filter(|version| version.file_path == old) {
+            version.file_path = new.to_string();
+            replaced += 1;
+        }
+        Ok(replaced)
+    }
+}
+
+#[async_trait]
+impl OrderRepository for MemoryRepository {
+    async fn find_by_id(&self, order_id: Uuid) -> Result<Option<Order>, sqlx::Error> {
+        Ok(self.lock().orders.get(&order_id).cloned())
+    }
+
+    async fn find_for_user(&self, order_id: Uuid, user_id: Uuid) -> Result<Option<Order>, sqlx::Error> {
+        Ok(self
+            .lock()
+            .orders
+            .get(&order_id)
+            .filter(|order| order.user_id == user_id)
+            .cloned())
+    }
+
+    async fn list_for_user(
+        &self,
+        user_id: Uuid,
+        status: Option<&OrderStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<Order>, i64), sqlx::Error> {
+        let matched: Vec<Order> = self
+            .lock()
+            .orders
+            .values()
+            .filter(|order| order.user_id == user_id)
+            .filter(|order| status.is_none_or(|s| &order.status == s))
+            .cloned()
+            .collect();
+        Ok(paginate(matched, |order| order.created_at, limit, offset))
+    }
+
+    async fn create(&self, order: &Order) -> Result<(), sqlx::Error> {
+        let mut store = self.lock();
+        if store.orders.contains_key(&order.order_id) {
+            return Err(Self::constraint_violation("UNIQUE constraint failed: orders.order_id"));
+        }
+        if !store.users.contains_key(&order.user_id) || !store.pickers.contains_key(&order.picker_id) {
+            return Err(Self::constraint_violation("FOREIGN KEY constraint failed: orders"));
+        }
+        store.orders.insert(order.order_id, order.clone());
+        Ok(())
+    }
+
+    async fn settle_premium(&self, order: &Order, dev_user_id: Uuid, dev_income: i64) -> Result<bool, sqlx::Error> {
+        // 持有锁期间完成全部校验后再修改，等价于事务的全部成功或全部回滚
+        let mut store = self.lock();
+        if store.orders.contains_key(&order.order_id) {
+            return Err(Self::constraint_violation("UNIQUE constraint failed: orders.order_id"));
+        }
+        if !store.users.contains_key(&order.user_id)
+            || !store.users.contains_key(&dev_user_id)
+            || !store.pickers.contains_key(&order.picker_id)
+        {
+            return Err(Self::constraint_violation("FOREIGN KEY constraint failed: orders"));
+        }
+        match store.users.get(&order.user_id) {
+            Some(user) if user.premium_balance >= order.amount => {}
+            _ => return Ok(false),
+        }
+
+        store.orders.insert(order.order_id, order.clone());
+        store.post_ledger_entries(purchase_entries(order, dev_user_id, dev_income));
+        if let Some(picker) = store.pickers.get_mut(&order.picker_id) {
+            picker.download_count += 1;
+        }
+        Ok(true)
+    }
+
+    async fn mark_success(&self, order_id: Uuid, picker_id: Uuid) -> Result<bool, sqlx::Error> {
+        let mut store = self.lock();
+        match store.orders.get_mut(&order_id) {
+            Some(order) if order.status == OrderStatus::Pending => order.status = OrderStatus::Success,
+            _ => return Ok(false),
+        }
+        if let Some(picker) = store.pickers.get_mut(&picker_id) {
+            picker.download_count += 1;
+        }
+        Ok(true)
+    }
+
+    async fn mark_expired(&self, order_id: Uuid) -> Result<bool, sqlx::Error> {
+        match self.lock().orders.get_mut(&order_id) {
+            Some(order) if order.status == OrderStatus::Pending => {
+                order.status = OrderStatus::Expired;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn mark_cancelled(&self, order_id: Uuid) -> Result<bool, sqlx::Error> {
+        match self.lock().orders.get_mut(&order_id) {
+            Some(order) if order.status == OrderStatus::Pending => {
+                order.status = OrderStatus::Cancelled;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn list_pending_wallet(&self, limit: i64) -> Result<Vec<Order>, sqlx::Error> {
+        let mut pending: Vec<Order> = self
+            .lock()
+            .orders
+            .values()
+            .filter(|order| order.status == OrderStatus::Pending && order.pay_type == PayType::Wallet)
+            .cloned()
+            .collect();
+        pending.sort_by_key(|order| order.created_at);
+        pending.truncate(limit.max(0) as usize);
+        Ok(pending)
+    }
+
+    async fn find_by_tx_hash(&self, tx_hash: &str) -> Result<Option<Order>, sqlx::Error> {
+        Ok(self
+            .lock()
+            .orders
+            .values()
+            .find(|order| order.tx_hash.as_deref() == Some(tx_hash))
+            .cloned())
+    }
+
+    async fn list_sales(
+        &self,
+        dev_user_id: Uuid,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<Sale>, sqlx::Error> {
+        let store = self.lock();
+        let mut sales: Vec<Sale> = store
+            .orders
+            .values()
+            .filter(|order| order.status == OrderStatus::Success && order.created_at >= from && order.created_at < to)
+            .filter(|order| {
+                store
+                    .pickers
+                    .get(&order.picker_id)
+                    .is_some_and(|picker| picker.dev_user_id == dev_user_id)
+            })
+            .map(|order| Sale {
+                order_id: order.order_id,
+                picker_id: order.picker_id,
+                pay_type: order.pay_type.clone(),
+                amount: order.amount,
+                dev_income: store
+                    .premium_ledger
+                    .iter()
+                    .find(|entry| {
+                        entry.order_id == Some(order.order_id)
+                            && entry.user_id == Some(dev_user_id)
+                            && entry.kind == LedgerEntryKind::DevPayout
+                    })
+                    .map(|entry| entry.amount),
+                created_at: order.created_at,
+            })
+            .collect();
+        sales.sort_by_key(|sale| sale.created_at);
+        Ok(sales)
+    }
+}
+
+#[async_trait]
+impl RefundRepository for MemoryRepository {
+    async fn list_for_order(&self, order_id: Uuid) -> Result<Vec<Refund>, sqlx::Error> {
+        let mut refunds: Vec<Refund> = self
+            .lock()
+            .refunds
+            .values()
+            .filter(|refund| refund.order_id == order_id)
+            .cloned()
+            .collect();
+        refunds.sort_by_key(|refund| std::cmp::Reverse(refund.created_at));
+        Ok(refunds)
+    }
+
+    async fn refund_premium(&self, refund: &Refund, dev_user_id: Uuid, dev_income: i64) -> Result<bool, sqlx::Error> {
+        // 持有锁期间完成全部校验后再修改，等价于事务的全部成功或全部回滚
+        let mut store = self.lock();
+        if store.refunds.contains_key(&refund.refund_id) {
+            return Err(Self::constraint_violation("UNIQUE constraint failed: refunds.refund_id"));
+        }
+        let buyer_id = match store.orders.get(&refund.order_id) {
+            Some(order) if order.status == OrderStatus::Success => order.user_id,
+            _ => return Ok(false),
+        };
+        match store.users.get(&dev_user_id) {
+            Some(dev_user) if dev_user.premium_balance >= dev_income => {}
+            _ => return Ok(false),
+        }
+
+        if let Some(order) = store.orders.get_mut(&refund.order_id) {
+            order.status = OrderStatus::Refunded;
+        }
+        store.post_ledger_entries(refund_entries(refund, buyer_id, dev_user_id, dev_income));
+        store.refunds.insert(refund.refund_id, refund.clone());
+        Ok(true)
+    }
+
+    async fn create(&self, refund: &Refund) -> Result<(), sqlx::Error> {
+        let mut store = self.lock();
+        if store.refunds.contains_key(&refund.refund_id) {
+            return Err(Self::constraint_violation("UNIQUE constraint failed: refunds.refund_id"));
+        }
+        if !store.orders.contains_key(&refund.order_id) {
+            return Err(Self::constraint_violation("FOREIGN KEY constraint failed: refunds"));
+        }
+        store.refunds.insert(refund.refund_id, refund.clone());
+        Ok(())
+    }
+
+    async fn mark_completed(&self, refund_id: Uuid) -> Result<bool, sqlx::Error> {
+        let mut store = self.lock();
+        let order_id = match store.refunds.get_mut(&refund_id) {
+            Some(refund) if refund.status == RefundStatus::Pending => {
+                refund.status = RefundStatus::Completed;
+                refund.completed_at = Some(chrono::Utc::now());
+                refund.order_id
+            }
+            _ => return Ok(false),
+        };
+        if let Some(order) = store.orders.get_mut(&order_id) {
+            if order.status == OrderStatus::Success {
+                order.status = OrderStatus::Refunded;
+            }
+        }
+        Ok(true)
+    }
+
+    async fn mark_failed(&self, refund_id: Uuid) -> Result<bool, sqlx::Error> {
+        match self.lock().refunds.get_mut(&refund_id) {
+            Some(refund) if refund.status == RefundStatus::Pending => {
+                refund.status = RefundStatus::Failed;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn list_pending(&self, limit: i64) -> Result<Vec<Refund>, sqlx::Error> {
+        let mut pending: Vec<Refund> = self
+            .lock()
+            .refunds
+            .values()
+            .filter(|refund| refund.status == RefundStatus::Pending)
+            .cloned()
+            .collect();
+        pending.sort_by_key(|refund| refund.created_at);
+        pending.truncate(limit.max(0) as usize);
+        Ok(pending)
+    }
+}
+
+#[async_trait]
+impl WithdrawalRepository for MemoryRepository {
+    async fn find_by_id(&self, withdrawal_id: Uuid) -> Result<Option<Withdrawal>, sqlx::Error> {
+        Ok(self.lock().withdrawals.get(&withdrawal_id).cloned())
+    }
+
+    async fn list_for_user(
+        &self,
+        user_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<Withdrawal>, i64), sqlx::Error> {
+        let matched = self
+            .lock()
+            .withdrawals
+            .values()
+            .filter(|withdrawal| withdrawal.user_id == user_id)
+            .cloned()
+            .collect();
+        Ok(paginate(matched, |withdrawal| withdrawal.created_at, limit, offset))
+    }
+
+    async fn list_by_status(&self, status: WithdrawalStatus, limit: i64) -> Result<Vec<Withdrawal>, sqlx::Error> {
+        let mut matched: Vec<Withdrawal> = self
+            .lock()
+            .withdrawals
+            .values()
+            .filter(|withdrawal| withdrawal.status == status)
+            .cloned()
+            .collect();
+        matched.sort_by_key(|withdrawal| withdrawal.created_at);
+        matched.truncate(limit.max(0) as usize);
+        Ok(matched)
+    }
+
+    async fn withdrawable_earnings(&self, user_id: Uuid) -> Result<i64, sqlx::Error> {
+        Ok(self.lock().withdrawable_earnings(user_id))
+    }
+
+    async fn request(&self, withdrawal: &Withdrawal) -> Result<bool, sqlx::Error> {
+        // 持有锁期间完成全部校验后再修改，等价于事务的全部成功或全部回滚
+        let mut store = self.lock();
+        if store.withdrawals.contains_key(&withdrawal.withdrawal_id) {
+            return Err(Self::constraint_violation("UNIQUE constraint failed: withdrawals.withdrawal_id"));
+        }
+        match store.users.get(&withdrawal.user_id) {
+            Some(user) if user.premium_balance >= withdrawal.amount => {}
+            _ => return Ok(false),
+        }
+        if store.withdrawable_earnings(withdrawal.user_id) < withdrawal.amount {
+            return Ok(false);
+        }
+        store.post_ledger_entries(withdrawal_entries(withdrawal));
+        store.withdrawals.insert(withdrawal.withdrawal_id, withdrawal.clone());
+        Ok(true)
+    }
+
+    async fn cancel(&self, withdrawal_id: Uuid) -> Result<bool, sqlx::Error> {
+        Ok(self
+            .lock()
+            .release_withdrawal(withdrawal_id, WithdrawalStatus::Requested, WithdrawalStatus::Cancelled))
+    }
+
+    async fn mark_processing(&self, withdrawal_id: Uuid, tx_hash: &str) -> Result<bool, sqlx::Error> {
+        match self.lock().withdrawals.get_mut(&withdrawal_id) {
+            Some(withdrawal) if withdrawal.status == WithdrawalStatus::Requested => {
+                withdrawal.status = WithdrawalStatus::Processing;
+                withdrawal.tx_hash = Some(tx_hash.to_string());
+                withdrawal.updated_at = chrono::Utc::now();
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn mark_completed(&self, withdrawal_id: Uuid) -> Result<bool, sqlx::Error> {
+        match self.lock().withdrawals.get_mut(&withdrawal_id) {
+            Some(withdrawal) if withdrawal.status == WithdrawalStatus::Processing => {
+                let now = chrono::Utc::now();
+                withdrawal.status = WithdrawalStatus::Completed;
+                withdrawal.updated_at = now;
+                withdrawal.completed_at = Some(now);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn mark_failed(&self, withdrawal_id: Uuid) -> Result<bool, sqlx::Error> {
+        Ok(self
+            .lock()
+            .release_withdrawal(withdrawal_id, WithdrawalStatus::Processing, WithdrawalStatus::Failed))
+    }
+}
+
+#[async_trait]
+impl PremiumLedgerRepository for MemoryRepository {
+    async fn list_for_user(
+        &self,
+        user_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<PremiumLedgerEntry>, i64), sqlx::Error> {
+        let entries = self
+            .lock()
+            .premium_ledger
+            .iter()
+            .filter(|entry| entry.user_id == Some(user_id))
+            .cloned()
+            .collect();
+        Ok(paginate(entries, |entry| entry.created_at, limit, offset))
+    }
+
+    async fn entries_for_order(&self, order_id: Uuid) -> Result<Vec<PremiumLedgerEntry>, sqlx::Error> {
+        Ok(self
+            .lock()
+            .premium_ledger
+            .iter()
+            .filter(|entry| entry.order_id == Some(order_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn balance_mismatches(&self, limit: i64) -> Result<Vec<BalanceMismatch>, sqlx::Error> {
+        let store = self.lock();
+        let mut ledger_balances: HashMap<Uuid, i64> = HashMap::new();
+        for entry in &store.premium_ledger {
+            if let Some(user_id) = entry.user_id {
+                *ledger_balances.entry(user_id).or_default() += entry.amount;
+            }
+        }
+        Ok(store
+            .users
+            .values()
+            .map(|user| BalanceMismatch {
+                user_id: user.user_id,
+                premium_balance: user.premium_balance,
+                ledger_balance: ledger_balances.get(&user.user_id).copied().unwrap_or_default(),
+            })
+            .filter(|mismatch| mismatch.premium_balance != mismatch.ledger_balance)
+            .take(limit.max(0) as usize)
+            .collect())
+    }
+
+    async fn grant_free(
+        &self,
+        user_id: Uuid,
+        period_index: i64,
+        amount: i64,
+        _granted_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<bool, sqlx::Error> {
+        let mut store = self.lock();
+        if !store.users.contains_key(&user_id) {
+            return Err(Self::constraint_violation("FOREIGN KEY constraint failed: premium_grants"));
+        }
+        if store.premium_grants.contains_key(&(user_id, period_index)) {
+            return Ok(false);
+        }
+        store.premium_grants.insert((user_id, period_index), amount);
+        store.post_ledger_entries(free_grant_entries(Uuid::new_v4(), user_id, amount));
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl ChainEventRepository for MemoryRepository {
+    async fn cursor(&self, name: &str) -> Result<Option<ChainCursor>, sqlx::Error> {
+        Ok(self.lock().chain_cursors.get(name).cloned())
+    }
+
+    async fn save_batch(&self, events: &[ChainEvent], cursor: &ChainCursor) -> Result<(), sqlx::Error> {
+        let mut store = self.lock();
+        for event in events {
+            store
+                .chain_events
+                .entry((event.tx_hash.clone(), event.log_index))
+                .or_insert_with(|| event.clone());
+        }
+        store.chain_cursors.insert(cursor.name.clone(), cursor.clone());
+        Ok(())
+    }
+
+    async fn rewind(&self, cursor: &ChainCursor) -> Result<u64, sqlx::Error> {
+        let mut store = self.lock();
+        let before = store.chain_events.len();
+        store
+            .chain_events
+            .retain(|_, event| event.block_number <= cursor.block_number);
+        let deleted = (before - store.chain_events.len()) as u64;
+        store.chain_cursors.insert(cursor.name.clone(), cursor.clone());
+        Ok(deleted)
+    }
+
+    async fn events_for_order(&self, order_id: Uuid) -> Result<Vec<ChainEvent>, sqlx::Error> {
+        let mut events: Vec<ChainEvent> = self
+            .lock()
+            .chain_events
+            .values()
+            .filter(|event| event.order_id == Some(order_id))
+            .cloned()
+            .collect();
+        events.sort_by_key(|event| (event.block_number, event.log_index));
+        Ok(events)
+    }
+}
@@ -0,0 +1,872 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::models::{
+    BalanceMismatch, ChainCursor, ChainEvent, LedgerEntryKind, Order, OrderStatus, Picker, PickerChainStatus,
+    PickerVersion, PremiumLedgerEntry, Refund, Sale, User, Withdrawal, WithdrawalStatus,
+};
+
+#[cfg(test)]
+pub mod fixtures;
+mod memory;
+mod sql;
+
+pub use memory::MemoryRepository;
+pub use sql::SqlRepository;
+
+// 用户数据访问
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn find_by_id(&self, user_id: Uuid) -> Result<Option<User>, sqlx::Error>;
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error>;
+    async fn exists(&self, user_id: Uuid) -> Result<bool, sqlx::Error>;
+    async fn create(&self, user: &User) -> Result<(), sqlx::Error>;
+    /// 更新密码哈希，用户不存在时返回 false
+    async fn update_password(&self, user_id: Uuid, password_hash: &str) -> Result<bool, sqlx::Error>;
+    /// 返回所有用户的 (user_id, 加密私钥)，用于主密钥轮换
+    async fn list_private_keys(&self) -> Result<Vec<(Uuid, String)>, sqlx::Error>;
+    /// 仅当当前私钥密文等于 expected 时替换，避免覆盖并发写入；未替换时返回 false
+    async fn replace_private_key(&self, user_id: Uuid, expected: &str, private_key: &str) -> Result<bool, sqlx::Error>;
+    /// 按 user_id 升序返回 after 之后的最多 limit 个 (user_id, 注册时间)，用于分批遍历全部用户
+    async fn list_registrations(&self, after: Option<Uuid>, limit: i64) -> Result<Vec<(Uuid, DateTime<Utc>)>, sqlx::Error>;
+}
+
+// Picker 数据访问
+#[async_trait]
+pub trait PickerRepository: Send + Sync {
+    async fn find_by_id(&self, picker_id: Uuid) -> Result<Option<Picker>, sqlx::Error>;
+    /// 仅返回上架（status = 'active'）的 Picker
+    async fn find_active(&self, picker_id: Uuid) -> Result<Option<Picker>, sqlx::Error>;
+    /// 分页查询上架的 Picker，keyword 匹配别名或描述（不区分大小写），返回 (列表, 总数)
+    async fn list_active(
+        &self,
+        keyword: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<Picker>, i64), sqlx::Error>;
+    /// 按创建时间倒序分页查询开发者名下未删除的 Picker（含已下架），返回 (列表, 总数)
+    async fn list_for_dev(&self, dev_user_id: Uuid, limit: i64, offset: i64) -> Result<(Vec<Picker>, i64), sqlx::Error>;
+    /// 在一个事务中写入 Picker 及其首个版本记录
+    async fn create(&self, picker: &Picker) -> Result<(), sqlx::Error>;
+    /// 在一个事务中写入新版本并将 Picker 的 version / file_path 更新为该版本，版本号已存在时返回 false
+    async fn publish_version(&self, version: &PickerVersion) -> Result<bool, sqlx::Error>;
+    /// 按发布时间倒序返回 Picker 的全部版本
+    async fn list_versions(&self, picker_id: Uuid) -> Result<Vec<PickerVersion>, sqlx::Error>;
+    async fn increment_download_count(&self, picker_id: Uuid) -> Result<(), sqlx::Error>;
+    /// 更新别名、描述、价格、图片与上架状态，Picker 不存在或已删除时返回 false
+    async fn update(&self, picker: &Picker) -> Result<bool, sqlx::Error>;
+    /// 软删除：置为 inactive 并记录删除时间，Picker 不存在或已删除时返回 false
+    async fn soft_delete(&self, picker_id: Uuid, deleted_at: DateTime<Utc>) -> Result<bool, sqlx::Error>;
+    /// 修改上架状态（'active' / 'inactive'），Picker 不存在时返回 false
+    async fn set_status(&self, picker_id: Uuid, status: &str) -> Result<bool, sqlx::Error>;
+    /// 记录合约注册状态；tx_hash 为 None 时保留原有的交易哈希
+    async fn update_chain_status(
+        &self,
+        picker_id: Uuid,
+        chain_status: PickerChainStatus,
+        tx_hash: Option<&str>,
+    ) -> Result<bool, sqlx::Error>;
+    /// 返回 Picker 与版本记录引用的全部文件 key（file_path / image_path，去重）
+    async fn file_references(&self) -> Result<Vec<String>, sqlx::Error>;
+    /// 是否仍有 Picker 或版本记录引用该文件 key
+    async fn is_file_referenced(&self, key: &str) -> Result<bool, sqlx::Error>;
+    /// 在一个事务中将所有对 old 的引用改为 new，返回修改的行数
+    async fn replace_file_reference(&self, old: &str, new: &str) -> Result<u64, sqlx::Error>;
+}
+
+// 订单数据访问
+#[async_trait]
+pub trait OrderRepository: Send + Sync {
+    async fn find_by_id(&self, order_id: Uuid) -> Result<Option<Order>, sqlx::Error>;
+    async fn find_for_user(&self, order_id: Uuid, user_id: Uuid) -> Result<Option<Order>, sqlx::Error>;
+    /// 按创建时间倒序分页查询用户订单，返回 (列表, 总数)
+    async fn list_for_user(
+        &self,
+        user_id: Uuid,
+        status: Option<&OrderStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<Order>, i64), sqlx::Error>;
+    async fn create(&self, order: &Order) -> Result<(), sqlx::Error>;
+    /// 在一个事务中完成 Premium 支付：写入订单、扣除用户余额、增加开发者收入、增加下载次数，并记录积分流水
+    /// 扣款时校验余额，余额已不足订单金额时不做任何修改并返回 false
+    async fn settle_premium(&self, order: &Order, dev_user_id: Uuid, dev_income: i64) -> Result<bool, sqlx::Error>;
+    /// 在一个事务中将待支付订单置为成功并增加对应 Picker 的下载次数
+    /// 订单已不是 pending 时不做任何修改并返回 false，请求内确认与后台对账可以安全地重复调用
+    async fn mark_success(&self, order_id: Uuid, picker_id: Uuid) -> Result<bool, sqlx::Error>;
+    /// 将待支付订单置为过期，订单已不是 pending 时返回 false
+    async fn mark_expired(&self, order_id: Uuid) -> Result<bool, sqlx::Error>;
+    /// 取消待支付订单，订单已不是 pending 时返回 false
+    async fn mark_cancelled(&self, order_id: Uuid) -> Result<bool, sqlx::Error>;
+    /// 按创建时间升序返回最多 limit 个待支付的钱包订单
+    async fn list_pending_wallet(&self, limit: i64) -> Result<Vec<Order>, sqlx::Error>;
+    async fn find_by_tx_hash(&self, tx_hash: &str) -> Result<Option<Order>, sqlx::Error>;
+    /// 按创建时间升序返回开发者名下 Picker 在 [from, to) 内的成功订单，附带流水中记录的开发者收入
+    async fn list_sales(
+        &self,
+        dev_user_id: Uuid,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Sale>, sqlx::Error>;
+}
+
+// 退款数据访问
+#[async_trait]
+pub trait RefundRepository: Send + Sync {
+    /// 按创建时间倒序返回订单的全部退款记录
+    async fn list_for_order(&self, order_id: Uuid) -> Result<Vec<Refund>, sqlx::Error>;
+    /// 在一个事务中完成 Premium 退款：订单 success -> refunded、退还用户余额、扣回开发者收入、写入退款记录与冲回流水
+    /// 订单已不是 success 或开发者余额不足 dev_income 时不做任何修改并返回 false
+    async fn refund_premium(&self, refund: &Refund, dev_user_id: Uuid, dev_income: i64) -> Result<bool, sqlx::Error>;
+    /// 写入待确认的钱包订单退款记录
+    async fn create(&self, refund: &Refund) -> Result<(), sqlx::Error>;
+    /// 在一个事务中将待确认的退款置为完成并将订单置为 refunded，退款已不是 pending 时返回 false
+    async fn mark_completed(&self, refund_id: Uuid) -> Result<bool, sqlx::Error>;
+    /// 将待确认的退款置为失败，退款已不是 pending 时返回 false
+    async fn mark_failed(&self, refund_id: Uuid) -> Result<bool, sqlx::Error>;
+    /// 按创建时间升序返回最多 limit 个待确认的退款
+    async fn list_pending(&self, limit: i64) -> Result<Vec<Refund>, sqlx::Error>;
+}
+
+// 开发者提现数据访问，积分的扣除与退回在同一事务中写入流水
+#[async_trait]
+pub trait WithdrawalRepository: Send + Sync {
+    async fn find_by_id(&self, withdrawal_id: Uuid) -> Result<Option<Withdrawal>, sqlx::Error>;
+    /// 按创建时间倒序分页查询用户的提现，返回 (列表, 总数)
+    async fn list_for_user(&self, user_id: Uuid, limit: i64, offset: i64) -> Result<(Vec<Withdrawal>, i64), sqlx::Error>;
+    /// 按创建时间升序返回最多 limit 个指定状态的提现
+    async fn list_by_status(&self, status: WithdrawalStatus, limit: i64) -> Result<Vec<Withdrawal>, sqlx::Error>;
+    /// 用户可提现的收入：销售收入减去已申请的提现（失败或取消的已冲回）与退款扣回的收入，免费积分等不计入
+    async fn withdrawable_earnings(&self, user_id: Uuid) -> Result<i64, sqlx::Error>;
+    /// 在一个事务中扣除用户积分并写入提现申请，余额或可提现收入不足时不做任何修改并返回 false
+    async fn request(&self, withdrawal: &Withdrawal) -> Result<bool, sqlx::Error>;
+    /// 在一个事务中取消 requested 状态的提现并退回积分，提现已不是 requested 时返回 false
+    async fn cancel(&self, withdrawal_id: Uuid) -> Result<bool, sqlx::Error>;
+    /// 记录管理员提交的转账交易，提现 requested -> processing，提现已不是 requested 时返回 false
+    async fn mark_processing(&self, withdrawal_id: Uuid, tx_hash: &str) -> Result<bool, sqlx::Error>;
+    /// 转账交易确认，提现 processing -> completed，提现已不是 processing 时返回 false
+    async fn mark_completed(&self, withdrawal_id: Uuid) -> Result<bool, sqlx::Error>;
+    /// 在一个事务中将 processing 的提现置为失败并退回积分，提现已不是 processing 时返回 false
+    async fn mark_failed(&self, withdrawal_id: Uuid) -> Result<bool, sqlx::Error>;
+}
+
+// Premium 积分流水数据访问，分录随余额变动在各仓储的事务中写入
+#[async_trait]
+pub trait PremiumLedgerRepository: Send + Sync {
+    /// 按创建时间倒序分页查询用户的分录，返回 (列表, 总数)
+    async fn list_for_user(&self, user_id: Uuid, limit: i64, offset: i64) -> Result<(Vec<PremiumLedgerEntry>, i64), sqlx::Error>;
+    /// 返回订单相关的全部分录（含平台分录）
+    async fn entries_for_order(&self, order_id: Uuid) -> Result<Vec<PremiumLedgerEntry>, sqlx::Error>;
+    /// 返回最多 limit 个余额与流水合计不一致的用户
+    async fn balance_mismatches(&self, limit: i64) -> Result<Vec<BalanceMismatch>, sqlx::Error>;
+    /// 在一个事务中记录第 period_index 个周期的免费积分发放并写入流水
+    /// 该用户该周期已发放过时不做任何修改并返回 false
+    async fn grant_free(
+        &self,
+        user_id: Uuid,
+        period_index: i64,
+        amount: i64,
+        granted_at: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error>;
+}
+
+// 新用户的初始余额记为期初分录，平台账户记对应的出账
+fn opening_entries(user: &User) -> Vec<PremiumLedgerEntry> {
+    if user.premium_balance == 0 {
+        return Vec::new();
+    }
+    let transaction_id = Uuid::new_v4();
+    vec![
+        PremiumLedgerEntry::new(transaction_id, Some(user.user_id), None, LedgerEntryKind::Opening, user.premium_balance),
+        PremiumLedgerEntry::new(transaction_id, None, None, LedgerEntryKind::Opening, -user.premium_balance),
+    ]
+}
+
+// Premium 支付：买家出账订单金额，开发者入账 dev_income，其余为平台手续费
+fn purchase_entries(order: &Order, dev_user_id: Uuid, dev_income: i64) -> Vec<PremiumLedgerEntry> {
+    let transaction_id = Uuid::new_v4();
+    let order_id = Some(order.order_id);
+    vec![
+        PremiumLedgerEntry::new(transaction_id, Some(order.user_id), order_id, LedgerEntryKind::Purchase, -order.amount),
+        PremiumLedgerEntry::new(transaction_id, Some(dev_user_id), order_id, LedgerEntryKind::DevPayout, dev_income),
+        PremiumLedgerEntry::new(transaction_id, None, order_id, LedgerEntryKind::PlatformFee, order.amount - dev_income),
+    ]
+}
+
+// 免费发放：用户入账，平台账户出账
+fn free_grant_entries(transaction_id: Uuid, user_id: Uuid, amount: i64) -> Vec<PremiumLedgerEntry> {
+    vec![
+        PremiumLedgerEntry::new(transaction_id, Some(user_id), None, LedgerEntryKind::FreeGrant, amount),
+        PremiumLedgerEntry::new(transaction_id, None, None, LedgerEntryKind::FreeGrant, -amount),
+    ]
+}
+
+// Premium 退款：先扣回开发者收入与平台手续费，再退还买家
+fn refund_entries(refund: &Refund, buyer_id: Uuid, dev_user_id: Uuid, dev_income: i64) -> Vec<PremiumLedgerEntry> {
+    let transaction_id = Uuid::new_v4();
+    let order_id = Some(refund.order_id);
+    vec![
+        PremiumLedgerEntry::new(transaction_id, Some(dev_user_id), order_id, LedgerEntryKind::Refund, -dev_income),
+        PremiumLedgerEntry::new(transaction_id, None, order_id, LedgerEntryKind::Refund, dev_income - refund.amount),
+        PremiumLedgerEntry::new(transaction_id, Some(buyer_id), order_id, LedgerEntryKind::Refund, refund.amount),
+    ]
+}
+
+// 是否计入可提现收入：销售收入、提现及其冲回、退款时扣回的开发者收入（买家的退款入账为正数，不计入）
+fn is_withdrawable_entry(entry: &PremiumLedgerEntry) -> bool {
+    match entry.kind {
+        LedgerEntryKind::DevPayout | LedgerEntryKind::Withdrawal => true,
+        LedgerEntryKind::Refund => entry.amount < 0,
+        _ => false,
+    }
+}
+
+// 开发者提现：用户出账，平台账户入账，transaction_id 即提现ID
+fn withdrawal_entries(withdrawal: &Withdrawal) -> Vec<PremiumLedgerEntry> {
+    vec![
+        PremiumLedgerEntry::new(
+            withdrawal.withdrawal_id,
+            Some(withdrawal.user_id),
+            None,
+            LedgerEntryKind::Withdrawal,
+            -withdrawal.amount,
+        ),
+        PremiumLedgerEntry::new(withdrawal.withdrawal_id, None, None, LedgerEntryKind::Withdrawal, withdrawal.amount),
+    ]
+}
+
+// 提现失败或取消：冲回提现分录，退回用户积分
+fn withdrawal_reversal_entries(user_id: Uuid, amount: i64) -> Vec<PremiumLedgerEntry> {
+    let transaction_id = Uuid::new_v4();
+    vec![
+        PremiumLedgerEntry::new(transaction_id, Some(user_id), None, LedgerEntryKind::Withdrawal, amount),
+        PremiumLedgerEntry::new(transaction_id, None, None, LedgerEntryKind::Withdrawal, -amount),
+    ]
+}
+
+// 链上事件索引数据访问
+#[async_trait]
+pub trait ChainEventRepository: Send + Sync {
+    async fn cursor(&self, name: &str) -> Result<Option<ChainCursor>, sqlx::Error>;
+    /// 在一个事务中写入一批事件并推进索引进度；已存在的 (tx_hash, log_index) 会被忽略
+    async fn save_batch(&self, events: &[ChainEvent], cursor: &ChainCursor) -> Result<(), sqlx::Error>;
+    /// 区块重组时回退：删除 cursor.block_number 之后的事件并将进度重置为 cursor，返回删除的事件数
+    async fn rewind(&self, cursor: &ChainCursor) -> Result<u64, sqlx::Error>;
+    async fn events_for_order(&self, order_id: Uuid) -> Result<Vec<ChainEvent>, sqlx::Error>;
+}
+
+impl Database {
+    pub fn users(&self) -> Arc<dyn UserRepository> {
+        match self {
+            Database::Sqlite(pool) => Arc::new(SqlRepository::new(pool.clone())),
+            Database::Postgres(pool) => Arc::new(SqlRepository::new(pool.clone())),
+        }
+    }
+
+    pub fn pickers(&self) -> Arc<dyn PickerRepository> {
+        match self {
+            Database::Sqlite(pool) => Arc::new(SqlRepository::new(pool.clone())),
+            Database::Postgres(pool) => Arc::new(SqlRepository::new(pool.clone())),
+        }
+    }
+
+    pub fn orders(&self) -> Arc<dyn OrderRepository> {
+        match self {
+            Database::Sqlite(pool) => Arc::new(SqlRepository::new(pool.clone())),
+            Database::Postgres(pool) => Arc::new(SqlRepository::new(pool.clone())),
+        }
+    }
+
+    pub fn refunds(&self) -> Arc<dyn RefundRepository> {
+        match self {
+            Database::Sqlite(pool) => Arc::new(SqlRepository::new(pool.clone())),
+            Database::Postgres(pool) => Arc::new(SqlRepository::new(pool.clone())),
+        }
+    }
+
+    pub fn withdrawals(&self) -> Arc<dyn WithdrawalRepository> {
+        match self {
+            Database::Sqlite(pool) => Arc::new(SqlRepository::new(pool.clone())),
+            Database::Postgres(pool) => Arc::new(SqlRepository::new(pool.clone())),
+        }
+    }
+
+    pub fn premium_ledger(&self) -> Arc<dyn PremiumLedgerRepository> {
+        match self {
+            Database::Sqlite(pool) => Arc::new(SqlRepository::new(pool.clone())),
+            Database::Postgres(pool) => Arc::new(SqlRepository::new(pool.clone())),
+        }
+    }
+
+    pub fn chain_events(&self) -> Arc<dyn ChainEventRepository> {
+        match self {
+            Database::Sqlite(pool) => Arc::new(SqlRepository::new(pool.clone())),
+            Database::Postgres(pool) => Arc::new(SqlRepository::new(pool.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::{self, order, user};
+    use super::*;
+    use crate::database::{create_memory_pool, init_database};
+    use crate::models::{PayType, RefundStatus, UserType};
+    use chrono::{Duration, Utc};
+    use sqlx::postgres::PgPoolOptions;
+
+    // 指定别名、上架状态和创建时间（offset_secs 秒前）的 Picker，用于列表排序与搜索
+    fn picker(dev_user_id: Uuid, alias: &str, status: &str, offset_secs: i64) -> Picker {
+        let created_at = Utc::now() - Duration::seconds(offset_secs);
+        Picker {
+            alias: alias.to_string(),
+            description: format!("{} description", alias),
+            created_at,
+            updated_at: created_at,
+            status: status.to_string(),
+            ..fixtures::picker(dev_user_id, 10)
+        }
+    }
+
+    // 各仓储实现共用的行为校验
+    async fn exercise_repositories(
+        users: Arc<dyn UserRepository>,
+        pickers: Arc<dyn PickerRepository>,
+        orders: Arc<dyn OrderRepository>,
+    ) {
+        let dev = user("dev@example.com", UserType::Dev, 0);
+        let buyer = user("buyer@example.com", UserType::Gen, 100);
+        users.create(&dev).await.unwrap();
+        users.create(&buyer).await.unwrap();
+
+        let found = users.find_by_email("buyer@example.com").await.unwrap().unwrap();
+        assert_eq!(found.user_id, buyer.user_id);
+        assert_eq!(found.user_type, UserType::Gen);
+        assert!(users.exists(dev.user_id).await.unwrap());
+        assert!(!users.exists(Uuid::new_v4()).await.unwrap());
+        assert!(users.find_by_id(Uuid::new_v4()).await.unwrap().is_none());
+
+        assert!(users.update_password(buyer.user_id, "new-hash").await.unwrap());
+        assert!(!users.update_password(Uuid::new_v4(), "new-hash").await.unwrap());
+        let found = users.find_by_id(buyer.user_id).await.unwrap().unwrap();
+        assert_eq!(found.user_password, "new-hash");
+
+        let first = users.list_registrations(None, 1).await.unwrap();
+        assert_eq!(first.len(), 1);
+        let rest = users.list_registrations(Some(first[0].0), 10).await.unwrap();
+        assert_eq!(rest.len(), 1);
+        assert!(rest[0].0 > first[0].0);
+
+        let private_keys = users.list_private_keys().await.unwrap();
+        assert!(private_keys.contains(&(buyer.user_id, buyer.private_key.clone())));
+        assert!(!users.replace_private_key(buyer.user_id, "stale", "rotated").await.unwrap());
+        assert!(users.replace_private_key(buyer.user_id, &buyer.private_key, "rotated").await.unwrap());
+        let found = users.find_by_id(buyer.user_id).await.unwrap().unwrap();
+        assert_eq!(found.private_key, "rotated");
+
+        let older = picker(dev.user_id, "Screenshot", "active", 60);
+        let newer = picker(dev.user_id, "Translate", "active", 0);
+        let retired = picker(dev.user_id, "Screenshot Legacy", "inactive", 30);
+        for p in [&older, &newer, &retired] {
+            pickers.create(p).await.unwrap();
+        }
+
+        let (list, total) = pickers.list_active(None, 10, 0).await.unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(list[0].picker_id, newer.picker_id);
+
+        let (list, total) = pickers.list_active(Some("SCREEN"), 10, 0).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(list[0].picker_id, older.picker_id);
+
+        let (list, total) = pickers.list_active(None, 1, 1).await.unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].picker_id, older.picker_id);
+
+        assert!(pickers.find_active(retired.picker_id).await.unwrap().is_none());
+        assert!(pickers.find_by_id(retired.picker_id).await.unwrap().is_some());
+
+        // 合约注册状态：不传交易哈希时保留上一次的哈希
+        assert!(pickers.update_chain_status(newer.picker_id, PickerChainStatus::Pending, Some("0xreg")).await.unwrap());
+        assert!(pickers.update_chain_status(newer.picker_id, PickerChainStatus::Registered, None).await.unwrap());
+        let stored = pickers.find_by_id(newer.picker_id).await.unwrap().unwrap();
+        assert_eq!(stored.chain_status, PickerChainStatus::Registered);
+        assert_eq!(stored.chain_tx_hash.as_deref(), Some("0xreg"));
+        assert!(!pickers.update_chain_status(Uuid::new_v4(), PickerChainStatus::Failed, None).await.unwrap());
+
+        assert!(pickers.set_status(retired.picker_id, "active").await.unwrap());
+        assert!(pickers.find_active(retired.picker_id).await.unwrap().is_some());
+        assert!(pickers.set_status(retired.picker_id, "inactive").await.unwrap());
+        assert!(!pickers.set_status(Uuid::new_v4(), "inactive").await.unwrap());
+
+        // 版本历史：创建时写入首个版本，发布新版本后 Picker 指向最新文件，重复的版本号被拒绝
+        let versions = pickers.list_versions(older.picker_id).await.unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].version_id, older.picker_id);
+        assert_eq!(versions[0].version, "1.0.0");
+        let update = PickerVersion {
+            version_id: Uuid::new_v4(),
+            picker_id: older.picker_id,
+            version: "1.1.0".to_string(),
+            file_path: "uploads/picker-1.1.0.zip".to_string(),
+            changelog: Some("faster capture".to_string()),
+            created_at: Utc::now(),
+        };
+        assert!(pickers.publish_version(&update).await.unwrap());
+        let duplicate = PickerVersion { version_id: Uuid::new_v4(), file_path: "uploads/other.zip".to_string(), ..update.clone() };
+        assert!(!pickers.publish_version(&duplicate).await.unwrap());
+        let stored = pickers.find_by_id(older.picker_id).await.unwrap().unwrap();
+        assert_eq!(stored.version, "1.1.0");
+        assert_eq!(stored.file_path, "uploads/picker-1.1.0.zip");
+        let versions = pickers.list_versions(older.picker_id).await.unwrap();
+        let listed: Vec<&str> = versions.iter().map(|v| v.version.as_str()).collect();
+        assert_eq!(listed, vec!["1.1.0", "1.0.0"]);
+        assert_eq!(versions[0].changelog.as_deref(), Some("faster capture"));
+        assert!(pickers.list_versions(Uuid::new_v4()).await.unwrap().is_empty());
+
+        // 开发者列表包含已下架的 Picker；修改只影响展示信息与上架状态，软删除后不再出现在列表中
+        let (list, total) = pickers.list_for_dev(dev.user_id, 10, 0).await.unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(list[0].picker_id, newer.picker_id);
+        let mut edited = pickers.find_by_id(retired.picker_id).await.unwrap().unwrap();
+        edited.alias = "Screenshot Classic".to_string();
+        edited.price = 20;
+        edited.status = "active".to_string();
+        edited.file_path = "ignored.zip".to_string();
+        assert!(pickers.update(&edited).await.unwrap());
+        let stored = pickers.find_active(retired.picker_id).await.unwrap().unwrap();
+        assert_eq!(stored.alias, "Screenshot Classic");
+        assert_eq!(stored.price, 20);
+        assert_eq!(stored.file_path, "uploads/picker.zip");
+
+        assert!(pickers.soft_delete(retired.picker_id, Utc::now()).await.unwrap());
+        assert!(!pickers.soft_delete(retired.picker_id, Utc::now()).await.unwrap());
+        assert!(!pickers.update(&edited).await.unwrap());
+        let stored = pickers.find_by_id(retired.picker_id).await.unwrap().unwrap();
+        assert_eq!(stored.status, "inactive");
+        assert!(stored.deleted_at.is_some());
+        let (list, total) = pickers.list_for_dev(dev.user_id, 10, 0).await.unwrap();
+        assert_eq!(total, 2);
+        assert!(list.iter().all(|p| p.picker_id != retired.picker_id));
+        assert_eq!(pickers.list_for_dev(buyer.user_id, 10, 0).await.unwrap().1, 0);
+
+        // 文件引用：去重后的全部 file_path / image_path；替换时同时修改 Picker 与版本记录
+        let references = pickers.file_references().await.unwrap();
+        assert_eq!(references.len(), 3);
+        for reference in ["uploads/picker.zip", "uploads/picker.png", "uploads/picker-1.1.0.zip"] {
+            assert!(references.iter().any(|r| r == reference));
+            assert!(pickers.is_file_referenced(reference).await.unwrap());
+        }
+        assert!(!pickers.is_file_referenced("uploads/other.zip").await.unwrap());
+        // newer、retired 的 file_path 与三个首版本记录
+        assert_eq!(pickers.replace_file_reference("uploads/picker.zip", "0a.zip").await.unwrap(), 5);
+        assert_eq!(pickers.replace_file_reference("uploads/picker.png", "0b.png").await.unwrap(), 3);
+        assert!(!pickers.is_file_referenced("uploads/picker.zip").await.unwrap());
+        assert_eq!(pickers.find_by_id(newer.picker_id).await.unwrap().unwrap().image_path, "0b.png");
+        assert_eq!(pickers.list_versions(older.picker_id).await.unwrap()[1].file_path, "0a.zip");
+
+        // Premium 支付：订单、双方余额与下载次数在同一事务中更新
+        let premium = order(buyer.user_id, older.picker_id, PayType::Premium, OrderStatus::Success);
+        assert!(orders.settle_premium(&premium, dev.user_id, 9).await.unwrap());
+        assert_eq!(users.find_by_id(buyer.user_id).await.unwrap().unwrap().premium_balance, 90);
+        assert_eq!(users.find_by_id(dev.user_id).await.unwrap().unwrap().premium_balance, 9);
+        assert_eq!(pickers.find_by_id(older.picker_id).await.unwrap().unwrap().download_count, 1);
+
+        // 买家余额已不足订单金额时不做任何修改，订单随事务一并放弃
+        let overdrawn = Order { amount: 91, ..order(buyer.user_id, older.picker_id, PayType::Premium, OrderStatus::Success) };
+        assert!(!orders.settle_premium(&overdrawn, dev.user_id, 86).await.unwrap());
+        assert!(orders.find_by_id(overdrawn.order_id).await.unwrap().is_none());
+        assert_eq!(users.find_by_id(buyer.user_id).await.unwrap().unwrap().premium_balance, 90);
+        assert_eq!(users.find_by_id(dev.user_id).await.unwrap().unwrap().premium_balance, 9);
+        assert_eq!(pickers.find_by_id(older.picker_id).await.unwrap().unwrap().download_count, 1);
+
+        // 钱包支付：先写入待支付订单，确认后置为成功
+        let mut wallet = order(buyer.user_id, newer.picker_id, PayType::Wallet, OrderStatus::Pending);
+        wallet.tx_hash = Some("0xabc".to_string());
+        wallet.expires_at = Some(Utc::now() + Duration::minutes(30));
+        orders.create(&wallet).await.unwrap();
+
+        let (pending, total) = orders.list_for_user(buyer.user_id, Some(&OrderStatus::Pending), 10, 0).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(pending[0].tx_hash.as_deref(), Some("0xabc"));
+        let by_hash = orders.find_by_tx_hash("0xabc").await.unwrap().unwrap();
+        assert_eq!(by_hash.order_id, wallet.order_id);
+        assert!(orders.find_by_tx_hash("0xdef").await.unwrap().is_none());
+
+        let pending_wallet = orders.list_pending_wallet(10).await.unwrap();
+        assert_eq!(pending_wallet.len(), 1);
+        assert_eq!(pending_wallet[0].order_id, wallet.order_id);
+
+        assert!(orders.mark_success(wallet.order_id, newer.picker_id).await.unwrap());
+        let stored = orders.find_for_user(wallet.order_id, buyer.user_id).await.unwrap().unwrap();
+        assert_eq!(stored.status, OrderStatus::Success);
+        // 已完成的订单不会重复增加下载次数，也不能再置为过期
+        assert!(!orders.mark_success(wallet.order_id, newer.picker_id).await.unwrap());
+        assert!(!orders.mark_expired(wallet.order_id).await.unwrap());
+        assert!(orders.list_pending_wallet(10).await.unwrap().is_empty());
+        assert!(orders.find_for_user(wallet.order_id, dev.user_id).await.unwrap().is_none());
+
+        pickers.increment_download_count(newer.picker_id).await.unwrap();
+        assert_eq!(pickers.find_by_id(newer.picker_id).await.unwrap().unwrap().download_count, 2);
+
+        let mut stale = order(buyer.user_id, newer.picker_id, PayType::Wallet, OrderStatus::Pending);
+        stale.expires_at = Some(Utc::now() - Duration::minutes(1));
+        orders.create(&stale).await.unwrap();
+        assert!(orders.mark_expired(stale.order_id).await.unwrap());
+        assert!(!orders.mark_success(stale.order_id, newer.picker_id).await.unwrap());
+        let stored = orders.find_by_id(stale.order_id).await.unwrap().unwrap();
+        assert_eq!(stored.status, OrderStatus::Expired);
+        assert_eq!(pickers.find_by_id(newer.picker_id).await.unwrap().unwrap().download_count, 2);
+
+        let (all, total) = orders.list_for_user(buyer.user_id, None, 10, 0).await.unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(all.len(), 3);
+        assert!(orders.find_by_id(premium.order_id).await.unwrap().is_some());
+    }
+
+    fn refund(order: &Order, requested_by: Uuid, status: RefundStatus, tx_hash: Option<&str>) -> Refund {
+        Refund {
+            refund_id: Uuid::new_v4(),
+            order_id: order.order_id,
+            requested_by,
+            amount: order.amount,
+            status,
+            tx_hash: tx_hash.map(str::to_string),
+            reason: Some("duplicate purchase".to_string()),
+            created_at: Utc::now(),
+            completed_at: None,
+        }
+    }
+
+    async fn exercise_refunds(
+        users: Arc<dyn UserRepository>,
+        pickers: Arc<dyn PickerRepository>,
+        orders: Arc<dyn OrderRepository>,
+        refunds: Arc<dyn RefundRepository>,
+        ledger: Arc<dyn PremiumLedgerRepository>,
+    ) {
+        let dev = user("refund-dev@example.com", UserType::Dev, 0);
+        let buyer = user("refund-buyer@example.com", UserType::Gen, 100);
+        users.create(&dev).await.unwrap();
+        users.create(&buyer).await.unwrap();
+        let target = picker(dev.user_id, "Refundable", "active", 0);
+        pickers.create(&target).await.unwrap();
+
+        // 待支付订单可以取消，取消后不能再置为成功
+        let pending = order(buyer.user_id, target.picker_id, PayType::Wallet, OrderStatus::Pending);
+        orders.create(&pending).await.unwrap();
+        assert!(orders.mark_cancelled(pending.order_id).await.unwrap());
+        assert!(!orders.mark_cancelled(pending.order_id).await.unwrap());
+        assert!(!orders.mark_success(pending.order_id, target.picker_id).await.unwrap());
+        assert_eq!(orders.find_by_id(pending.order_id).await.unwrap().unwrap().status, OrderStatus::Cancelled);
+
+        // Premium 退款：订单状态与双方余额在同一事务中回退
+        let premium = order(buyer.user_id, target.picker_id, PayType::Premium, OrderStatus::Success);
+        assert!(orders.settle_premium(&premium, dev.user_id, 9).await.unwrap());
+
+        // 买家余额不足时支付分录不会执行，订单与流水随事务一并放弃
+        let overdrawn = Order { amount: 91, ..order(buyer.user_id, target.picker_id, PayType::Premium, OrderStatus::Success) };
+        assert!(!orders.settle_premium(&overdrawn, dev.user_id, 86).await.unwrap());
+        assert!(orders.find_by_id(overdrawn.order_id).await.unwrap().is_none());
+        assert!(ledger.entries_for_order(overdrawn.order_id).await.unwrap().is_empty());
+        assert_eq!(users.find_by_id(buyer.user_id).await.unwrap().unwrap().premium_balance, 90);
+        assert_eq!(users.find_by_id(dev.user_id).await.unwrap().unwrap().premium_balance, 9);
+        assert_eq!(pickers.find_by_id(target.picker_id).await.unwrap().unwrap().download_count, 1);
+
+        let mut completed = refund(&premium, dev.user_id, RefundStatus::Completed, None);
+        completed.completed_at = Some(Utc::now());
+
+        // 开发者余额不足以扣回收入时不做任何修改
+        assert!(!refunds.refund_premium(&completed, dev.user_id, 10).await.unwrap());
+        assert_eq!(orders.find_by_id(premium.order_id).await.unwrap().unwrap().status, OrderStatus::Success);
+        assert_eq!(users.find_by_id(buyer.user_id).await.unwrap().unwrap().premium_balance, 90);
+        assert!(refunds.list_for_order(premium.order_id).await.unwrap().is_empty());
+
+        assert!(refunds.refund_premium(&completed, dev.user_id, 9).await.unwrap());
+        assert_eq!(orders.find_by_id(premium.order_id).await.unwrap().unwrap().status, OrderStatus::Refunded);
+        assert_eq!(users.find_by_id(buyer.user_id).await.unwrap().unwrap().premium_balance, 100);
+        assert_eq!(users.find_by_id(dev.user_id).await.unwrap().unwrap().premium_balance, 0);
+        let stored = refunds.list_for_order(premium.order_id).await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].status, RefundStatus::Completed);
+        // 已退款的订单不能重复退款
+        let again = refund(&premium, dev.user_id, RefundStatus::Completed, None);
+        assert!(!refunds.refund_premium(&again, dev.user_id, 0).await.unwrap());
+
+        // 钱包退款：先记录待确认的提现交易，确认后订单置为 refunded
+        let wallet = order(buyer.user_id, target.picker_id, PayType::Wallet, OrderStatus::Success);
+        orders.create(&wallet).await.unwrap();
+        let first = refund(&wallet, dev.user_id, RefundStatus::Pending, Some("0xrefund1"));
+        refunds.create(&first).await.unwrap();
+        let pending_refunds = refunds.list_pending(10).await.unwrap();
+        assert_eq!(pending_refunds.len(), 1);
+        assert_eq!(pending_refunds[0].tx_hash.as_deref(), Some("0xrefund1"));
+
+        assert!(refunds.mark_failed(first.refund_id).await.unwrap());
+        assert!(!refunds.mark_completed(first.refund_id).await.unwrap());
+        assert_eq!(orders.find_by_id(wallet.order_id).await.unwrap().unwrap().status, OrderStatus::Success);
+
+        let second = refund(&wallet, dev.user_id, RefundStatus::Pending, Some("0xrefund2"));
+        refunds.create(&second).await.unwrap();
+        assert!(refunds.mark_completed(second.refund_id).await.unwrap());
+        assert!(!refunds.mark_failed(second.refund_id).await.unwrap());
+        assert!(refunds.list_pending(10).await.unwrap().is_empty());
+        assert_eq!(orders.find_by_id(wallet.order_id).await.unwrap().unwrap().status, OrderStatus::Refunded);
+        let stored = refunds.list_for_order(wallet.order_id).await.unwrap();
+        assert_eq!(stored.len(), 2);
+        let latest = stored.iter().find(|r| r.refund_id == second.refund_id).unwrap();
+        assert_eq!(latest.status, RefundStatus::Completed);
+        assert!(latest.completed_at.is_some());
+
+        // 积分流水：期初、支付与退款分录，每组之和为 0，用户余额与流水一致
+        let (entries, total) = ledger.list_for_user(buyer.user_id, 10, 0).await.unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(entries.iter().map(|entry| entry.amount).sum::<i64>(), 100);
+        let (_, total) = ledger.list_for_user(buyer.user_id, 1, 2).await.unwrap();
+        assert_eq!(total, 3);
+        let order_entries = ledger.entries_for_order(premium.order_id).await.unwrap();
+        assert_eq!(order_entries.len(), 6);
+        assert_eq!(order_entries.iter().map(|entry| entry.amount).sum::<i64>(), 0);
+        assert!(order_entries
+            .iter()
+            .any(|entry| entry.kind == LedgerEntryKind::PlatformFee && entry.user_id.is_none() && entry.amount == 1));
+        assert!(ledger.balance_mismatches(100).await.unwrap().is_empty());
+
+        // 免费积分：同一用户同一周期只发放一次
+        assert!(ledger.grant_free(buyer.user_id, 0, 5, Utc::now()).await.unwrap());
+        assert!(!ledger.grant_free(buyer.user_id, 0, 5, Utc::now()).await.unwrap());
+        assert!(ledger.grant_free(buyer.user_id, 1, 5, Utc::now()).await.unwrap());
+        assert_eq!(users.find_by_id(buyer.user_id).await.unwrap().unwrap().premium_balance, 110);
+        assert!(ledger.balance_mismatches(100).await.unwrap().is_empty());
+    }
+
+    fn withdrawal(user: &User, amount: i64) -> Withdrawal {
+        Withdrawal {
+            withdrawal_id: Uuid::new_v4(),
+            user_id: user.user_id,
+            amount,
+            amount_usd: amount,
+            token_price: "0.5".to_string(),
+            amount_wei: (amount as u128 * 2_000_000_000_000_000_000).to_string(),
+            wallet_address: user.wallet_address.clone(),
+            status: WithdrawalStatus::Requested,
+            tx_hash: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            completed_at: None,
+        }
+    }
+
+    async fn exercise_withdrawals(
+        users: Arc<dyn UserRepository>,
+        pickers: Arc<dyn PickerRepository>,
+        orders: Arc<dyn OrderRepository>,
+        withdrawals: Arc<dyn WithdrawalRepository>,
+        ledger: Arc<dyn PremiumLedgerRepository>,
+    ) {
+        let dev = user("withdraw-dev@example.com", UserType::Dev, 0);
+        let buyer = user("withdraw-buyer@example.com", UserType::Gen, 100);
+        users.create(&dev).await.unwrap();
+        users.create(&buyer).await.unwrap();
+        let sold = picker(dev.user_id, "Sold", "active", 0);
+        pickers.create(&sold).await.unwrap();
+
+        // 销售记录：只包含成功订单，Premium 订单附带流水中的开发者收入
+        let start = Utc::now() - Duration::minutes(1);
+        let premium = order(buyer.user_id, sold.picker_id, PayType::Premium, OrderStatus::Success);
+        orders.settle_premium(&premium, dev.user_id, 9).await.unwrap();
+        let wallet = order(buyer.user_id, sold.picker_id, PayType::Wallet, OrderStatus::Success);
+        orders.create(&wallet).await.unwrap();
+        let pending = order(buyer.user_id, sold.picker_id, PayType::Wallet, OrderStatus::Pending);
+        orders.create(&pending).await.unwrap();
+
+        let sales = orders.list_sales(dev.user_id, start, Utc::now() + Duration::minutes(1)).await.unwrap();
+        assert_eq!(sales.len(), 2);
+        let premium_sale = sales.iter().find(|sale| sale.order_id == premium.order_id).unwrap();
+        assert_eq!(premium_sale.dev_income, Some(9));
+        assert_eq!(premium_sale.pay_type, PayType::Premium);
+        let wallet_sale = sales.iter().find(|sale| sale.order_id == wallet.order_id).unwrap();
+        assert_eq!(wallet_sale.dev_income, None);
+        assert!(orders.list_sales(buyer.user_id, start, Utc::now()).await.unwrap().is_empty());
+        assert!(orders.list_sales(dev.user_id, start - Duration::hours(1), start).await.unwrap().is_empty());
+
+        // 余额不足时不写入提现
+        assert!(!withdrawals.request(&withdrawal(&dev, 10)).await.unwrap());
+        assert!(withdrawals.list_for_user(dev.user_id, 10, 0).await.unwrap().0.is_empty());
+        // 可提现收入只包含销售收入，买家的期初余额不能提现
+        assert_eq!(withdrawals.withdrawable_earnings(dev.user_id).await.unwrap(), 9);
+        assert_eq!(withdrawals.withdrawable_earnings(buyer.user_id).await.unwrap(), 0);
+        assert!(!withdrawals.request(&withdrawal(&buyer, 10)).await.unwrap());
+        assert_eq!(users.find_by_id(buyer.user_id).await.unwrap().unwrap().premium_balance, 90);
+
+        let cancelled = withdrawal(&dev, 5);
+        assert!(withdrawals.request(&cancelled).await.unwrap());
+        assert_eq!(users.find_by_id(dev.user_id).await.unwrap().unwrap().premium_balance, 4);
+        assert!(withdrawals.cancel(cancelled.withdrawal_id).await.unwrap());
+        assert!(!withdrawals.cancel(cancelled.withdrawal_id).await.unwrap());
+        assert!(!withdrawals.mark_processing(cancelled.withdrawal_id, "0xpayout0").await.unwrap());
+        assert_eq!(users.find_by_id(dev.user_id).await.unwrap().unwrap().premium_balance, 9);
+
+        let failed = withdrawal(&dev, 4);
+        let completed = withdrawal(&dev, 5);
+        assert!(withdrawals.request(&failed).await.unwrap());
+        assert!(withdrawals.request(&completed).await.unwrap());
+        assert_eq!(users.find_by_id(dev.user_id).await.unwrap().unwrap().premium_balance, 0);
+        let requested = withdrawals.list_by_status(WithdrawalStatus::Requested, 10).await.unwrap();
+        assert_eq!(requested.len(), 2);
+
+        // 转账交易确认前不能完成，回滚时退回积分
+        assert!(!withdrawals.mark_completed(failed.withdrawal_id).await.unwrap());
+        assert!(withdrawals.mark_processing(failed.withdrawal_id, "0xpayout1").await.unwrap());
+        assert!(withdrawals.mark_processing(completed.withdrawal_id, "0xpayout2").await.unwrap());
+        assert!(!withdrawals.cancel(failed.withdrawal_id).await.unwrap());
+        assert!(withdrawals.mark_failed(failed.withdrawal_id).await.unwrap());
+        assert!(!withdrawals.mark_failed(failed.withdrawal_id).await.unwrap());
+        assert!(withdrawals.mark_completed(completed.withdrawal_id).await.unwrap());
+        assert!(!withdrawals.mark_failed(completed.withdrawal_id).await.unwrap());
+        assert_eq!(users.find_by_id(dev.user_id).await.unwrap().unwrap().premium_balance, 4);
+
+        let stored = withdrawals.find_by_id(completed.withdrawal_id).await.unwrap().unwrap();
+        assert_eq!(stored.status, WithdrawalStatus::Completed);
+        assert_eq!(stored.tx_hash.as_deref(), Some("0xpayout2"));
+        assert_eq!(stored.amount_wei, "10000000000000000000");
+        assert!(stored.completed_at.is_some());
+        let (list, total) = withdrawals.list_for_user(dev.user_id, 2, 0).await.unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(list.len(), 2);
+        assert!(withdrawals.list_by_status(WithdrawalStatus::Processing, 10).await.unwrap().is_empty());
+
+        // 提现与退回的分录各自平衡，余额与流水一致
+        let (entries, _) = ledger.list_for_user(dev.user_id, 20, 0).await.unwrap();
+        assert_eq!(entries.iter().filter(|entry| entry.kind == LedgerEntryKind::Withdrawal).count(), 5);
+        assert!(ledger.balance_mismatches(100).await.unwrap().is_empty());
+    }
+
+    fn chain_event(tx_hash: &str, log_index: i64, block_number: i64, order_id: Option<Uuid>) -> ChainEvent {
+        ChainEvent {
+            tx_hash: tx_hash.to_string(),
+            log_index,
+            block_number,
+            block_hash: format!("0xblock{}", block_number),
+            event_name: "PaymentProcessed".to_string(),
+            picker_id: Uuid::new_v4(),
+            wallet_address: None,
+            amount: Some("1000000000000000000".to_string()),
+            order_id,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn chain_cursor(block_number: i64) -> ChainCursor {
+        ChainCursor {
+            name: "picker_payment".to_string(),
+            block_number,
+            block_hash: format!("0xblock{}", block_number),
+            updated_at: Utc::now(),
+        }
+    }
+
+    async fn exercise_chain_events(chain_events: Arc<dyn ChainEventRepository>) {
+        assert!(chain_events.cursor("picker_payment").await.unwrap().is_none());
+
+        let order_id = Uuid::new_v4();
+        let first = chain_event("0x01", 0, 10, Some(order_id));
+        let second = chain_event("0x02", 3, 12, None);
+        chain_events.save_batch(&[first.clone(), second], &chain_cursor(12)).await.unwrap();
+        assert_eq!(chain_events.cursor("picker_payment").await.unwrap().unwrap().block_number, 12);
+
+        // 重复写入同一条日志会被忽略
+        let mut duplicate = first.clone();
+        duplicate.order_id = None;
+        chain_events.save_batch(&[duplicate], &chain_cursor(13)).await.unwrap();
+        let stored = chain_events.events_for_order(order_id).await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].tx_hash, "0x01");
+        assert_eq!(stored[0].amount.as_deref(), Some("1000000000000000000"));
+        assert_eq!(chain_events.cursor("picker_payment").await.unwrap().unwrap().block_number, 13);
+
+        // 回退到区块 10：之后的事件被删除
+        assert_eq!(chain_events.rewind(&chain_cursor(10)).await.unwrap(), 1);
+        assert_eq!(chain_events.rewind(&chain_cursor(9)).await.unwrap(), 1);
+        assert!(chain_events.events_for_order(order_id).await.unwrap().is_empty());
+        let cursor = chain_events.cursor("picker_payment").await.unwrap().unwrap();
+        assert_eq!(cursor.block_number, 9);
+        assert_eq!(cursor.block_hash, "0xblock9");
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_repositories() {
+        let pool = create_memory_pool().await.unwrap();
+        init_database(&pool).await.unwrap();
+        let db = Database::Sqlite(pool);
+        exercise_repositories(db.users(), db.pickers(), db.orders()).await;
+        exercise_refunds(db.users(), db.pickers(), db.orders(), db.refunds(), db.premium_ledger()).await;
+        exercise_withdrawals(db.users(), db.pickers(), db.orders(), db.withdrawals(), db.premium_ledger()).await;
+        exercise_chain_events(db.chain_events()).await;
+    }
+
+    #[tokio::test]
+    async fn test_memory_repositories() {
+        let repo = MemoryRepository::new();
+        exercise_repositories(Arc::new(repo.clone()), Arc::new(repo.clone()), Arc::new(repo.clone())).await;
+        exercise_refunds(
+            Arc::new(repo.clone()),
+            Arc::new(repo.clone()),
+            Arc::new(repo.clone()),
+            Arc::new(repo.clone()),
+            Arc::new(repo.clone()),
+        )
+        .await;
+        exercise_withdrawals(
+            Arc::new(repo.clone()),
+            Arc::new(repo.clone()),
+            Arc::new(repo.clone()),
+            Arc::new(repo.clone()),
+            Arc::new(repo.clone()),
+        )
+        .await;
+        exercise_chain_events(Arc::new(repo)).await;
+    }
+
+    // 需要可用的 PostgreSQL，通过 PICKER_TEST_POSTGRES_URL 指定，未设置时跳过
+    #[tokio::test]
+    async fn test_postgres_repositories() {
+        let Ok(url) = std::env::var("PICKER_TEST_POSTGRES_URL") else {
+            return;
+        };
+
+        // 每次运行使用独立的 schema，避免测试数据相互影响
+        let schema = format!("repo_test_{}", Uuid::new_v4().simple());
+        let admin = PgPoolOptions::new().max_connections(1).connect(&url).await.unwrap();
+        sqlx::query(&format!("CREATE SCHEMA {}", schema)).execute(&admin).await.unwrap();
+
+        let search_path = schema.clone();
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .after_connect(move |conn, _| {
+                let sql = format!("SET search_path TO {}", search_path);
+                Box::pin(async move {
+                    sqlx::query(&sql).execute(conn).await?;
+                    Ok(())
+                })
+            })
+            .connect(&url)
+            .await
+            .unwrap();
+
+        init_database(Database::Postgres(pool.clone())).await.unwrap();
+        let db = Database::Postgres(pool.clone());
+        exercise_repositories(db.users(), db.pickers(), db.orders()).await;
+        exercise_refunds(db.users(), db.pickers(), db.orders(), db.refunds(), db.premium_ledger()).await;
+        exercise_withdrawals(db.users(), db.pickers(), db.orders(), db.withdrawals(), db.premium_ledger()).await;
+        exercise_chain_events(db.chain_events()).await;
+
+        pool.close().await;
+        sqlx::query(&format!("DROP SCHEMA {} CASCADE", schema)).execute(&admin).await.unwrap();
+    }
+}
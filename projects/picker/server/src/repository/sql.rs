@@ -0,0 +1,1120 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Postgres, Sqlite};
+use uuid::Uuid;
+
+use super::{
+    free_grant_entries, opening_entries, purchase_entries, refund_entries, withdrawal_entries, withdrawal_reversal_entries,
+    ChainEventRepository, OrderRepository, PickerRepository, PremiumLedgerRepository, RefundRepository, UserRepository,
+    WithdrawalRepository,
+};
+use crate::models::{
+    BalanceMismatch, ChainCursor, ChainEvent, LedgerEntryKind, Order, OrderStatus, PayType, Picker, PickerChainStatus,
+    PickerVersion, PremiumLedgerEntry, Refund, RefundStatus, Sale, User, Withdrawal, WithdrawalStatus,
+};
+
+const UPSERT_CHAIN_CURSOR: &str = r#"
+    INSERT INTO chain_cursors (name, block_number, block_hash, updated_at)
+    VALUES ($1, $2, $3, $4)
+    ON CONFLICT (name) DO UPDATE
+    SET block_number = excluded.block_number, block_hash = excluded.block_hash, updated_at = excluded.updated_at
+"#;
+
+const INSERT_LEDGER_ENTRY: &str = r#"
+    INSERT INTO premium_ledger (entry_id, transaction_id, user_id, order_id, kind, amount, balance_after, created_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+"#;
+
+const APPLY_LEDGER_ENTRY: &str = "UPDATE users SET premium_balance = premium_balance + $1 WHERE user_id = $2 RETURNING premium_balance";
+
+// 出账后余额不能为负
+const APPLY_GUARDED_LEDGER_ENTRY: &str =
+    "UPDATE users SET premium_balance = premium_balance + $1 WHERE user_id = $2 AND premium_balance + $1 >= 0 RETURNING premium_balance";
+
+// 与 is_withdrawable_entry 对应；PostgreSQL 的 SUM(BIGINT) 返回 NUMERIC，显式转换为 BIGINT
+const WITHDRAWABLE_EARNINGS: &str = r#"
+    SELECT CAST(COALESCE(SUM(amount), 0) AS BIGINT) FROM premium_ledger
+    WHERE user_id = $1 AND (kind IN ($2, $3) OR (kind = $4 AND amount < 0))
+"#;
+
+// 基于 sqlx 连接池的仓储实现，SQLite 与 PostgreSQL 共用同一套 SQL
+// 占位符统一使用 $N 形式，两种后端都支持；时间字段直接绑定 DateTime<Utc>
+#[derive(Clone)]
+pub struct SqlRepository<DB: sqlx::Database> {
+    pool: Pool<DB>,
+}
+
+impl<DB: sqlx::Database> SqlRepository<DB> {
+    pub fn new(pool: Pool<DB>) -> Self {
+        Self { pool }
+    }
+}
+
+// sqlx 的 Executor/Encode 约束无法在泛型实现中简洁表达，这里用宏为每种后端各生成一份实现
+macro_rules! impl_sql_repository {
+    ($db:ty) => {
+        impl SqlRepository<$db> {
+            // 在事务中依次更新分录对应用户的余额并写入分录
+            // guard_debits 为 true 时出账后余额为负的分录不会执行并返回 false，调用方应放弃事务
+            async fn post_ledger_entries(
+                tx: &mut sqlx::Transaction<'_, $db>,
+                entries: &[PremiumLedgerEntry],
+                guard_debits: bool,
+            ) -> Result<bool, sqlx::Error> {
+                for entry in entries {
+                    let balance_after = match entry.user_id {
+                        Some(user_id) => {
+                            let sql = if guard_debits { APPLY_GUARDED_LEDGER_ENTRY } else { APPLY_LEDGER_ENTRY };
+                            let balance: Option<(i64,)> = sqlx::query_as(sql)
+                                .bind(entry.amount)
+                                .bind(user_id)
+                                .fetch_optional(&mut **tx)
+                                .await?;
+                            match balance {
+                                Some((balance,)) => Some(balance),
+                                None if guard_debits => return Ok(false),
+                                None => return Err(sqlx::Error::RowNotFound),
+                            }
+                        }
+                        None => None,
+                    };
+
+                    sqlx::query(INSERT_LEDGER_ENTRY)
+                        .bind(entry.entry_id)
+                        .bind(entry.transaction_id)
+                        .bind(entry.user_id)
+                        .bind(entry.order_id)
+                        .bind(&entry.kind)
+                        .bind(entry.amount)
+                        .bind(balance_after)
+                        .bind(entry.created_at)
+                        .execute(&mut **tx)
+                        .await?;
+                }
+                Ok(true)
+            }
+
+            // 在事务中写入版本记录，同一 Picker 的版本号已存在时返回 false
+            async fn insert_picker_version(
+                tx: &mut sqlx::Transaction<'_, $db>,
+                version: &PickerVersion,
+            ) -> Result<bool, sqlx::Error> {
+                let inserted = sqlx::query(
+                    r#"
+                    INSERT INTO picker_versions (version_id, picker_id, version, file_path, changelog, created_at)
+                    VALUES ($1, $2, $3, $4, $5, $6)
+                    ON CONFLICT (picker_id, version) DO NOTHING
+                    "#,
+                )
+                .bind(version.version_id)
+                .bind(version.picker_id)
+                .bind(&version.version)
+                .bind(&version.file_path)
+                .bind(&version.changelog)
+                .bind(version.created_at)
+                .execute(&mut **tx)
+                .await?;
+                Ok(inserted.rows_affected() > 0)
+            }
+
+            // 在一个事务中将提现从 from 状态置为 to 状态并退回积分，提现已不是 from 状态时返回 false
+            async fn release_withdrawal(
+                &self,
+                withdrawal_id: Uuid,
+                from: WithdrawalStatus,
+                to: WithdrawalStatus,
+            ) -> Result<bool, sqlx::Error> {
+                let mut tx = self.pool.begin().await?;
+
+                let released: Option<(Uuid, i64)> = sqlx::query_as(
+                    "UPDATE withdrawals SET status = $1, updated_at = $2 WHERE withdrawal_id = $3 AND status = $4 RETURNING user_id, amount",
+                )
+                .bind(to)
+                .bind(Utc::now())
+                .bind(withdrawal_id)
+                .bind(from)
+                .fetch_optional(&mut *tx)
+                .await?;
+                let Some((user_id, amount)) = released else {
+                    return Ok(false);
+                };
+
+                Self::post_ledger_entries(&mut tx, &withdrawal_reversal_entries(user_id, amount), false).await?;
+                tx.commit().await?;
+                Ok(true)
+            }
+        }
+
+        #[async_trait]
+        impl UserRepository for SqlRepository<$db> {
+            async fn find_by_id(&self, user_id: Uuid) -> Result<Option<User>, sqlx::Error> {
+                sqlx::query_as::<_, User>("SELECT * FROM users WHERE user_id = $1")
+                    .bind(user_id)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
+                sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1")
+                    .bind(email)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn exists(&self, user_id: Uuid) -> Result<bool, sqlx::Error> {
+                let row: Option<(i32,)> = sqlx::query_as("SELECT 1 FROM users WHERE user_id = $1 LIMIT 1")
+                    .bind(user_id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                Ok(row.is_some())
+            }
+
+            async fn create(&self, user: &User) -> Result<(), sqlx::Error> {
+                let mut tx = self.pool.begin().await?;
+
+                // 余额先以 0 写入，初始余额通过期初分录计入，保证余额与流水一致
+                sqlx::query(
+                    r#"
+                    INSERT INTO users (user_id, email, user_name, user_password, user_type, private_key, wallet_address, premium_balance, created_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, 0, $8)
+                    "#,
+                )
+                .bind(user.user_id)
+                .bind(&user.email)
+                .bind(&user.user_name)
+                .bind(&user.user_password)
+                .bind(&user.user_type)
+                .bind(&user.private_key)
+                .bind(&user.wallet_address)
+                .bind(user.created_at)
+                .execute(&mut *tx)
+                .await?;
+
+                Self::post_ledger_entries(&mut tx, &opening_entries(user), false).await?;
+                tx.commit().await
+            }
+
+            async fn update_password(&self, user_id: Uuid, password_hash: &str) -> Result<bool, sqlx::Error> {
+                let result = sqlx::query("UPDATE users SET user_password = $1 WHERE user_id = $2")
+                    .bind(password_hash)
+                    .bind(user_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn list_private_keys(&self) -> Result<Vec<(Uuid, String)>, sqlx::Error> {
+                sqlx::query_as("SELECT user_id, private_key FROM users ORDER BY created_at")
+                    .fetch_all(&self.pool)
+                    .await
+            }
+
+            async fn replace_private_key(&self, user_id: Uuid, expected: &str, private_key: &str) -> Result<bool, sqlx::Error> {
+                let result = sqlx::query("UPDATE users SET private_key = $1 WHERE user_id = $2 AND private_key = $3")
+                    .bind(private_key)
+                    .bind(user_id)
+                    .bind(expected)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+
+            async fn list_registrations(&self, after: Option<Uuid>, limit: i64) -> Result<Vec<(Uuid, DateTime<Utc>)>, sqlx::Error> {
+                match after {
+                    Some(after) => {
+                        sqlx::query_as("SELECT user_id, created_at FROM users WHERE user_id > $1 ORDER BY user_id LIMIT $2")
+                            .bind(after)
+                            .bind(limit)
+                            .fetch_all(&self.pool)
+                            .await
+                    }
+                    None => {
+                        sqlx::query_as("SELECT user_id, created_at FROM users ORDER BY user_id LIMIT $1")
+                            .bind(limit)
+                            .fetch_all(&self.pool)
+                            .await
+                    }
+                }
+            }
+        }
+
+        #[async_trait]
+        impl PickerRepository for SqlRepository<$db> {
+            async fn find_by_id(&self, picker_id: Uuid) -> Result<Option<Picker>, sqlx::Error> {
+                sqlx::query_as::<_, Picker>("SELECT * FROM pickers WHERE picker_id = $1")
+                    .bind(picker_id)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn find_active(&self, picker_id: Uuid) -> Result<Option<Picker>, sqlx::Error> {
+                sqlx::query_as::<_, Picker>("SELECT * FROM pickers WHERE picker_id = $1 AND status = 'active'")
+                    .bind(picker_id)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn list_active(
+                &self,
+                keyword: Option<&str>,
+                limit: i64,
+                offset: i64,
+            ) -> Result<(Vec<Picker>, i64), sqlx::Error> {
+                match keyword {
+                    Some(keyword) => {
+                        let search_pattern = format!("%{}%", keyword.to_lowercase());
+
+                        let total: (i64,) = sqlx::query_as(
+                            "SELECT COUNT(*) FROM pickers WHERE status = 'active' AND (LOWER(alias) LIKE $1 OR LOWER(description) LIKE $1)",
+                        )
+                        .bind(&search_pattern)
+                        .fetch_one(&self.pool)
+                        .await?;
+
+                        let pickers = sqlx::query_as::<_, Picker>(
+                            "SELECT * FROM pickers WHERE status = 'active' AND (LOWER(alias) LIKE $1 OR LOWER(description) LIKE $1) ORDER BY created_at DESC LIMIT $2 OFFSET $3",
+                        )
+                        .bind(&search_pattern)
+                        .bind(limit)
+                        .bind(offset)
+                        .fetch_all(&self.pool)
+                        .await?;
+
+                        Ok((pickers, total.0))
+                    }
+                    None => {
+                        let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM pickers WHERE status = 'active'")
+                            .fetch_one(&self.pool)
+                            .await?;
+
+                        let pickers = sqlx::query_as::<_, Picker>(
+                            "SELECT * FROM pickers WHERE status = 'active' ORDER BY created_at DESC LIMIT $1 OFFSET $2",
+                        )
+                        .bind(limit)
+                        .bind(offset)
+                        .fetch_all(&self.pool)
+                        .await?;
+
+                        Ok((pickers, total.0))
+                    }
+                }
+            }
+
+            async fn list_for_dev(
+                &self,
+                dev_user_id: Uuid,
+                limit: i64,
+                offset: i64,
+            ) -> Result<(Vec<Picker>, i64), sqlx::Error> {
+                let total: (i64,) =
+                    sqlx::query_as("SELECT COUNT(*) FROM pickers WHERE dev_user_id = $1 AND deleted_at IS NULL")
+                        .bind(dev_user_id)
+                        .fetch_one(&self.pool)
+                        .await?;
+
+                let pickers = sqlx::query_as::<_, Picker>(
+                    "SELECT * FROM pickers WHERE dev_user_id = $1 AND deleted_at IS NULL ORDER BY created_at DESC LIMIT $2 OFFSET $3",
+                )
+                .bind(dev_user_id)
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(&self.pool)
+                .await?;
+
+                Ok((pickers, total.0))
+            }
+
+            async fn create(&self, picker: &Picker) -> Result<(), sqlx::Error> {
+                let mut tx = self.pool.begin().await?;
+
+                sqlx::query(
+                    r#"
+                    INSERT INTO pickers (picker_id, dev_user_id, alias, description, price, image_path, file_path, version, status, download_count, created_at, updated_at, chain_status, chain_tx_hash)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+                    "#,
+                )
+                .bind(picker.picker_id)
+                .bind(picker.dev_user_id)
+                .bind(&picker.alias)
+                .bind(&picker.description)
+                .bind(picker.price)
+                .bind(&picker.image_path)
+                .bind(&picker.file_path)
+                .bind(&picker.version)
+                .bind(&picker.status)
+                .bind(picker.download_count)
+                .bind(picker.created_at)
+                .bind(picker.updated_at)
+                .bind(picker.chain_status)
+                .bind(&picker.chain_tx_hash)
+                .execute(&mut *tx)
+                .await?;
+
+                Self::insert_picker_version(&mut tx, &PickerVersion::initial(picker)).await?;
+
+                tx.commit().await?;
+                Ok(())
+            }
+
+            async fn publish_version(&self, version: &PickerVersion) -> Result<bool, sqlx::Error> {
+                let mut tx = self.pool.begin().await?;
+
+                // 版本号已存在时直接返回，事务随 tx 析构回滚
+                if !Self::insert_picker_version(&mut tx, version).await? {
+                    return Ok(false);
+                }
+
+                sqlx::query("UPDATE pickers SET version = $1, file_path = $2, updated_at = $3 WHERE picker_id = $4")
+                    .bind(&version.version)
+                    .bind(&version.file_path)
+                    .bind(version.created_at)
+                    .bind(version.picker_id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                tx.commit().await?;
+                Ok(true)
+            }
+
+            async fn list_versions(&self, picker_id: Uuid) -> Result<Vec<PickerVersion>, sqlx::Error> {
+                sqlx::query_as::<_, PickerVersion>(
+                    "SELECT * FROM picker_versions WHERE picker_id = $1 ORDER BY created_at DESC",
+                )
+                .bind(picker_id)
+                .fetch_all(&self.pool)
+                .await
+            }
+
+            async fn increment_download_count(&self, picker_id: Uuid) -> Result<(), sqlx::Error> {
+                sqlx::query("UPDATE pickers SET download_count = download_count + 1 WHERE picker_id = $1")
+                    .bind(picker_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            async fn update(&self, picker: &Picker) -> Result<bool, sqlx::Error> {
+                let updated = sqlx::query(
+                    r#"
+                    UPDATE pickers SET alias = $1, description = $2, price = $3, image_path = $4, status = $5, updated_at = $6
+                    WHERE picker_id = $7 AND deleted_at IS NULL
+                    "#,
+                )
+                .bind(&picker.alias)
+                .bind(&picker.description)
+                .bind(picker.price)
+                .bind(&picker.image_path)
+                .bind(&picker.status)
+                .bind(picker.updated_at)
+                .bind(picker.picker_id)
+                .execute(&self.pool)
+                .await?;
+                Ok(updated.rows_affected() > 0)
+            }
+
+            async fn soft_delete(&self, picker_id: Uuid, deleted_at: DateTime<Utc>) -> Result<bool, sqlx::Error> {
+                let deleted = sqlx::query(
+                    "UPDATE pickers SET status = 'inactive', deleted_at = $1, updated_at = $1 WHERE picker_id = $2 AND deleted_at IS NULL",
+                )
+                .bind(deleted_at)
+                .bind(picker_id)
+                .execute(&self.pool)
+                .await?;
+                Ok(deleted.rows_affected() > 0)
+            }
+
+            async fn set_status(&self, picker_id: Uuid, status: &str) -> Result<bool, sqlx::Error> {
+                let updated = sqlx::query("UPDATE pickers SET status = $1, updated_at = $2 WHERE picker_id = $3")
+                    .bind(status)
+                    .bind(Utc::now())
+                    .bind(picker_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(updated.rows_affected() > 0)
+            }
+
+            async fn update_chain_status(
+                &self,
+                picker_id: Uuid,
+                chain_status: PickerChainStatus,
+                tx_hash: Option<&str>,
+            ) -> Result<bool, sqlx::Error> {
+                let updated = sqlx::query(
+                    "UPDATE pickers SET chain_status = $1, chain_tx_hash = COALESCE($2, chain_tx_hash) WHERE picker_id = $3",
+                )
+                .bind(chain_status)
+                .bind(tx_hash)
+                .bind(picker_id)
+                .execute(&self.pool)
+                .await?;
+                Ok(updated.rows_affected() > 0)
+            }
+
+            async fn file_references(&self) -> Result<Vec<String>, sqlx::Error> {
+                sqlx::query_scalar::<_, String>(
+                    "SELECT file_path FROM pickers UNION SELECT image_path FROM pickers UNION SELECT file_path FROM picker_versions",
+                )
+                .fetch_all(&self.pool)
+                .await
+            }
+
+            async fn is_file_referenced(&self, key: &str) -> Result<bool, sqlx::Error> {
+                let references: i64 = sqlx::query_scalar(
+                    r#"
+                    SELECT COUNT(*) FROM (
+                        SELECT picker_id FROM pickers WHERE file_path = $1 OR image_path = $1
+                        UNION ALL
+                        SELECT picker_id FROM picker_versions WHERE file_path = $1
+                    ) AS refs
+                    "#,
+                )
+                .bind(key)
+                .fetch_one(&self.pool)
+                .await?;
+                Ok(references > 0)
+            }
+
+            async fn replace_file_reference(&self, old: &str, new: &str) -> Result<u64, sqlx::Error> {
+                let mut tx = self.pool.begin().await?;
+                let mut replaced = 0;
+                for sql in [
+                    "UPDATE pickers SET file_path = $1 WHERE file_path = $2",
+                    "UPDATE pickers SET image_path = $1 WHERE image_path = $2",
+                    "UPDATE picker_versions SET file_path = $1 WHERE file_path = $2",
+                ] {
+                    replaced += sqlx::query(sql).bind(new).bind(old).execute(&mut *tx).await?.rows_affected();
+                }
+                tx.commit().await?;
+                Ok(replaced)
+            }
+        }
+
+        #[async_trait]
+        impl OrderRepository for SqlRepository<$db> {
+            async fn find_by_id(&self, order_id: Uuid) -> Result<Option<Order>, sqlx::Error> {
+                sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE order_id = $1")
+                    .bind(order_id)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn find_for_user(&self, order_id: Uuid, user_id: Uuid) -> Result<Option<Order>, sqlx::Error> {
+                sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE order_id = $1 AND user_id = $2")
+                    .bind(order_id)
+                    .bind(user_id)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn list_for_user(
+                &self,
+                user_id: Uuid,
+                status: Option<&OrderStatus>,
+                limit: i64,
+                offset: i64,
+            ) -> Result<(Vec<Order>, i64), sqlx::Error> {
+                match status {
+                    Some(status) => {
+                        let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM orders WHERE user_id = $1 AND status = $2")
+                            .bind(user_id)
+                            .bind(status)
+                            .fetch_one(&self.pool)
+                            .await?;
+
+                        let orders = sqlx::query_as::<_, Order>(
+                            "SELECT * FROM orders WHERE user_id = $1 AND status = $2 ORDER BY created_at DESC LIMIT $3 OFFSET $4",
+                        )
+                        .bind(user_id)
+                        .bind(status)
+                        .bind(limit)
+                        .bind(offset)
+                        .fetch_all(&self.pool)
+                        .await?;
+
+                        Ok((orders, total.0))
+                    }
+                    None => {
+                        let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM orders WHERE user_id = $1")
+                            .bind(user_id)
+                            .fetch_one(&self.pool)
+                            .await?;
+
+                        let orders = sqlx::query_as::<_, Order>(
+                            "SELECT * FROM orders WHERE user_id = $1 ORDER BY created_at DESC LIMIT $2 OFFSET $3",
+                        )
+                        .bind(user_id)
+                        .bind(limit)
+                        .bind(offset)
+                        .fetch_all(&self.pool)
+                        .await?;
+
+                        Ok((orders, total.0))
+                    }
+                }
+            }
+
+            async fn create(&self, order: &Order) -> Result<(), sqlx::Error> {
+                sqlx::query(
+                    r#"
+                    INSERT INTO orders (order_id, user_id, picker_id, amount, pay_type, status, tx_hash, created_at, expires_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                    "#,
+                )
+                .bind(order.order_id)
+                .bind(order.user_id)
+                .bind(order.picker_id)
+                .bind(order.amount)
+                .bind(&order.pay_type)
+                .bind(&order.status)
+                .bind(&order.tx_hash)
+                .bind(order.created_at)
+                .bind(order.expires_at)
+                .execute(&self.pool)
+                .await?;
+                Ok(())
+            }
+
+            async fn settle_premium(&self, order: &Order, dev_user_id: Uuid, dev_income: i64) -> Result<bool, sqlx::Error> {
+                let mut tx = self.pool.begin().await?;
+
+                sqlx::query(
+                    r#"
+                    INSERT INTO orders (order_id, user_id, picker_id, amount, pay_type, status, tx_hash, created_at, expires_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                    "#,
+                )
+                .bind(order.order_id)
+                .bind(order.user_id)
+                .bind(order.picker_id)
+                .bind(order.amount)
+                .bind(&order.pay_type)
+                .bind(&order.status)
+                .bind(&order.tx_hash)
+                .bind(order.created_at)
+                .bind(order.expires_at)
+                .execute(&mut *tx)
+                .await?;
+
+                // 扣款分录带余额校验，并发支付不会透支；余额不足时放弃事务，已写入的订单随之回滚
+                if !Self::post_ledger_entries(&mut tx, &purchase_entries(order, dev_user_id, dev_income), true).await? {
+                    return Ok(false);
+                }
+
+                sqlx::query("UPDATE pickers SET download_count = download_count + 1 WHERE picker_id = $1")
+                    .bind(order.picker_id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                tx.commit().await?;
+                Ok(true)
+            }
+
+            async fn mark_success(&self, order_id: Uuid, picker_id: Uuid) -> Result<bool, sqlx::Error> {
+                let mut tx = self.pool.begin().await?;
+
+                let updated = sqlx::query("UPDATE orders SET status = $1 WHERE order_id = $2 AND status = $3")
+                    .bind(&OrderStatus::Success)
+                    .bind(order_id)
+                    .bind(&OrderStatus::Pending)
+                    .execute(&mut *tx)
+                    .await?;
+                if updated.rows_affected() == 0 {
+                    return Ok(false);
+                }
+
+                sqlx::query("UPDATE pickers SET download_count = download_count + 1 WHERE picker_id = $1")
+                    .bind(picker_id)
+                    .execute(&mut *tx)
+                    .await?;
+
+                tx.commit().await?;
+                Ok(true)
+            }
+
+            async fn mark_expired(&self, order_id: Uuid) -> Result<bool, sqlx::Error> {
+                let updated = sqlx::query("UPDATE orders SET status = $1 WHERE order_id = $2 AND status = $3")
+                    .bind(&OrderStatus::Expired)
+                    .bind(order_id)
+                    .bind(&OrderStatus::Pending)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(updated.rows_affected() > 0)
+            }
+
+            async fn mark_cancelled(&self, order_id: Uuid) -> Result<bool, sqlx::Error> {
+                let updated = sqlx::query("UPDATE orders SET status = $1 WHERE order_id = $2 AND status = $3")
+                    .bind(&OrderStatus::Cancelled)
+                    .bind(order_id)
+                    .bind(&OrderStatus::Pending)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(updated.rows_affected() > 0)
+            }
+
+            async fn list_pending_wallet(&self, limit: i64) -> Result<Vec<Order>, sqlx::Error> {
+                sqlx::query_as::<_, Order>(
+                    "SELECT * FROM orders WHERE status = $1 AND pay_type = $2 ORDER BY created_at ASC LIMIT $3",
+                )
+                .bind(&OrderStatus::Pending)
+                .bind(&PayType::Wallet)
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await
+            }
+
+            async fn find_by_tx_hash(&self, tx_hash: &str) -> Result<Option<Order>, sqlx::Error> {
+                sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE tx_hash = $1")
+                    .bind(tx_hash)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn list_sales(
+                &self,
+                dev_user_id: Uuid,
+                from: DateTime<Utc>,
+                to: DateTime<Utc>,
+            ) -> Result<Vec<Sale>, sqlx::Error> {
+                sqlx::query_as::<_, Sale>(
+                    r#"
+                    SELECT o.order_id, o.picker_id, o.pay_type, o.amount, l.amount AS dev_income, o.created_at
+                    FROM orders o
+                    JOIN pickers p ON p.picker_id = o.picker_id
+                    LEFT JOIN premium_ledger l ON l.order_id = o.order_id AND l.user_id = p.dev_user_id AND l.kind = $2
+                    WHERE p.dev_user_id = $1 AND o.status = $3 AND o.created_at >= $4 AND o.created_at < $5
+                    ORDER BY o.created_at ASC
+                    "#,
+                )
+                .bind(dev_user_id)
+                .bind(LedgerEntryKind::DevPayout)
+                .bind(&OrderStatus::Success)
+                .bind(from)
+                .bind(to)
+                .fetch_all(&self.pool)
+                .await
+            }
+        }
+
+        #[async_trait]
+        impl RefundRepository for SqlRepository<$db> {
+            async fn list_for_order(&self, order_id: Uuid) -> Result<Vec<Refund>, sqlx::Error> {
+                sqlx::query_as::<_, Refund>("SELECT * FROM refunds WHERE order_id = $1 ORDER BY created_at DESC")
+                    .bind(order_id)
+                    .fetch_all(&self.pool)
+                    .await
+            }
+
+            async fn refund_premium(&self, refund: &Refund, dev_user_id: Uuid, dev_income: i64) -> Result<bool, sqlx::Error> {
+                // 任一步骤未命中时直接返回，事务随 tx 析构回滚
+                let mut tx = self.pool.begin().await?;
+
+                let updated = sqlx::query("UPDATE orders SET status = $1 WHERE order_id = $2 AND status = $3")
+                    .bind(&OrderStatus::Refunded)
+                    .bind(refund.order_id)
+                    .bind(&OrderStatus::Success)
+                    .execute(&mut *tx)
+                    .await?;
+                if updated.rows_affected() == 0 {
+                    return Ok(false);
+                }
+
+                let (buyer_id,): (Uuid,) = sqlx::query_as("SELECT user_id FROM orders WHERE order_id = $1")
+                    .bind(refund.order_id)
+                    .fetch_one(&mut *tx)
+                    .await?;
+                let entries = refund_entries(refund, buyer_id, dev_user_id, dev_income);
+                if !Self::post_ledger_entries(&mut tx, &entries, true).await? {
+                    return Ok(false);
+                }
+
+                sqlx::query(
+                    r#"
+                    INSERT INTO refunds (refund_id, order_id, requested_by, amount, status, tx_hash, reason, created_at, completed_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                    "#,
+                )
+                .bind(refund.refund_id)
+                .bind(refund.order_id)
+                .bind(refund.requested_by)
+                .bind(refund.amount)
+                .bind(&refund.status)
+                .bind(&refund.tx_hash)
+                .bind(&refund.reason)
+                .bind(refund.created_at)
+                .bind(refund.completed_at)
+                .execute(&mut *tx)
+                .await?;
+
+                tx.commit().await?;
+                Ok(true)
+            }
+
+            async fn create(&self, refund: &Refund) -> Result<(), sqlx::Error> {
+                sqlx::query(
+                    r#"
+                    INSERT INTO refunds (refund_id, order_id, requested_by, amount, status, tx_hash, reason, created_at, completed_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                    "#,
+                )
+                .bind(refund.refund_id)
+                .bind(refund.order_id)
+                .bind(refund.requested_by)
+                .bind(refund.amount)
+                .bind(&refund.status)
+                .bind(&refund.tx_hash)
+                .bind(&refund.reason)
+                .bind(refund.created_at)
+                .bind(refund.completed_at)
+                .execute(&self.pool)
+                .await?;
+                Ok(())
+            }
+
+            async fn mark_completed(&self, refund_id: Uuid) -> Result<bool, sqlx::Error> {
+                let mut tx = self.pool.begin().await?;
+
+                let updated = sqlx::query(
+                    "UPDATE refunds SET status = $1, completed_at = $2 WHERE refund_id = $3 AND status = $4",
+                )
+                .bind(&RefundStatus::Completed)
+                .bind(Utc::now())
+                .bind(refund_id)
+                .bind(&RefundStatus::Pending)
+                .execute(&mut *tx)
+                .await?;
+                if updated.rows_affected() == 0 {
+                    return Ok(false);
+                }
+
+                sqlx::query(
+                    "UPDATE orders SET status = $1 WHERE order_id = (SELECT order_id FROM refunds WHERE refund_id = $2) AND status = $3",
+                )
+                .bind(&OrderStatus::Refunded)
+                .bind(refund_id)
+                .bind(&OrderStatus::Success)
+                .execute(&mut *tx)
+                .await?;
+
+                tx.commit().await?;
+                Ok(true)
+            }
+
+            async fn mark_failed(&self, refund_id: Uuid) -> Result<bool, sqlx::Error> {
+                let updated = sqlx::query("UPDATE refunds SET status = $1 WHERE refund_id = $2 AND status = $3")
+                    .bind(&RefundStatus::Failed)
+                    .bind(refund_id)
+                    .bind(&RefundStatus::Pending)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(updated.rows_affected() > 0)
+            }
+
+            async fn list_pending(&self, limit: i64) -> Result<Vec<Refund>, sqlx::Error> {
+                sqlx::query_as::<_, Refund>("SELECT * FROM refunds WHERE status = $1 ORDER BY created_at ASC LIMIT $2")
+                    .bind(&RefundStatus::Pending)
+                    .bind(limit)
+                    .fetch_all(&self.pool)
+                    .await
+            }
+        }
+
+        #[async_trait]
+        impl WithdrawalRepository for SqlRepository<$db> {
+            async fn find_by_id(&self, withdrawal_id: Uuid) -> Result<Option<Withdrawal>, sqlx::Error> {
+                sqlx::query_as::<_, Withdrawal>("SELECT * FROM withdrawals WHERE withdrawal_id = $1")
+                    .bind(withdrawal_id)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn list_for_user(
+                &self,
+                user_id: Uuid,
+                limit: i64,
+                offset: i64,
+            ) -> Result<(Vec<Withdrawal>, i64), sqlx::Error> {
+                let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM withdrawals WHERE user_id = $1")
+                    .bind(user_id)
+                    .fetch_one(&self.pool)
+                    .await?;
+
+                let withdrawals = sqlx::query_as::<_, Withdrawal>(
+                    "SELECT * FROM withdrawals WHERE user_id = $1 ORDER BY created_at DESC LIMIT $2 OFFSET $3",
+                )
+                .bind(user_id)
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(&self.pool)
+                .await?;
+
+                Ok((withdrawals, total.0))
+            }
+
+            async fn list_by_status(&self, status: WithdrawalStatus, limit: i64) -> Result<Vec<Withdrawal>, sqlx::Error> {
+                sqlx::query_as::<_, Withdrawal>(
+                    "SELECT * FROM withdrawals WHERE status = $1 ORDER BY created_at ASC LIMIT $2",
+                )
+                .bind(status)
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await
+            }
+
+            async fn withdrawable_earnings(&self, user_id: Uuid) -> Result<i64, sqlx::Error> {
+                let (earnings,): (i64,) = sqlx::query_as(WITHDRAWABLE_EARNINGS)
+                    .bind(user_id)
+                    .bind(LedgerEntryKind::DevPayout)
+                    .bind(LedgerEntryKind::Withdrawal)
+                    .bind(LedgerEntryKind::Refund)
+                    .fetch_one(&self.pool)
+                    .await?;
+                Ok(earnings)
+            }
+
+            async fn request(&self, withdrawal: &Withdrawal) -> Result<bool, sqlx::Error> {
+                // 余额不足时直接返回，事务随 tx 析构回滚
+                let mut tx = self.pool.begin().await?;
+
+                if !Self::post_ledger_entries(&mut tx, &withdrawal_entries(withdrawal), true).await? {
+                    return Ok(false);
+                }
+                // 扣款已锁定用户行，并发的提现会等待本事务结束，此时计入本次提现后的可提现收入不能为负
+                let (earnings,): (i64,) = sqlx::query_as(WITHDRAWABLE_EARNINGS)
+                    .bind(withdrawal.user_id)
+                    .bind(LedgerEntryKind::DevPayout)
+                    .bind(LedgerEntryKind::Withdrawal)
+                    .bind(LedgerEntryKind::Refund)
+                    .fetch_one(&mut *tx)
+                    .await?;
+                if earnings < 0 {
+                    return Ok(false);
+                }
+
+                sqlx::query(
+                    r#"
+                    INSERT INTO withdrawals (withdrawal_id, user_id, amount, amount_usd, token_price, amount_wei, wallet_address, status, tx_hash, created_at, updated_at, completed_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                    "#,
+                )
+                .bind(withdrawal.withdrawal_id)
+                .bind(withdrawal.user_id)
+                .bind(withdrawal.amount)
+                .bind(withdrawal.amount_usd)
+                .bind(&withdrawal.token_price)
+                .bind(&withdrawal.amount_wei)
+                .bind(&withdrawal.wallet_address)
+                .bind(withdrawal.status)
+                .bind(&withdrawal.tx_hash)
+                .bind(withdrawal.created_at)
+                .bind(withdrawal.updated_at)
+                .bind(withdrawal.completed_at)
+                .execute(&mut *tx)
+                .await?;
+
+                tx.commit().await?;
+                Ok(true)
+            }
+
+            async fn cancel(&self, withdrawal_id: Uuid) -> Result<bool, sqlx::Error> {
+                self.release_withdrawal(withdrawal_id, WithdrawalStatus::Requested, WithdrawalStatus::Cancelled)
+                    .await
+            }
+
+            async fn mark_processing(&self, withdrawal_id: Uuid, tx_hash: &str) -> Result<bool, sqlx::Error> {
+                let updated = sqlx::query(
+                    "UPDATE withdrawals SET status = $1, tx_hash = $2, updated_at = $3 WHERE withdrawal_id = $4 AND status = $5",
+                )
+                .bind(WithdrawalStatus::Processing)
+                .bind(tx_hash)
+                .bind(Utc::now())
+                .bind(withdrawal_id)
+                .bind(WithdrawalStatus::Requested)
+                .execute(&self.pool)
+                .await?;
+                Ok(updated.rows_affected() > 0)
+            }
+
+            async fn mark_completed(&self, withdrawal_id: Uuid) -> Result<bool, sqlx::Error> {
+                let now = Utc::now();
+                let updated = sqlx::query(
+                    "UPDATE withdrawals SET status = $1, updated_at = $2, completed_at = $2 WHERE withdrawal_id = $3 AND status = $4",
+                )
+                .bind(WithdrawalStatus::Completed)
+                .bind(now)
+                .bind(withdrawal_id)
+                .bind(WithdrawalStatus::Processing)
+                .execute(&self.pool)
+                .await?;
+                Ok(updated.rows_affected() > 0)
+            }
+
+            async fn mark_failed(&self, withdrawal_id: Uuid) -> Result<bool, sqlx::Error> {
+                self.release_withdrawal(withdrawal_id, WithdrawalStatus::Processing, WithdrawalStatus::Failed)
+                    .await
+            }
+        }
+
+        #[async_trait]
+        impl PremiumLedgerRepository for SqlRepository<$db> {
+            async fn list_for_user(
+                &self,
+                user_id: Uuid,
+                limit: i64,
+                offset: i64,
+            ) -> Result<(Vec<PremiumLedgerEntry>, i64), sqlx::Error> {
+                let total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM premium_ledger WHERE user_id = $1")
+                    .bind(user_id)
+                    .fetch_one(&self.pool)
+                    .await?;
+
+                let entries = sqlx::query_as::<_, PremiumLedgerEntry>(
+                    "SELECT * FROM premium_ledger WHERE user_id = $1 ORDER BY created_at DESC, entry_id LIMIT $2 OFFSET $3",
+                )
+                .bind(user_id)
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(&self.pool)
+                .await?;
+
+                Ok((entries, total.0))
+            }
+
+            async fn entries_for_order(&self, order_id: Uuid) -> Result<Vec<PremiumLedgerEntry>, sqlx::Error> {
+                sqlx::query_as::<_, PremiumLedgerEntry>(
+                    "SELECT * FROM premium_ledger WHERE order_id = $1 ORDER BY created_at ASC",
+                )
+                .bind(order_id)
+                .fetch_all(&self.pool)
+                .await
+            }
+
+            async fn balance_mismatches(&self, limit: i64) -> Result<Vec<BalanceMismatch>, sqlx::Error> {
+                // PostgreSQL 的 SUM(BIGINT) 返回 NUMERIC，显式转换为 BIGINT
+                sqlx::query_as::<_, BalanceMismatch>(
+                    r#"
+                    SELECT u.user_id, u.premium_balance, CAST(COALESCE(SUM(l.amount), 0) AS BIGINT) AS ledger_balance
+                    FROM users u
+                    LEFT JOIN premium_ledger l ON l.user_id = u.user_id
+                    GROUP BY u.user_id, u.premium_balance
+                    HAVING u.premium_balance <> CAST(COALESCE(SUM(l.amount), 0) AS BIGINT)
+                    LIMIT $1
+                    "#,
+                )
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await
+            }
+
+            async fn grant_free(
+                &self,
+                user_id: Uuid,
+                period_index: i64,
+                amount: i64,
+                granted_at: DateTime<Utc>,
+            ) -> Result<bool, sqlx::Error> {
+                let mut tx = self.pool.begin().await?;
+                let transaction_id = Uuid::new_v4();
+
+                let inserted = sqlx::query(
+                    r#"
+                    INSERT INTO premium_grants (user_id, period_index, amount, transaction_id, granted_at)
+                    VALUES ($1, $2, $3, $4, $5)
+                    ON CONFLICT (user_id, period_index) DO NOTHING
+                    "#,
+                )
+                .bind(user_id)
+                .bind(period_index)
+                .bind(amount)
+                .bind(transaction_id)
+                .bind(granted_at)
+                .execute(&mut *tx)
+                .await?;
+                if inserted.rows_affected() == 0 {
+                    return Ok(false);
+                }
+
+                Self::post_ledger_entries(&mut tx, &free_grant_entries(transaction_id, user_id, amount), false).await?;
+                tx.commit().await?;
+                Ok(true)
+            }
+        }
+
+        #[async_trait]
+        impl ChainEventRepository for SqlRepository<$db> {
+            async fn cursor(&self, name: &str) -> Result<Option<ChainCursor>, sqlx::Error> {
+                sqlx::query_as::<_, ChainCursor>("SELECT * FROM chain_cursors WHERE name = $1")
+                    .bind(name)
+                    .fetch_optional(&self.pool)
+                    .await
+            }
+
+            async fn save_batch(&self, events: &[ChainEvent], cursor: &ChainCursor) -> Result<(), sqlx::Error> {
+                let mut tx = self.pool.begin().await?;
+
+                for event in events {
+                    sqlx::query(
+                        r#"
+                        INSERT INTO chain_events (tx_hash, log_index, block_number, block_hash, event_name, picker_id, wallet_address, amount, order_id, created_at)
+                        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                        ON CONFLICT (tx_hash, log_index) DO NOTHING
+                        "#,
+                    )
+                    .bind(&event.tx_hash)
+                    .bind(event.log_index)
+                    .bind(event.block_number)
+                    .bind(&event.block_hash)
+                    .bind(&event.event_name)
+                    .bind(event.picker_id)
+                    .bind(&event.wallet_address)
+                    .bind(&event.amount)
+                    .bind(event.order_id)
+                    .bind(event.created_at)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+
+                sqlx::query(UPSERT_CHAIN_CURSOR)
+                    .bind(&cursor.name)
+                    .bind(cursor.block_number)
+                    .bind(&cursor.block_hash)
+                    .bind(cursor.updated_at)
+                    .execute(&mut *tx)
+                    .await?;
+
+                tx.commit().await
+            }
+
+            async fn rewind(&self, cursor: &ChainCursor) -> Result<u64, sqlx::Error> {
+                let mut tx = self.pool.begin().await?;
+
+                let deleted = sqlx::query("DELETE FROM chain_events WHERE block_number > $1")
+                    .bind(cursor.block_number)
+                    .execute(&mut *tx)
+                    .await?;
+
+                sqlx::query(UPSERT_CHAIN_CURSOR)
+                    .bind(&cursor.name)
+                    .bind(cursor.block_number)
+                    .bind(&cursor.block_hash)
+                    .bind(cursor.updated_at)
+                    .execute(&mut *tx)
+                    .await?;
+
+                tx.commit().await?;
+                Ok(deleted.rows_affected())
+            }
+
+            async fn events_for_order(&self, order_id: Uuid) -> Result<Vec<ChainEvent>, sqlx::Error> {
+                sqlx::query_as::<_, ChainEvent>(
+                    "SELECT * FROM chain_events WHERE order_id = $1 ORDER BY block_number, log_index",
+                )
+                .bind(order_id)
+                .fetch_all(&self.pool)
+                .await
+            }
+        }
+    };
+}
+
+impl_sql_repository!(Sqlite);
+impl_sql_repository!(Postgres);
@@ -0,0 +1,427 @@
+use chrono::Utc;
+use serde::Deserialize;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::config::{AppState, SeedProfile};
+use crate::database::{insert_test_data, Database};
+use crate::models::{Order, OrderStatus, PayType, Picker, PickerChainStatus, User, UserType};
+use crate::keyring::WalletKeyring;
+use crate::utils::{generate_wallet, AppError, PasswordHashing};
+
+// 夹具集合，可从 TOML 或 JSON 文件加载
+// 所有记录使用固定 ID，已存在的记录会被跳过，重复加载是幂等的
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Fixtures {
+    #[serde(default)]
+    pub users: Vec<UserFixture>,
+    #[serde(default)]
+    pub pickers: Vec<PickerFixture>,
+    #[serde(default)]
+    pub orders: Vec<OrderFixture>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserFixture {
+    pub user_id: Uuid,
+    pub email: String,
+    pub user_name: String,
+    // 明文密码，写入时按配置的 salt 哈希
+    pub password: String,
+    pub user_type: UserType,
+    #[serde(default)]
+    pub premium_balance: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PickerFixture {
+    pub picker_id: Uuid,
+    pub dev_user_id: Uuid,
+    pub alias: String,
+    pub description: String,
+    pub price: i64,
+    #[serde(default)]
+    pub image_path: String,
+    #[serde(default)]
+    pub file_path: String,
+    #[serde(default = "default_picker_version")]
+    pub version: String,
+    #[serde(default = "default_picker_status")]
+    pub status: String,
+    #[serde(default)]
+    pub download_count: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OrderFixture {
+    pub order_id: Uuid,
+    pub user_id: Uuid,
+    pub picker_id: Uuid,
+    pub pay_type: PayType,
+    pub status: OrderStatus,
+    pub amount: i64,
+    pub tx_hash: Option<String>,
+}
+
+fn default_picker_version() -> String {
+    "1.0.0".to_string()
+}
+
+fn default_picker_status() -> String {
+    "active".to_string()
+}
+
+/// 从文件加载夹具，按扩展名识别 TOML 或 JSON
+pub fn load_fixtures(path: &str) -> Result<Fixtures, config::ConfigError> {
+    config::Config::builder()
+        .add_source(config::File::from(std::path::Path::new(path)))
+        .build()?
+        .try_deserialize()
+}
+
+/// 将夹具写入数据库，用户密码按传入的哈希配置处理，钱包私钥使用传入的主密钥加密
+/// 已存在的记录（按 ID）会被跳过
+pub async fn apply_fixtures(
+    db: &Database,
+    fixtures: &Fixtures,
+    hashing: &PasswordHashing,
+    keyring: &WalletKeyring,
+) -> Result<(), sqlx::Error> {
+    let (users, pickers, orders) = (db.users(), db.pickers(), db.orders());
+    let now = Utc::now();
+
+    for user in &fixtures.users {
+        if users.find_by_id(user.user_id).await?.is_some() {
+            continue;
+        }
+        let (private_key, wallet_address) = generate_wallet(keyring)
+            .map_err(|_| sqlx::Error::Protocol("failed to encrypt fixture wallet key".to_string()))?;
+        let user_password = hashing
+            .hash(&user.password)
+            .map_err(|_| sqlx::Error::Protocol("failed to hash fixture password".to_string()))?;
+        users
+            .create(&User {
+                user_id: user.user_id,
+                email: user.email.clone(),
+                user_name: user.user_name.clone(),
+                user_password,
+                user_type: user.user_type.clone(),
+                wallet_address,
+                private_key,
+                premium_balance: user.premium_balance,
+                created_at: now,
+            })
+            .await?;
+    }
+
+    for picker in &fixtures.pickers {
+        if pickers.find_by_id(picker.picker_id).await?.is_some() {
+            continue;
+        }
+        pickers
+            .create(&Picker {
+                picker_id: picker.picker_id,
+                dev_user_id: picker.dev_user_id,
+                alias: picker.alias.clone(),
+                description: picker.description.clone(),
+                price: picker.price,
+                file_path: picker.file_path.clone(),
+                download_count: picker.download_count,
+                created_at: now,
+                updated_at: now,
+                image_path: picker.image_path.clone(),
+                version: picker.version.clone(),
+                status: picker.status.clone(),
+                chain_status: PickerChainStatus::Unregistered,
+                chain_tx_hash: None,
+                deleted_at: None,
+            })
+            .await?;
+    }
+
+    for order in &fixtures.orders {
+        if orders.find_by_id(order.order_id).await?.is_some() {
+            continue;
+        }
+        orders
+            .create(&Order {
+                order_id: order.order_id,
+                user_id: order.user_id,
+                picker_id: order.picker_id,
+                amount: order.amount,
+                pay_type: order.pay_type.clone(),
+                status: order.status.clone(),
+                tx_hash: order.tx_hash.clone(),
+                created_at: now,
+                expires_at: None,
+            })
+            .await?;
+    }
+
+    info!(
+        "Applied fixtures: {} users, {} pickers, {} orders",
+        fixtures.users.len(),
+        fixtures.pickers.len(),
+        fixtures.orders.len()
+    );
+    Ok(())
+}
+
+/// 内置开发数据：testdata@openpick.org 开发者账号、一个测试 Picker 和一笔待支付的钱包订单
+/// 使用固定的 UUID 以确保幂等性
+pub fn dev_fixtures() -> Fixtures {
+    let test_user_id = Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000").unwrap();
+    let test_picker_id = Uuid::parse_str("550e8400-e29b-41d4-a716-446655440001").unwrap();
+    let test_order_id = Uuid::parse_str("550e8400-e29b-41d4-a716-446655440002").unwrap();
+    let test_picker_price = 1;
+
+    Fixtures {
+        users: vec![UserFixture {
+            user_id: test_user_id,
+            email: "testdata@openpick.org".to_string(),
+            user_name: "Test Data User".to_string(),
+            password: "testpassword".to_string(),
+            // 设置为开发者类型，可以创建picker
+            user_type: UserType::Dev,
+            premium_balance: 1000,
+        }],
+        pickers: vec![PickerFixture {
+            picker_id: test_picker_id,
+            dev_user_id: test_user_id,
+            alias: "testpicker".to_string(),
+            description: "testpicker description".to_string(),
+            price: test_picker_price,
+            image_path: "test_image.jpg".to_string(),
+            file_path: "test_picker.zip".to_string(),
+            version: default_picker_version(),
+            status: default_picker_status(),
+            download_count: 0,
+        }],
+        orders: vec![OrderFixture {
+            order_id: test_order_id,
+            user_id: test_user_id,
+            picker_id: test_picker_id,
+            pay_type: PayType::Wallet,
+            status: OrderStatus::Pending,
+            amount: test_picker_price,
+            tx_hash: None,
+        }],
+    }
+}
+
+/// 加载夹具文件并写入数据库
+pub async fn load_and_apply_fixtures(state: &AppState, path: &str) -> Result<(), AppError> {
+    let fixtures = load_fixtures(path).map_err(|e| {
+        error!("Failed to load fixtures from {}: {}", path, e);
+        AppError::InternalServerError
+    })?;
+
+    apply_fixtures(
+        &state.db,
+        &fixtures,
+        &state.password_hashing,
+        &state.wallet_keyring,
+    )
+    .await
+    .map_err(|e| {
+        error!("Failed to apply fixtures from {}: {}", path, e);
+        AppError::DatabaseError
+    })
+}
+
+/// 按 seed.profile 写入种子数据
+/// prod: 不写入；dev: 内置开发数据 + 夹具文件；test: 仅夹具文件
+pub async fn seed_database(state: &AppState) -> Result<(), AppError> {
+    match state.seed_profile {
+        SeedProfile::Prod => {
+            info!("Seed profile is prod, skipping seed data");
+            return Ok(());
+        }
+        SeedProfile::Dev => {
+            insert_test_data(state.db.clone()).await.map_err(|e| {
+                error!("Failed to insert dev seed data: {}", e);
+                AppError::DatabaseError
+            })?;
+        }
+        SeedProfile::Test => {}
+    }
+
+    if let Some(path) = &state.seed_fixtures {
+        load_and_apply_fixtures(state, path).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{create_memory_pool, init_database};
+    use crate::models::{Order, Picker, User};
+    use crate::utils::verify_password_with_user_id;
+    use crate::utils_tests::test_pool;
+    use std::io::Write;
+
+    const FIXTURE_TOML: &str = r#"
+[[users]]
+user_id = "6f1c1a52-0000-4000-8000-000000000001"
+email = "dev@fixture.test"
+user_name = "Fixture Dev"
+password = "devpassword"
+user_type = "dev"
+
+[[users]]
+user_id = "6f1c1a52-0000-4000-8000-000000000002"
+email = "buyer@fixture.test"
+user_name = "Fixture Buyer"
+password = "buyerpassword"
+user_type = "gen"
+premium_balance = 50
+
+[[pickers]]
+picker_id = "6f1c1a52-0000-4000-8000-000000000101"
+dev_user_id = "6f1c1a52-0000-4000-8000-000000000001"
+alias = "fixture-picker"
+description = "fixture picker"
+price = 10
+
+[[orders]]
+order_id = "6f1c1a52-0000-4000-8000-000000000201"
+user_id = "6f1c1a52-0000-4000-8000-000000000002"
+picker_id = "6f1c1a52-0000-4000-8000-000000000101"
+pay_type = "premium"
+status = "success"
+amount = 10
+"#;
+
+    fn write_fixture(extension: &str, content: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::Builder::new()
+            .suffix(extension)
+            .tempfile()
+            .expect("Failed to create fixture file");
+        file.write_all(content.as_bytes()).unwrap();
+        file
+    }
+
+    async fn create_seed_state(profile: SeedProfile, fixtures: Option<String>) -> AppState {
+        let pool = create_memory_pool().await.expect("Failed to create in-memory pool");
+        init_database(&pool).await.expect("Failed to init database");
+        let mut state = AppState::new(pool);
+        state.seed_profile = profile;
+        state.seed_fixtures = fixtures;
+        state
+    }
+
+    async fn count_users(state: &AppState) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(test_pool(state))
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_load_fixtures_toml() {
+        let file = write_fixture(".toml", FIXTURE_TOML);
+        let fixtures = load_fixtures(file.path().to_str().unwrap()).expect("Failed to load TOML fixtures");
+
+        assert_eq!(fixtures.users.len(), 2);
+        assert_eq!(fixtures.users[0].user_type, UserType::Dev);
+        assert_eq!(fixtures.users[1].premium_balance, 50);
+        assert_eq!(fixtures.pickers[0].version, "1.0.0");
+        assert_eq!(fixtures.pickers[0].status, "active");
+        assert_eq!(fixtures.orders[0].pay_type, PayType::Premium);
+        assert!(fixtures.orders[0].tx_hash.is_none());
+    }
+
+    #[test]
+    fn test_load_fixtures_json() {
+        let json = r#"{
+            "users": [{
+                "user_id": "6f1c1a52-0000-4000-8000-000000000003",
+                "email": "json@fixture.test",
+                "user_name": "Json User",
+                "password": "jsonpassword",
+                "user_type": "gen"
+            }]
+        }"#;
+        let file = write_fixture(".json", json);
+        let fixtures = load_fixtures(file.path().to_str().unwrap()).expect("Failed to load JSON fixtures");
+
+        assert_eq!(fixtures.users.len(), 1);
+        assert_eq!(fixtures.users[0].email, "json@fixture.test");
+        assert!(fixtures.pickers.is_empty());
+        assert!(fixtures.orders.is_empty());
+    }
+
+    #[test]
+    fn test_load_fixtures_missing_file() {
+        assert!(load_fixtures("does/not/exist.toml").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_apply_fixtures_idempotent() {
+        let file = write_fixture(".toml", FIXTURE_TOML);
+        let state = create_seed_state(SeedProfile::Test, None).await;
+
+        load_and_apply_fixtures(&state, file.path().to_str().unwrap()).await.expect("First load should succeed");
+        load_and_apply_fixtures(&state, file.path().to_str().unwrap()).await.expect("Second load should be a no-op");
+        assert_eq!(count_users(&state).await, 2);
+
+        let buyer = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = ?")
+            .bind("buyer@fixture.test")
+            .fetch_one(test_pool(&state))
+            .await
+            .unwrap();
+        assert_eq!(buyer.premium_balance, 50);
+        assert!(verify_password_with_user_id("buyerpassword", buyer.user_id, &buyer.user_password, &state.password_salt));
+
+        let picker = sqlx::query_as::<_, Picker>("SELECT * FROM pickers")
+            .fetch_one(test_pool(&state))
+            .await
+            .unwrap();
+        assert_eq!(picker.alias, "fixture-picker");
+
+        let order = sqlx::query_as::<_, Order>("SELECT * FROM orders")
+            .fetch_one(test_pool(&state))
+            .await
+            .unwrap();
+        assert_eq!(order.status, OrderStatus::Success);
+        assert_eq!(order.user_id, buyer.user_id);
+    }
+
+    #[tokio::test]
+    async fn test_seed_database_prod_skips() {
+        let file = write_fixture(".toml", FIXTURE_TOML);
+        let state = create_seed_state(SeedProfile::Prod, Some(file.path().to_str().unwrap().to_string())).await;
+
+        seed_database(&state).await.expect("Seeding should succeed");
+        assert_eq!(count_users(&state).await, 0, "prod profile must not insert any data");
+    }
+
+    #[tokio::test]
+    async fn test_seed_database_dev_inserts_builtin_data() {
+        let state = create_seed_state(SeedProfile::Dev, None).await;
+
+        seed_database(&state).await.expect("Seeding should succeed");
+        let email: String = sqlx::query_scalar("SELECT email FROM users")
+            .fetch_one(test_pool(&state))
+            .await
+            .unwrap();
+        assert_eq!(email, "testdata@openpick.org");
+    }
+
+    #[tokio::test]
+    async fn test_seed_database_test_loads_fixtures_only() {
+        let file = write_fixture(".toml", FIXTURE_TOML);
+        let state = create_seed_state(SeedProfile::Test, Some(file.path().to_str().unwrap().to_string())).await;
+
+        seed_database(&state).await.expect("Seeding should succeed");
+        assert_eq!(count_users(&state).await, 2);
+
+        let builtin: Option<String> = sqlx::query_scalar("SELECT email FROM users WHERE email = 'testdata@openpick.org'")
+            .fetch_optional(test_pool(&state))
+            .await
+            .unwrap();
+        assert!(builtin.is_none(), "test profile should not insert the built-in dev account");
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::{PayType, Sale, UserType};
+use crate::repository::{OrderRepository, PickerRepository, UserRepository};
+use crate::services::orders::premium_dev_income;
+use crate::utils::AppError;
+
+/// 单次查询的最长时间范围
+pub const MAX_EARNINGS_DAYS: i64 = 366;
+
+/// 销售汇总：Premium 订单按积分计，钱包订单按 Picker 价格（USD）计
+#[derive(Debug, Clone, Default, PartialEq, Serialize, ToSchema)]
+pub struct EarningsTotals {
+    pub premium_orders: i64,
+    // Premium 订单的销售额（积分）
+    pub premium_amount: i64,
+    // 扣除平台手续费后开发者实得的积分
+    pub premium_income: i64,
+    pub wallet_orders: i64,
+    // 钱包订单的销售额（USD）
+    pub wallet_amount: i64,
+}
+
+impl EarningsTotals {
+    fn add(&mut self, sale: &Sale, payment_rate: i64) {
+        match sale.pay_type {
+            PayType::Premium => {
+                self.premium_orders += 1;
+                self.premium_amount += sale.amount;
+                // 启用流水前的订单没有记录开发者收入，按当前 payment_rate 估算
+                self.premium_income += sale.dev_income.unwrap_or_else(|| premium_dev_income(sale.amount, payment_rate));
+            }
+            PayType::Wallet => {
+                self.wallet_orders += 1;
+                self.wallet_amount += sale.amount;
+            }
+        }
+    }
+}
+
+/// 单个 Picker 的销售汇总
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct PickerEarnings {
+    pub picker_id: Uuid,
+    pub alias: String,
+    pub totals: EarningsTotals,
+}
+
+/// 单日（UTC）的销售汇总
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct DailyEarnings {
+    pub date: NaiveDate,
+    pub totals: EarningsTotals,
+}
+
+/// 开发者在 [from, to) 内的销售汇总
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct DeveloperEarnings {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    // 当前可提现的积分余额
+    pub premium_balance: i64,
+    pub totals: EarningsTotals,
+    // 按订单数从多到少排列，只包含有销售的 Picker
+    pub pickers: Vec<PickerEarnings>,
+    // 按日期升序排列，没有销售的日期为 0
+    pub days: Vec<DailyEarnings>,
+}
+
+/// 汇总开发者名下 Picker 在 [from, to) 内的成功订单（已退款的订单不计入）
+pub async fn developer_earnings(
+    users: &dyn UserRepository,
+    pickers: &dyn PickerRepository,
+    orders: &dyn OrderRepository,
+    user_id: Uuid,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    payment_rate: i64,
+) -> Result<DeveloperEarnings, AppError> {
+    if from >= to {
+        return Err(AppError::BadRequest("from must be earlier than to".to_string()));
+    }
+    if to - from > Duration::days(MAX_EARNINGS_DAYS) {
+        return Err(AppError::BadRequest(format!(
+            "Time range cannot exceed {} days",
+            MAX_EARNINGS_DAYS
+        )));
+    }
+
+    let user = users
+        .find_by_id(user_id)
+        .await
+        .map_err(|_| AppError::DatabaseError)?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    if user.user_type != UserType::Dev {
+        return Err(AppError::BadRequest("Only developers have earnings".to_string()));
+    }
+
+    let sales = orders
+        .list_sales(user_id, from, to)
+        .await
+        .map_err(|_| AppError::DatabaseError)?;
+
+    let mut totals = EarningsTotals::default();
+    let mut by_picker: HashMap<Uuid, EarningsTotals> = HashMap::new();
+    let mut by_day: BTreeMap<NaiveDate, EarningsTotals> = BTreeMap::new();
+    let last_day = (to - Duration::nanoseconds(1)).date_naive();
+    let mut day = from.date_naive();
+    while day <= last_day {
+        by_day.insert(day, EarningsTotals::default());
+        day = day.succ_opt().unwrap_or(NaiveDate::MAX);
+    }
+
+    for sale in &sales {
+        totals.add(sale, payment_rate);
+        by_picker.entry(sale.picker_id).or_default().add(sale, payment_rate);
+        by_day.entry(sale.created_at.date_naive()).or_default().add(sale, payment_rate);
+    }
+
+    let mut picker_earnings = Vec::with_capacity(by_picker.len());
+    for (picker_id, totals) in by_picker {
+        let alias = pickers
+            .find_by_id(picker_id)
+            .await
+            .map_err(|_| AppError::DatabaseError)?
+            .map(|picker| picker.alias)
+            .unwrap_or_default();
+        picker_earnings.push(PickerEarnings { picker_id, alias, totals });
+    }
+    picker_earnings.sort_by(|a, b| {
+        let orders = |p: &PickerEarnings| p.totals.premium_orders + p.totals.wallet_orders;
+        orders(b).cmp(&orders(a)).then_with(|| a.alias.cmp(&b.alias))
+    });
+
+    Ok(DeveloperEarnings {
+        from,
+        to,
+        premium_balance: user.premium_balance,
+        totals,
+        pickers: picker_earnings,
+        days: by_day
+            .into_iter()
+            .map(|(date, totals)| DailyEarnings { date, totals })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Order, OrderStatus, Picker};
+    use crate::repository::fixtures::{self, user};
+    use crate::repository::MemoryRepository;
+    use chrono::TimeZone;
+
+    fn picker(dev_user_id: Uuid, alias: &str) -> Picker {
+        Picker {
+            alias: alias.to_string(),
+            description: format!("{} description", alias),
+            ..fixtures::picker(dev_user_id, 100)
+        }
+    }
+
+    fn order(user_id: Uuid, picker_id: Uuid, pay_type: PayType, status: OrderStatus, created_at: DateTime<Utc>) -> Order {
+        Order { amount: 100, created_at, ..fixtures::order(user_id, picker_id, pay_type, status) }
+    }
+
+    fn day(d: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, d, hour, 0, 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_aggregates_sales_per_picker_and_day() {
+        let repo = MemoryRepository::new();
+        let dev = user("dev@example.com", UserType::Dev, 0);
+        let other_dev = user("other@example.com", UserType::Dev, 0);
+        let buyer = user("buyer@example.com", UserType::Gen, 1000);
+        for u in [&dev, &other_dev, &buyer] {
+            UserRepository::create(&repo, u).await.unwrap();
+        }
+        let translate = picker(dev.user_id, "Translate");
+        let screenshot = picker(dev.user_id, "Screenshot");
+        let foreign = picker(other_dev.user_id, "Foreign");
+        for p in [&translate, &screenshot, &foreign] {
+            PickerRepository::create(&repo, p).await.unwrap();
+        }
+
+        // Premium 订单按流水中的开发者收入计，启用流水前的订单按 payment_rate 估算
+        let paid = order(buyer.user_id, translate.picker_id, PayType::Premium, OrderStatus::Success, day(1, 9));
+        OrderRepository::settle_premium(&repo, &paid, dev.user_id, 80).await.unwrap();
+        let legacy = order(buyer.user_id, translate.picker_id, PayType::Premium, OrderStatus::Success, day(3, 23));
+        OrderRepository::create(&repo, &legacy).await.unwrap();
+        let wallet = order(buyer.user_id, screenshot.picker_id, PayType::Wallet, OrderStatus::Success, day(3, 1));
+        OrderRepository::create(&repo, &wallet).await.unwrap();
+        // 未支付、已退款、其他开发者与时间范围外的订单不计入
+        for (picker_id, status, created_at) in [
+            (screenshot.picker_id, OrderStatus::Pending, day(2, 0)),
+            (screenshot.picker_id, OrderStatus::Refunded, day(2, 0)),
+            (foreign.picker_id, OrderStatus::Success, day(2, 0)),
+            (translate.picker_id, OrderStatus::Success, day(4, 0)),
+        ] {
+            let ignored = order(buyer.user_id, picker_id, PayType::Wallet, status, created_at);
+            OrderRepository::create(&repo, &ignored).await.unwrap();
+        }
+
+        let earnings = developer_earnings(&repo, &repo, &repo, dev.user_id, day(1, 0), day(4, 0), 10)
+            .await
+            .unwrap();
+        assert_eq!(earnings.premium_balance, 80);
+        assert_eq!(
+            earnings.totals,
+            EarningsTotals {
+                premium_orders: 2,
+                premium_amount: 200,
+                premium_income: 170,
+                wallet_orders: 1,
+                wallet_amount: 100,
+            }
+        );
+
+        assert_eq!(earnings.pickers.len(), 2);
+        assert_eq!(earnings.pickers[0].alias, "Translate");
+        assert_eq!(earnings.pickers[0].totals.premium_income, 170);
+        assert_eq!(earnings.pickers[1].alias, "Screenshot");
+        assert_eq!(earnings.pickers[1].totals.wallet_orders, 1);
+
+        let days: Vec<(NaiveDate, i64, i64)> = earnings
+            .days
+            .iter()
+            .map(|d| (d.date, d.totals.premium_orders, d.totals.wallet_orders))
+            .collect();
+        assert_eq!(
+            days,
+            vec![
+                (day(1, 0).date_naive(), 1, 0),
+                (day(2, 0).date_naive(), 0, 0),
+                (day(3, 0).date_naive(), 1, 1),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_invalid_requests() {
+        let repo = MemoryRepository::new();
+        let dev = user("dev@example.com", UserType::Dev, 0);
+        let buyer = user("buyer@example.com", UserType::Gen, 0);
+        UserRepository::create(&repo, &dev).await.unwrap();
+        UserRepository::create(&repo, &buyer).await.unwrap();
+
+        let result = developer_earnings(&repo, &repo, &repo, buyer.user_id, day(1, 0), day(2, 0), 10).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+        let result = developer_earnings(&repo, &repo, &repo, dev.user_id, day(2, 0), day(1, 0), 10).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+        let result = developer_earnings(
+            &repo,
+            &repo,
+            &repo,
+            dev.user_id,
+            day(1, 0) - Duration::days(MAX_EARNINGS_DAYS + 1),
+            day(1, 0),
+            10,
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+        let result = developer_earnings(&repo, &repo, &repo, Uuid::new_v4(), day(1, 0), day(2, 0), 10).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+}
@@ -0,0 +1,533 @@
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::{Address, B256};
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use alloy::rpc::types::{Filter, Log};
+use alloy::sol_types::SolEventInterface;
+use async_trait::async_trait;
+use chrono::Utc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::contract::{bytes16_to_uuid, PickerPayment::PickerPaymentEvents};
+use crate::models::{ChainCursor, ChainEvent, OrderStatus, PayType};
+use crate::repository::{ChainEventRepository, OrderRepository};
+use crate::utils::AppError;
+
+/// PickerPayment 事件索引进度在 chain_cursors 中的名称
+pub const PAYMENT_CURSOR: &str = "picker_payment";
+
+/// 区块与日志来源，生产环境通过 JSON-RPC 查询，测试中可替换
+#[async_trait]
+pub trait LogSource: Send + Sync {
+    async fn latest_block(&self) -> Result<u64, AppError>;
+    async fn block_hash(&self, number: u64) -> Result<Option<B256>, AppError>;
+    async fn logs(&self, address: Address, from_block: u64, to_block: u64) -> Result<Vec<Log>, AppError>;
+}
+
+/// 通过 blockchain.rpc_url 查询区块与 eth_getLogs
+pub struct RpcLogSource {
+    provider: DynProvider,
+}
+
+impl RpcLogSource {
+    pub fn new(rpc_url: &str) -> Result<Self, AppError> {
+        let url = rpc_url.parse().map_err(|e| {
+            error!("Invalid RPC URL: {}", e);
+            AppError::InternalServerError
+        })?;
+        Ok(Self {
+            provider: ProviderBuilder::new().connect_http(url).erased(),
+        })
+    }
+}
+
+fn rpc_error(action: &str, e: impl std::fmt::Display) -> AppError {
+    error!("Failed to {}: {}", action, e);
+    AppError::InternalServerError
+}
+
+#[async_trait]
+impl LogSource for RpcLogSource {
+    async fn latest_block(&self) -> Result<u64, AppError> {
+        self.provider
+            .get_block_number()
+            .await
+            .map_err(|e| rpc_error("fetch latest block number", e))
+    }
+
+    async fn block_hash(&self, number: u64) -> Result<Option<B256>, AppError> {
+        let block = self
+            .provider
+            .get_block_by_number(BlockNumberOrTag::Number(number))
+            .await
+            .map_err(|e| rpc_error("fetch block", e))?;
+        Ok(block.map(|block| block.header.hash))
+    }
+
+    async fn logs(&self, address: Address, from_block: u64, to_block: u64) -> Result<Vec<Log>, AppError> {
+        let filter = Filter::new().address(address).from_block(from_block).to_block(to_block);
+        self.provider
+            .get_logs(&filter)
+            .await
+            .map_err(|e| rpc_error("fetch logs", e))
+    }
+}
+
+/// 索引参数，来自 [indexer] 配置与 blockchain.authorized_contract_address
+#[derive(Debug, Clone)]
+pub struct IndexerSettings {
+    pub contract: Address,
+    pub start_block: u64,
+    pub confirmations: u64,
+    pub batch_blocks: u64,
+}
+
+/// 一次索引的结果
+#[derive(Debug, Default, PartialEq)]
+pub struct IndexReport {
+    // 本次处理的区块范围，没有新的已确认区块时为 None
+    pub blocks: Option<(u64, u64)>,
+    // 写入的事件数
+    pub events: usize,
+    // 通过 PaymentProcessed 确认为 success 的订单数
+    pub confirmed: usize,
+    // 检测到重组时删除的事件数
+    pub rewound: u64,
+    // 已追上最新的已确认区块
+    pub caught_up: bool,
+}
+
+/// 索引一批已确认区块中的 PickerPayment 事件
+/// 进度记录已处理的最后一个区块及其哈希；下次执行时哈希不一致说明发生了重组，
+/// 回退 confirmations 个区块并删除这些区块中的事件，下次执行时重新索引
+pub async fn index_payment_events(
+    source: &dyn LogSource,
+    chain_events: &dyn ChainEventRepository,
+    orders: &dyn OrderRepository,
+    settings: &IndexerSettings,
+) -> Result<IndexReport, AppError> {
+    let cursor = chain_events.cursor(PAYMENT_CURSOR).await.map_err(|e| {
+        error!("Failed to load indexer cursor: {}", e);
+        AppError::DatabaseError
+    })?;
+
+    let mut report = IndexReport::default();
+    let from_block = match cursor {
+        Some(cursor) => {
+            let processed = cursor.block_number as u64;
+            let canonical = source.block_hash(processed).await?;
+            if canonical.map(|hash| format!("{:#x}", hash)).as_deref() != Some(cursor.block_hash.as_str()) {
+                report.rewound = rewind(source, chain_events, settings, processed).await?;
+                return Ok(report);
+            }
+            processed + 1
+        }
+        None => settings.start_block,
+    };
+
+    let safe_head = source.latest_block().await?.saturating_sub(settings.confirmations);
+    if from_block > safe_head {
+        report.caught_up = true;
+        return Ok(report);
+    }
+    let to_block = safe_head.min(from_block + settings.batch_blocks.max(1) - 1);
+    let to_hash = source.block_hash(to_block).await?.ok_or_else(|| {
+        error!("Block {} not found", to_block);
+        AppError::InternalServerError
+    })?;
+
+    let logs = source.logs(settings.contract, from_block, to_block).await?;
+    let mut events = Vec::new();
+    let mut matched_orders = Vec::new();
+    for log in logs {
+        let Some(mut event) = decode_event(&log) else {
+            continue;
+        };
+        if event.event_name == "PaymentProcessed" {
+            if let Some((order_id, picker_id)) = match_order(orders, &event).await? {
+                event.order_id = Some(order_id);
+                matched_orders.push((order_id, picker_id));
+            }
+        }
+        events.push(event);
+    }
+
+    let cursor = ChainCursor {
+        name: PAYMENT_CURSOR.to_string(),
+        block_number: to_block as i64,
+        block_hash: format!("{:#x}", to_hash),
+        updated_at: Utc::now(),
+    };
+    chain_events.save_batch(&events, &cursor).await.map_err(|e| {
+        error!("Failed to save chain events: {}", e);
+        AppError::DatabaseError
+    })?;
+
+    // 事件已持久化，即使这里失败，订单也会由对账任务通过交易回执确认
+    for (order_id, picker_id) in matched_orders {
+        let confirmed = orders.mark_success(order_id, picker_id).await.map_err(|e| {
+            error!("Failed to mark order {} as success: {}", order_id, e);
+            AppError::DatabaseError
+        })?;
+        if confirmed {
+            info!("Order {} confirmed by PaymentProcessed event", order_id);
+            report.confirmed += 1;
+        }
+    }
+
+    report.blocks = Some((from_block, to_block));
+    report.events = events.len();
+    report.caught_up = to_block == safe_head;
+    Ok(report)
+}
+
+// 回退到 processed - confirmations（不早于 start_block 之前的区块）
+async fn rewind(
+    source: &dyn LogSource,
+    chain_events: &dyn ChainEventRepository,
+    settings: &IndexerSettings,
+    processed: u64,
+) -> Result<u64, AppError> {
+    let target = processed
+        .saturating_sub(settings.confirmations.max(1))
+        .max(settings.start_block.saturating_sub(1));
+    let target_hash = source.block_hash(target).await?.ok_or_else(|| {
+        error!("Block {} not found while rewinding", target);
+        AppError::InternalServerError
+    })?;
+
+    let cursor = ChainCursor {
+        name: PAYMENT_CURSOR.to_string(),
+        block_number: target as i64,
+        block_hash: format!("{:#x}", target_hash),
+        updated_at: Utc::now(),
+    };
+    let deleted = chain_events.rewind(&cursor).await.map_err(|e| {
+        error!("Failed to rewind chain events: {}", e);
+        AppError::DatabaseError
+    })?;
+    warn!(
+        "Chain reorg detected at block {}, rewound to block {} ({} events removed)",
+        processed, target, deleted
+    );
+    Ok(deleted)
+}
+
+// 解码 PickerPayment 事件，其他合约事件、已被移除或缺少位置信息的日志返回 None
+fn decode_event(log: &Log) -> Option<ChainEvent> {
+    if log.removed {
+        return None;
+    }
+    let (tx_hash, log_index, block_number, block_hash) =
+        (log.transaction_hash?, log.log_index?, log.block_number?, log.block_hash?);
+
+    let (event_name, picker_id, wallet_address, amount) = match PickerPaymentEvents::decode_log(&log.inner).ok()?.data {
+        PickerPaymentEvents::PaymentProcessed(event) => {
+            ("PaymentProcessed", event.pickerId, None, Some(event.amount.to_string()))
+        }
+        PickerPaymentEvents::PickerRegistered(event) => {
+            ("PickerRegistered", event.pickerId, Some(event.wallet.to_checksum(None)), None)
+        }
+        PickerPaymentEvents::PickerRemoved(event) => ("PickerRemoved", event.pickerId, None, None),
+    };
+
+    Some(ChainEvent {
+        tx_hash: format!("{:#x}", tx_hash),
+        log_index: log_index as i64,
+        block_number: block_number as i64,
+        block_hash: format!("{:#x}", block_hash),
+        event_name: event_name.to_string(),
+        picker_id: bytes16_to_uuid(picker_id),
+        wallet_address,
+        amount,
+        order_id: None,
+        created_at: Utc::now(),
+    })
+}
+
+// 按交易哈希匹配钱包订单，Picker 不一致时视为不匹配；返回 (订单ID, PickerID)
+async fn match_order(orders: &dyn OrderRepository, event: &ChainEvent) -> Result<Option<(Uuid, Uuid)>, AppError> {
+    let order = orders.find_by_tx_hash(&event.tx_hash).await.map_err(|e| {
+        error!("Failed to look up order by tx hash {}: {}", event.tx_hash, e);
+        AppError::DatabaseError
+    })?;
+
+    Ok(match order {
+        Some(order) if order.pay_type == PayType::Wallet && order.picker_id == event.picker_id => {
+            if order.status == OrderStatus::Expired {
+                warn!("Payment {} arrived for expired order {}", event.tx_hash, order.order_id);
+            }
+            Some((order.order_id, order.picker_id))
+        }
+        Some(order) => {
+            warn!(
+                "Payment {} does not match order {} (picker {})",
+                event.tx_hash, order.order_id, order.picker_id
+            );
+            None
+        }
+        None => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use alloy::primitives::{address, FixedBytes, U256};
+    use alloy::sol_types::SolEvent;
+    use chrono::Duration;
+
+    use super::*;
+    use crate::contract::{uuid_to_bytes16, PickerPayment};
+    use crate::models::{Order, UserType};
+    use crate::repository::fixtures::{order, picker, user};
+    use crate::repository::{MemoryRepository, PickerRepository, UserRepository};
+
+    const CONTRACT: Address = address!("1B0b2ef32Eba0Aa15B123633e2145D708eD2C5E9");
+
+    // 内存中的链：head 为最新区块号，hashes 可修改以模拟重组
+    struct FakeChain {
+        head: Mutex<u64>,
+        hashes: Mutex<HashMap<u64, B256>>,
+        logs: Mutex<Vec<Log>>,
+    }
+
+    impl FakeChain {
+        fn new(head: u64) -> Self {
+            let hashes = (0..=head).map(|number| (number, block_hash(number, 0))).collect();
+            Self {
+                head: Mutex::new(head),
+                hashes: Mutex::new(hashes),
+                logs: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn push(&self, block_number: u64, tx_hash: B256, log_index: u64, data: alloy::primitives::LogData) {
+            let block_hash = self.hashes.lock().unwrap()[&block_number];
+            self.logs.lock().unwrap().push(Log {
+                inner: alloy::primitives::Log { address: CONTRACT, data },
+                block_hash: Some(block_hash),
+                block_number: Some(block_number),
+                block_timestamp: None,
+                transaction_hash: Some(tx_hash),
+                transaction_index: Some(0),
+                log_index: Some(log_index),
+                removed: false,
+            });
+        }
+
+        // 从 from 开始替换为另一条分叉上的区块，原有日志被丢弃
+        fn reorg(&self, from: u64, fork: u8) {
+            let head = *self.head.lock().unwrap();
+            let mut hashes = self.hashes.lock().unwrap();
+            for number in from..=head {
+                hashes.insert(number, block_hash(number, fork));
+            }
+            self.logs.lock().unwrap().retain(|log| log.block_number.unwrap() < from);
+        }
+
+        fn advance(&self, head: u64) {
+            let mut hashes = self.hashes.lock().unwrap();
+            for number in *self.head.lock().unwrap()..=head {
+                hashes.entry(number).or_insert_with(|| block_hash(number, 0));
+            }
+            *self.head.lock().unwrap() = head;
+        }
+    }
+
+    #[async_trait]
+    impl LogSource for FakeChain {
+        async fn latest_block(&self) -> Result<u64, AppError> {
+            Ok(*self.head.lock().unwrap())
+        }
+
+        async fn block_hash(&self, number: u64) -> Result<Option<B256>, AppError> {
+            Ok(self.hashes.lock().unwrap().get(&number).copied())
+        }
+
+        async fn logs(&self, address: Address, from_block: u64, to_block: u64) -> Result<Vec<Log>, AppError> {
+            Ok(self
+                .logs
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|log| log.address() == address)
+                .filter(|log| (from_block..=to_block).contains(&log.block_number.unwrap()))
+                .cloned()
+                .collect())
+        }
+    }
+
+    fn block_hash(number: u64, fork: u8) -> B256 {
+        let mut bytes = [fork; 32];
+        bytes[24..].copy_from_slice(&number.to_be_bytes());
+        B256::from(bytes)
+    }
+
+    fn payment(picker_id: Uuid, amount: u64) -> alloy::primitives::LogData {
+        PickerPayment::PaymentProcessed {
+            pickerId: uuid_to_bytes16(picker_id),
+            amount: U256::from(amount),
+        }
+        .encode_log_data()
+    }
+
+    fn registration(picker_id: Uuid) -> alloy::primitives::LogData {
+        PickerPayment::PickerRegistered {
+            pickerId: uuid_to_bytes16(picker_id),
+            wallet: address!("abcdef1234567890abcdef1234567890abcdef12"),
+        }
+        .encode_log_data()
+    }
+
+    fn settings(start_block: u64) -> IndexerSettings {
+        IndexerSettings {
+            contract: CONTRACT,
+            start_block,
+            confirmations: 2,
+            batch_blocks: 100,
+        }
+    }
+
+    // 写入买家、Picker 和一个待支付的钱包订单，返回 (仓储, 订单)
+    async fn setup(tx_hash: B256) -> (MemoryRepository, Order) {
+        let repo = MemoryRepository::new();
+        let user = user("buyer@example.com", UserType::Gen, 0);
+        let picker = picker(user.user_id, 100);
+        let order = Order {
+            amount: 100,
+            tx_hash: Some(format!("{:#x}", tx_hash)),
+            expires_at: Some(Utc::now() + Duration::hours(1)),
+            ..order(user.user_id, picker.picker_id, PayType::Wallet, OrderStatus::Pending)
+        };
+        UserRepository::create(&repo, &user).await.unwrap();
+        PickerRepository::create(&repo, &picker).await.unwrap();
+        OrderRepository::create(&repo, &order).await.unwrap();
+        (repo, order)
+    }
+
+    #[tokio::test]
+    async fn test_index_payment_events_matches_orders() {
+        let tx_hash = B256::repeat_byte(0x11);
+        let (repo, order) = setup(tx_hash).await;
+        let chain = FakeChain::new(20);
+        chain.push(10, B256::repeat_byte(0x22), 0, registration(order.picker_id));
+        chain.push(12, tx_hash, 1, payment(order.picker_id, 1_000_000_000_000_000_000));
+        // 确认数不足的区块暂不索引
+        chain.push(19, B256::repeat_byte(0x33), 0, payment(order.picker_id, 5));
+
+        let report = index_payment_events(&chain, &repo, &repo, &settings(5)).await.unwrap();
+        assert_eq!(report.blocks, Some((5, 18)));
+        assert_eq!(report.events, 2);
+        assert_eq!(report.confirmed, 1);
+        assert!(report.caught_up);
+
+        let stored = OrderRepository::find_by_id(&repo, order.order_id).await.unwrap().unwrap();
+        assert_eq!(stored.status, OrderStatus::Success);
+        let events = ChainEventRepository::events_for_order(&repo, order.order_id).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_name, "PaymentProcessed");
+        assert_eq!(events[0].picker_id, order.picker_id);
+        assert_eq!(events[0].amount.as_deref(), Some("1000000000000000000"));
+        let cursor = ChainEventRepository::cursor(&repo, PAYMENT_CURSOR).await.unwrap().unwrap();
+        assert_eq!(cursor.block_number, 18);
+        assert_eq!(cursor.block_hash, format!("{:#x}", block_hash(18, 0)));
+
+        // 没有新区块时不做任何处理
+        let report = index_payment_events(&chain, &repo, &repo, &settings(5)).await.unwrap();
+        assert_eq!(report.blocks, None);
+        assert!(report.caught_up);
+
+        // 新区块确认后从进度之后继续
+        chain.advance(23);
+        let report = index_payment_events(&chain, &repo, &repo, &settings(5)).await.unwrap();
+        assert_eq!(report.blocks, Some((19, 21)));
+        assert_eq!(report.events, 1);
+        assert_eq!(report.confirmed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_index_payment_events_ignores_mismatched_picker() {
+        let tx_hash = B256::repeat_byte(0x11);
+        let (repo, order) = setup(tx_hash).await;
+        let chain = FakeChain::new(20);
+        chain.push(12, tx_hash, 0, payment(Uuid::new_v4(), 100));
+
+        let report = index_payment_events(&chain, &repo, &repo, &settings(0)).await.unwrap();
+        assert_eq!(report.events, 1);
+        assert_eq!(report.confirmed, 0);
+        let stored = OrderRepository::find_by_id(&repo, order.order_id).await.unwrap().unwrap();
+        assert_eq!(stored.status, OrderStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_index_payment_events_respects_batch_size() {
+        let (repo, _) = setup(B256::repeat_byte(0x11)).await;
+        let chain = FakeChain::new(30);
+        let mut settings = settings(0);
+        settings.batch_blocks = 10;
+
+        let report = index_payment_events(&chain, &repo, &repo, &settings).await.unwrap();
+        assert_eq!(report.blocks, Some((0, 9)));
+        assert!(!report.caught_up);
+        let report = index_payment_events(&chain, &repo, &repo, &settings).await.unwrap();
+        assert_eq!(report.blocks, Some((10, 19)));
+    }
+
+    #[tokio::test]
+    async fn test_index_payment_events_rewinds_on_reorg() {
+        let (repo, order) = setup(B256::repeat_byte(0x11)).await;
+        let chain = FakeChain::new(20);
+        chain.push(15, B256::repeat_byte(0x44), 0, registration(order.picker_id));
+        chain.push(17, B256::repeat_byte(0x55), 0, payment(order.picker_id, 7));
+
+        let report = index_payment_events(&chain, &repo, &repo, &settings(0)).await.unwrap();
+        assert_eq!(report.events, 2);
+
+        // 区块 17 起被替换，进度中的区块 18 哈希不再一致
+        chain.reorg(17, 9);
+        let report = index_payment_events(&chain, &repo, &repo, &settings(0)).await.unwrap();
+        assert_eq!(report.rewound, 1);
+        assert_eq!(report.blocks, None);
+        let cursor = ChainEventRepository::cursor(&repo, PAYMENT_CURSOR).await.unwrap().unwrap();
+        assert_eq!(cursor.block_number, 16);
+
+        // 从回退点重新索引新分叉上的区块
+        let report = index_payment_events(&chain, &repo, &repo, &settings(0)).await.unwrap();
+        assert_eq!(report.blocks, Some((17, 18)));
+        assert_eq!(report.events, 0);
+        let cursor = ChainEventRepository::cursor(&repo, PAYMENT_CURSOR).await.unwrap().unwrap();
+        assert_eq!(cursor.block_hash, format!("{:#x}", block_hash(18, 9)));
+    }
+
+    #[test]
+    fn test_decode_event_skips_foreign_and_removed_logs() {
+        let picker_id = Uuid::new_v4();
+        let mut log = Log {
+            inner: alloy::primitives::Log {
+                address: CONTRACT,
+                data: payment(picker_id, 42),
+            },
+            block_hash: Some(block_hash(1, 0)),
+            block_number: Some(1),
+            block_timestamp: None,
+            transaction_hash: Some(B256::repeat_byte(0x66)),
+            transaction_index: Some(0),
+            log_index: Some(3),
+            removed: false,
+        };
+        let event = decode_event(&log).unwrap();
+        assert_eq!(event.picker_id, picker_id);
+        assert_eq!(event.amount.as_deref(), Some("42"));
+        assert_eq!(event.log_index, 3);
+
+        log.removed = true;
+        assert!(decode_event(&log).is_none());
+
+        log.removed = false;
+        log.inner.data = alloy::primitives::LogData::new_unchecked(vec![FixedBytes::ZERO], Default::default());
+        assert!(decode_event(&log).is_none());
+    }
+}
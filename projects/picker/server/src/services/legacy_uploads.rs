@@ -0,0 +1,143 @@
+use std::path::Path;
+
+use tracing::{error, info, warn};
+
+use crate::repository::PickerRepository;
+use crate::storage::{file_content_key, is_content_key, BlobStore};
+use crate::utils::AppError;
+
+/// 旧版上传文件的导入结果
+#[derive(Debug, Default, PartialEq)]
+pub struct LegacyImportReport {
+    // 已写入文件存储，引用改为 content key
+    pub imported: usize,
+    // 原文件不存在，引用保持不变
+    pub missing: usize,
+    // 读取或写入文件存储失败，下次执行时重试
+    pub failed: usize,
+}
+
+/// 将以相对路径（如 uploads/files/…）保存的 file_path / image_path 导入文件存储，并把引用改为 content key
+/// base_dir 为旧路径的基准目录（即原来的工作目录）；原文件不会被删除，可重复执行，已导入的引用会被跳过
+pub async fn import_legacy_uploads(
+    pickers: &dyn PickerRepository,
+    blobs: &dyn BlobStore,
+    base_dir: &Path,
+) -> Result<LegacyImportReport, AppError> {
+    let references = pickers.file_references().await.map_err(|e| {
+        error!("Failed to load file references for import: {}", e);
+        AppError::DatabaseError
+    })?;
+
+    let mut report = LegacyImportReport::default();
+    for reference in references.into_iter().filter(|reference| !is_content_key(reference)) {
+        let path = base_dir.join(&reference);
+        if !tokio::fs::metadata(&path).await.is_ok_and(|metadata| metadata.is_file()) {
+            warn!("Legacy upload {} not found, reference left unchanged", path.display());
+            report.missing += 1;
+            continue;
+        }
+
+        let key = match file_content_key(&path, &legacy_extension(&reference)).await {
+            Ok(key) => key,
+            Err(e) => {
+                error!("Failed to read legacy upload {}: {}", path.display(), e);
+                report.failed += 1;
+                continue;
+            }
+        };
+        if let Err(e) = blobs.put_file(&key, &path).await {
+            error!("Failed to import legacy upload {}: {}", path.display(), e);
+            report.failed += 1;
+            continue;
+        }
+
+        pickers.replace_file_reference(&reference, &key).await.map_err(|e| {
+            error!("Failed to replace file reference {}: {}", reference, e);
+            AppError::DatabaseError
+        })?;
+        report.imported += 1;
+    }
+
+    if report != LegacyImportReport::default() {
+        info!(
+            "Legacy upload import finished: {} imported, {} missing, {} failed",
+            report.imported, report.missing, report.failed
+        );
+    }
+    Ok(report)
+}
+
+// 沿用原文件的扩展名（转为小写），没有可用的扩展名时使用 bin
+fn legacy_extension(reference: &str) -> String {
+    Path::new(reference)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase)
+        .filter(|extension| !extension.is_empty() && extension.bytes().all(|b| b.is_ascii_alphanumeric()))
+        .unwrap_or_else(|| "bin".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Picker, UserType};
+    use crate::repository::fixtures::{self, user};
+    use crate::repository::{MemoryRepository, UserRepository};
+    use crate::storage::LocalBlobStore;
+    use uuid::Uuid;
+
+    fn picker(dev_user_id: Uuid, file_path: &str, image_path: &str) -> Picker {
+        Picker {
+            file_path: file_path.to_string(),
+            image_path: image_path.to_string(),
+            ..fixtures::picker(dev_user_id, 10)
+        }
+    }
+
+    #[test]
+    fn test_legacy_extension() {
+        assert_eq!(legacy_extension("uploads/files/1b4e_picker.ZIP"), "zip");
+        assert_eq!(legacy_extension("uploads/files/1b4e_picker"), "bin");
+        assert_eq!(legacy_extension("uploads/images/1b4e_a.j-pg"), "bin");
+    }
+
+    #[tokio::test]
+    async fn test_import_legacy_uploads() {
+        let base = tempfile::tempdir().unwrap();
+        let root = tempfile::tempdir().unwrap();
+        let blobs = LocalBlobStore::new(root.path());
+        std::fs::create_dir_all(base.path().join("uploads/files")).unwrap();
+        std::fs::create_dir_all(base.path().join("uploads/images")).unwrap();
+        std::fs::write(base.path().join("uploads/files/a_picker.zip"), b"same archive").unwrap();
+        std::fs::write(base.path().join("uploads/files/b_picker.zip"), b"same archive").unwrap();
+        std::fs::write(base.path().join("uploads/images/a_icon.PNG"), b"icon").unwrap();
+
+        let repo = MemoryRepository::new();
+        let dev = user("dev@example.com", UserType::Dev, 0);
+        UserRepository::create(&repo, &dev).await.unwrap();
+        let first = picker(dev.user_id, "uploads/files/a_picker.zip", "uploads/images/a_icon.PNG");
+        let second = picker(dev.user_id, "uploads/files/b_picker.zip", "uploads/images/missing.png");
+        PickerRepository::create(&repo, &first).await.unwrap();
+        PickerRepository::create(&repo, &second).await.unwrap();
+
+        let report = import_legacy_uploads(&repo, &blobs, base.path()).await.unwrap();
+        assert_eq!(report, LegacyImportReport { imported: 3, missing: 1, failed: 0 });
+
+        // 内容相同的两个文件导入后共用同一个 key，版本记录一并更新
+        let first = PickerRepository::find_by_id(&repo, first.picker_id).await.unwrap().unwrap();
+        let second = PickerRepository::find_by_id(&repo, second.picker_id).await.unwrap().unwrap();
+        assert!(is_content_key(&first.file_path) && first.file_path.ends_with(".zip"));
+        assert_eq!(first.file_path, second.file_path);
+        assert!(is_content_key(&first.image_path) && first.image_path.ends_with(".png"));
+        assert_eq!(second.image_path, "uploads/images/missing.png");
+        assert_eq!(repo.list_versions(first.picker_id).await.unwrap()[0].file_path, first.file_path);
+        assert!(blobs.exists(&first.file_path).await.unwrap());
+        assert!(blobs.exists(&first.image_path).await.unwrap());
+        assert!(base.path().join("uploads/files/a_picker.zip").exists());
+
+        // 再次执行只会跳过已导入的引用
+        let report = import_legacy_uploads(&repo, &blobs, base.path()).await.unwrap();
+        assert_eq!(report, LegacyImportReport { imported: 0, missing: 1, failed: 0 });
+    }
+}
@@ -0,0 +1,11 @@
+// 业务规则层：只依赖仓储 trait，不依赖 axum 与具体数据库，可直接使用内存仓储做单元测试
+pub mod earnings;
+pub mod indexer;
+pub mod legacy_uploads;
+pub mod orders;
+pub mod picker_registry;
+pub mod premium_grants;
+pub mod reconciler;
+pub mod refunds;
+pub mod wallet_keys;
+pub mod withdrawals;
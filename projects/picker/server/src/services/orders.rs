@@ -0,0 +1,248 @@
+use chrono::Utc;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::models::{Order, OrderStatus, PayType};
+use crate::repository::{OrderRepository, PickerRepository, UserRepository};
+use crate::utils::AppError;
+
+/// 计算 Premium 支付中开发者实得的积分：价格扣除平台手续费（payment_rate 为百分比）
+/// 全程使用整数运算，手续费向下取整
+pub fn premium_dev_income(price: i64, payment_rate: i64) -> i64 {
+    let fee = (price as i128 * payment_rate.clamp(0, 100) as i128 / 100) as i64;
+    price.checked_sub(fee).unwrap_or_default()
+}
+
+/// 使用 Premium 积分购买 Picker
+/// 校验用户、Picker（需上架）与余额后，在一个事务中扣除用户积分、增加开发者积分并写入成功订单
+/// 事务内扣款时会再次校验余额，并发支付导致余额不足时同样返回余额不足
+pub async fn purchase_with_premium(
+    users: &dyn UserRepository,
+    pickers: &dyn PickerRepository,
+    orders: &dyn OrderRepository,
+    user_id: Uuid,
+    picker_id: Uuid,
+    payment_rate: i64,
+) -> Result<Order, AppError> {
+    let user = users
+        .find_by_id(user_id)
+        .await
+        .map_err(|_| AppError::NotFound("User not found".to_string()))?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let picker = pickers
+        .find_active(picker_id)
+        .await
+        .map_err(|_| AppError::DatabaseError)?
+        .ok_or_else(|| AppError::NotFound("Picker not found".to_string()))?;
+
+    info!(
+        "Processing premium payment, user balance: {}, picker price: {}",
+        user.premium_balance, picker.price
+    );
+    if user.premium_balance < picker.price {
+        return Err(AppError::BadRequest(
+            "Insufficient premium balance.".to_string(),
+        ));
+    }
+
+    let dev_user = users
+        .find_by_id(picker.dev_user_id)
+        .await
+        .map_err(|_| AppError::NotFound("Dev User not found".to_string()))?
+        .ok_or_else(|| AppError::NotFound("Dev User not found".to_string()))?;
+
+    let dev_income = premium_dev_income(picker.price, payment_rate);
+    info!("Increase balance to dev: {}", dev_income);
+
+    let order = Order {
+        order_id: Uuid::new_v4(),
+        user_id,
+        picker_id,
+        amount: picker.price,
+        pay_type: PayType::Premium,
+        status: OrderStatus::Success,
+        tx_hash: None,
+        created_at: Utc::now(),
+        expires_at: None,
+    };
+
+    let settled = orders
+        .settle_premium(&order, dev_user.user_id, dev_income)
+        .await
+        .map_err(|e| {
+            info!("Failed to settle premium order: {:?}", e);
+            AppError::DatabaseError
+        })?;
+    if !settled {
+        return Err(AppError::BadRequest(
+            "Insufficient premium balance.".to_string(),
+        ));
+    }
+
+    info!("Premium order settled successfully");
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Picker, User, UserType};
+    use crate::repository::fixtures::{picker, user};
+    use crate::repository::MemoryRepository;
+
+    // 写入买家、开发者和一个 Picker，返回 (仓储, 买家ID, 开发者ID, PickerID)
+    async fn setup(balance: i64, price: i64, status: &str) -> (MemoryRepository, Uuid, Uuid, Uuid) {
+        let repo = MemoryRepository::new();
+        let buyer = user("buyer@example.com", UserType::Gen, balance);
+        let dev = user("dev@example.com", UserType::Dev, 0);
+        let picker = Picker { status: status.to_string(), ..picker(dev.user_id, price) };
+        UserRepository::create(&repo, &buyer).await.unwrap();
+        UserRepository::create(&repo, &dev).await.unwrap();
+        PickerRepository::create(&repo, &picker).await.unwrap();
+        (repo, buyer.user_id, dev.user_id, picker.picker_id)
+    }
+
+    #[test]
+    fn test_premium_dev_income() {
+        assert_eq!(premium_dev_income(100, 5), 95);
+        assert_eq!(premium_dev_income(500, 5), 475);
+        assert_eq!(premium_dev_income(10, 0), 10);
+        // 手续费向下取整，开发者收入不会因取整而减少
+        assert_eq!(premium_dev_income(10, 5), 10);
+        assert_eq!(premium_dev_income(0, 5), 0);
+        // 大额价格也不会丢失精度
+        assert_eq!(premium_dev_income(1_000_000_007, 3), 970_000_007);
+    }
+
+    #[tokio::test]
+    async fn test_purchase_with_premium_settles_balances() {
+        let (repo, buyer_id, dev_id, picker_id) = setup(1000, 500, "active").await;
+
+        let order = purchase_with_premium(&repo, &repo, &repo, buyer_id, picker_id, 5)
+            .await
+            .unwrap();
+
+        assert_eq!(order.status, OrderStatus::Success);
+        assert_eq!(order.pay_type, PayType::Premium);
+        assert_eq!(order.amount, 500);
+
+        let buyer = UserRepository::find_by_id(&repo, buyer_id).await.unwrap().unwrap();
+        let dev = UserRepository::find_by_id(&repo, dev_id).await.unwrap().unwrap();
+        let picker = PickerRepository::find_by_id(&repo, picker_id).await.unwrap().unwrap();
+        assert_eq!(buyer.premium_balance, 500);
+        assert_eq!(dev.premium_balance, 475);
+        assert_eq!(picker.download_count, 1);
+        assert!(OrderRepository::find_by_id(&repo, order.order_id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_purchase_with_premium_exact_balance() {
+        let (repo, buyer_id, _, picker_id) = setup(500, 500, "active").await;
+
+        purchase_with_premium(&repo, &repo, &repo, buyer_id, picker_id, 5)
+            .await
+            .unwrap();
+
+        let buyer = UserRepository::find_by_id(&repo, buyer_id).await.unwrap().unwrap();
+        assert_eq!(buyer.premium_balance, 0);
+    }
+
+    #[tokio::test]
+    async fn test_purchase_with_premium_insufficient_balance() {
+        let (repo, buyer_id, dev_id, picker_id) = setup(100, 500, "active").await;
+
+        let result = purchase_with_premium(&repo, &repo, &repo, buyer_id, picker_id, 5).await;
+        assert!(matches!(result, Err(AppError::BadRequest(msg)) if msg == "Insufficient premium balance."));
+
+        // 余额不足时不产生任何变更
+        let buyer = UserRepository::find_by_id(&repo, buyer_id).await.unwrap().unwrap();
+        let dev = UserRepository::find_by_id(&repo, dev_id).await.unwrap().unwrap();
+        let (orders, total) = repo.list_for_user(buyer_id, None, 10, 0).await.unwrap();
+        assert_eq!(buyer.premium_balance, 100);
+        assert_eq!(dev.premium_balance, 0);
+        assert!(orders.is_empty());
+        assert_eq!(total, 0);
+    }
+
+    // 返回校验前读取的用户快照，模拟校验与结算之间余额被并发支付扣减
+    struct StaleUsers<'a> {
+        inner: &'a MemoryRepository,
+        snapshot: User,
+    }
+
+    #[async_trait::async_trait]
+    impl UserRepository for StaleUsers<'_> {
+        async fn find_by_id(&self, user_id: Uuid) -> Result<Option<User>, sqlx::Error> {
+            if user_id == self.snapshot.user_id {
+                return Ok(Some(self.snapshot.clone()));
+            }
+            UserRepository::find_by_id(self.inner, user_id).await
+        }
+        async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
+            self.inner.find_by_email(email).await
+        }
+        async fn exists(&self, user_id: Uuid) -> Result<bool, sqlx::Error> {
+            UserRepository::exists(self.inner, user_id).await
+        }
+        async fn create(&self, user: &User) -> Result<(), sqlx::Error> {
+            UserRepository::create(self.inner, user).await
+        }
+        async fn update_password(&self, user_id: Uuid, password_hash: &str) -> Result<bool, sqlx::Error> {
+            self.inner.update_password(user_id, password_hash).await
+        }
+        async fn list_private_keys(&self) -> Result<Vec<(Uuid, String)>, sqlx::Error> {
+            self.inner.list_private_keys().await
+        }
+        async fn replace_private_key(&self, user_id: Uuid, expected: &str, private_key: &str) -> Result<bool, sqlx::Error> {
+            self.inner.replace_private_key(user_id, expected, private_key).await
+        }
+        async fn list_registrations(
+            &self,
+            after: Option<Uuid>,
+            limit: i64,
+        ) -> Result<Vec<(Uuid, chrono::DateTime<Utc>)>, sqlx::Error> {
+            self.inner.list_registrations(after, limit).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_purchase_with_premium_balance_spent_before_settlement() {
+        let (repo, buyer_id, dev_id, picker_id) = setup(500, 500, "active").await;
+        let snapshot = UserRepository::find_by_id(&repo, buyer_id).await.unwrap().unwrap();
+
+        // 校验之后、结算之前，另一笔支付已花掉全部余额
+        purchase_with_premium(&repo, &repo, &repo, buyer_id, picker_id, 5)
+            .await
+            .unwrap();
+
+        let users = StaleUsers { inner: &repo, snapshot };
+        let result = purchase_with_premium(&users, &repo, &repo, buyer_id, picker_id, 5).await;
+        assert!(matches!(result, Err(AppError::BadRequest(msg)) if msg == "Insufficient premium balance."));
+
+        // 第二笔支付不产生任何变更，余额不会变为负数
+        let buyer = UserRepository::find_by_id(&repo, buyer_id).await.unwrap().unwrap();
+        let dev = UserRepository::find_by_id(&repo, dev_id).await.unwrap().unwrap();
+        let picker = PickerRepository::find_by_id(&repo, picker_id).await.unwrap().unwrap();
+        assert_eq!(buyer.premium_balance, 0);
+        assert_eq!(dev.premium_balance, 475);
+        assert_eq!(picker.download_count, 1);
+        assert_eq!(repo.list_for_user(buyer_id, None, 10, 0).await.unwrap().1, 1);
+    }
+
+    #[tokio::test]
+    async fn test_purchase_with_premium_inactive_picker() {
+        let (repo, buyer_id, _, picker_id) = setup(1000, 500, "inactive").await;
+
+        let result = purchase_with_premium(&repo, &repo, &repo, buyer_id, picker_id, 5).await;
+        assert!(matches!(result, Err(AppError::NotFound(msg)) if msg == "Picker not found"));
+    }
+
+    #[tokio::test]
+    async fn test_purchase_with_premium_unknown_user() {
+        let (repo, _, _, picker_id) = setup(1000, 500, "active").await;
+
+        let result = purchase_with_premium(&repo, &repo, &repo, Uuid::new_v4(), picker_id, 5).await;
+        assert!(matches!(result, Err(AppError::NotFound(msg)) if msg == "User not found"));
+    }
+}
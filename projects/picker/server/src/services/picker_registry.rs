@@ -0,0 +1,368 @@
+use alloy::primitives::Address;
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use alloy::signers::local::PrivateKeySigner;
+use async_trait::async_trait;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::contract::{uuid_to_bytes16, PickerPayment};
+use crate::models::{Picker, PickerChainStatus};
+use crate::repository::PickerRepository;
+use crate::services::reconciler::{ReceiptSource, ReceiptStatus, RpcReceiptSource};
+use crate::utils::AppError;
+
+/// 向 PickerPayment 合约登记 / 移除 Picker，只负责发送交易并返回交易哈希
+/// 回执通过 ReceiptSource 查询，生产环境使用运营方私钥签名，测试中可替换
+#[async_trait]
+pub trait PickerRegistrar: ReceiptSource {
+    async fn register(&self, picker_id: Uuid, dev_user_id: Uuid, dev_wallet: &str) -> Result<String, AppError>;
+    async fn remove(&self, picker_id: Uuid) -> Result<String, AppError>;
+}
+
+/// 使用 blockchain.operator_private_key 签名，该地址需在合约中拥有 OPERATOR_ROLE
+pub struct ContractRegistrar {
+    provider: DynProvider,
+    contract: Address,
+    receipts: RpcReceiptSource,
+}
+
+impl ContractRegistrar {
+    pub fn new(rpc_url: &str, contract_address: &str, operator_private_key: &str) -> Result<Self, AppError> {
+        let signer: PrivateKeySigner = operator_private_key.parse().map_err(|e| {
+            error!("Invalid operator private key: {}", e);
+            AppError::InternalServerError
+        })?;
+        let contract = contract_address.parse().map_err(|e| {
+            error!("Invalid Authorized Contract Address: {}", e);
+            AppError::InternalServerError
+        })?;
+        let url = rpc_url.parse().map_err(|e| {
+            error!("Invalid RPC URL: {}", e);
+            AppError::InternalServerError
+        })?;
+        info!("Picker registrar uses operator address {}", signer.address());
+
+        Ok(Self {
+            provider: ProviderBuilder::new().wallet(signer).connect_http(url).erased(),
+            contract,
+            receipts: RpcReceiptSource::new(rpc_url)?,
+        })
+    }
+}
+
+#[async_trait]
+impl ReceiptSource for ContractRegistrar {
+    async fn receipt_status(&self, tx_hash: &str) -> Result<ReceiptStatus, AppError> {
+        self.receipts.receipt_status(tx_hash).await
+    }
+}
+
+#[async_trait]
+impl PickerRegistrar for ContractRegistrar {
+    async fn register(&self, picker_id: Uuid, dev_user_id: Uuid, dev_wallet: &str) -> Result<String, AppError> {
+        let dev_wallet = dev_wallet.parse::<Address>().map_err(|e| {
+            error!("Invalid developer wallet address: {}", e);
+            AppError::BadRequest("Invalid developer wallet address".to_string())
+        })?;
+
+        let contract = PickerPayment::new(self.contract, &self.provider);
+        let pending_tx = contract
+            .registerPicker(uuid_to_bytes16(picker_id), uuid_to_bytes16(dev_user_id), dev_wallet)
+            .send()
+            .await
+            .map_err(|e| {
+                error!("Failed to send registerPicker for {}: {}", picker_id, e);
+                AppError::InternalServerError
+            })?;
+        Ok(format!("0x{}", hex::encode(pending_tx.tx_hash())))
+    }
+
+    async fn remove(&self, picker_id: Uuid) -> Result<String, AppError> {
+        let contract = PickerPayment::new(self.contract, &self.provider);
+        let pending_tx = contract
+            .removePicker(uuid_to_bytes16(picker_id))
+            .send()
+            .await
+            .map_err(|e| {
+                error!("Failed to send removePicker for {}: {}", picker_id, e);
+                AppError::InternalServerError
+            })?;
+        Ok(format!("0x{}", hex::encode(pending_tx.tx_hash())))
+    }
+}
+
+/// 回执查询的重试次数与间隔，取自 blockchain.retry_times / retry_interval_seconds
+#[derive(Debug, Clone, Copy)]
+pub struct RegistrationSettings {
+    pub attempts: u32,
+    pub interval: Duration,
+}
+
+/// 上架后登记到合约：发送 registerPicker 后状态置为 pending 并记录交易哈希，
+/// 交易成功置为 registered，回滚或发送失败置为 failed；重试用尽仍未上链时保持 pending
+/// 已登记或正在登记的 Picker 不会重复发送交易
+pub async fn publish_picker(
+    pickers: &dyn PickerRepository,
+    registrar: &dyn PickerRegistrar,
+    picker: &Picker,
+    dev_wallet: &str,
+    settings: RegistrationSettings,
+) -> Result<PickerChainStatus, AppError> {
+    if picker.chain_status == PickerChainStatus::Registered
+        || (picker.chain_status == PickerChainStatus::Pending && picker.chain_tx_hash.is_some())
+    {
+        return Ok(picker.chain_status);
+    }
+
+    let tx_hash = match registrar.register(picker.picker_id, picker.dev_user_id, dev_wallet).await {
+        Ok(tx_hash) => tx_hash,
+        Err(e) => {
+            update_chain_status(pickers, picker.picker_id, PickerChainStatus::Failed, None).await?;
+            return Err(e);
+        }
+    };
+    update_chain_status(pickers, picker.picker_id, PickerChainStatus::Pending, Some(&tx_hash)).await?;
+
+    let status = match wait_for_receipt(registrar, &tx_hash, settings).await {
+        Some(ReceiptStatus::Succeeded) => PickerChainStatus::Registered,
+        Some(ReceiptStatus::Reverted) => {
+            warn!("registerPicker {} reverted for picker {}", tx_hash, picker.picker_id);
+            PickerChainStatus::Failed
+        }
+        _ => return Ok(PickerChainStatus::Pending),
+    };
+    update_chain_status(pickers, picker.picker_id, status, None).await?;
+    info!("Picker {} registration finished: {:?} ({})", picker.picker_id, status, tx_hash);
+    Ok(status)
+}
+
+/// 下架 Picker：状态置为 inactive，已登记到合约的同时发送 removePicker
+/// 交易成功置为 removed；回滚时合约中仍有该 Picker，保持 registered
+/// 未配置 registrar 或尚未登记成功时只修改上架状态
+pub async fn deactivate_picker(
+    pickers: &dyn PickerRepository,
+    registrar: Option<&dyn PickerRegistrar>,
+    picker: &Picker,
+    settings: RegistrationSettings,
+) -> Result<PickerChainStatus, AppError> {
+    pickers.set_status(picker.picker_id, "inactive").await.map_err(|e| {
+        error!("Failed to deactivate picker {}: {}", picker.picker_id, e);
+        AppError::DatabaseError
+    })?;
+
+    let Some(registrar) = registrar.filter(|_| picker.chain_status == PickerChainStatus::Registered) else {
+        return Ok(picker.chain_status);
+    };
+
+    let tx_hash = registrar.remove(picker.picker_id).await?;
+    update_chain_status(pickers, picker.picker_id, PickerChainStatus::Pending, Some(&tx_hash)).await?;
+
+    let status = match wait_for_receipt(registrar, &tx_hash, settings).await {
+        Some(ReceiptStatus::Succeeded) => PickerChainStatus::Removed,
+        Some(ReceiptStatus::Reverted) => {
+            warn!("removePicker {} reverted for picker {}", tx_hash, picker.picker_id);
+            PickerChainStatus::Registered
+        }
+        _ => return Ok(PickerChainStatus::Pending),
+    };
+    update_chain_status(pickers, picker.picker_id, status, None).await?;
+    info!("Picker {} removal finished: {:?} ({})", picker.picker_id, status, tx_hash);
+    Ok(status)
+}
+
+// 查询回执直到交易上链，RPC 错误计为一次尝试；重试用尽返回 None
+async fn wait_for_receipt(
+    receipts: &dyn PickerRegistrar,
+    tx_hash: &str,
+    settings: RegistrationSettings,
+) -> Option<ReceiptStatus> {
+    for attempt in 1..=settings.attempts.max(1) {
+        match receipts.receipt_status(tx_hash).await {
+            Ok(ReceiptStatus::NotFound) => {}
+            Ok(status) => return Some(status),
+            Err(e) => warn!("Receipt lookup for {} failed (attempt {}): {:?}", tx_hash, attempt, e),
+        }
+        if attempt < settings.attempts {
+            tokio::time::sleep(settings.interval).await;
+        }
+    }
+    None
+}
+
+async fn update_chain_status(
+    pickers: &dyn PickerRepository,
+    picker_id: Uuid,
+    status: PickerChainStatus,
+    tx_hash: Option<&str>,
+) -> Result<(), AppError> {
+    pickers.update_chain_status(picker_id, status, tx_hash).await.map_err(|e| {
+        error!("Failed to update chain status of picker {}: {}", picker_id, e);
+        AppError::DatabaseError
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::models::{User, UserType};
+    use crate::repository::fixtures::{picker, user};
+    use crate::repository::{MemoryRepository, UserRepository};
+
+    // 按交易哈希返回预设回执，记录发送过的交易
+    #[derive(Default)]
+    struct FakeRegistrar {
+        receipts: Mutex<HashMap<String, ReceiptStatus>>,
+        sent: Mutex<Vec<(String, Uuid)>>,
+        fail_send: bool,
+        outcome: Option<ReceiptStatus>,
+    }
+
+    impl FakeRegistrar {
+        fn with_outcome(outcome: Option<ReceiptStatus>) -> Self {
+            Self {
+                outcome,
+                ..Default::default()
+            }
+        }
+
+        fn send(&self, method: &str, picker_id: Uuid) -> Result<String, AppError> {
+            if self.fail_send {
+                return Err(AppError::InternalServerError);
+            }
+            let mut sent = self.sent.lock().unwrap();
+            let tx_hash = format!("0x{:064x}", sent.len() + 1);
+            sent.push((method.to_string(), picker_id));
+            if let Some(outcome) = self.outcome {
+                self.receipts.lock().unwrap().insert(tx_hash.clone(), outcome);
+            }
+            Ok(tx_hash)
+        }
+    }
+
+    #[async_trait]
+    impl ReceiptSource for FakeRegistrar {
+        async fn receipt_status(&self, tx_hash: &str) -> Result<ReceiptStatus, AppError> {
+            Ok(self.receipts.lock().unwrap().get(tx_hash).copied().unwrap_or(ReceiptStatus::NotFound))
+        }
+    }
+
+    #[async_trait]
+    impl PickerRegistrar for FakeRegistrar {
+        async fn register(&self, picker_id: Uuid, _dev_user_id: Uuid, _dev_wallet: &str) -> Result<String, AppError> {
+            self.send("registerPicker", picker_id)
+        }
+
+        async fn remove(&self, picker_id: Uuid) -> Result<String, AppError> {
+            self.send("removePicker", picker_id)
+        }
+    }
+
+    const SETTINGS: RegistrationSettings = RegistrationSettings {
+        attempts: 2,
+        interval: Duration::from_millis(1),
+    };
+
+    async fn setup() -> (MemoryRepository, Picker) {
+        let repo = MemoryRepository::new();
+        let dev = User {
+            wallet_address: "0x0000000000000000000000000000000000000001".to_string(),
+            ..user("dev@example.com", UserType::Dev, 0)
+        };
+        UserRepository::create(&repo, &dev).await.unwrap();
+
+        let picker = picker(dev.user_id, 10);
+        PickerRepository::create(&repo, &picker).await.unwrap();
+        (repo, picker)
+    }
+
+    async fn stored(repo: &MemoryRepository, picker_id: Uuid) -> Picker {
+        PickerRepository::find_by_id(repo, picker_id).await.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_publish_records_registration() {
+        let (repo, picker) = setup().await;
+        let registrar = FakeRegistrar::with_outcome(Some(ReceiptStatus::Succeeded));
+
+        let status = publish_picker(&repo, &registrar, &picker, "0x01", SETTINGS).await.unwrap();
+        assert_eq!(status, PickerChainStatus::Registered);
+        let picker = stored(&repo, picker.picker_id).await;
+        assert_eq!(picker.chain_status, PickerChainStatus::Registered);
+        assert_eq!(picker.chain_tx_hash.as_deref(), Some(format!("0x{:064x}", 1).as_str()));
+
+        // 已登记的 Picker 不会重复发送交易
+        let status = publish_picker(&repo, &registrar, &picker, "0x01", SETTINGS).await.unwrap();
+        assert_eq!(status, PickerChainStatus::Registered);
+        assert_eq!(registrar.sent.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_publish_reverted_or_unsent_marks_failed() {
+        let (repo, picker) = setup().await;
+        let registrar = FakeRegistrar::with_outcome(Some(ReceiptStatus::Reverted));
+        let status = publish_picker(&repo, &registrar, &picker, "0x01", SETTINGS).await.unwrap();
+        assert_eq!(status, PickerChainStatus::Failed);
+        assert!(stored(&repo, picker.picker_id).await.chain_tx_hash.is_some());
+
+        let (repo, picker) = setup().await;
+        let registrar = FakeRegistrar {
+            fail_send: true,
+            ..Default::default()
+        };
+        assert!(publish_picker(&repo, &registrar, &picker, "0x01", SETTINGS).await.is_err());
+        let picker = stored(&repo, picker.picker_id).await;
+        assert_eq!(picker.chain_status, PickerChainStatus::Failed);
+        assert!(picker.chain_tx_hash.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_publish_keeps_pending_until_mined() {
+        let (repo, picker) = setup().await;
+        let registrar = FakeRegistrar::with_outcome(None);
+
+        let status = publish_picker(&repo, &registrar, &picker, "0x01", SETTINGS).await.unwrap();
+        assert_eq!(status, PickerChainStatus::Pending);
+        let picker = stored(&repo, picker.picker_id).await;
+        assert_eq!(picker.chain_status, PickerChainStatus::Pending);
+        assert!(picker.chain_tx_hash.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_deactivate_removes_registered_picker() {
+        let (repo, picker) = setup().await;
+        let registrar = FakeRegistrar::with_outcome(Some(ReceiptStatus::Succeeded));
+        publish_picker(&repo, &registrar, &picker, "0x01", SETTINGS).await.unwrap();
+        let picker = stored(&repo, picker.picker_id).await;
+
+        let status = deactivate_picker(&repo, Some(&registrar), &picker, SETTINGS).await.unwrap();
+        assert_eq!(status, PickerChainStatus::Removed);
+        let stored = stored(&repo, picker.picker_id).await;
+        assert_eq!(stored.status, "inactive");
+        assert_eq!(stored.chain_status, PickerChainStatus::Removed);
+        assert_eq!(stored.chain_tx_hash.as_deref(), Some(format!("0x{:064x}", 2).as_str()));
+        assert_eq!(registrar.sent.lock().unwrap()[1], ("removePicker".to_string(), picker.picker_id));
+    }
+
+    #[tokio::test]
+    async fn test_deactivate_unregistered_picker_skips_contract() {
+        let (repo, picker) = setup().await;
+        let registrar = FakeRegistrar::with_outcome(Some(ReceiptStatus::Succeeded));
+
+        let status = deactivate_picker(&repo, Some(&registrar), &picker, SETTINGS).await.unwrap();
+        assert_eq!(status, PickerChainStatus::Unregistered);
+        assert_eq!(stored(&repo, picker.picker_id).await.status, "inactive");
+        assert!(registrar.sent.lock().unwrap().is_empty());
+
+        // 回滚时合约中仍有该 Picker
+        let (repo, mut picker) = setup().await;
+        picker.chain_status = PickerChainStatus::Registered;
+        let registrar = FakeRegistrar::with_outcome(Some(ReceiptStatus::Reverted));
+        let status = deactivate_picker(&repo, Some(&registrar), &picker, SETTINGS).await.unwrap();
+        assert_eq!(status, PickerChainStatus::Registered);
+    }
+}
@@ -0,0 +1,190 @@
+use chrono::{DateTime, Duration, Utc};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::repository::{PremiumLedgerRepository, UserRepository};
+use crate::utils::AppError;
+
+/// 免费积分发放规则，对应 [premium] free / period
+#[derive(Debug, Clone, Copy)]
+pub struct GrantSettings {
+    // 每个周期发放的积分数
+    pub amount: i64,
+    // 周期长度（天）
+    pub period_days: i64,
+}
+
+/// 一轮发放的结果
+#[derive(Debug, Default, PartialEq)]
+pub struct GrantReport {
+    // 本轮新发放的用户数
+    pub granted: usize,
+    // 当前周期已发放过的用户数
+    pub skipped: usize,
+}
+
+/// 计算 now 所在的周期序号：从注册时间起每 period_days 天为一个周期，注册当天即为第 0 个周期
+/// 周期长度无效或 now 早于注册时间时返回 None
+pub fn grant_period(registered_at: DateTime<Utc>, now: DateTime<Utc>, period_days: i64) -> Option<i64> {
+    if period_days <= 0 || now < registered_at {
+        return None;
+    }
+    Some((now - registered_at).num_seconds() / Duration::days(period_days).num_seconds())
+}
+
+/// 为所有用户发放当前周期的免费积分，每次分批读取 batch_size 个用户
+/// 每个用户每个周期只发放一次（由发放记录的主键保证），重复执行或服务重启不会重复发放；
+/// 服务停机错过的周期不会补发
+pub async fn grant_free_premium(
+    users: &dyn UserRepository,
+    ledger: &dyn PremiumLedgerRepository,
+    settings: GrantSettings,
+    now: DateTime<Utc>,
+    batch_size: i64,
+) -> Result<GrantReport, AppError> {
+    let mut report = GrantReport::default();
+    if settings.amount <= 0 {
+        return Ok(report);
+    }
+
+    let mut after: Option<Uuid> = None;
+    loop {
+        let batch = users.list_registrations(after, batch_size.max(1)).await.map_err(|e| {
+            error!("Failed to load users for premium grants: {}", e);
+            AppError::DatabaseError
+        })?;
+        let Some((last, _)) = batch.last() else {
+            break;
+        };
+        after = Some(*last);
+
+        for (user_id, registered_at) in batch {
+            let Some(period_index) = grant_period(registered_at, now, settings.period_days) else {
+                continue;
+            };
+            let granted = ledger
+                .grant_free(user_id, period_index, settings.amount, now)
+                .await
+                .map_err(|e| {
+                    error!("Failed to grant free premium to user {}: {}", user_id, e);
+                    AppError::DatabaseError
+                })?;
+            if granted {
+                report.granted += 1;
+            } else {
+                report.skipped += 1;
+            }
+        }
+    }
+
+    if report.granted > 0 {
+        info!("Granted {} free premium credits to {} users", settings.amount, report.granted);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{LedgerEntryKind, User, UserType};
+    use crate::repository::fixtures;
+    use crate::repository::MemoryRepository;
+
+    const SETTINGS: GrantSettings = GrantSettings { amount: 30, period_days: 30 };
+
+    fn user(email: &str, created_at: DateTime<Utc>) -> User {
+        User { created_at, ..fixtures::user(email, UserType::Gen, 0) }
+    }
+
+    async fn balance(repo: &MemoryRepository, user_id: Uuid) -> i64 {
+        UserRepository::find_by_id(repo, user_id).await.unwrap().unwrap().premium_balance
+    }
+
+    #[test]
+    fn test_grant_period() {
+        let registered_at = Utc::now();
+        assert_eq!(grant_period(registered_at, registered_at, 30), Some(0));
+        assert_eq!(grant_period(registered_at, registered_at + Duration::days(29), 30), Some(0));
+        assert_eq!(grant_period(registered_at, registered_at + Duration::days(30), 30), Some(1));
+        assert_eq!(grant_period(registered_at, registered_at + Duration::days(95), 30), Some(3));
+        assert_eq!(grant_period(registered_at, registered_at - Duration::seconds(1), 30), None);
+        assert_eq!(grant_period(registered_at, registered_at, 0), None);
+    }
+
+    #[tokio::test]
+    async fn test_grants_once_per_period() {
+        let repo = MemoryRepository::new();
+        let start = Utc::now();
+        let alice = user("alice@example.com", start);
+        UserRepository::create(&repo, &alice).await.unwrap();
+
+        let report = grant_free_premium(&repo, &repo, SETTINGS, start, 10).await.unwrap();
+        assert_eq!(report, GrantReport { granted: 1, skipped: 0 });
+        assert_eq!(balance(&repo, alice.user_id).await, 30);
+
+        // 同一周期内重复执行（如服务重启）不会重复发放
+        let report = grant_free_premium(&repo, &repo, SETTINGS, start + Duration::days(29), 10).await.unwrap();
+        assert_eq!(report, GrantReport { granted: 0, skipped: 1 });
+        assert_eq!(balance(&repo, alice.user_id).await, 30);
+
+        let report = grant_free_premium(&repo, &repo, SETTINGS, start + Duration::days(30), 10).await.unwrap();
+        assert_eq!(report, GrantReport { granted: 1, skipped: 0 });
+        assert_eq!(balance(&repo, alice.user_id).await, 60);
+
+        // 停机错过的周期不补发，只发放当前周期
+        grant_free_premium(&repo, &repo, SETTINGS, start + Duration::days(100), 10).await.unwrap();
+        assert_eq!(balance(&repo, alice.user_id).await, 90);
+
+        let (entries, total) = repo.list_for_user(alice.user_id, 10, 0).await.unwrap();
+        assert_eq!(total, 3);
+        assert!(entries.iter().all(|entry| entry.kind == LedgerEntryKind::FreeGrant));
+        assert!(repo.balance_mismatches(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_periods_follow_each_registration_date() {
+        let repo = MemoryRepository::new();
+        let start = Utc::now();
+        let early = user("early@example.com", start);
+        let late = user("late@example.com", start + Duration::days(20));
+        UserRepository::create(&repo, &early).await.unwrap();
+        UserRepository::create(&repo, &late).await.unwrap();
+
+        // 尚未注册的用户不发放
+        let report = grant_free_premium(&repo, &repo, SETTINGS, start + Duration::days(1), 1).await.unwrap();
+        assert_eq!(report, GrantReport { granted: 1, skipped: 0 });
+
+        let report = grant_free_premium(&repo, &repo, SETTINGS, start + Duration::days(31), 1).await.unwrap();
+        assert_eq!(report, GrantReport { granted: 2, skipped: 0 });
+        assert_eq!(balance(&repo, early.user_id).await, 60);
+        assert_eq!(balance(&repo, late.user_id).await, 30);
+
+        // 第二个用户的下一个周期从其注册后第 30 天开始
+        let report = grant_free_premium(&repo, &repo, SETTINGS, start + Duration::days(49), 1).await.unwrap();
+        assert_eq!(report, GrantReport { granted: 0, skipped: 2 });
+        let report = grant_free_premium(&repo, &repo, SETTINGS, start + Duration::days(50), 1).await.unwrap();
+        assert_eq!(report, GrantReport { granted: 1, skipped: 1 });
+        assert_eq!(balance(&repo, late.user_id).await, 60);
+    }
+
+    #[tokio::test]
+    async fn test_grants_are_recorded_in_database() {
+        let state = crate::utils_tests::create_test_app_state().await;
+        let start = Utc::now();
+        let alice = user("alice@example.com", start);
+        state.users.create(&alice).await.unwrap();
+
+        let report = grant_free_premium(state.users.as_ref(), state.premium_ledger.as_ref(), SETTINGS, start, 10)
+            .await
+            .unwrap();
+        assert_eq!(report.granted, 1);
+        let report = grant_free_premium(state.users.as_ref(), state.premium_ledger.as_ref(), SETTINGS, start, 10)
+            .await
+            .unwrap();
+        assert_eq!(report, GrantReport { granted: 0, skipped: 1 });
+
+        let stored = state.users.find_by_id(alice.user_id).await.unwrap().unwrap();
+        assert_eq!(stored.premium_balance, 30);
+        assert!(state.premium_ledger.balance_mismatches(10).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,382 @@
+use alloy::primitives::TxHash;
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::models::{Order, OrderStatus};
+use crate::repository::OrderRepository;
+use crate::utils::AppError;
+
+/// 链上交易回执的查询结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReceiptStatus {
+    // 交易尚未上链（或不存在）
+    NotFound,
+    Succeeded,
+    Reverted,
+}
+
+/// 交易回执来源，生产环境通过 JSON-RPC 查询，测试中可替换
+#[async_trait]
+pub trait ReceiptSource: Send + Sync {
+    async fn receipt_status(&self, tx_hash: &str) -> Result<ReceiptStatus, AppError>;
+}
+
+/// 通过 blockchain.rpc_url 查询 eth_getTransactionReceipt
+pub struct RpcReceiptSource {
+    provider: DynProvider,
+}
+
+impl RpcReceiptSource {
+    pub fn new(rpc_url: &str) -> Result<Self, AppError> {
+        let url = rpc_url.parse().map_err(|e| {
+            error!("Invalid RPC URL: {}", e);
+            AppError::InternalServerError
+        })?;
+        Ok(Self {
+            provider: ProviderBuilder::new().connect_http(url).erased(),
+        })
+    }
+}
+
+#[async_trait]
+impl ReceiptSource for RpcReceiptSource {
+    async fn receipt_status(&self, tx_hash: &str) -> Result<ReceiptStatus, AppError> {
+        // 无法解析的哈希永远查不到回执，按未上链处理，订单到期后会被置为过期
+        let Ok(hash) = tx_hash.parse::<TxHash>() else {
+            warn!("Invalid transaction hash: {}", tx_hash);
+            return Ok(ReceiptStatus::NotFound);
+        };
+
+        let receipt = self.provider.get_transaction_receipt(hash).await.map_err(|e| {
+            error!("Failed to fetch receipt for {}: {}", tx_hash, e);
+            AppError::InternalServerError
+        })?;
+        Ok(match receipt {
+            Some(receipt) if receipt.status() => ReceiptStatus::Succeeded,
+            Some(_) => ReceiptStatus::Reverted,
+            None => ReceiptStatus::NotFound,
+        })
+    }
+}
+
+/// 创建钱包订单后在后台确认交易：最多查询 attempts 次回执，每次间隔 interval
+/// 交易成功置为 success，回滚置为 expired，返回订单的新状态；仍未上链时返回 None，交给后台对账处理
+pub async fn confirm_wallet_order(
+    orders: &dyn OrderRepository,
+    receipts: &dyn ReceiptSource,
+    order: &Order,
+    attempts: u32,
+    interval: Duration,
+) -> Result<Option<OrderStatus>, AppError> {
+    let Some(tx_hash) = order.tx_hash.as_deref().filter(|hash| !hash.is_empty()) else {
+        return Ok(None);
+    };
+
+    for attempt in 1..=attempts.max(1) {
+        match receipts.receipt_status(tx_hash).await {
+            Ok(ReceiptStatus::Succeeded) => {
+                orders.mark_success(order.order_id, order.picker_id).await.map_err(|e| {
+                    error!("Failed to mark order {} as success: {}", order.order_id, e);
+                    AppError::DatabaseError
+                })?;
+                info!("Order {} confirmed on chain", order.order_id);
+                return Ok(Some(OrderStatus::Success));
+            }
+            Ok(ReceiptStatus::Reverted) => {
+                orders.mark_expired(order.order_id).await.map_err(|e| {
+                    error!("Failed to mark order {} as expired: {}", order.order_id, e);
+                    AppError::DatabaseError
+                })?;
+                info!("Transaction of order {} reverted", order.order_id);
+                return Ok(Some(OrderStatus::Expired));
+            }
+            Ok(ReceiptStatus::NotFound) | Err(_) => {
+                info!("Order {} not confirmed yet (attempt {}/{})", order.order_id, attempt, attempts);
+                if attempt < attempts {
+                    tokio::time::sleep(interval).await;
+                }
+            }
+        }
+    }
+
+    info!("Order {} still pending, leaving it to the reconciler", order.order_id);
+    Ok(None)
+}
+
+/// 一轮对账的结果
+#[derive(Debug, Default, PartialEq)]
+pub struct ReconcileReport {
+    // 交易已成功，订单置为 success
+    pub succeeded: usize,
+    // 超过 expires_at 仍未成功或交易已回滚，订单置为 expired
+    pub expired: usize,
+    // 交易尚未上链且未过期，等待下一轮
+    pub pending: usize,
+    // 查询回执失败，订单保持不变，等待下一轮
+    pub failed: usize,
+}
+
+/// 对最多 batch_size 个待支付的钱包订单进行一轮对账
+/// 查询回执失败时不会将订单置为过期，避免 RPC 不可用时误判已支付的订单；
+/// 订单状态只在仍为 pending 时修改，与请求内的确认并发执行也不会重复增加下载次数
+pub async fn reconcile_pending_orders(
+    orders: &dyn OrderRepository,
+    receipts: &dyn ReceiptSource,
+    now: DateTime<Utc>,
+    batch_size: i64,
+) -> Result<ReconcileReport, AppError> {
+    let pending = orders.list_pending_wallet(batch_size).await.map_err(|e| {
+        error!("Failed to load pending wallet orders: {}", e);
+        AppError::DatabaseError
+    })?;
+
+    let mut report = ReconcileReport::default();
+    for order in pending {
+        let status = match order.tx_hash.as_deref().filter(|hash| !hash.is_empty()) {
+            Some(tx_hash) => receipts.receipt_status(tx_hash).await,
+            None => Ok(ReceiptStatus::NotFound),
+        };
+        let Ok(status) = status else {
+            report.failed += 1;
+            continue;
+        };
+        let expired = order.expires_at.is_some_and(|expires_at| expires_at <= now);
+
+        let result = match status {
+            ReceiptStatus::Succeeded => orders.mark_success(order.order_id, order.picker_id).await.map(|changed| {
+                if changed {
+                    info!("Order {} confirmed on chain, marked as success", order.order_id);
+                    report.succeeded += 1;
+                }
+            }),
+            ReceiptStatus::NotFound if !expired => {
+                report.pending += 1;
+                Ok(())
+            }
+            // 回滚的交易不会再成功，无需等到 expires_at
+            ReceiptStatus::NotFound | ReceiptStatus::Reverted => orders.mark_expired(order.order_id).await.map(|changed| {
+                if changed {
+                    info!("Order {} was not paid ({:?}), marked as expired", order.order_id, status);
+                    report.expired += 1;
+                }
+            }),
+        };
+
+        result.map_err(|e| {
+            error!("Failed to update order {}: {}", order.order_id, e);
+            AppError::DatabaseError
+        })?;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use axum::{routing::post, Json, Router};
+    use chrono::Duration;
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::models::{PayType, UserType};
+    use crate::repository::fixtures::{order, picker, user};
+    use crate::repository::{MemoryRepository, PickerRepository, UserRepository};
+
+    const SUCCEEDED_TX: &str = "0x1111111111111111111111111111111111111111111111111111111111111111";
+    const REVERTED_TX: &str = "0x2222222222222222222222222222222222222222222222222222222222222222";
+    const UNKNOWN_TX: &str = "0x3333333333333333333333333333333333333333333333333333333333333333";
+
+    fn receipt(tx_hash: &str, success: bool) -> Value {
+        json!({
+            "transactionHash": tx_hash,
+            "transactionIndex": "0x0",
+            "blockHash": format!("0x{}", "ab".repeat(32)),
+            "blockNumber": "0x10",
+            "from": format!("0x{}", "01".repeat(20)),
+            "to": format!("0x{}", "02".repeat(20)),
+            "cumulativeGasUsed": "0x5208",
+            "gasUsed": "0x5208",
+            "effectiveGasPrice": "0x3b9aca00",
+            "contractAddress": null,
+            "logs": [],
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "type": "0x2",
+            "status": if success { "0x1" } else { "0x0" },
+        })
+    }
+
+    // 本地 JSON-RPC 节点：eth_getTransactionReceipt 按交易哈希返回预置的回执，其余返回 null
+    async fn mock_rpc_server(receipts: HashMap<String, bool>) -> String {
+        let app = Router::new().route(
+            "/",
+            post(move |Json(request): Json<Value>| {
+                let receipts = receipts.clone();
+                async move {
+                    let result = match request["method"].as_str() {
+                        Some("eth_getTransactionReceipt") => {
+                            let tx_hash = request["params"][0].as_str().unwrap_or_default().to_string();
+                            receipts
+                                .get(&tx_hash)
+                                .map(|success| receipt(&tx_hash, *success))
+                                .unwrap_or(Value::Null)
+                        }
+                        _ => Value::Null,
+                    };
+                    Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+                }
+            }),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", address)
+    }
+
+    fn wallet_order(user_id: Uuid, picker_id: Uuid, tx_hash: Option<&str>, expires_in: Duration) -> Order {
+        Order {
+            amount: 100,
+            tx_hash: tx_hash.map(str::to_string),
+            expires_at: Some(Utc::now() + expires_in),
+            ..order(user_id, picker_id, PayType::Wallet, OrderStatus::Pending)
+        }
+    }
+
+    // 写入买家和一个 Picker，返回 (仓储, 买家ID, PickerID)
+    async fn setup() -> (MemoryRepository, Uuid, Uuid) {
+        let repo = MemoryRepository::new();
+        let buyer = user("buyer@example.com", UserType::Gen, 0);
+        let picker = picker(buyer.user_id, 100);
+        UserRepository::create(&repo, &buyer).await.unwrap();
+        PickerRepository::create(&repo, &picker).await.unwrap();
+        (repo, buyer.user_id, picker.picker_id)
+    }
+
+    async fn status_of(repo: &MemoryRepository, order: &Order) -> OrderStatus {
+        OrderRepository::find_by_id(repo, order.order_id).await.unwrap().unwrap().status
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_against_mock_rpc() {
+        let (repo, user_id, picker_id) = setup().await;
+        let rpc_url = mock_rpc_server(HashMap::from([
+            (SUCCEEDED_TX.to_string(), true),
+            (REVERTED_TX.to_string(), false),
+        ]))
+        .await;
+        let receipts = RpcReceiptSource::new(&rpc_url).unwrap();
+
+        let paid = wallet_order(user_id, picker_id, Some(SUCCEEDED_TX), Duration::minutes(30));
+        let reverted = wallet_order(user_id, picker_id, Some(REVERTED_TX), Duration::minutes(30));
+        let waiting = wallet_order(user_id, picker_id, Some(UNKNOWN_TX), Duration::minutes(30));
+        let stale = wallet_order(user_id, picker_id, Some(UNKNOWN_TX), Duration::minutes(-1));
+        let unsigned = wallet_order(user_id, picker_id, None, Duration::minutes(-1));
+        for order in [&paid, &reverted, &waiting, &stale, &unsigned] {
+            OrderRepository::create(&repo, order).await.unwrap();
+        }
+
+        let report = reconcile_pending_orders(&repo, &receipts, Utc::now(), 100).await.unwrap();
+        assert_eq!(
+            report,
+            ReconcileReport {
+                succeeded: 1,
+                expired: 3,
+                pending: 1,
+                failed: 0,
+            }
+        );
+        assert_eq!(status_of(&repo, &paid).await, OrderStatus::Success);
+        assert_eq!(status_of(&repo, &reverted).await, OrderStatus::Expired);
+        assert_eq!(status_of(&repo, &waiting).await, OrderStatus::Pending);
+        assert_eq!(status_of(&repo, &stale).await, OrderStatus::Expired);
+        assert_eq!(status_of(&repo, &unsigned).await, OrderStatus::Expired);
+        let picker = PickerRepository::find_by_id(&repo, picker_id).await.unwrap().unwrap();
+        assert_eq!(picker.download_count, 1);
+
+        // 再次对账只处理仍在等待的订单，不会重复增加下载次数
+        let report = reconcile_pending_orders(&repo, &receipts, Utc::now(), 100).await.unwrap();
+        assert_eq!(report.pending, 1);
+        assert_eq!(report.succeeded, 0);
+        let picker = PickerRepository::find_by_id(&repo, picker_id).await.unwrap().unwrap();
+        assert_eq!(picker.download_count, 1);
+
+        // 超过 expires_at 后置为过期
+        let report = reconcile_pending_orders(&repo, &receipts, Utc::now() + Duration::hours(1), 100)
+            .await
+            .unwrap();
+        assert_eq!(report.expired, 1);
+        assert_eq!(status_of(&repo, &waiting).await, OrderStatus::Expired);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_keeps_orders_when_rpc_unavailable() {
+        let (repo, user_id, picker_id) = setup().await;
+        // 绑定后立即释放端口，连接会被拒绝
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let rpc_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let receipts = RpcReceiptSource::new(&rpc_url).unwrap();
+
+        let stale = wallet_order(user_id, picker_id, Some(SUCCEEDED_TX), Duration::minutes(-1));
+        OrderRepository::create(&repo, &stale).await.unwrap();
+
+        let report = reconcile_pending_orders(&repo, &receipts, Utc::now(), 100).await.unwrap();
+        assert_eq!(report.failed, 1);
+        assert_eq!(report.expired, 0);
+        assert_eq!(status_of(&repo, &stale).await, OrderStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_confirm_wallet_order() {
+        let (repo, user_id, picker_id) = setup().await;
+        let rpc_url = mock_rpc_server(HashMap::from([
+            (SUCCEEDED_TX.to_string(), true),
+            (REVERTED_TX.to_string(), false),
+        ]))
+        .await;
+        let receipts = RpcReceiptSource::new(&rpc_url).unwrap();
+        let interval = std::time::Duration::from_millis(1);
+
+        let paid = wallet_order(user_id, picker_id, Some(SUCCEEDED_TX), Duration::minutes(30));
+        let reverted = wallet_order(user_id, picker_id, Some(REVERTED_TX), Duration::minutes(30));
+        let waiting = wallet_order(user_id, picker_id, Some(UNKNOWN_TX), Duration::minutes(30));
+        for order in [&paid, &reverted, &waiting] {
+            OrderRepository::create(&repo, order).await.unwrap();
+        }
+
+        let status = confirm_wallet_order(&repo, &receipts, &paid, 3, interval).await.unwrap();
+        assert_eq!(status, Some(OrderStatus::Success));
+        assert_eq!(status_of(&repo, &paid).await, OrderStatus::Success);
+
+        let status = confirm_wallet_order(&repo, &receipts, &reverted, 3, interval).await.unwrap();
+        assert_eq!(status, Some(OrderStatus::Expired));
+
+        let status = confirm_wallet_order(&repo, &receipts, &waiting, 3, interval).await.unwrap();
+        assert_eq!(status, None);
+        assert_eq!(status_of(&repo, &waiting).await, OrderStatus::Pending);
+
+        let picker = PickerRepository::find_by_id(&repo, picker_id).await.unwrap().unwrap();
+        assert_eq!(picker.download_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_respects_batch_size() {
+        let (repo, user_id, picker_id) = setup().await;
+        let receipts = RpcReceiptSource::new(&mock_rpc_server(HashMap::new()).await).unwrap();
+        for _ in 0..3 {
+            let order = wallet_order(user_id, picker_id, None, Duration::minutes(-1));
+            OrderRepository::create(&repo, &order).await.unwrap();
+        }
+
+        let report = reconcile_pending_orders(&repo, &receipts, Utc::now(), 2).await.unwrap();
+        assert_eq!(report.expired, 2);
+        let report = reconcile_pending_orders(&repo, &receipts, Utc::now(), 2).await.unwrap();
+        assert_eq!(report.expired, 1);
+    }
+}
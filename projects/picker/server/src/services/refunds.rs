@@ -0,0 +1,510 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::models::{LedgerEntryKind, OrderStatus, PayType, Refund, RefundStatus};
+use crate::repository::{OrderRepository, PickerRepository, PremiumLedgerRepository, RefundRepository, UserRepository};
+use crate::services::orders::premium_dev_income;
+use crate::services::reconciler::{ReceiptSource, ReceiptStatus};
+use crate::utils::AppError;
+
+/// 发起退款的用户身份
+#[derive(Debug, Clone, Copy)]
+pub struct Requester {
+    pub user_id: Uuid,
+    pub is_admin: bool,
+}
+
+/// 退款/取消的结果：订单的新状态与本次写入的退款记录（取消订单时为 None）
+#[derive(Debug)]
+pub struct RefundOutcome {
+    pub order_status: OrderStatus,
+    pub refund: Option<Refund>,
+}
+
+/// 取消或退款订单
+/// - 待支付订单：买家、Picker 开发者或管理员可以取消，订单置为 cancelled；支付交易已广播的钱包订单不能取消
+/// - 已支付的 Premium 订单：开发者或管理员可以退款，在一个事务中退还买家积分并扣回开发者收入
+///   （按积分流水中该订单的开发者收入冲回，没有流水的历史订单按当前 payment_rate 计算；开发者余额不足时拒绝退款）
+/// - 已支付的钱包订单：只有管理员可以退款，需提供合约 withdrawFunds 提现交易的哈希，
+///   写入 pending 退款记录，交易确认后订单置为 refunded
+///
+/// 与订单无关的用户得到 404，不暴露订单是否存在
+#[allow(clippy::too_many_arguments)]
+pub async fn refund_order(
+    users: &dyn UserRepository,
+    pickers: &dyn PickerRepository,
+    orders: &dyn OrderRepository,
+    refunds: &dyn RefundRepository,
+    ledger: &dyn PremiumLedgerRepository,
+    requester: Requester,
+    order_id: Uuid,
+    reason: Option<String>,
+    tx_hash: Option<String>,
+    payment_rate: i64,
+) -> Result<RefundOutcome, AppError> {
+    let order = orders
+        .find_by_id(order_id)
+        .await
+        .map_err(|_| AppError::DatabaseError)?
+        .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
+
+    let picker = pickers
+        .find_by_id(order.picker_id)
+        .await
+        .map_err(|_| AppError::DatabaseError)?
+        .ok_or_else(|| AppError::NotFound("Picker not found".to_string()))?;
+
+    let is_developer = picker.dev_user_id == requester.user_id;
+    let is_buyer = order.user_id == requester.user_id;
+    if !requester.is_admin && !is_developer && !is_buyer {
+        return Err(AppError::NotFound("Order not found".to_string()));
+    }
+
+    match order.status {
+        OrderStatus::Pending => {
+            // 支付交易已广播的钱包订单可能已经到账，取消后到账的交易不会再确认订单；等待对账确认后再走钱包退款流程
+            if order.pay_type == PayType::Wallet && order.tx_hash.is_some() {
+                return Err(AppError::BadRequest(
+                    "Wallet payment has been broadcast, wait for it to be confirmed before requesting a refund".to_string(),
+                ));
+            }
+            if !orders.mark_cancelled(order.order_id).await.map_err(|_| AppError::DatabaseError)? {
+                return Err(AppError::BadRequest("Order status has changed, please retry".to_string()));
+            }
+            info!("Order {} cancelled by {}", order.order_id, requester.user_id);
+            return Ok(RefundOutcome {
+                order_status: OrderStatus::Cancelled,
+                refund: None,
+            });
+        }
+        OrderStatus::Success => {}
+        _ => {
+            return Err(AppError::BadRequest(
+                "Only pending or successful orders can be cancelled or refunded".to_string(),
+            ));
+        }
+    }
+
+    if !requester.is_admin && !is_developer {
+        return Err(AppError::BadRequest(
+            "Only the picker developer or an admin can refund a paid order".to_string(),
+        ));
+    }
+
+    let existing = refunds.list_for_order(order.order_id).await.map_err(|_| AppError::DatabaseError)?;
+    if existing.iter().any(|refund| refund.status != RefundStatus::Failed) {
+        return Err(AppError::BadRequest("Order already has a refund in progress".to_string()));
+    }
+
+    let mut refund = Refund {
+        refund_id: Uuid::new_v4(),
+        order_id: order.order_id,
+        requested_by: requester.user_id,
+        amount: order.amount,
+        status: RefundStatus::Pending,
+        tx_hash: None,
+        reason: reason.filter(|reason| !reason.trim().is_empty()),
+        created_at: Utc::now(),
+        completed_at: None,
+    };
+
+    match order.pay_type {
+        PayType::Premium => {
+            let entries = ledger.entries_for_order(order.order_id).await.map_err(|_| AppError::DatabaseError)?;
+            let dev_income = entries
+                .iter()
+                .find(|entry| entry.kind == LedgerEntryKind::DevPayout && entry.user_id == Some(picker.dev_user_id))
+                .map(|entry| entry.amount)
+                .unwrap_or_else(|| premium_dev_income(order.amount, payment_rate));
+            let dev_user = users
+                .find_by_id(picker.dev_user_id)
+                .await
+                .map_err(|_| AppError::DatabaseError)?
+                .ok_or_else(|| AppError::NotFound("Dev User not found".to_string()))?;
+            if dev_user.premium_balance < dev_income {
+                return Err(AppError::BadRequest(
+                    "Developer premium balance is insufficient to reverse the payment".to_string(),
+                ));
+            }
+
+            refund.status = RefundStatus::Completed;
+            refund.completed_at = Some(refund.created_at);
+            let refunded = refunds
+                .refund_premium(&refund, dev_user.user_id, dev_income)
+                .await
+                .map_err(|e| {
+                    error!("Failed to refund premium order {}: {}", order.order_id, e);
+                    AppError::DatabaseError
+                })?;
+            if !refunded {
+                return Err(AppError::BadRequest("Order status has changed, please retry".to_string()));
+            }
+            info!("Premium order {} refunded, {} returned to buyer", order.order_id, order.amount);
+
+            Ok(RefundOutcome {
+                order_status: OrderStatus::Refunded,
+                refund: Some(refund),
+            })
+        }
+        PayType::Wallet => {
+            // 合约的 withdrawFunds 只能由管理员调用，退款金额由管理员在链上转给买家
+            if !requester.is_admin {
+                return Err(AppError::BadRequest("Wallet orders can only be refunded by an admin".to_string()));
+            }
+            let tx_hash = tx_hash
+                .map(|hash| hash.trim().to_string())
+                .filter(|hash| is_tx_hash(hash))
+                .ok_or_else(|| {
+                    AppError::BadRequest("tx_hash of the withdrawal transaction is required".to_string())
+                })?;
+
+            refund.tx_hash = Some(tx_hash);
+            refunds.create(&refund).await.map_err(|e| {
+                error!("Failed to create refund for order {}: {}", order.order_id, e);
+                AppError::DatabaseError
+            })?;
+            info!("Wallet refund {} recorded for order {}", refund.refund_id, order.order_id);
+
+            Ok(RefundOutcome {
+                order_status: OrderStatus::Success,
+                refund: Some(refund),
+            })
+        }
+    }
+}
+
+// 0x 开头的 32 字节十六进制交易哈希
+pub(crate) fn is_tx_hash(hash: &str) -> bool {
+    hash.strip_prefix("0x")
+        .is_some_and(|hex| hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// 确认钱包订单退款的提现交易：最多查询 attempts 次回执，每次间隔 interval
+/// 交易成功置为 completed（订单置为 refunded），回滚置为 failed，返回退款的新状态；仍未上链时返回 None，交给后台对账处理
+pub async fn confirm_refund(
+    refunds: &dyn RefundRepository,
+    receipts: &dyn ReceiptSource,
+    refund: &Refund,
+    attempts: u32,
+    interval: Duration,
+) -> Result<Option<RefundStatus>, AppError> {
+    let Some(tx_hash) = refund.tx_hash.as_deref() else {
+        return Ok(None);
+    };
+
+    for attempt in 1..=attempts.max(1) {
+        let (result, status) = match receipts.receipt_status(tx_hash).await {
+            Ok(ReceiptStatus::Succeeded) => (refunds.mark_completed(refund.refund_id).await, RefundStatus::Completed),
+            Ok(ReceiptStatus::Reverted) => (refunds.mark_failed(refund.refund_id).await, RefundStatus::Failed),
+            Ok(ReceiptStatus::NotFound) | Err(_) => {
+                info!("Refund {} not confirmed yet (attempt {}/{})", refund.refund_id, attempt, attempts);
+                if attempt < attempts {
+                    tokio::time::sleep(interval).await;
+                }
+                continue;
+            }
+        };
+        result.map_err(|e| {
+            error!("Failed to update refund {}: {}", refund.refund_id, e);
+            AppError::DatabaseError
+        })?;
+        info!("Refund {} of order {} is {:?}", refund.refund_id, refund.order_id, status);
+        return Ok(Some(status));
+    }
+
+    info!("Refund {} still pending, leaving it to the reconciler", refund.refund_id);
+    Ok(None)
+}
+
+/// 一轮退款对账的结果
+#[derive(Debug, Default, PartialEq)]
+pub struct RefundReconcileReport {
+    pub completed: usize,
+    pub failed: usize,
+    // 交易尚未上链或查询回执失败，等待下一轮
+    pub pending: usize,
+}
+
+/// 对最多 batch_size 个待确认的钱包退款进行一轮对账
+pub async fn reconcile_pending_refunds(
+    refunds: &dyn RefundRepository,
+    receipts: &dyn ReceiptSource,
+    batch_size: i64,
+) -> Result<RefundReconcileReport, AppError> {
+    let pending = refunds.list_pending(batch_size).await.map_err(|e| {
+        error!("Failed to load pending refunds: {}", e);
+        AppError::DatabaseError
+    })?;
+
+    let mut report = RefundReconcileReport::default();
+    for refund in pending {
+        let status = match refund.tx_hash.as_deref() {
+            Some(tx_hash) => receipts.receipt_status(tx_hash).await.unwrap_or(ReceiptStatus::NotFound),
+            None => ReceiptStatus::NotFound,
+        };
+        let result = match status {
+            ReceiptStatus::Succeeded => refunds.mark_completed(refund.refund_id).await.map(|changed| {
+                if changed {
+                    report.completed += 1;
+                }
+            }),
+            ReceiptStatus::Reverted => refunds.mark_failed(refund.refund_id).await.map(|changed| {
+                if changed {
+                    report.failed += 1;
+                }
+            }),
+            ReceiptStatus::NotFound => {
+                report.pending += 1;
+                Ok(())
+            }
+        };
+        result.map_err(|e| {
+            error!("Failed to update refund {}: {}", refund.refund_id, e);
+            AppError::DatabaseError
+        })?;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::models::{Order, Picker, UserType};
+    use crate::repository::fixtures::{self, picker, user};
+    use crate::repository::MemoryRepository;
+
+    const WITHDRAW_TX: &str = "0x4444444444444444444444444444444444444444444444444444444444444444";
+
+    // 按交易哈希返回预置的回执状态，未预置的视为未上链
+    struct FakeReceipts(HashMap<String, ReceiptStatus>);
+
+    #[async_trait::async_trait]
+    impl ReceiptSource for FakeReceipts {
+        async fn receipt_status(&self, tx_hash: &str) -> Result<ReceiptStatus, AppError> {
+            Ok(self.0.get(tx_hash).copied().unwrap_or(ReceiptStatus::NotFound))
+        }
+    }
+
+    // 退款测试统一使用 100 积分的订单
+    fn order(user_id: Uuid, picker_id: Uuid, pay_type: PayType, status: OrderStatus) -> Order {
+        Order { amount: 100, ..fixtures::order(user_id, picker_id, pay_type, status) }
+    }
+
+    // 写入开发者（已有 90 积分收入）、买家和一个 Picker，返回 (仓储, 开发者ID, 买家ID, PickerID)
+    async fn setup() -> (MemoryRepository, Uuid, Uuid, Uuid) {
+        let repo = MemoryRepository::new();
+        let dev = user("dev@example.com", UserType::Dev, 90);
+        let buyer = user("buyer@example.com", UserType::Gen, 0);
+        UserRepository::create(&repo, &dev).await.unwrap();
+        UserRepository::create(&repo, &buyer).await.unwrap();
+        let picker = Picker {
+            alias: "Refundable".to_string(),
+            description: "Refundable picker".to_string(),
+            download_count: 1,
+            ..picker(dev.user_id, 100)
+        };
+        PickerRepository::create(&repo, &picker).await.unwrap();
+        (repo, dev.user_id, buyer.user_id, picker.picker_id)
+    }
+
+    async fn refund(
+        repo: &MemoryRepository,
+        requester: Requester,
+        order_id: Uuid,
+        tx_hash: Option<&str>,
+    ) -> Result<RefundOutcome, AppError> {
+        refund_order(
+            repo,
+            repo,
+            repo,
+            repo,
+            repo,
+            requester,
+            order_id,
+            Some("requested by buyer".to_string()),
+            tx_hash.map(str::to_string),
+            10,
+        )
+        .await
+    }
+
+    fn user_requester(user_id: Uuid) -> Requester {
+        Requester { user_id, is_admin: false }
+    }
+
+    fn admin() -> Requester {
+        Requester { user_id: Uuid::new_v4(), is_admin: true }
+    }
+
+    #[tokio::test]
+    async fn test_developer_refunds_premium_order() {
+        let (repo, dev_id, buyer_id, picker_id) = setup().await;
+        let paid = order(buyer_id, picker_id, PayType::Premium, OrderStatus::Success);
+        OrderRepository::create(&repo, &paid).await.unwrap();
+
+        let outcome = refund(&repo, user_requester(dev_id), paid.order_id, None).await.unwrap();
+        assert_eq!(outcome.order_status, OrderStatus::Refunded);
+        let record = outcome.refund.unwrap();
+        assert_eq!(record.status, RefundStatus::Completed);
+        assert_eq!(record.amount, 100);
+
+        // 买家拿回全部积分，开发者扣回扣除 10% 手续费后的收入
+        assert_eq!(UserRepository::find_by_id(&repo, buyer_id).await.unwrap().unwrap().premium_balance, 100);
+        assert_eq!(UserRepository::find_by_id(&repo, dev_id).await.unwrap().unwrap().premium_balance, 0);
+
+        let again = refund(&repo, user_requester(dev_id), paid.order_id, None).await;
+        assert!(matches!(again, Err(AppError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_premium_refund_reverses_recorded_split() {
+        let (repo, dev_id, _buyer_id, picker_id) = setup().await;
+        let buyer = user("premium@example.com", UserType::Gen, 100);
+        UserRepository::create(&repo, &buyer).await.unwrap();
+
+        // 下单时手续费为 20%，退款时 payment_rate 已改为 10%，仍按流水中的 80 冲回
+        let paid = order(buyer.user_id, picker_id, PayType::Premium, OrderStatus::Success);
+        OrderRepository::settle_premium(&repo, &paid, dev_id, 80).await.unwrap();
+        assert_eq!(UserRepository::find_by_id(&repo, dev_id).await.unwrap().unwrap().premium_balance, 170);
+
+        refund(&repo, user_requester(dev_id), paid.order_id, None).await.unwrap();
+        assert_eq!(UserRepository::find_by_id(&repo, dev_id).await.unwrap().unwrap().premium_balance, 90);
+        assert_eq!(UserRepository::find_by_id(&repo, buyer.user_id).await.unwrap().unwrap().premium_balance, 100);
+
+        // 支付与退款各三条分录，每组金额之和为 0，余额与流水一致
+        let entries = repo.entries_for_order(paid.order_id).await.unwrap();
+        assert_eq!(entries.len(), 6);
+        assert_eq!(entries.iter().map(|entry| entry.amount).sum::<i64>(), 0);
+        assert!(repo.balance_mismatches(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_premium_refund_requires_developer_balance() {
+        let (repo, dev_id, buyer_id, picker_id) = setup().await;
+        let mut paid = order(buyer_id, picker_id, PayType::Premium, OrderStatus::Success);
+        paid.amount = 200;
+        OrderRepository::create(&repo, &paid).await.unwrap();
+
+        let result = refund(&repo, admin(), paid.order_id, None).await;
+        assert!(matches!(result, Err(AppError::BadRequest(msg)) if msg.contains("insufficient")));
+        let stored = OrderRepository::find_by_id(&repo, paid.order_id).await.unwrap().unwrap();
+        assert_eq!(stored.status, OrderStatus::Success);
+        assert_eq!(UserRepository::find_by_id(&repo, dev_id).await.unwrap().unwrap().premium_balance, 90);
+    }
+
+    #[tokio::test]
+    async fn test_buyer_cancels_pending_order_but_cannot_refund() {
+        let (repo, _dev_id, buyer_id, picker_id) = setup().await;
+        let pending = order(buyer_id, picker_id, PayType::Wallet, OrderStatus::Pending);
+        OrderRepository::create(&repo, &pending).await.unwrap();
+
+        let outcome = refund(&repo, user_requester(buyer_id), pending.order_id, None).await.unwrap();
+        assert_eq!(outcome.order_status, OrderStatus::Cancelled);
+        assert!(outcome.refund.is_none());
+        let again = refund(&repo, user_requester(buyer_id), pending.order_id, None).await;
+        assert!(matches!(again, Err(AppError::BadRequest(_))));
+
+        let paid = order(buyer_id, picker_id, PayType::Premium, OrderStatus::Success);
+        OrderRepository::create(&repo, &paid).await.unwrap();
+        let result = refund(&repo, user_requester(buyer_id), paid.order_id, None).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_wallet_order_cannot_be_cancelled() {
+        let (repo, _dev_id, buyer_id, picker_id) = setup().await;
+        let broadcast = Order {
+            tx_hash: Some("0x5555555555555555555555555555555555555555555555555555555555555555".to_string()),
+            ..order(buyer_id, picker_id, PayType::Wallet, OrderStatus::Pending)
+        };
+        OrderRepository::create(&repo, &broadcast).await.unwrap();
+
+        for requester in [user_requester(buyer_id), admin()] {
+            let result = refund(&repo, requester, broadcast.order_id, None).await;
+            assert!(matches!(result, Err(AppError::BadRequest(_))));
+        }
+        // 订单保持待支付，交易确认后仍可由对账置为成功
+        let stored = OrderRepository::find_by_id(&repo, broadcast.order_id).await.unwrap().unwrap();
+        assert_eq!(stored.status, OrderStatus::Pending);
+        assert!(OrderRepository::mark_success(&repo, broadcast.order_id, picker_id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_unrelated_user_gets_not_found() {
+        let (repo, _dev_id, buyer_id, picker_id) = setup().await;
+        let paid = order(buyer_id, picker_id, PayType::Premium, OrderStatus::Success);
+        OrderRepository::create(&repo, &paid).await.unwrap();
+
+        let result = refund(&repo, user_requester(Uuid::new_v4()), paid.order_id, None).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+        let result = refund(&repo, admin(), Uuid::new_v4(), None).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_wallet_refund_is_confirmed_by_withdrawal_receipt() {
+        let (repo, dev_id, buyer_id, picker_id) = setup().await;
+        let paid = order(buyer_id, picker_id, PayType::Wallet, OrderStatus::Success);
+        OrderRepository::create(&repo, &paid).await.unwrap();
+
+        // 钱包订单只能由管理员退款，且必须提供提现交易哈希
+        let result = refund(&repo, user_requester(dev_id), paid.order_id, Some(WITHDRAW_TX)).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+        let result = refund(&repo, admin(), paid.order_id, Some("0x1234")).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+
+        let outcome = refund(&repo, admin(), paid.order_id, Some(WITHDRAW_TX)).await.unwrap();
+        assert_eq!(outcome.order_status, OrderStatus::Success);
+        let record = outcome.refund.unwrap();
+        assert_eq!(record.status, RefundStatus::Pending);
+        let duplicate = refund(&repo, admin(), paid.order_id, Some(WITHDRAW_TX)).await;
+        assert!(matches!(duplicate, Err(AppError::BadRequest(_))));
+
+        let unconfirmed = FakeReceipts(HashMap::new());
+        let status = confirm_refund(&repo, &unconfirmed, &record, 2, Duration::ZERO).await.unwrap();
+        assert_eq!(status, None);
+
+        let receipts = FakeReceipts(HashMap::from([(WITHDRAW_TX.to_string(), ReceiptStatus::Succeeded)]));
+        let status = confirm_refund(&repo, &receipts, &record, 1, Duration::ZERO).await.unwrap();
+        assert_eq!(status, Some(RefundStatus::Completed));
+        let stored = OrderRepository::find_by_id(&repo, paid.order_id).await.unwrap().unwrap();
+        assert_eq!(stored.status, OrderStatus::Refunded);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_pending_refunds() {
+        let (repo, _dev_id, buyer_id, picker_id) = setup().await;
+        let reverted_tx = "0x5555555555555555555555555555555555555555555555555555555555555555";
+        let unknown_tx = "0x6666666666666666666666666666666666666666666666666666666666666666";
+        let mut ids = Vec::new();
+        for tx_hash in [WITHDRAW_TX, reverted_tx, unknown_tx] {
+            let paid = order(buyer_id, picker_id, PayType::Wallet, OrderStatus::Success);
+            OrderRepository::create(&repo, &paid).await.unwrap();
+            refund(&repo, admin(), paid.order_id, Some(tx_hash)).await.unwrap();
+            ids.push(paid.order_id);
+        }
+
+        let receipts = FakeReceipts(HashMap::from([
+            (WITHDRAW_TX.to_string(), ReceiptStatus::Succeeded),
+            (reverted_tx.to_string(), ReceiptStatus::Reverted),
+        ]));
+        let report = reconcile_pending_refunds(&repo, &receipts, 10).await.unwrap();
+        assert_eq!(report, RefundReconcileReport { completed: 1, failed: 1, pending: 1 });
+
+        let mut statuses = Vec::new();
+        for order_id in &ids {
+            statuses.push(OrderRepository::find_by_id(&repo, *order_id).await.unwrap().unwrap().status);
+        }
+        assert_eq!(statuses, vec![OrderStatus::Refunded, OrderStatus::Success, OrderStatus::Success]);
+
+        // 回滚的提现可以重新发起退款
+        let outcome = refund(&repo, admin(), ids[1], Some(WITHDRAW_TX)).await.unwrap();
+        assert_eq!(outcome.refund.unwrap().status, RefundStatus::Pending);
+    }
+}
@@ -0,0 +1,120 @@
+use tracing::{error, info};
+
+use crate::keyring::WalletKeyring;
+use crate::repository::UserRepository;
+use crate::utils::AppError;
+
+/// 主密钥轮换结果
+#[derive(Debug, Default, PartialEq)]
+pub struct RotationReport {
+    // 已用当前主密钥重新加密
+    pub rotated: usize,
+    // 已经是当前主密钥加密，无需处理
+    pub unchanged: usize,
+    // 无法解密，或写回时被并发修改，需要人工检查
+    pub failed: usize,
+}
+
+/// 用当前主密钥重新加密所有 users.private_key
+/// 旧格式与使用 previous_keys 加密的记录会被解密后重新加密；可重复执行，已轮换的记录会被跳过
+pub async fn rotate_wallet_keys(users: &dyn UserRepository, keyring: &WalletKeyring) -> Result<RotationReport, AppError> {
+    let private_keys = users.list_private_keys().await.map_err(|e| {
+        error!("Failed to load private keys for rotation: {}", e);
+        AppError::DatabaseError
+    })?;
+
+    let mut report = RotationReport::default();
+    for (user_id, stored) in private_keys {
+        if !keyring.needs_reencrypt(&stored) {
+            report.unchanged += 1;
+            continue;
+        }
+
+        let reencrypted = match keyring.decrypt(&stored).and_then(|plaintext| keyring.encrypt(&plaintext)) {
+            Ok(reencrypted) => reencrypted,
+            Err(_) => {
+                error!("Failed to re-encrypt private key of user {}", user_id);
+                report.failed += 1;
+                continue;
+            }
+        };
+
+        let replaced = users
+            .replace_private_key(user_id, &stored, &reencrypted)
+            .await
+            .map_err(|e| {
+                error!("Failed to store re-encrypted private key of user {}: {}", user_id, e);
+                AppError::DatabaseError
+            })?;
+        if replaced {
+            report.rotated += 1;
+        } else {
+            error!("Private key of user {} changed during rotation, skipped", user_id);
+            report.failed += 1;
+        }
+    }
+
+    info!(
+        "Wallet key rotation finished: {} rotated, {} unchanged, {} failed",
+        report.rotated, report.unchanged, report.failed
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyring::tests::{legacy_encrypt, password_config};
+    use crate::models::{User, UserType};
+    use crate::repository::MemoryRepository;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    const OLD_KEY: &str = "openpickopenpickopenpickopenpick";
+    const NEW_KEY: &str = "00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff";
+
+    async fn create_user(repo: &MemoryRepository, private_key: String) -> Uuid {
+        let user = User {
+            user_id: Uuid::new_v4(),
+            email: format!("{}@example.com", Uuid::new_v4().simple()),
+            user_name: "test".to_string(),
+            user_password: "hash".to_string(),
+            user_type: UserType::Gen,
+            wallet_address: "0x0000000000000000000000000000000000000000".to_string(),
+            private_key,
+            premium_balance: 0,
+            created_at: Utc::now(),
+        };
+        UserRepository::create(repo, &user).await.unwrap();
+        user.user_id
+    }
+
+    async fn private_key(repo: &MemoryRepository, user_id: Uuid) -> String {
+        UserRepository::find_by_id(repo, user_id).await.unwrap().unwrap().private_key
+    }
+
+    #[tokio::test]
+    async fn test_rotate_wallet_keys() {
+        let repo = MemoryRepository::new();
+        let old = WalletKeyring::from_config(&password_config("k1", OLD_KEY, &[])).unwrap();
+        let enveloped = create_user(&repo, old.encrypt("key-a").unwrap()).await;
+        let legacy = create_user(&repo, legacy_encrypt("key-b", OLD_KEY, "openpickopen")).await;
+        let broken = create_user(&repo, "not-a-ciphertext".to_string()).await;
+
+        let rotated = WalletKeyring::from_config(&password_config("k2", NEW_KEY, &[("k1", OLD_KEY)])).unwrap();
+        let report = rotate_wallet_keys(&repo, &rotated).await.unwrap();
+        assert_eq!(report, RotationReport { rotated: 2, unchanged: 0, failed: 1 });
+
+        // 轮换后只需新密钥即可解密
+        let new_only = WalletKeyring::from_config(&password_config("k2", NEW_KEY, &[])).unwrap();
+        let stored = private_key(&repo, enveloped).await;
+        assert!(stored.starts_with("v1:k2:"));
+        assert_eq!(new_only.decrypt(&stored).unwrap(), "key-a");
+        assert_eq!(new_only.decrypt(&private_key(&repo, legacy).await).unwrap(), "key-b");
+        assert_eq!(private_key(&repo, broken).await, "not-a-ciphertext");
+
+        // 重复执行不会再次修改
+        let report = rotate_wallet_keys(&repo, &rotated).await.unwrap();
+        assert_eq!(report, RotationReport { rotated: 0, unchanged: 2, failed: 1 });
+    }
+}
@@ -0,0 +1,466 @@
+use std::time::Duration;
+
+use alloy::primitives::Address;
+use chrono::Utc;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::models::{UserType, Withdrawal, WithdrawalStatus};
+use crate::price_oracle::{usd_to_wei, PriceOracle};
+use crate::repository::{UserRepository, WithdrawalRepository};
+use crate::services::reconciler::{ReceiptSource, ReceiptStatus};
+use crate::services::refunds::{is_tx_hash, Requester};
+use crate::utils::AppError;
+
+/// 提现规则，对应 [premium] to_usd / min_withdrawal
+#[derive(Debug, Clone, Copy)]
+pub struct WithdrawalSettings {
+    // 1 premium 折算的 USD
+    pub premium_to_usd: i64,
+    // 单次提现的最少积分
+    pub min_amount: i64,
+}
+
+/// 开发者申请提现：金额不能超过余额与可提现的销售收入，按 premium.to_usd 折算为 USD，再按当前代币价格锁定应转账的 wei 金额，
+/// 同时扣除积分；转账由管理员在链上完成后通过 submit_payout 提交交易哈希
+pub async fn request_withdrawal(
+    users: &dyn UserRepository,
+    withdrawals: &dyn WithdrawalRepository,
+    oracle: &dyn PriceOracle,
+    user_id: Uuid,
+    amount: i64,
+    settings: WithdrawalSettings,
+) -> Result<Withdrawal, AppError> {
+    let user = users
+        .find_by_id(user_id)
+        .await
+        .map_err(|_| AppError::DatabaseError)?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    if user.user_type != UserType::Dev {
+        return Err(AppError::BadRequest("Only developers can request withdrawals".to_string()));
+    }
+    if amount < settings.min_amount.max(1) {
+        return Err(AppError::BadRequest(format!(
+            "Withdrawal amount must be at least {}",
+            settings.min_amount.max(1)
+        )));
+    }
+    if user.premium_balance < amount {
+        return Err(AppError::BadRequest("Insufficient premium balance".to_string()));
+    }
+    // 只有销售收入可以提现，免费发放或充值的积分只能用于购买
+    let earnings = withdrawals.withdrawable_earnings(user_id).await.map_err(|e| {
+        error!("Failed to load withdrawable earnings for user {}: {}", user_id, e);
+        AppError::DatabaseError
+    })?;
+    if earnings < amount {
+        return Err(AppError::BadRequest("Insufficient withdrawable earnings".to_string()));
+    }
+    if user.wallet_address.parse::<Address>().is_err() {
+        return Err(AppError::BadRequest("Developer wallet address is invalid".to_string()));
+    }
+
+    let amount_usd = amount
+        .checked_mul(settings.premium_to_usd)
+        .ok_or_else(|| AppError::BadRequest("Withdrawal amount is too large".to_string()))?;
+    let token_price = oracle.token_price().await.map_err(|e| {
+        error!("Failed to get token price for withdrawal: {}", e);
+        AppError::InternalServerError
+    })?;
+    let amount_wei = usd_to_wei(amount_usd, &token_price.price).map_err(|e| {
+        error!("Failed to convert withdrawal amount: {}", e);
+        AppError::InternalServerError
+    })?;
+
+    let now = Utc::now();
+    let withdrawal = Withdrawal {
+        withdrawal_id: Uuid::new_v4(),
+        user_id,
+        amount,
+        amount_usd,
+        token_price: token_price.price.to_string(),
+        amount_wei: amount_wei.to_string(),
+        wallet_address: user.wallet_address,
+        status: WithdrawalStatus::Requested,
+        tx_hash: None,
+        created_at: now,
+        updated_at: now,
+        completed_at: None,
+    };
+    let requested = withdrawals.request(&withdrawal).await.map_err(|e| {
+        error!("Failed to request withdrawal for user {}: {}", user_id, e);
+        AppError::DatabaseError
+    })?;
+    // 余额或可提现收入在校验后被并发的支付或提现扣减
+    if !requested {
+        return Err(AppError::BadRequest("Insufficient premium balance or withdrawable earnings".to_string()));
+    }
+    info!(
+        "Withdrawal {} requested by {}: {} premium, {} wei",
+        withdrawal.withdrawal_id, user_id, amount, withdrawal.amount_wei
+    );
+
+    Ok(withdrawal)
+}
+
+/// 开发者取消尚未转账的提现并退回积分，不属于该开发者的提现返回 404
+pub async fn cancel_withdrawal(
+    withdrawals: &dyn WithdrawalRepository,
+    user_id: Uuid,
+    withdrawal_id: Uuid,
+) -> Result<Withdrawal, AppError> {
+    let withdrawal = find_withdrawal(withdrawals, withdrawal_id)
+        .await?
+        .filter(|withdrawal| withdrawal.user_id == user_id)
+        .ok_or_else(|| AppError::NotFound("Withdrawal not found".to_string()))?;
+    if withdrawal.status != WithdrawalStatus::Requested {
+        return Err(AppError::BadRequest("Only requested withdrawals can be cancelled".to_string()));
+    }
+    if !withdrawals.cancel(withdrawal_id).await.map_err(|_| AppError::DatabaseError)? {
+        return Err(AppError::BadRequest("Withdrawal status has changed, please retry".to_string()));
+    }
+    info!("Withdrawal {} cancelled by {}", withdrawal_id, user_id);
+
+    reload(withdrawals, withdrawal_id).await
+}
+
+/// 管理员提交链上转账的交易哈希，提现置为 processing，交易确认后由 confirm_withdrawal 或后台对账完成
+/// 非管理员得到 404，不暴露提现是否存在
+pub async fn submit_payout(
+    withdrawals: &dyn WithdrawalRepository,
+    requester: Requester,
+    withdrawal_id: Uuid,
+    tx_hash: &str,
+) -> Result<Withdrawal, AppError> {
+    let withdrawal = find_withdrawal(withdrawals, withdrawal_id)
+        .await?
+        .filter(|_| requester.is_admin)
+        .ok_or_else(|| AppError::NotFound("Withdrawal not found".to_string()))?;
+    let tx_hash = tx_hash.trim();
+    if !is_tx_hash(tx_hash) {
+        return Err(AppError::BadRequest("tx_hash of the payout transaction is required".to_string()));
+    }
+    if withdrawal.status != WithdrawalStatus::Requested {
+        return Err(AppError::BadRequest("Only requested withdrawals can be paid out".to_string()));
+    }
+    if !withdrawals
+        .mark_processing(withdrawal_id, tx_hash)
+        .await
+        .map_err(|_| AppError::DatabaseError)?
+    {
+        return Err(AppError::BadRequest("Withdrawal status has changed, please retry".to_string()));
+    }
+    info!("Payout {} submitted for withdrawal {} by {}", tx_hash, withdrawal_id, requester.user_id);
+
+    reload(withdrawals, withdrawal_id).await
+}
+
+async fn find_withdrawal(
+    withdrawals: &dyn WithdrawalRepository,
+    withdrawal_id: Uuid,
+) -> Result<Option<Withdrawal>, AppError> {
+    withdrawals.find_by_id(withdrawal_id).await.map_err(|_| AppError::DatabaseError)
+}
+
+async fn reload(withdrawals: &dyn WithdrawalRepository, withdrawal_id: Uuid) -> Result<Withdrawal, AppError> {
+    find_withdrawal(withdrawals, withdrawal_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Withdrawal not found".to_string()))
+}
+
+// 按回执更新提现状态，返回新状态；交易尚未上链时返回 None
+async fn apply_receipt(
+    withdrawals: &dyn WithdrawalRepository,
+    withdrawal_id: Uuid,
+    status: ReceiptStatus,
+) -> Result<Option<WithdrawalStatus>, AppError> {
+    let (result, status) = match status {
+        ReceiptStatus::Succeeded => (withdrawals.mark_completed(withdrawal_id).await, WithdrawalStatus::Completed),
+        ReceiptStatus::Reverted => (withdrawals.mark_failed(withdrawal_id).await, WithdrawalStatus::Failed),
+        ReceiptStatus::NotFound => return Ok(None),
+    };
+    let changed = result.map_err(|e| {
+        error!("Failed to update withdrawal {}: {}", withdrawal_id, e);
+        AppError::DatabaseError
+    })?;
+    Ok(changed.then_some(status))
+}
+
+/// 确认提现的转账交易：最多查询 attempts 次回执，每次间隔 interval
+/// 交易成功置为 completed，回滚置为 failed 并退回积分，返回提现的新状态；仍未上链时返回 None，交给后台对账处理
+pub async fn confirm_withdrawal(
+    withdrawals: &dyn WithdrawalRepository,
+    receipts: &dyn ReceiptSource,
+    withdrawal: &Withdrawal,
+    attempts: u32,
+    interval: Duration,
+) -> Result<Option<WithdrawalStatus>, AppError> {
+    let Some(tx_hash) = withdrawal.tx_hash.as_deref() else {
+        return Ok(None);
+    };
+
+    for attempt in 1..=attempts.max(1) {
+        match receipts.receipt_status(tx_hash).await {
+            Ok(status @ (ReceiptStatus::Succeeded | ReceiptStatus::Reverted)) => {
+                let status = apply_receipt(withdrawals, withdrawal.withdrawal_id, status).await?;
+                info!("Withdrawal {} is {:?}", withdrawal.withdrawal_id, status);
+                return Ok(status);
+            }
+            Ok(ReceiptStatus::NotFound) | Err(_) => {
+                info!(
+                    "Withdrawal {} not confirmed yet (attempt {}/{})",
+                    withdrawal.withdrawal_id, attempt, attempts
+                );
+                if attempt < attempts {
+                    tokio::time::sleep(interval).await;
+                }
+            }
+        }
+    }
+
+    info!("Withdrawal {} still processing, leaving it to the reconciler", withdrawal.withdrawal_id);
+    Ok(None)
+}
+
+/// 一轮提现对账的结果
+#[derive(Debug, Default, PartialEq)]
+pub struct WithdrawalReconcileReport {
+    pub completed: usize,
+    pub failed: usize,
+    // 交易尚未上链或查询回执失败，等待下一轮
+    pub pending: usize,
+}
+
+/// 对最多 batch_size 个转账中的提现进行一轮对账
+pub async fn reconcile_processing_withdrawals(
+    withdrawals: &dyn WithdrawalRepository,
+    receipts: &dyn ReceiptSource,
+    batch_size: i64,
+) -> Result<WithdrawalReconcileReport, AppError> {
+    let processing = withdrawals
+        .list_by_status(WithdrawalStatus::Processing, batch_size)
+        .await
+        .map_err(|e| {
+            error!("Failed to load processing withdrawals: {}", e);
+            AppError::DatabaseError
+        })?;
+
+    let mut report = WithdrawalReconcileReport::default();
+    for withdrawal in processing {
+        let status = match withdrawal.tx_hash.as_deref() {
+            Some(tx_hash) => receipts.receipt_status(tx_hash).await.unwrap_or(ReceiptStatus::NotFound),
+            None => ReceiptStatus::NotFound,
+        };
+        match apply_receipt(withdrawals, withdrawal.withdrawal_id, status).await? {
+            Some(WithdrawalStatus::Completed) => report.completed += 1,
+            Some(_) => report.failed += 1,
+            None if status == ReceiptStatus::NotFound => report.pending += 1,
+            None => {}
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::models::{LedgerEntryKind, Order, OrderStatus, PayType, User};
+    use crate::price_oracle::FixedPriceOracle;
+    use crate::repository::fixtures::{self, order, picker};
+    use crate::repository::{MemoryRepository, OrderRepository, PickerRepository, PremiumLedgerRepository};
+
+    const PAYOUT_TX: &str = "0x7777777777777777777777777777777777777777777777777777777777777777";
+    const SETTINGS: WithdrawalSettings = WithdrawalSettings { premium_to_usd: 1, min_amount: 10 };
+
+    // 按交易哈希返回预置的回执状态，未预置的视为未上链
+    struct FakeReceipts(HashMap<String, ReceiptStatus>);
+
+    #[async_trait::async_trait]
+    impl ReceiptSource for FakeReceipts {
+        async fn receipt_status(&self, tx_hash: &str) -> Result<ReceiptStatus, AppError> {
+            Ok(self.0.get(tx_hash).copied().unwrap_or(ReceiptStatus::NotFound))
+        }
+    }
+
+    // 提现测试使用非零的钱包地址，便于核对提现记录中的收款地址
+    fn user(email: &str, user_type: UserType, premium_balance: i64) -> User {
+        User {
+            wallet_address: "0x1111111111111111111111111111111111111111".to_string(),
+            ..fixtures::user(email, user_type, premium_balance)
+        }
+    }
+
+    fn admin() -> Requester {
+        Requester { user_id: Uuid::new_v4(), is_admin: true }
+    }
+
+    async fn balance(repo: &MemoryRepository, user_id: Uuid) -> i64 {
+        UserRepository::find_by_id(repo, user_id).await.unwrap().unwrap().premium_balance
+    }
+
+    // 以 Premium 支付卖出一个 Picker，开发者获得 dev_income 的销售收入
+    async fn sell(repo: &MemoryRepository, dev_id: Uuid, dev_income: i64) {
+        let buyer = user(&format!("buyer-{}@example.com", Uuid::new_v4()), UserType::Gen, dev_income);
+        UserRepository::create(repo, &buyer).await.unwrap();
+        let picker = picker(dev_id, dev_income);
+        PickerRepository::create(repo, &picker).await.unwrap();
+        let order = Order {
+            amount: dev_income,
+            ..order(buyer.user_id, picker.picker_id, PayType::Premium, OrderStatus::Success)
+        };
+        assert!(repo.settle_premium(&order, dev_id, dev_income).await.unwrap());
+    }
+
+    // 写入一个有 500 积分销售收入的开发者，返回 (仓储, 开发者ID)
+    async fn setup() -> (MemoryRepository, Uuid) {
+        let repo = MemoryRepository::new();
+        let dev = user("dev@example.com", UserType::Dev, 0);
+        UserRepository::create(&repo, &dev).await.unwrap();
+        sell(&repo, dev.user_id, 500).await;
+        (repo, dev.user_id)
+    }
+
+    async fn request(repo: &MemoryRepository, user_id: Uuid, amount: i64) -> Result<Withdrawal, AppError> {
+        // 代币价格 0.5 USD：1 premium = 1 USD = 2 个代币
+        let oracle = FixedPriceOracle::new("0.5".parse().unwrap());
+        request_withdrawal(repo, repo, &oracle, user_id, amount, SETTINGS).await
+    }
+
+    #[tokio::test]
+    async fn test_request_locks_amount_and_debits_balance() {
+        let (repo, dev_id) = setup().await;
+
+        let withdrawal = request(&repo, dev_id, 200).await.unwrap();
+        assert_eq!(withdrawal.status, WithdrawalStatus::Requested);
+        assert_eq!(withdrawal.amount_usd, 200);
+        assert_eq!(withdrawal.amount_wei, "400000000000000000000");
+        assert_eq!(withdrawal.wallet_address, "0x1111111111111111111111111111111111111111");
+        assert_eq!(balance(&repo, dev_id).await, 300);
+
+        let (entries, _) = PremiumLedgerRepository::list_for_user(&repo, dev_id, 10, 0).await.unwrap();
+        assert_eq!(entries[0].kind, LedgerEntryKind::Withdrawal);
+        assert_eq!(entries[0].amount, -200);
+        assert_eq!(entries[0].transaction_id, withdrawal.withdrawal_id);
+        assert!(repo.balance_mismatches(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_request_validation() {
+        let (repo, dev_id) = setup().await;
+        let buyer = user("buyer@example.com", UserType::Gen, 500);
+        UserRepository::create(&repo, &buyer).await.unwrap();
+
+        let result = request(&repo, buyer.user_id, 100).await;
+        assert!(matches!(result, Err(AppError::BadRequest(msg)) if msg.contains("developers")));
+        let result = request(&repo, dev_id, 5).await;
+        assert!(matches!(result, Err(AppError::BadRequest(msg)) if msg.contains("at least 10")));
+        let result = request(&repo, dev_id, 501).await;
+        assert!(matches!(result, Err(AppError::BadRequest(msg)) if msg.contains("Insufficient")));
+        assert_eq!(balance(&repo, dev_id).await, 500);
+    }
+
+    #[tokio::test]
+    async fn test_request_is_capped_by_earnings() {
+        let repo = MemoryRepository::new();
+        let dev = user("granted-dev@example.com", UserType::Dev, 0);
+        UserRepository::create(&repo, &dev).await.unwrap();
+
+        // 免费发放的积分可以消费，但不能提现
+        assert!(repo.grant_free(dev.user_id, 0, 300, Utc::now()).await.unwrap());
+        let result = request(&repo, dev.user_id, 100).await;
+        assert!(matches!(result, Err(AppError::BadRequest(msg)) if msg == "Insufficient withdrawable earnings"));
+
+        // 只能提取销售收入部分，已提现的部分不能重复提取
+        sell(&repo, dev.user_id, 50).await;
+        assert_eq!(repo.withdrawable_earnings(dev.user_id).await.unwrap(), 50);
+        let result = request(&repo, dev.user_id, 51).await;
+        assert!(matches!(result, Err(AppError::BadRequest(msg)) if msg == "Insufficient withdrawable earnings"));
+        let withdrawal = request(&repo, dev.user_id, 30).await.unwrap();
+        assert_eq!(repo.withdrawable_earnings(dev.user_id).await.unwrap(), 20);
+        assert!(request(&repo, dev.user_id, 30).await.is_err());
+
+        // 取消的提现冲回后可以再次提取
+        cancel_withdrawal(&repo, dev.user_id, withdrawal.withdrawal_id).await.unwrap();
+        assert_eq!(repo.withdrawable_earnings(dev.user_id).await.unwrap(), 50);
+        request(&repo, dev.user_id, 50).await.unwrap();
+        assert_eq!(balance(&repo, dev.user_id).await, 300);
+        assert!(repo.balance_mismatches(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_returns_credits() {
+        let (repo, dev_id) = setup().await;
+        let withdrawal = request(&repo, dev_id, 100).await.unwrap();
+
+        // 其他用户看不到该提现
+        let result = cancel_withdrawal(&repo, Uuid::new_v4(), withdrawal.withdrawal_id).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+
+        let cancelled = cancel_withdrawal(&repo, dev_id, withdrawal.withdrawal_id).await.unwrap();
+        assert_eq!(cancelled.status, WithdrawalStatus::Cancelled);
+        assert_eq!(balance(&repo, dev_id).await, 500);
+        let again = cancel_withdrawal(&repo, dev_id, withdrawal.withdrawal_id).await;
+        assert!(matches!(again, Err(AppError::BadRequest(_))));
+        assert!(repo.balance_mismatches(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_payout_is_confirmed_by_receipt() {
+        let (repo, dev_id) = setup().await;
+        let withdrawal = request(&repo, dev_id, 100).await.unwrap();
+
+        // 只有管理员可以提交转账，且必须是有效的交易哈希
+        let developer = Requester { user_id: dev_id, is_admin: false };
+        let result = submit_payout(&repo, developer, withdrawal.withdrawal_id, PAYOUT_TX).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+        let result = submit_payout(&repo, admin(), withdrawal.withdrawal_id, "0x1234").await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+
+        let processing = submit_payout(&repo, admin(), withdrawal.withdrawal_id, PAYOUT_TX).await.unwrap();
+        assert_eq!(processing.status, WithdrawalStatus::Processing);
+        assert_eq!(processing.tx_hash.as_deref(), Some(PAYOUT_TX));
+        // 已转账的提现不能再取消
+        let result = cancel_withdrawal(&repo, dev_id, withdrawal.withdrawal_id).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+
+        let unconfirmed = FakeReceipts(HashMap::new());
+        let status = confirm_withdrawal(&repo, &unconfirmed, &processing, 2, Duration::ZERO).await.unwrap();
+        assert_eq!(status, None);
+
+        let receipts = FakeReceipts(HashMap::from([(PAYOUT_TX.to_string(), ReceiptStatus::Succeeded)]));
+        let status = confirm_withdrawal(&repo, &receipts, &processing, 1, Duration::ZERO).await.unwrap();
+        assert_eq!(status, Some(WithdrawalStatus::Completed));
+        let stored = WithdrawalRepository::find_by_id(&repo, withdrawal.withdrawal_id).await.unwrap().unwrap();
+        assert!(stored.completed_at.is_some());
+        assert_eq!(balance(&repo, dev_id).await, 400);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_processing_withdrawals() {
+        let (repo, dev_id) = setup().await;
+        let reverted_tx = "0x8888888888888888888888888888888888888888888888888888888888888888";
+        let unknown_tx = "0x9999999999999999999999999999999999999999999999999999999999999999";
+        for tx_hash in [PAYOUT_TX, reverted_tx, unknown_tx] {
+            let withdrawal = request(&repo, dev_id, 100).await.unwrap();
+            submit_payout(&repo, admin(), withdrawal.withdrawal_id, tx_hash).await.unwrap();
+        }
+        assert_eq!(balance(&repo, dev_id).await, 200);
+
+        let receipts = FakeReceipts(HashMap::from([
+            (PAYOUT_TX.to_string(), ReceiptStatus::Succeeded),
+            (reverted_tx.to_string(), ReceiptStatus::Reverted),
+        ]));
+        let report = reconcile_processing_withdrawals(&repo, &receipts, 10).await.unwrap();
+        assert_eq!(report, WithdrawalReconcileReport { completed: 1, failed: 1, pending: 1 });
+
+        // 回滚的转账退回积分
+        assert_eq!(balance(&repo, dev_id).await, 300);
+        assert!(repo.balance_mismatches(10).await.unwrap().is_empty());
+        let processing = repo.list_by_status(WithdrawalStatus::Processing, 10).await.unwrap();
+        assert_eq!(processing.len(), 1);
+        assert_eq!(processing[0].tx_hash.as_deref(), Some(unknown_tx));
+    }
+}
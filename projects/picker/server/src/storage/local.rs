@@ -0,0 +1,108 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use tokio::fs;
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+use super::{is_content_key, Blob, BlobStore, StorageError};
+
+/// 本地文件系统存储：文件按 key 前两位分目录保存在 root 下，如 `{root}/3a/3a7b…e1.zip`
+pub struct LocalBlobStore {
+    root: PathBuf,
+}
+
+impl LocalBlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    // 只接受 content key，key 不会拼出 root 以外的路径
+    fn path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        if !is_content_key(key) {
+            return Err(StorageError::Invalid(format!("{:?} is not a content key", key)));
+        }
+        Ok(self.root.join(&key[..2]).join(key))
+    }
+}
+
+#[async_trait]
+impl BlobStore for LocalBlobStore {
+    async fn put_file(&self, key: &str, source: &Path) -> Result<bool, StorageError> {
+        let path = self.path(key)?;
+        if fs::try_exists(&path).await? {
+            return Ok(false);
+        }
+        let dir = path.parent().expect("blob path has a parent directory");
+        fs::create_dir_all(dir).await?;
+
+        // 先复制到同目录的临时文件再重命名，读取方不会看到写了一半的文件
+        let partial = dir.join(format!("{}.{}.part", key, Uuid::new_v4()));
+        let copied = match fs::copy(source, &partial).await {
+            Ok(_) => fs::rename(&partial, &path).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = copied {
+            let _ = fs::remove_file(&partial).await;
+            return Err(e.into());
+        }
+        Ok(true)
+    }
+
+    async fn get(&self, key: &str) -> Result<Blob, StorageError> {
+        let file = match fs::File::open(self.path(key)?).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Err(StorageError::NotFound(key.to_string())),
+            Err(e) => return Err(e.into()),
+        };
+        let size = file.metadata().await?.len();
+        Ok(Blob {
+            size: Some(size),
+            stream: ReaderStream::new(file).boxed(),
+        })
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, StorageError> {
+        Ok(fs::try_exists(self.path(key)?).await?)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match fs::remove_file(self.path(key)?).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::storage::tests::exercise_store;
+
+    #[tokio::test]
+    async fn test_local_blob_store() {
+        let root = tempfile::tempdir().unwrap();
+        exercise_store(Arc::new(LocalBlobStore::new(root.path()))).await;
+    }
+
+    #[tokio::test]
+    async fn test_local_blob_store_layout() {
+        let root = tempfile::tempdir().unwrap();
+        let store = LocalBlobStore::new(root.path());
+        let source = root.path().join("image.png");
+        tokio::fs::write(&source, b"").await.unwrap();
+
+        let key = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855.png";
+        assert!(store.put_file(key, &source).await.unwrap());
+        assert!(root.path().join("e3").join(key).is_file());
+
+        // 旧记录中的相对路径不是 content key，不会被当作存储中的文件
+        assert!(matches!(store.exists("uploads/images/a.png").await, Err(StorageError::Invalid(_))));
+        assert!(matches!(store.get("../../etc/passwd").await, Err(StorageError::Invalid(_))));
+    }
+}
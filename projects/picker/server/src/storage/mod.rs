@@ -0,0 +1,176 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::body::Bytes;
+use futures_util::stream::BoxStream;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
+
+use crate::config::{StorageBackend, StorageConfig};
+
+mod local;
+mod s3;
+
+pub use local::LocalBlobStore;
+pub use s3::S3BlobStore;
+
+// 文件存储错误
+#[derive(Debug)]
+pub enum StorageError {
+    // key 不存在
+    NotFound(String),
+    // 配置或 key 无效
+    Invalid(String),
+    // 对象存储返回错误
+    Backend(String),
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageError::NotFound(key) => write!(f, "blob {} not found", key),
+            StorageError::Invalid(msg) => write!(f, "invalid storage request: {}", msg),
+            StorageError::Backend(msg) => write!(f, "storage backend error: {}", msg),
+            StorageError::Io(e) => write!(f, "io error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<std::io::Error> for StorageError {
+    fn from(e: std::io::Error) -> Self {
+        StorageError::Io(e)
+    }
+}
+
+/// 读取到的文件内容
+pub struct Blob {
+    pub size: Option<u64>,
+    pub stream: BoxStream<'static, Result<Bytes, std::io::Error>>,
+}
+
+// Picker 文件与图片的存储。文件以内容的 sha256 命名（见 content_key），相同内容只保存一份；
+// 数据库中保存的是 key 而不是文件路径，更换工作目录或存储位置不影响已有记录
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    /// 将本地文件以 key 保存，key 已存在时不重复写入；返回是否新写入
+    async fn put_file(&self, key: &str, source: &Path) -> Result<bool, StorageError>;
+    async fn get(&self, key: &str) -> Result<Blob, StorageError>;
+    async fn exists(&self, key: &str) -> Result<bool, StorageError>;
+    /// 删除文件，key 不存在时视为成功
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+}
+
+/// 由内容的 sha256 与扩展名生成 key，如 `3a7b…e1.zip`
+pub fn content_key(digest: &[u8], extension: &str) -> String {
+    format!("{}.{}", hex::encode(digest), extension)
+}
+
+/// 是否为 content_key 生成的 key；此前的记录保存的是 uploads/ 下的相对路径
+pub fn is_content_key(key: &str) -> bool {
+    match key.split_once('.') {
+        Some((digest, extension)) => {
+            digest.len() == 64
+                && digest.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+                && !extension.is_empty()
+                && extension.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+/// 计算本地文件的 content key
+pub async fn file_content_key(path: &Path, extension: &str) -> std::io::Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(content_key(&hasher.finalize(), extension))
+}
+
+/// 按 [storage] 配置创建文件存储
+pub fn build_blob_store(config: &StorageConfig) -> Result<Arc<dyn BlobStore>, StorageError> {
+    match config.backend {
+        StorageBackend::Local => Ok(Arc::new(LocalBlobStore::new(&config.root))),
+        StorageBackend::S3 => {
+            let s3 = config
+                .s3
+                .as_ref()
+                .ok_or_else(|| StorageError::Invalid("[storage.s3] is required for the s3 backend".to_string()))?;
+            Ok(Arc::new(S3BlobStore::new(s3)?))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::TryStreamExt;
+
+    // 各存储实现共用的行为校验
+    pub(crate) async fn exercise_store(store: Arc<dyn BlobStore>) {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("picker.zip");
+        tokio::fs::write(&source, b"picker archive").await.unwrap();
+        let key = file_content_key(&source, "zip").await.unwrap();
+
+        assert!(!store.exists(&key).await.unwrap());
+        assert!(matches!(store.get(&key).await, Err(StorageError::NotFound(_))));
+
+        // 相同内容只写入一次
+        assert!(store.put_file(&key, &source).await.unwrap());
+        assert!(!store.put_file(&key, &source).await.unwrap());
+        assert!(store.exists(&key).await.unwrap());
+
+        let blob = store.get(&key).await.unwrap();
+        assert_eq!(blob.size, Some(14));
+        let chunks: Vec<Bytes> = blob.stream.try_collect().await.unwrap();
+        assert_eq!(chunks.concat(), b"picker archive");
+
+        store.delete(&key).await.unwrap();
+        assert!(!store.exists(&key).await.unwrap());
+        // 删除不存在的 key 不报错
+        store.delete(&key).await.unwrap();
+    }
+
+    #[test]
+    fn test_content_key() {
+        let key = content_key(&Sha256::digest(b""), "zip");
+        assert_eq!(key, "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855.zip");
+        assert!(is_content_key(&key));
+        assert!(!is_content_key("uploads/files/1b4e28ba_picker.exe"));
+        assert!(!is_content_key("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"));
+        assert!(!is_content_key("E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855.zip"));
+        assert!(!is_content_key("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855.zip/../x"));
+    }
+
+    #[tokio::test]
+    async fn test_file_content_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("empty");
+        tokio::fs::write(&path, b"").await.unwrap();
+        assert_eq!(
+            file_content_key(&path, "png").await.unwrap(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855.png"
+        );
+    }
+
+    #[test]
+    fn test_build_blob_store_requires_s3_section() {
+        let config = StorageConfig {
+            backend: StorageBackend::S3,
+            ..StorageConfig::default()
+        };
+        assert!(matches!(build_blob_store(&config), Err(StorageError::Invalid(_))));
+        assert!(build_blob_store(&StorageConfig::default()).is_ok());
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::config::Claims;
+use crate::ephemeral::{EphemeralStore, REVOKED_ACCESS_TOKENS, SESSIONS_REVOKED};
+
+/// 访问令牌的吊销记录，保存在短期数据存储中
+/// 登出时吊销单个访问令牌；修改或重置密码时作废用户此前签发的全部令牌
+#[derive(Clone)]
+pub struct TokenRevocations {
+    ephemeral: Arc<dyn EphemeralStore>,
+    // 整体作废记录的保留时长，与刷新令牌的有效期一致
+    session_ttl: Duration,
+}
+
+impl TokenRevocations {
+    pub fn new(ephemeral: Arc<dyn EphemeralStore>, refresh_token_days: i64) -> Self {
+        Self {
+            ephemeral,
+            session_ttl: Duration::days(refresh_token_days),
+        }
+    }
+
+    // 吊销访问令牌，记录保留到令牌本身过期为止
+    pub async fn revoke_access_token(&self, claims: &Claims) -> Result<(), sqlx::Error> {
+        let expires_at = DateTime::from_timestamp(claims.exp as i64, 0).unwrap_or_else(Utc::now);
+        self.ephemeral
+            .put(REVOKED_ACCESS_TOKENS, &claims.jti, "", expires_at)
+            .await
+    }
+
+    pub async fn is_access_token_revoked(&self, jti: &str) -> Result<bool, sqlx::Error> {
+        Ok(self.ephemeral.get(REVOKED_ACCESS_TOKENS, jti).await?.is_some())
+    }
+
+    // 作废用户此前签发的全部令牌（修改或重置密码后调用）
+    // 只记录作废时间（Unix 毫秒），早于该时间签发的令牌都不再有效，记录保留到刷新令牌全部过期为止
+    pub async fn revoke_user_sessions(&self, user_id: Uuid) -> Result<(), sqlx::Error> {
+        let now = Utc::now();
+        let expires_at = now + self.session_ttl;
+        self.ephemeral
+            .put(SESSIONS_REVOKED, &user_id.to_string(), &now.timestamp_millis().to_string(), expires_at)
+            .await
+    }
+
+    pub async fn sessions_revoked_at(&self, user_id: Uuid) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        let revoked_at = self.ephemeral.get(SESSIONS_REVOKED, &user_id.to_string()).await?;
+        Ok(revoked_at
+            .and_then(|revoked_at| revoked_at.parse::<i64>().ok())
+            .and_then(DateTime::from_timestamp_millis))
+    }
+
+    /// 访问令牌已登出吊销，或签发于用户令牌被整体作废之前
+    /// iat 只精确到秒，与作废操作同一秒内签发的访问令牌仍然有效
+    pub async fn is_claims_revoked(&self, user_id: Uuid, claims: &Claims) -> Result<bool, sqlx::Error> {
+        if !claims.jti.is_empty() && self.is_access_token_revoked(&claims.jti).await? {
+            return Ok(true);
+        }
+        Ok(self
+            .sessions_revoked_at(user_id)
+            .await?
+            .is_some_and(|revoked_at| (claims.iat as i64) < revoked_at.timestamp()))
+    }
+
+    // 清理过期的吊销记录，返回删除数量
+    pub async fn purge_expired(&self, now: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+        let revoked = self.ephemeral.purge_expired(REVOKED_ACCESS_TOKENS, now).await?;
+        let sessions = self.ephemeral.purge_expired(SESSIONS_REVOKED, now).await?;
+        Ok(revoked + sessions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ephemeral::MemoryEphemeralStore;
+
+    #[tokio::test]
+    async fn test_revocations() {
+        let revocations = TokenRevocations::new(Arc::new(MemoryEphemeralStore::new()), 30);
+        let user_id = Uuid::new_v4();
+        let mut claims = Claims::new(user_id, Duration::minutes(15));
+        assert!(!revocations.is_claims_revoked(user_id, &claims).await.unwrap());
+
+        let revoked_jti = claims.jti.clone();
+        revocations.revoke_access_token(&claims).await.unwrap();
+        assert!(revocations.is_access_token_revoked(&revoked_jti).await.unwrap());
+        assert!(revocations.is_claims_revoked(user_id, &claims).await.unwrap());
+
+        // 作废全部令牌后，更早签发的令牌失效
+        let other = Claims::new(user_id, Duration::minutes(15));
+        assert!(!revocations.is_claims_revoked(user_id, &other).await.unwrap());
+        revocations.revoke_user_sessions(user_id).await.unwrap();
+        assert!(revocations.sessions_revoked_at(user_id).await.unwrap().is_some());
+        claims.jti = Uuid::new_v4().to_string();
+        claims.iat -= 60;
+        assert!(revocations.is_claims_revoked(user_id, &claims).await.unwrap());
+
+        // 访问令牌吊销记录随令牌过期清理，作废记录保留到刷新令牌过期
+        let purged = revocations.purge_expired(Utc::now() + Duration::hours(1)).await.unwrap();
+        assert_eq!(purged, 1);
+        assert!(!revocations.is_access_token_revoked(&revoked_jti).await.unwrap());
+        assert!(revocations.sessions_revoked_at(user_id).await.unwrap().is_some());
+    }
+}
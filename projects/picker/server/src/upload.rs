@@ -0,0 +1,298 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use axum::extract::multipart::Field;
+use sha2::{Digest, Sha256};
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::storage::{content_key, BlobStore};
+use crate::utils::AppError;
+
+// 上传的文件先写入系统临时目录下的该目录，校验通过后再写入文件存储
+const TEMP_DIR_NAME: &str = "pickers-server-uploads";
+
+// 识别文件格式所需的文件头长度
+const SNIFF_LEN: usize = 12;
+
+// 请求体中除文件外的表单字段预留的大小
+const FORM_OVERHEAD_BYTES: u64 = 1024 * 1024;
+
+/// Picker 压缩包中可识别的入口文件，与桌面端运行任务时查找的入口文件一致
+pub const ENTRY_FILES: [&str; 4] = ["entry.py", "entry.js", "entry.ps1", "entry.sh"];
+
+/// 上传文件的类型，决定大小上限与允许的格式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UploadKind {
+    // Picker 压缩包（zip）
+    Picker,
+    // Picker 图片（png / jpeg / webp）
+    Image,
+}
+
+impl UploadKind {
+    fn label(self) -> &'static str {
+        match self {
+            UploadKind::Picker => "Picker file",
+            UploadKind::Image => "Image",
+        }
+    }
+}
+
+/// 上传大小上限（字节），取自 [upload] max_picker_bytes / max_image_bytes
+#[derive(Debug, Clone, Copy)]
+pub struct UploadLimits {
+    pub max_picker_bytes: u64,
+    pub max_image_bytes: u64,
+}
+
+impl UploadLimits {
+    fn max_bytes(&self, kind: UploadKind) -> u64 {
+        match kind {
+            UploadKind::Picker => self.max_picker_bytes,
+            UploadKind::Image => self.max_image_bytes,
+        }
+    }
+
+    /// 上传接口的请求体上限：一个压缩包、一张图片以及其余表单字段
+    pub fn body_limit(&self) -> usize {
+        (self.max_picker_bytes + self.max_image_bytes + FORM_OVERHEAD_BYTES) as usize
+    }
+}
+
+/// 已写入文件存储的上传文件。调用 keep 之前被丢弃时在后台删除本次新写入的文件，
+/// 因此请求在校验或写入数据库时失败不会留下孤立文件
+pub struct StoredUpload {
+    key: String,
+    // 内容与已有文件相同时为 false，该文件仍被其他记录引用，不能删除
+    created: bool,
+    kept: bool,
+    blobs: Arc<dyn BlobStore>,
+}
+
+impl StoredUpload {
+    /// 文件在存储中的 content key，保存到 file_path / image_path
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// 文件已被数据库记录引用，不再自动删除，返回 key
+    pub fn keep(mut self) -> String {
+        self.kept = true;
+        std::mem::take(&mut self.key)
+    }
+}
+
+impl Drop for StoredUpload {
+    fn drop(&mut self) {
+        if self.kept || !self.created {
+            return;
+        }
+        let (blobs, key) = (self.blobs.clone(), std::mem::take(&mut self.key));
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn(async move {
+                    if let Err(e) = blobs.delete(&key).await {
+                        warn!("Failed to remove orphaned upload {}: {}", key, e);
+                    }
+                });
+            }
+            Err(_) => warn!("Orphaned upload {} left in storage", key),
+        }
+    }
+}
+
+// 临时文件，离开作用域时删除
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.0) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("Failed to remove temporary upload {}: {}", self.0.display(), e);
+            }
+        }
+    }
+}
+
+/// 将 multipart 字段以流式写入临时文件，超过大小上限时立即中止
+/// 写入完成后按文件头识别格式（不使用客户端提供的文件名与类型），Picker 压缩包还须包含入口文件，
+/// 校验通过后以内容的 sha256 为 key 写入文件存储
+pub async fn save_field(
+    mut field: Field<'_>,
+    kind: UploadKind,
+    limits: UploadLimits,
+    blobs: &Arc<dyn BlobStore>,
+) -> Result<StoredUpload, AppError> {
+    let max_bytes = limits.max_bytes(kind);
+    let temp_dir = std::env::temp_dir().join(TEMP_DIR_NAME);
+    fs::create_dir_all(&temp_dir).await.map_err(internal_error)?;
+    let temp = TempFile(temp_dir.join(format!("{}.part", Uuid::new_v4())));
+    let mut file = File::create(&temp.0).await.map_err(internal_error)?;
+
+    let mut hasher = Sha256::new();
+    let mut header = Vec::with_capacity(SNIFF_LEN);
+    let mut size = 0u64;
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|_| AppError::BadRequest(format!("Invalid {} data", kind.label().to_lowercase())))?
+    {
+        size += chunk.len() as u64;
+        if size > max_bytes {
+            return Err(AppError::BadRequest(format!(
+                "{} exceeds the maximum size of {} bytes",
+                kind.label(),
+                max_bytes
+            )));
+        }
+        if header.len() < SNIFF_LEN {
+            let take = (SNIFF_LEN - header.len()).min(chunk.len());
+            header.extend_from_slice(&chunk[..take]);
+        }
+        hasher.update(&chunk);
+        file.write_all(&chunk).await.map_err(internal_error)?;
+    }
+    file.flush().await.map_err(internal_error)?;
+    drop(file);
+
+    let extension = match kind {
+        UploadKind::Picker => {
+            if !is_zip(&header) {
+                return Err(AppError::BadRequest("Picker file must be a zip archive".to_string()));
+            }
+            let path = temp.0.clone();
+            let names = tokio::task::spawn_blocking(move || archive_file_names(&path))
+                .await
+                .map_err(internal_error)?
+                .map_err(|_| AppError::BadRequest("Invalid zip archive".to_string()))?;
+            if !has_entry_file(&names) {
+                return Err(AppError::BadRequest(format!(
+                    "Picker archive must contain an entry file ({})",
+                    ENTRY_FILES.join(", ")
+                )));
+            }
+            "zip"
+        }
+        UploadKind::Image => image_extension(&header)
+            .ok_or_else(|| AppError::BadRequest("Image must be a PNG, JPEG or WebP file".to_string()))?,
+    };
+
+    let key = content_key(&hasher.finalize(), extension);
+    let created = blobs.put_file(&key, &temp.0).await.map_err(internal_error)?;
+    Ok(StoredUpload {
+        key,
+        created,
+        kept: false,
+        blobs: blobs.clone(),
+    })
+}
+
+fn internal_error(e: impl std::fmt::Display) -> AppError {
+    error!("Failed to store upload: {}", e);
+    AppError::InternalServerError
+}
+
+// zip 本地文件头
+fn is_zip(header: &[u8]) -> bool {
+    header.starts_with(b"PK\x03\x04")
+}
+
+// 按文件头识别图片格式，返回保存时使用的扩展名
+fn image_extension(header: &[u8]) -> Option<&'static str> {
+    if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("png")
+    } else if header.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("jpg")
+    } else if header.len() >= 12 && &header[..4] == b"RIFF" && &header[8..12] == b"WEBP" {
+        Some("webp")
+    } else {
+        None
+    }
+}
+
+// 读取 zip 中央目录中的全部条目名
+fn archive_file_names(path: &Path) -> zip::result::ZipResult<Vec<String>> {
+    let archive = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+    Ok(archive.file_names().map(str::to_string).collect())
+}
+
+// 任意目录层级下存在入口文件即可，桌面端解压后会递归查找
+fn has_entry_file(names: &[String]) -> bool {
+    names.iter().filter(|name| !name.ends_with('/')).any(|name| {
+        let file_name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+        ENTRY_FILES.contains(&file_name)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::LocalBlobStore;
+    use crate::utils_tests::{eventually, picker_archive, TEST_PNG};
+
+    #[test]
+    fn test_image_extension() {
+        assert_eq!(image_extension(TEST_PNG), Some("png"));
+        assert_eq!(image_extension(&[0xFF, 0xD8, 0xFF, 0xE0, 0, 0x10]), Some("jpg"));
+        assert_eq!(image_extension(b"RIFF\x24\0\0\0WEBPVP8 "), Some("webp"));
+        assert_eq!(image_extension(b"RIFF\x24\0\0\0WAVEfmt "), None);
+        assert_eq!(image_extension(b"GIF89a"), None);
+        assert_eq!(image_extension(b""), None);
+    }
+
+    #[test]
+    fn test_has_entry_file() {
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        assert!(has_entry_file(&names(&["entry.py", "config.toml"])));
+        assert!(has_entry_file(&names(&["my-picker/", "my-picker/src/entry.js"])));
+        assert!(!has_entry_file(&names(&["main.py", "entry.py.bak", "entry.exe"])));
+        assert!(!has_entry_file(&names(&["entry.sh/"])));
+        assert!(!has_entry_file(&[]));
+    }
+
+    #[test]
+    fn test_archive_file_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("picker.zip");
+        std::fs::write(&path, picker_archive(&["picker/entry.ps1", "picker/readme.md"])).unwrap();
+        let names = archive_file_names(&path).unwrap();
+        assert_eq!(names.len(), 2);
+        assert!(has_entry_file(&names));
+        assert!(is_zip(&std::fs::read(&path).unwrap()));
+
+        std::fs::write(&path, b"PK\x03\x04 truncated").unwrap();
+        assert!(archive_file_names(&path).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stored_upload_removed_unless_kept() {
+        let root = tempfile::tempdir().unwrap();
+        let blobs: Arc<dyn BlobStore> = Arc::new(LocalBlobStore::new(root.path()));
+        let source = root.path().join("upload.part");
+        let mut keys = Vec::new();
+        for content in [&b"dropped"[..], b"kept", b"existing"] {
+            std::fs::write(&source, content).unwrap();
+            let key = content_key(&Sha256::digest(content), "zip");
+            blobs.put_file(&key, &source).await.unwrap();
+            keys.push(key);
+        }
+        let upload = |key: &str, created: bool| StoredUpload {
+            key: key.to_string(),
+            created,
+            kept: false,
+            blobs: blobs.clone(),
+        };
+
+        drop(upload(&keys[0], true));
+        assert_eq!(upload(&keys[1], true).keep(), keys[1]);
+        // 内容与已有文件相同（未新写入）时不删除
+        drop(upload(&keys[2], false));
+
+        assert!(eventually(|| async { !blobs.exists(&keys[0]).await.unwrap() }).await);
+        assert!(blobs.exists(&keys[1]).await.unwrap());
+        assert!(blobs.exists(&keys[2]).await.unwrap());
+    }
+}
@@ -1,5 +1,5 @@
 use axum::{
-    http::StatusCode,
+    http::{header::RETRY_AFTER, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
@@ -7,10 +7,15 @@ use rand::RngCore;
 use serde_json::json;
 use sha2::{Digest, Sha256};
 use uuid::Uuid;
-use bcrypt::{hash, verify, DEFAULT_COST};
+use bcrypt::{hash, verify};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
 use alloy::signers::local::PrivateKeySigner;
-use base64::{Engine as _, engine::general_purpose};
-use aes_gcm::{Aes256Gcm, KeyInit, aead::{Aead, Nonce}};
+
+use crate::config::{PasswordAlgorithm, PasswordConfig};
+use crate::keyring::WalletKeyring;
 
 // 自定义错误类型
 #[derive(Debug)]
@@ -19,6 +24,8 @@ pub enum AppError {
     Unauthorized(String),
     NotFound(String),
     UnprocessableEntity(String),
+    // 请求过于频繁，参数为客户端应等待的秒数（写入 Retry-After）
+    TooManyRequests(u64),
     InternalServerError,
     DatabaseError,
 }
@@ -30,6 +37,17 @@ impl IntoResponse for AppError {
             AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             AppError::UnprocessableEntity(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg),
+            AppError::TooManyRequests(retry_after) => {
+                let body = Json(json!({
+                    "error": "Too many requests, please try again later"
+                }));
+                return (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(RETRY_AFTER, retry_after.max(1).to_string())],
+                    body,
+                )
+                    .into_response();
+            }
             AppError::InternalServerError => (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error".to_string()),
             AppError::DatabaseError => (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string()),
         };
@@ -43,7 +61,7 @@ impl IntoResponse for AppError {
 }
 
 // 生成EVM钱包地址
-pub fn generate_wallet(master_key: &str, nonce: &str) -> (String, String) {
+pub fn generate_wallet(keyring: &WalletKeyring) -> Result<(String, String), AppError> {
     // 1.创建一个随机的私钥签名器
     let signer = PrivateKeySigner::random();
 
@@ -54,16 +72,10 @@ pub fn generate_wallet(master_key: &str, nonce: &str) -> (String, String) {
     let private_key_bytes = signer.to_bytes();
     let private_key_hex = hex::encode(private_key_bytes);
 
-    // 4.加密私钥
-    let encrypted_pk = match encrypt_private_key(&private_key_hex, master_key, nonce) {
-        Ok(encrypted) => encrypted,
-        Err(e) => {
-            tracing::error!("Failed to encrypt private key: {:?}", e);
-            private_key_hex.clone() // 在加密失败时返回原始私钥（仅用于测试环境）
-        }
-    };
-    
-    (encrypted_pk, address.to_string())
+    // 4.加密私钥，加密失败时直接返回错误，绝不保存明文私钥
+    let encrypted_pk = keyring.encrypt(&private_key_hex)?;
+
+    Ok((encrypted_pk, address.to_string()))
 }
 
 // 生成随机token
@@ -80,89 +92,8 @@ pub fn is_valid_email(email: &str) -> bool {
     email_regex.is_match(email) && !email.contains("..")
 }
 
-// 使用AES-256-GCM加密密码（可恢复）
-// 修改encrypt_private_key和decrypt_private_key函数，移除不必要的base64编码
-
-// 修改后的encrypt_private_key函数
-pub fn encrypt_private_key(password: &str, master_key: &str, nonce: &str) -> Result<String, AppError> {
-    // 直接使用master_key的字节，不再进行base64编码
-    let master_key_bytes = master_key.as_bytes();
-    
-    // 确保master_key有32字节（AES-256需要）
-    let mut key_array = [0u8; 32];
-    let copy_len = std::cmp::min(master_key_bytes.len(), 32);
-    key_array[..copy_len].copy_from_slice(&master_key_bytes[..copy_len]);
-    
-    // 直接使用nonce的字节，不再进行base64编码
-    let nonce_bytes = nonce.as_bytes();
-    
-    // 确保nonce有12字节（GCM模式推荐）
-    let mut nonce_array = [0u8; 12];
-    let copy_len = std::cmp::min(nonce_bytes.len(), 12);
-    nonce_array[..copy_len].copy_from_slice(&nonce_bytes[..copy_len]);
-    
-    // 创建nonce对象
-    let nonce_obj = Nonce::<Aes256Gcm>::from_slice(&nonce_array);
-    
-    // 创建加密器并加密密码
-    let cipher = Aes256Gcm::new_from_slice(&key_array).map_err(|e| {
-        tracing::error!("AES-256-GCM key init failed: {:?}", e);
-        AppError::InternalServerError})?;
-    let ciphertext = cipher.encrypt(nonce_obj, password.as_bytes())
-        .map_err(|e| {
-            tracing::error!("AES-256-GCM encrypt failed: {:?}", e);
-            AppError::InternalServerError})?;
-    
-    // 将密文编码为Base64并返回
-    Ok(general_purpose::STANDARD
-        .encode(ciphertext))
-}
-
-// 解密恢复原始密码
-pub fn decrypt_private_key(encrypted: &str, master_key: &str, nonce: &str) -> Result<String, AppError> {
-    // 直接使用master_key的字节，不再进行base64编码
-    let master_key_bytes = master_key.as_bytes();
-    
-    // 确保master_key有32字节（AES-256需要）
-    let mut key_array = [0u8; 32];
-    let copy_len = std::cmp::min(master_key_bytes.len(), 32);
-    key_array[..copy_len].copy_from_slice(&master_key_bytes[..copy_len]);
-    
-    // 直接使用nonce的字节，不再进行base64编码
-    let nonce_bytes = nonce.as_bytes();
-    
-    // 确保nonce有12字节（GCM模式推荐）
-    let mut nonce_array = [0u8; 12];
-    let copy_len = std::cmp::min(nonce_bytes.len(), 12);
-    nonce_array[..copy_len].copy_from_slice(&nonce_bytes[..copy_len]);
-    
-    // 创建nonce对象
-    let nonce_obj = Nonce::<Aes256Gcm>::from_slice(&nonce_array);
-    
-    // Base64解码密文
-    let ciphertext = general_purpose::STANDARD
-        .decode(encrypted)
-        .map_err(|e| {
-            tracing::error!("Base64 decode failed: {:?}", e);
-            AppError::InternalServerError})?;
-    
-    // 创建解密器并解密
-    let cipher = Aes256Gcm::new_from_slice(&key_array).map_err(|e| {
-        tracing::error!("AES-256-GCM key init failed: {:?}", e);
-        AppError::InternalServerError})?;
-    let plaintext = cipher.decrypt(nonce_obj, ciphertext.as_ref())
-        .map_err(|e| {
-            tracing::error!("AES-256-GCM decrypt failed: {:?}", e);
-            AppError::InternalServerError})?;
-    
-    // 转换为字符串
-    String::from_utf8(plaintext).map_err(|e| {
-        tracing::error!("Decrypted plaintext is not valid UTF-8: {:?}", e);
-        AppError::InternalServerError})
-}
-
-// 使用自定义 salt 哈希密码
-// salt = user_id<UUID>字符串 + AppState中的配置salt
+// 旧版密码哈希：SHA-256(password + user_id + 配置salt) 的十六进制字符串
+// 只用于校验存量数据，新密码统一使用 PasswordHashing::hash
 pub fn hash_password_with_user_id(password: &str, user_id: Uuid, salt: &str) -> String {
     let full_salt = format!("{}{}", user_id, salt);
     let mut hasher = Sha256::new();
@@ -171,20 +102,108 @@ pub fn hash_password_with_user_id(password: &str, user_id: Uuid, salt: &str) ->
     hex::encode(hasher.finalize())
 }
 
-// 验证密码
+// 验证密码，同时支持新格式（argon2id / bcrypt）与旧版 SHA-256 哈希
 pub fn verify_password_with_user_id(password: &str, user_id: Uuid, hash: &str, salt: &str) -> bool {
-    let computed_hash = hash_password_with_user_id(password, user_id, salt);
-    computed_hash == hash
+    if is_legacy_password_hash(hash) {
+        let computed_hash = hash_password_with_user_id(password, user_id, salt);
+        return computed_hash == hash;
+    }
+    verify_password(password, hash).unwrap_or(false)
 }
 
-// 哈希密码
-pub fn hash_password(password: &str) -> Result<String, AppError> {
-    hash(password, DEFAULT_COST).map_err(|_| AppError::InternalServerError)
+// 验证新格式的密码哈希，哈希中自带算法与参数，无法识别时返回错误
+pub fn verify_password(password: &str, hash: &str) -> Result<bool, AppError> {
+    if hash.starts_with("$argon2") {
+        let parsed = PasswordHash::new(hash).map_err(|_| AppError::InternalServerError)?;
+        Ok(Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+    } else if hash.starts_with("$2") {
+        verify(password, hash).map_err(|_| AppError::InternalServerError)
+    } else {
+        Err(AppError::InternalServerError)
+    }
 }
 
-// 验证密码
-pub fn verify_password(password: &str, hash: &str) -> Result<bool, AppError> {
-    verify(password, hash).map_err(|_| AppError::InternalServerError)
+// 新格式哈希都是以 $ 开头的 PHC / MCF 字符串，旧版是纯十六进制
+fn is_legacy_password_hash(hash: &str) -> bool {
+    !hash.starts_with('$')
+}
+
+/// 按 [password] 配置生成密码哈希
+/// argon2id 输出 PHC 字符串（$argon2id$v=19$m=..,t=..,p=..$salt$hash），bcrypt 输出 $2b$cost$...，
+/// 算法与参数都记录在哈希中，调整配置后旧哈希仍可校验
+#[derive(Debug, Clone)]
+pub struct PasswordHashing {
+    pub algorithm: PasswordAlgorithm,
+    pub bcrypt_cost: u32,
+    pub argon2_memory_kib: u32,
+    pub argon2_iterations: u32,
+    pub argon2_parallelism: u32,
+}
+
+impl From<&PasswordConfig> for PasswordHashing {
+    fn from(config: &PasswordConfig) -> Self {
+        Self {
+            algorithm: config.algorithm,
+            bcrypt_cost: config.bcrypt_cost,
+            argon2_memory_kib: config.argon2_memory_kib,
+            argon2_iterations: config.argon2_iterations,
+            argon2_parallelism: config.argon2_parallelism,
+        }
+    }
+}
+
+impl PasswordHashing {
+    fn argon2_params(&self) -> Result<Params, AppError> {
+        Params::new(self.argon2_memory_kib, self.argon2_iterations, self.argon2_parallelism, None).map_err(|e| {
+            tracing::error!("Invalid argon2 parameters: {}", e);
+            AppError::InternalServerError
+        })
+    }
+
+    pub fn hash(&self, password: &str) -> Result<String, AppError> {
+        match self.algorithm {
+            PasswordAlgorithm::Argon2id => {
+                let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, self.argon2_params()?);
+                let salt = SaltString::generate(&mut OsRng);
+                argon2
+                    .hash_password(password.as_bytes(), &salt)
+                    .map(|hash| hash.to_string())
+                    .map_err(|e| {
+                        tracing::error!("argon2 hashing failed: {}", e);
+                        AppError::InternalServerError
+                    })
+            }
+            PasswordAlgorithm::Bcrypt => hash(password, self.bcrypt_cost).map_err(|e| {
+                tracing::error!("bcrypt hashing failed: {}", e);
+                AppError::InternalServerError
+            }),
+        }
+    }
+
+    /// 哈希不是按当前配置（算法与参数）生成时返回 true，包括旧版 SHA-256 哈希
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        match self.algorithm {
+            PasswordAlgorithm::Argon2id => {
+                let Ok(parsed) = PasswordHash::new(hash) else {
+                    return true;
+                };
+                let Ok(params) = Params::try_from(&parsed) else {
+                    return true;
+                };
+                parsed.algorithm != Algorithm::Argon2id.ident()
+                    || params.m_cost() != self.argon2_memory_kib
+                    || params.t_cost() != self.argon2_iterations
+                    || params.p_cost() != self.argon2_parallelism
+            }
+            PasswordAlgorithm::Bcrypt => {
+                // $2b$12$<salt+hash>
+                let mut parts = hash.split('$').skip(1);
+                let version = parts.next().unwrap_or_default();
+                let cost = parts.next().and_then(|cost| cost.parse::<u32>().ok());
+                !version.starts_with('2') || cost != Some(self.bcrypt_cost)
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -219,17 +238,23 @@ pub mod test_utils {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils_tests::test_pool;
 
     #[test]
     fn test_generate_wallet() {
-        let master_key = "openpickopenpickopenpickopenpick";
-        let nonce = "openpickopen";
+        let keyring = test_keyring();
 
-        let (private_key, wallet_address) = generate_wallet(master_key, nonce);
+        let (private_key, wallet_address) = generate_wallet(&keyring).unwrap();
         
-        // 私钥应该是64个字符的十六进制字符串
-        assert_eq!(private_key.len(), 64);
-        assert!(private_key.chars().all(|c| c.is_ascii_hexdigit()));
+        // 私钥以信封格式加密保存，解密后是64个字符的十六进制字符串
+        assert!(private_key.starts_with("v1:k1:"));
+        let plaintext = keyring.decrypt(&private_key).unwrap();
+        assert_eq!(plaintext.len(), 64);
+        assert!(plaintext.chars().all(|c| c.is_ascii_hexdigit()));
+
+        // 私钥与地址对应
+        let signer: PrivateKeySigner = plaintext.parse().unwrap();
+        assert_eq!(signer.address().to_string(), wallet_address);
         
         // 钱包地址应该以0x开头，后跟40个十六进制字符
         assert!(wallet_address.starts_with("0x"));
@@ -239,17 +264,28 @@ mod tests {
 
     #[test]
     fn test_generate_wallet_uniqueness() {
-        let master_key = "openpickopenpickopenpickopenpick";
-        let nonce = "openpickopen";
+        let keyring = test_keyring();
 
-        let (private_key1, wallet_address1) = generate_wallet(master_key, nonce);
-        let (private_key2, wallet_address2) = generate_wallet(master_key, nonce);
+        let (private_key1, wallet_address1) = generate_wallet(&keyring).unwrap();
+        let (private_key2, wallet_address2) = generate_wallet(&keyring).unwrap();
         
         // 每次生成的钱包应该不同
         assert_ne!(private_key1, private_key2);
         assert_ne!(wallet_address1, wallet_address2);
     }
 
+    #[test]
+    fn test_generate_wallet_fails_without_master_key() {
+        // 加密失败时返回错误，不会退回保存明文私钥
+        let result = generate_wallet(&WalletKeyring::unavailable());
+        assert!(matches!(result, Err(AppError::InternalServerError)));
+    }
+
+    fn test_keyring() -> WalletKeyring {
+        let config = crate::keyring::tests::password_config("k1", "openpickopenpickopenpickopenpick", &[]);
+        WalletKeyring::from_config(&config).unwrap()
+    }
+
     #[test]
     fn test_generate_token() {
         let token = generate_token();
@@ -287,7 +323,7 @@ mod tests {
     async fn test_create_test_app_state() {
         let state = test_utils::create_test_app_state().await;
         // 验证状态创建成功
-        assert!(!state.db.is_closed());
+        assert!(!test_pool(&state).is_closed());
     }
 
     #[tokio::test]
@@ -357,18 +393,34 @@ mod tests {
         assert!(!verify_password_with_user_id(password, different_user_id, &hash, salt));
     }
 
+    // 测试使用较低的计算成本
+    fn hashing(algorithm: PasswordAlgorithm) -> PasswordHashing {
+        PasswordHashing {
+            algorithm,
+            bcrypt_cost: 4,
+            argon2_memory_kib: 1024,
+            argon2_iterations: 1,
+            argon2_parallelism: 1,
+        }
+    }
+
     #[test]
     fn test_hash_password() {
         let password = "test_password";
         
-        let result = hash_password(password);
+        let result = hashing(PasswordAlgorithm::Bcrypt).hash(password);
         assert!(result.is_ok());
         
         let hash = result.unwrap();
         assert!(!hash.is_empty());
         
         // bcrypt哈希应该以$2b$开头
-        assert!(hash.starts_with("$2b$"));
+        assert!(hash.starts_with("$2b$04$"));
+
+        let hash = hashing(PasswordAlgorithm::Argon2id).hash(password).unwrap();
+        assert!(hash.starts_with("$argon2id$v=19$m=1024,t=1,p=1$"));
+        // 随机盐，同一密码每次哈希结果不同
+        assert_ne!(hash, hashing(PasswordAlgorithm::Argon2id).hash(password).unwrap());
     }
 
     #[test]
@@ -376,7 +428,7 @@ mod tests {
         let password = "test_password";
         let wrong_password = "wrong_password";
         
-        let hash = hash_password(password).unwrap();
+        let hash = hashing(PasswordAlgorithm::Bcrypt).hash(password).unwrap();
         
         // 正确的密码应该验证成功
         let result = verify_password(password, &hash);
@@ -403,6 +455,50 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_verify_password_with_user_id_supports_all_formats() {
+        let user_id = Uuid::new_v4();
+        let salt = "openpick";
+        let hashes = [
+            hash_password_with_user_id("test_password", user_id, salt),
+            hashing(PasswordAlgorithm::Bcrypt).hash("test_password").unwrap(),
+            hashing(PasswordAlgorithm::Argon2id).hash("test_password").unwrap(),
+        ];
+
+        for hash in &hashes {
+            assert!(verify_password_with_user_id("test_password", user_id, hash, salt));
+            assert!(!verify_password_with_user_id("wrong_password", user_id, hash, salt));
+        }
+        assert!(!verify_password_with_user_id("test_password", user_id, "$unknown$hash", salt));
+    }
+
+    #[test]
+    fn test_needs_rehash() {
+        let argon2 = hashing(PasswordAlgorithm::Argon2id);
+        let bcrypt = hashing(PasswordAlgorithm::Bcrypt);
+        let legacy = hash_password_with_user_id("test_password", Uuid::new_v4(), "openpick");
+        let argon2_hash = argon2.hash("test_password").unwrap();
+        let bcrypt_hash = bcrypt.hash("test_password").unwrap();
+
+        // 旧版哈希总是需要升级
+        assert!(argon2.needs_rehash(&legacy));
+        assert!(bcrypt.needs_rehash(&legacy));
+
+        // 与当前配置一致的哈希不需要升级
+        assert!(!argon2.needs_rehash(&argon2_hash));
+        assert!(!bcrypt.needs_rehash(&bcrypt_hash));
+
+        // 切换算法后需要升级
+        assert!(argon2.needs_rehash(&bcrypt_hash));
+        assert!(bcrypt.needs_rehash(&argon2_hash));
+
+        // 调整参数后需要升级
+        let stronger_argon2 = PasswordHashing { argon2_iterations: 2, ..argon2.clone() };
+        let stronger_bcrypt = PasswordHashing { bcrypt_cost: 5, ..bcrypt.clone() };
+        assert!(stronger_argon2.needs_rehash(&argon2_hash));
+        assert!(stronger_bcrypt.needs_rehash(&bcrypt_hash));
+    }
+
     #[test]
     fn test_app_error_into_response() {
         // 测试BadRequest错误
@@ -1,12 +1,45 @@
+use std::sync::Arc;
+
 use crate::config::AppState;
-use crate::database::{create_pool, init_database};
+use crate::database::{create_memory_pool, init_database, Database, DbPool};
+use crate::repository::MemoryRepository;
+use crate::storage::LocalBlobStore;
 
 /// 创建测试用的应用状态
+/// 每次使用独立的内存数据库，测试之间不共享数据
 pub async fn create_test_app_state() -> AppState {
-    let pool = create_pool().await.expect("Failed to create test database pool");
+    let pool = create_memory_pool().await.expect("Failed to create test database pool");
     init_database(&pool).await.expect("Failed to initialize test database");
 
-    AppState::new(pool)
+    let mut state = AppState::new(pool);
+    state.blobs = Arc::new(LocalBlobStore::new(test_blob_root()));
+    state
+}
+
+/// 测试使用的本地文件存储目录；文件以内容命名，各测试共用同一目录不会互相覆盖
+pub fn test_blob_root() -> std::path::PathBuf {
+    std::env::temp_dir().join("pickers-server-tests").join("blobs")
+}
+
+/// 创建使用内存仓储的应用状态，处理器的读写全部落在 repo 上，不经过数据库
+pub async fn create_mock_app_state(repo: &MemoryRepository) -> AppState {
+    let mut state = create_test_app_state().await;
+    state.users = Arc::new(repo.clone());
+    state.pickers = Arc::new(repo.clone());
+    state.orders = Arc::new(repo.clone());
+    state.refunds = Arc::new(repo.clone());
+    state.withdrawals = Arc::new(repo.clone());
+    state.premium_ledger = Arc::new(repo.clone());
+    state.chain_events = Arc::new(repo.clone());
+    state
+}
+
+/// 取出测试状态中的 SQLite 连接池，供测试直接执行 SQL
+pub fn test_pool(state: &AppState) -> &DbPool {
+    match &state.db {
+        Database::Sqlite(pool) => pool,
+        Database::Postgres(_) => panic!("tests run on the SQLite backend"),
+    }
 }
 
 /// 测试用的JWT密钥
@@ -22,6 +55,7 @@ pub fn create_test_jwt_token(user_id: &str) -> String {
         sub: user_id.to_string(),
         exp: (now + chrono::Duration::hours(24)).timestamp() as usize,
         iat: now.timestamp() as usize,
+        jti: uuid::Uuid::new_v4().to_string(),
     };
 
     encode(
@@ -64,6 +98,59 @@ pub fn is_valid_test_email(email: &str) -> bool {
     true
 }
 
+/// 等待后台任务（如删除孤立的上传文件）完成，最多约 2 秒，返回条件最终是否满足
+pub async fn eventually<F, Fut>(mut condition: F) -> bool
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    for _ in 0..200 {
+        if condition().await {
+            return true;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    false
+}
+
+/// 测试用的 PNG 图片（只需文件头即可通过格式识别）
+pub const TEST_PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+/// 生成包含指定条目的 Picker 压缩包（不压缩），条目内容为其文件名
+pub fn picker_archive(entries: &[&str]) -> Vec<u8> {
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for entry in entries {
+        writer.start_file(*entry, options).expect("Failed to add archive entry");
+        writer.write_all(entry.as_bytes()).expect("Failed to write archive entry");
+    }
+    writer.finish().expect("Failed to finish archive").into_inner()
+}
+
+/// 构造 multipart/form-data 请求体，parts 为 (字段名, 文件名, 内容)，文件名为 None 的是普通字段
+pub fn multipart_body(boundary: &str, parts: &[(&str, Option<&str>, &[u8])]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for (name, filename, content) in parts {
+        body.extend_from_slice(format!("--{boundary}\r\n").as_bytes());
+        match filename {
+            Some(filename) => body.extend_from_slice(
+                format!(
+                    "Content-Disposition: form-data; name=\"{name}\"; filename=\"{filename}\"\r\nContent-Type: application/octet-stream\r\n\r\n"
+                )
+                .as_bytes(),
+            ),
+            None => body.extend_from_slice(format!("Content-Disposition: form-data; name=\"{name}\"\r\n\r\n").as_bytes()),
+        }
+        body.extend_from_slice(content);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+    body
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -72,8 +159,8 @@ mod tests {
     async fn test_create_test_app_state() {
         let state = create_test_app_state().await;
         assert!(!state.jwt_secret.is_empty());
-        assert!(state.verification_codes.lock().unwrap().is_empty());
-        assert!(state.download_tokens.lock().unwrap().is_empty());
+        assert_eq!(state.ephemeral.count(crate::ephemeral::VERIFICATION_CODES).await.unwrap(), 0);
+        assert_eq!(state.ephemeral.count(crate::ephemeral::DOWNLOAD_TOKENS).await.unwrap(), 0);
     }
 
     #[test]
@@ -105,4 +192,4 @@ mod tests {
         assert!(!is_valid_test_email("@domain.com"));
         assert!(!is_valid_test_email("user@"));
     }
-}
\ No newline at end of file
+}
@@ -4,43 +4,112 @@ use axum::{
     Router,
 };
 use pickers_server::{
-    config::AppState,
-    database::{create_pool, init_database},
-    handlers::create_routes,
+    config::{AppState, Config, PasswordAlgorithm, RateLimitConfig, SeedProfile},
+    database::{create_memory_pool, init_database, Database},
+    ephemeral::{EphemeralStore, SqlEphemeralStore},
+    handlers::{create_protected_routes, create_routes},
+    keyring::WalletKeyring,
+    mailer::OutboxMailer,
+    middleware::RateLimiter,
+    price_oracle::FixedPriceOracle,
+    quotes::QuoteSigner,
+    seed::load_and_apply_fixtures,
+    storage::LocalBlobStore,
+    token_revocation::TokenRevocations,
+    utils::PasswordHashing,
 };
 use serde_json::json;
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use tower::ServiceExt;
 
-async fn create_test_app() -> Router {
-    let pool = create_pool().await.expect("Failed to create test database pool");
+async fn create_test_state() -> AppState {
+    let pool = create_memory_pool().await.expect("Failed to create test database pool");
     init_database(&pool).await.expect("Failed to initialize test database");
+    let db = Database::from(pool.clone());
+    let ephemeral: Arc<dyn EphemeralStore> = Arc::new(SqlEphemeralStore::new(pool));
 
-    let state = AppState {
-        db: pool,
+    AppState {
+        users: db.users(),
+        pickers: db.pickers(),
+        orders: db.orders(),
+        refunds: db.refunds(),
+        withdrawals: db.withdrawals(),
+        premium_ledger: db.premium_ledger(),
+        chain_events: db.chain_events(),
+        db,
         jwt_secret: "test_secret_key_for_testing_purposes_only".to_string(),
+        jwt_access_token_minutes: 15,
+        jwt_refresh_token_days: 30,
         password_salt: "test_salt_for_testing_purposes_only".to_string(),
-        password_master_key: "openpickopenpickopenpickopenpick".to_string(),
-        password_nonce: "openpickopen".to_string(),
+        password_hashing: PasswordHashing {
+            algorithm: PasswordAlgorithm::Argon2id,
+            bcrypt_cost: 4,
+            argon2_memory_kib: 1024,
+            argon2_iterations: 1,
+            argon2_parallelism: 1,
+        },
+        wallet_keyring: Arc::new(WalletKeyring::from_config(&Config::load().password).unwrap()),
         pending_registration_cleanup_minutes: 10,
-        verification_codes: Arc::new(Mutex::new(HashMap::new())),
-        download_tokens: Arc::new(Mutex::new(HashMap::new())),
-        pending_registrations: Arc::new(Mutex::new(HashMap::new())),
+        ephemeral: ephemeral.clone(),
+        token_revocations: TokenRevocations::new(ephemeral, 30),
         blockchain_name: "Conflux".to_string(),
         blockchain_rpc_url: "https://evmtestnet.confluxrpc.com".to_string(),
         blockchain_token_usdt_url: "https://www.okx.com/api/v5/market/ticker?instId=USDC-USDT".to_string(),
         blockchain_authorized_contract_address: "0x2ed3dddae5b2f321af0806181fbfa6d049be47d8".to_string(),
         blockchain_retry_times: 5,
         blockchain_retry_interval_seconds: 10,
+        picker_registrar: None,
+        price_oracle: Arc::new(FixedPriceOracle::new("0.1".parse().unwrap())),
+        quote_ttl_seconds: 120,
+        quote_signer: Arc::new(QuoteSigner::new("test_quote_secret")),
+        admin_user_ids: Vec::new(),
+        upload_max_picker_bytes: 100 * 1024 * 1024,
+        upload_max_image_bytes: 5 * 1024 * 1024,
+        blobs: Arc::new(LocalBlobStore::new(std::env::temp_dir().join("pickers-server-tests").join("blobs"))),
         premium_payment_rate: 5,
         premium_to_usd: 1,
         premium_free: 30,
         premium_period: 30,
         premium_start: true,
-    };
+        premium_grant_interval_seconds: 3600,
+        premium_min_withdrawal: 10,
+        seed_profile: SeedProfile::Test,
+        seed_fixtures: None,
+        mailer: Arc::new(OutboxMailer::stdout("OpenPick <no-reply@openpick.org>")),
+        mail_templates_dir: None,
+        rate_limiter: Arc::new(RateLimiter::new(&RateLimitConfig::default())),
+        verification_max_failures: 5,
+        verification_lockout_minutes: 15,
+        reconciler_enabled: false,
+        reconciler_interval_seconds: 30,
+        reconciler_batch_size: 100,
+        indexer_enabled: false,
+        indexer_start_block: 0,
+        indexer_confirmations: 6,
+        indexer_batch_blocks: 500,
+        indexer_interval_seconds: 15,
+    }
+}
+
+async fn create_test_app() -> Router {
+    let state = create_test_state().await;
+    create_routes(state.clone()).with_state(state)
+}
+
+// 加载 tests/fixtures 下的夹具场景
+async fn create_test_app_with_fixtures(name: &str) -> Router {
+    let state = create_test_state().await;
+    let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+    load_and_apply_fixtures(&state, &path).await.expect("Failed to load fixtures");
 
-    create_routes().with_state(state)
+    create_routes(state.clone())
+        .merge(create_protected_routes(state.clone()))
+        .with_state(state)
+}
+
+async fn response_json(response: axum::response::Response) -> serde_json::Value {
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&body).unwrap()
 }
 
 #[tokio::test]
@@ -305,3 +374,162 @@ async fn test_health_check() {
     let response = app.oneshot(request).await.unwrap();
     assert_eq!(response.status(), StatusCode::OK);
 }
+
+#[tokio::test]
+async fn test_market_with_fixtures() {
+    let app = create_test_app_with_fixtures("marketplace.toml").await;
+
+    let request = Request::builder()
+        .method("GET")
+        .uri("/api/pickers")
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // 下架的 Picker 不应出现在市场列表中
+    let body = response_json(response).await;
+    assert_eq!(body["total"], 2);
+    let aliases: Vec<&str> = body["pickers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["alias"].as_str().unwrap())
+        .collect();
+    assert!(aliases.contains(&"screenshot-picker"));
+    assert!(aliases.contains(&"translate-picker"));
+    assert!(!aliases.contains(&"retired-picker"));
+}
+
+#[tokio::test]
+async fn test_market_keyword_with_fixtures() {
+    let app = create_test_app_with_fixtures("marketplace.toml").await;
+
+    let request = Request::builder()
+        .method("GET")
+        .uri("/api/pickers?keyword=translate")
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response_json(response).await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["pickers"][0]["version"], "2.1.0");
+}
+
+#[tokio::test]
+async fn test_login_with_fixture_user() {
+    let app = create_test_app_with_fixtures("marketplace.toml").await;
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/users/login")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({
+                "email": "buyer@marketplace.test",
+                "user_password": "buyerpassword"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response_json(response).await;
+    assert_eq!(body["user"]["premium_balance"], 100);
+}
+
+#[tokio::test]
+async fn test_login_rate_limited_per_email() {
+    let app = create_test_app_with_fixtures("marketplace.toml").await;
+
+    let login_request = || {
+        Request::builder()
+            .method("POST")
+            .uri("/api/users/login")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({
+                    "email": "buyer@marketplace.test",
+                    "user_password": "wrongpassword"
+                })
+                .to_string(),
+            ))
+            .unwrap()
+    };
+
+    // 默认每个邮箱最多连续 5 次
+    for _ in 0..5 {
+        let response = app.clone().oneshot(login_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    let response = app.oneshot(login_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key("retry-after"));
+}
+
+#[tokio::test]
+async fn test_refresh_and_logout_flow() {
+    let app = create_test_app_with_fixtures("marketplace.toml").await;
+
+    let post = |uri: &str, token: Option<&str>, body: serde_json::Value| {
+        let mut builder = Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("content-type", "application/json");
+        if let Some(token) = token {
+            builder = builder.header("authorization", format!("Bearer {}", token));
+        }
+        builder.body(Body::from(body.to_string())).unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(post(
+            "/api/users/login",
+            None,
+            json!({ "email": "buyer@marketplace.test", "user_password": "buyerpassword" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let login = response_json(response).await;
+    assert_eq!(login["expires_in"], 15 * 60);
+
+    let response = app
+        .clone()
+        .oneshot(post("/api/users/refresh", None, json!({ "refresh_token": login["refresh_token"] })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let refreshed = response_json(response).await;
+    let token = refreshed["token"].as_str().unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(post("/api/users/logout", Some(token), json!({ "refresh_token": refreshed["refresh_token"] })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // 登出后访问令牌与刷新令牌都已失效
+    let request = Request::builder()
+        .uri("/api/users/profile")
+        .header("authorization", format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .oneshot(post("/api/users/refresh", None, json!({ "refresh_token": refreshed["refresh_token"] })))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}